@@ -3,13 +3,18 @@ extern crate cc;
 
 use bindgen::Bindings;
 use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::error::Error;
 use std::path::{Path, PathBuf};
 use std::env;
 use glob::glob;
 
-const BINDGEN_FILENAME: &str = "src/bindings.rs";
+/// Bindings committed for 64-bit desktop targets, used with the `prebuilt-bindings` feature
+const PREBUILT_BINDINGS: &str = "src/bindings.rs";
+/// The VkFFT version the committed bindings were generated from
+const PREBUILT_BINDINGS_VERSION: &str = "src/bindings.version";
 
 //from https://github.com/SnowflakePowered/glslang-rs/blob/master/glslang-sys/build.rs
 pub fn add_subdirectory(build: &mut cc::Build, directory: &str) {
@@ -29,8 +34,98 @@ pub fn add_subdirectory(build: &mut cc::Build, directory: &str) {
   }
 }
 
+fn is_android() -> bool {
+  env::var("CARGO_CFG_TARGET_OS").map(|os| os == "android").unwrap_or(false)
+}
+
+fn is_ios() -> bool {
+  env::var("CARGO_CFG_TARGET_OS").map(|os| os == "ios").unwrap_or(false)
+}
+
+fn is_ios_simulator() -> bool {
+  let target = env::var("TARGET").unwrap();
+  target.ends_with("-sim") || target.starts_with("x86_64-apple-ios")
+}
+
+/// Path of the iOS SDK matching the current target, as reported by `xcrun`.
+fn ios_sdk_path() -> Option<String> {
+  let sdk = if is_ios_simulator() { "iphonesimulator" } else { "iphoneos" };
+  let output = std::process::Command::new("xcrun")
+    .args(["--sdk", sdk, "--show-sdk-path"])
+    .output()
+    .ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+/// Locates the Android NDK using the environment variables understood by the NDK tooling.
+fn android_ndk() -> Option<PathBuf> {
+  ["ANDROID_NDK_HOME", "ANDROID_NDK_ROOT", "NDK_HOME"]
+    .iter()
+    .find_map(|key| env::var(key).ok())
+    .map(PathBuf::from)
+}
+
+/// The minimum Android API level to compile for. Vulkan is available from API level 24.
+fn android_api_level() -> String {
+  env::var("ANDROID_PLATFORM")
+    .or_else(|_| env::var("ANDROID_API_LEVEL"))
+    .map(|level| level.trim_start_matches("android-").to_string())
+    .unwrap_or_else(|_| "24".to_string())
+}
+
+fn android_toolchain(ndk: &Path) -> PathBuf {
+  let host = env::var("HOST").unwrap();
+  let host_tag = if host.contains("windows") {
+    "windows-x86_64"
+  } else if host.contains("darwin") {
+    "darwin-x86_64"
+  } else {
+    "linux-x86_64"
+  };
+  ndk.join("toolchains").join("llvm").join("prebuilt").join(host_tag)
+}
+
+/// Points `build` at the NDK's clang for the current Android target, unless the user already
+/// configured a compiler through the usual `CC_<target>`/`CXX_<target>` variables.
+fn configure_android(build: &mut cc::Build, cpp: bool) {
+  if !is_android() {
+    return;
+  }
+
+  let target = env::var("TARGET").unwrap();
+  let compiler_var = if cpp { "CXX" } else { "CC" };
+  println!("cargo:rerun-if-env-changed={}_{}", compiler_var, target);
+  if env::var(format!("{}_{}", compiler_var, target)).is_ok()
+    || env::var(format!("{}_{}", compiler_var, target.replace('-', "_"))).is_ok()
+  {
+    return;
+  }
+
+  let ndk = match android_ndk() {
+    Some(ndk) => ndk,
+    None => return,
+  };
+
+  let bin = android_toolchain(&ndk).join("bin");
+  let clang_target = target.replace("armv7-", "armv7a-");
+  let suffix = if env::var("HOST").unwrap().contains("windows") { ".cmd" } else { "" };
+  let compiler = format!(
+    "{}{}-clang{}{}",
+    clang_target,
+    android_api_level(),
+    if cpp { "++" } else { "" },
+    suffix
+  );
+
+  build.compiler(bin.join(compiler)).archiver(bin.join("llvm-ar"));
+}
+
 fn build_glslang(){
   let mut glslang_build = cc::Build::new();
+  configure_android(&mut glslang_build, true);
   glslang_build
       .cpp(true)
       .std("c++17")
@@ -44,12 +139,88 @@ fn build_glslang(){
   add_subdirectory(&mut glslang_build, "glslang/GenericCodeGen");
   add_subdirectory(&mut glslang_build, "glslang/HLSL");
   add_subdirectory(&mut glslang_build, "glslang/MachineIndependent");
+  add_subdirectory(&mut glslang_build, "glslang/ResourceLimits");
   add_subdirectory(&mut glslang_build, "SPIRV");
 
   glslang_build.compile("glslang");
   println!("cargo:rustc-link-lib=static=glslang");
 }
 
+/// glslang's libraries, in link order. Releases split the compiler into different libraries, so
+/// whichever of them exist are linked.
+const GLSLANG_LIBS: &[&str] = &[
+  "glslang",
+  "SPIRV",
+  "glslang-default-resource-limits",
+  "MachineIndependent",
+  "GenericCodeGen",
+  "OSDependent",
+  "OGLCompiler",
+  "HLSL",
+];
+
+/// Links a glslang installed on the system, with the `system-glslang` feature or when
+/// `GLSLANG_LIB_DIR` is set, instead of compiling the vendored one. Searches `GLSLANG_LIB_DIR`,
+/// the Vulkan SDK and the usual library directories, and returns false if none contains glslang.
+fn link_system_glslang() -> bool {
+  println!("cargo:rerun-if-env-changed=GLSLANG_LIB_DIR");
+  let lib_dir = env::var("GLSLANG_LIB_DIR").ok();
+  if lib_dir.is_none() && env::var("CARGO_FEATURE_SYSTEM_GLSLANG").is_err() {
+    return false;
+  }
+
+  let target_family = env::var("CARGO_CFG_TARGET_FAMILY").unwrap_or_default();
+  let multiarch = format!(
+    "/usr/lib/{}-linux-gnu",
+    env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default()
+  );
+  let mut candidates = lib_dir
+    .into_iter()
+    .map(PathBuf::from)
+    .chain(env::var("VULKAN_SDK").ok().map(|sdk| {
+      Path::new(&sdk).join(if target_family == "windows" { "Lib" } else { "lib" })
+    }))
+    .chain(["/usr/local/lib", multiarch.as_str(), "/usr/lib"].map(PathBuf::from));
+
+  let find = |dir: &Path, name: &str| {
+    let static_names = [format!("lib{name}.a"), format!("{name}.lib")];
+    let shared_names = [
+      format!("lib{name}.so"),
+      format!("lib{name}.dylib"),
+      format!("{name}.dll"),
+    ];
+    if static_names.iter().any(|file| dir.join(file).exists()) {
+      Some("static")
+    } else if shared_names.iter().any(|file| dir.join(file).exists()) {
+      Some("dylib")
+    } else {
+      None
+    }
+  };
+  let dir = match candidates.find(|dir| find(dir, "glslang").is_some()) {
+    Some(dir) => dir,
+    None => {
+      println!("cargo:warning=No system glslang found, building the vendored one.");
+      return false;
+    }
+  };
+
+  println!("cargo:rustc-link-search=native={}", dir.display());
+  let mut any_static = false;
+  for name in GLSLANG_LIBS {
+    if let Some(kind) = find(&dir, name) {
+      any_static |= kind == "static";
+      println!("cargo:rustc-link-lib={kind}={name}");
+    }
+  }
+  // Static glslang is C++ and needs the C++ runtime the vendored build would have linked
+  if any_static && target_family != "windows" {
+    let apple = env::var("CARGO_CFG_TARGET_VENDOR").map_or(false, |vendor| vendor == "apple");
+    println!("cargo:rustc-link-lib={}", if apple { "c++" } else { "stdc++" });
+  }
+  true
+}
+
 fn gen_wrapper<F, const N: usize>(
   file: F,
   defines: &[(&str, &str); N],
@@ -67,10 +238,30 @@ where
 
   let include_dirs: Vec<String> = include_dirs.iter().map(|s| format!("-I{}", s)).collect();
 
+  let mut target_args = Vec::new();
+  if is_android() {
+    target_args.push(format!("--target={}", env::var("TARGET")?));
+    if let Some(ndk) = android_ndk() {
+      let sysroot = android_toolchain(&ndk).join("sysroot");
+      target_args.push(format!("--sysroot={}", sysroot.display()));
+    }
+  } else if is_ios() {
+    let target = env::var("TARGET")?.replace("aarch64-", "arm64-");
+    let target = match target.strip_suffix("-sim") {
+      Some(target) => format!("{}-simulator", target),
+      None => target,
+    };
+    target_args.push(format!("--target={}", target));
+    if let Some(sdk) = ios_sdk_path() {
+      target_args.push(format!("-isysroot{}", sdk));
+    }
+  }
+
   let clang_args = base_args
     .iter()
     .chain(defines.iter())
-    .chain(include_dirs.iter());
+    .chain(include_dirs.iter())
+    .chain(target_args.iter());
 
   println!("{:?}", clang_args);
 
@@ -147,18 +338,25 @@ fn build_vkfft() -> Result<(), Box<dyn Error>>{
 
   println!("cargo:rerun-if-changed=wrapper.c");
   println!("cargo:rerun-if-changed=build.rs");
+  let source = vkfft_source_dir();
+  println!("cargo:rerun-if-changed={}", source);
 
-  let mut include_dirs = vec!["VkFFT/vkFFT/vkFFt".to_string()];
+  let mut include_dirs = vec![source.clone()];
 
   if let Ok(var) = env::var("VULKAN_SDK") {
     include_dirs.push(var.to_string()+"/Include");
   }
 
-  let defines = [("VKFFT_BACKEND", "0"), ("VK_API_VERSION", "11")];
+  let max_dimensions = max_fft_dimensions()?;
+  let defines = [
+    ("VKFFT_BACKEND", "0"),
+    ("VK_API_VERSION", "11"),
+    ("VKFFT_MAX_FFT_DIMENSIONS", max_dimensions.as_str()),
+  ];
 
   let wrapper = process_includes(
     &format!("../vkFFT.h"),
-    "VkFFT/vkFFT/vkFFT",
+    &source,
     "vkFFT"
   )?
   .replace("static inline VkFFTResult VkFFTSync", "VkFFTResult VkFFTSync")
@@ -171,11 +369,32 @@ fn build_vkfft() -> Result<(), Box<dyn Error>>{
   .replace("pfLD double_PI;", "double double_PI;")
   .replace("pfLD d; // long double", "double d; uint64_t alignment[2];// long double replaced with double");
 
+  // The crate exposes double-double precision, which older VkFFT releases lack
+  if !wrapper.contains("quadDoubleDoublePrecision") {
+    return Err("the bundled VkFFT doesn't support double-double precision, update the VkFFT submodule".into());
+  }
+
+  let version = source_version(&wrapper)?;
+  println!("cargo:rustc-env=VKFFT_SOURCE_VERSION={}", version);
+
+  // Mobile targets link their Vulkan implementation, iOS even statically
+  let wrapper = if dynamic_vulkan() {
+    // glibc before 2.34 keeps dlopen and pthread_once in libraries of their own
+    if env::var("CARGO_CFG_TARGET_OS").map(|os| os == "linux").unwrap_or(false) {
+      println!("cargo:rustc-link-lib=dl");
+      println!("cargo:rustc-link-lib=pthread");
+    }
+    vulkan_loader(&wrapper)
+  } else {
+    wrapper
+  };
+
   let rw = out_dir.join("vkfft_rw.h");
   
   std::fs::write(&rw, wrapper.as_str())?;
 
   let mut build = cc::Build::default();
+  configure_android(&mut build, false);
 
   build
     .file("wrapper.c")
@@ -195,17 +414,386 @@ fn build_vkfft() -> Result<(), Box<dyn Error>>{
   build.compile("vkfft");
 
 
-  let bindings_path = Path::new(BINDGEN_FILENAME);
+  // The committed bindings describe the desktop targets, so mobile targets always get their own
+  if env::var("CARGO_FEATURE_PREBUILT_BINDINGS").is_ok() && !is_android() && !is_ios() {
+    if max_dimensions != DEFAULT_MAX_FFT_DIMENSIONS {
+      return Err("the committed bindings have 4 dimensions, disable prebuilt-bindings to raise VKFFT_MAX_FFT_DIMENSIONS".into());
+    }
+    println!("cargo:rerun-if-changed={}", PREBUILT_BINDINGS_VERSION);
+    let bindings_version = std::fs::read_to_string(PREBUILT_BINDINGS_VERSION)?;
+    let bindings_version = bindings_version.trim();
+    if bindings_version != version.to_string() {
+      return Err(format!("the VkFFT sources are version {version}, but the committed bindings are for {bindings_version}, disable prebuilt-bindings or update them with VKFFT_UPDATE_BINDINGS").into());
+    }
+    println!("cargo:rustc-env=VKFFT_BINDINGS_VERSION={}", bindings_version);
+    return Ok(());
+  }
 
-  if !bindings_path.exists() {
+  // Bindings are only regenerated when the processed header, the defines or the target change
+  let key = bindings_key(&wrapper, &defines)?;
+  let key_path = out_dir.join("bindings.key");
+  let bindings_path = out_dir.join("bindings.rs");
+  let cached = std::fs::read_to_string(&key_path).map_or(false, |cached| cached == key);
+  if !cached || !bindings_path.exists() {
     let bindings = gen_wrapper(&rw, &defines, &include_dirs)?;
-    bindings.write_to_file(bindings_path)?;
+    bindings.write_to_file(&bindings_path)?;
+    std::fs::write(&key_path, &key)?;
+  }
+  println!("cargo:rustc-cfg=vkfft_generated_bindings");
+  println!("cargo:rustc-env=VKFFT_BINDINGS_VERSION={}", version);
+
+  // Refreshes the committed fallback used by the `prebuilt-bindings` feature
+  println!("cargo:rerun-if-env-changed=VKFFT_UPDATE_BINDINGS");
+  if env::var("VKFFT_UPDATE_BINDINGS").is_ok() {
+    std::fs::copy(&bindings_path, PREBUILT_BINDINGS)?;
+    std::fs::write(PREBUILT_BINDINGS_VERSION, version.to_string())?;
+  }
+
+  Ok(())
+}
+
+const DEFAULT_MAX_FFT_DIMENSIONS: &str = "4";
+
+/// `VKFFT_MAX_FFT_DIMENSIONS` from the environment variable of the same name. The crate's
+/// defaults fill four axes, so it can only be raised.
+fn max_fft_dimensions() -> Result<String, Box<dyn Error>> {
+  println!("cargo:rerun-if-env-changed=VKFFT_MAX_FFT_DIMENSIONS");
+  let value = match env::var("VKFFT_MAX_FFT_DIMENSIONS") {
+    Ok(value) => value.trim().to_string(),
+    Err(_) => return Ok(DEFAULT_MAX_FFT_DIMENSIONS.to_string()),
+  };
+  match value.parse::<usize>() {
+    Ok(dimensions) if dimensions >= 4 => Ok(dimensions.to_string()),
+    _ => Err(format!("VKFFT_MAX_FFT_DIMENSIONS must be a number of at least 4, got {value}").into()),
+  }
+}
+
+/// Whether VkFFT resolves Vulkan at runtime instead of linking the loader, with the
+/// `dynamic-vulkan` feature. Mobile targets always link it.
+fn dynamic_vulkan() -> bool {
+  env::var("CARGO_FEATURE_DYNAMIC_VULKAN").is_ok() && !is_android() && !is_ios()
+}
+
+/// `wrapper` with the Vulkan commands VkFFT calls routed through function pointers looked up in
+/// the loader library, once, by the first call to `initializeVkFFT` or `deleteVkFFT`, instead of
+/// linking the loader. Without the library both return early, `initializeVkFFT` with an error.
+fn vulkan_loader(wrapper: &str) -> String {
+  let re = Regex::new(r"\b(vk[A-Z]\w*)\s*\(").unwrap();
+  let mut commands: Vec<&str> = re
+    .captures_iter(wrapper)
+    .map(|capture| capture.get(1).unwrap().as_str())
+    .collect();
+  commands.sort_unstable();
+  commands.dedup();
+
+  let fields: String = commands
+    .iter()
+    .map(|command| format!("  PFN_{command} {command};\n"))
+    .collect();
+  let lookups: String = commands
+    .iter()
+    .map(|command| {
+      format!(
+        "  vkfftVulkan.{command} = (PFN_{command})vkfftVulkanSymbol(library, \"{command}\");\n  if (!vkfftVulkan.{command}) return;\n"
+      )
+    })
+    .collect();
+  let redirects: String = commands
+    .iter()
+    .map(|command| format!("#define {command} (vkfftVulkan.{command})\n"))
+    .collect();
+  let wrapper = wrapper
+    .replace("VkFFTResult initializeVkFFT(", "static VkFFTResult vkfftInitializeLoaded(")
+    .replace("void deleteVkFFT(", "static void vkfftDeleteLoaded(");
+
+  format!(
+    r#"#define VK_NO_PROTOTYPES
+#include <vulkan/vulkan.h>
+#if defined(_WIN32)
+#include <windows.h>
+#define vkfftVulkanSymbol(library, name) ((void*)GetProcAddress((HMODULE)(library), name))
+#else
+#include <dlfcn.h>
+#include <pthread.h>
+#define vkfftVulkanSymbol(library, name) dlsym(library, name)
+#endif
+
+typedef struct {{
+{fields}}} VkFFTVulkanFunctions;
+
+static VkFFTVulkanFunctions vkfftVulkan;
+static VkResult vkfftVulkanStatus = VK_ERROR_INITIALIZATION_FAILED;
+
+static void* vkfftVulkanLibrary(void) {{
+#if defined(_WIN32)
+  return (void*)LoadLibraryA("vulkan-1.dll");
+#elif defined(__APPLE__)
+  void* library = dlopen("libvulkan.1.dylib", RTLD_NOW | RTLD_LOCAL);
+  return library ? library : dlopen("libMoltenVK.dylib", RTLD_NOW | RTLD_LOCAL);
+#else
+  void* library = dlopen("libvulkan.so.1", RTLD_NOW | RTLD_LOCAL);
+  return library ? library : dlopen("libvulkan.so", RTLD_NOW | RTLD_LOCAL);
+#endif
+}}
+
+static void vkfftLoadVulkan(void) {{
+  void* library = vkfftVulkanLibrary();
+  if (!library) return;
+{lookups}  vkfftVulkanStatus = VK_SUCCESS;
+}}
+
+#if defined(_WIN32)
+static INIT_ONCE vkfftVulkanOnce = INIT_ONCE_STATIC_INIT;
+
+static BOOL CALLBACK vkfftLoadVulkanOnce(PINIT_ONCE once, PVOID parameter, PVOID* context) {{
+  vkfftLoadVulkan();
+  return TRUE;
+}}
+#else
+static pthread_once_t vkfftVulkanOnce = PTHREAD_ONCE_INIT;
+#endif
+
+// Loads the commands on the first call, from whichever thread makes it, and returns
+// VK_ERROR_INITIALIZATION_FAILED if the library or any command is missing
+static VkResult vkfftVulkanFunctions(void) {{
+#if defined(_WIN32)
+  InitOnceExecuteOnce(&vkfftVulkanOnce, vkfftLoadVulkanOnce, NULL, NULL);
+#else
+  pthread_once(&vkfftVulkanOnce, vkfftLoadVulkan);
+#endif
+  return vkfftVulkanStatus;
+}}
+
+{redirects}
+{wrapper}
+
+VkFFTResult initializeVkFFT(VkFFTApplication* app, VkFFTConfiguration inputLaunchConfiguration) {{
+  if (vkfftVulkanFunctions() != VK_SUCCESS) return VKFFT_ERROR_FAILED_TO_INITIALIZE;
+  return vkfftInitializeLoaded(app, inputLaunchConfiguration);
+}}
+
+// An application can only exist once the commands were loaded
+void deleteVkFFT(VkFFTApplication* app) {{
+  if (vkfftVulkanFunctions() == VK_SUCCESS) vkfftDeleteLoaded(app);
+}}
+"#
+  )
+}
+
+/// Directory of VkFFT's headers: `vkFFT/vkFFT` in `VKFFT_SOURCE_DIR`, a VkFFT checkout, if set,
+/// otherwise in the bundled submodule.
+fn vkfft_source_dir() -> String {
+  println!("cargo:rerun-if-env-changed=VKFFT_SOURCE_DIR");
+  let root = env::var("VKFFT_SOURCE_DIR").unwrap_or_else(|_| "VkFFT".to_string());
+  Path::new(&root).join("vkFFT").join("vkFFT").display().to_string()
+}
+
+/// The version `VkFFTGetVersion` of `wrapper` returns, as `major * 10000 + minor * 100 + patch`.
+fn source_version(wrapper: &str) -> Result<u32, Box<dyn Error>> {
+  let re = Regex::new(r"VkFFTGetVersion\(\)\s*\{\s*return\s+(\d+)").unwrap();
+  let captures = re
+    .captures(wrapper)
+    .ok_or("couldn't find the version of the VkFFT sources")?;
+  Ok(captures[1].parse()?)
+}
+
+/// Identifies the bindings of `wrapper` built with `defines` for the current target.
+fn bindings_key(wrapper: &str, defines: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+  let mut hasher = DefaultHasher::new();
+  wrapper.hash(&mut hasher);
+  defines.hash(&mut hasher);
+  env::var("TARGET")?.hash(&mut hasher);
+  Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// A VkFFT backend built next to the Vulkan one. Its configuration has the same name but a
+/// different layout, so it is compiled in a translation unit of its own, exporting the entry
+/// points with `suffix`, and gets bindings of its own in `vkfft_sys::<name>`.
+struct Backend {
+  name: &'static str,
+  /// Value of `VKFFT_BACKEND`
+  id: &'static str,
+  suffix: &'static str,
+  include_dirs: Vec<String>,
+  lib_dirs: Vec<PathBuf>,
+  libs: &'static [&'static str],
+  /// Functions of the backend's API bound along with VkFFT's
+  functions: &'static [&'static str],
+}
+
+/// The CUDA toolkit, from `CUDA_PATH` or `CUDA_HOME`, or its default location.
+fn cuda_backend() -> Result<Backend, Box<dyn Error>> {
+  println!("cargo:rerun-if-env-changed=CUDA_PATH");
+  println!("cargo:rerun-if-env-changed=CUDA_HOME");
+  let cuda = env::var("CUDA_PATH")
+    .or_else(|_| env::var("CUDA_HOME"))
+    .map(PathBuf::from)
+    .unwrap_or_else(|_| PathBuf::from("/usr/local/cuda"));
+  let lib_dir = if env::var("CARGO_CFG_TARGET_FAMILY")? == "windows" {
+    cuda.join("lib").join("x64")
+  } else {
+    cuda.join("lib64")
+  };
+  Ok(Backend {
+    name: "cuda",
+    id: "1",
+    suffix: "Cuda",
+    include_dirs: vec![cuda.join("include").display().to_string()],
+    lib_dirs: vec![lib_dir],
+    libs: &["cuda", "cudart", "nvrtc"],
+    functions: &[
+      "cuInit",
+      "cuDeviceGet",
+      "cuDevicePrimaryCtxRetain",
+      "cuCtxSetCurrent",
+      "cuStreamCreate",
+      "cuStreamSynchronize",
+      // cuda.h renames these to their current versions
+      "cuStreamDestroy_v2",
+      "cuDevicePrimaryCtxRelease_v2",
+    ],
+  })
+}
+
+/// The oneAPI Level Zero loader, from `LEVEL_ZERO_PATH`, or the system's paths. VkFFT includes
+/// `ze_api.h` without its `level_zero` directory.
+fn level_zero_backend() -> Backend {
+  println!("cargo:rerun-if-env-changed=LEVEL_ZERO_PATH");
+  let root = env::var("LEVEL_ZERO_PATH")
+    .map(PathBuf::from)
+    .unwrap_or_else(|_| PathBuf::from("/usr"));
+  Backend {
+    name: "level_zero",
+    id: "4",
+    suffix: "LevelZero",
+    include_dirs: vec![root.join("include").join("level_zero").display().to_string()],
+    lib_dirs: vec![root.join("lib")],
+    libs: &["ze_loader"],
+    functions: &["zeCommandListHostSynchronize"],
+  }
+}
+
+fn build_vkfft_backend(backend: Backend) -> Result<(), Box<dyn Error>> {
+  let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+  let source = vkfft_source_dir();
+  let include_dirs: Vec<String> = Some(source.clone())
+    .into_iter()
+    .chain(backend.include_dirs.iter().cloned())
+    .collect();
+  let max_dimensions = max_fft_dimensions()?;
+  let defines = [
+    ("VKFFT_BACKEND", backend.id),
+    ("VKFFT_MAX_FFT_DIMENSIONS", max_dimensions.as_str()),
+  ];
+
+  let wrapper_c = format!("wrapper_{}.c", backend.name);
+  println!("cargo:rerun-if-changed={}", wrapper_c);
+
+  let wrapper = process_includes(&format!("../vkFFT.h"), &source, "vkFFT")?
+    .replace("pfLD double_PI;", "double double_PI;")
+    .replace("pfLD d; // long double", "double d; uint64_t alignment[2];// long double replaced with double")
+    + &format!(
+      "
+VkFFTResult initializeVkFFT{0}(VkFFTApplication* app, VkFFTConfiguration inputLaunchConfiguration);
+VkFFTResult VkFFTAppend{0}(VkFFTApplication* app, int inverse, VkFFTLaunchParams* launchParams);
+void deleteVkFFT{0}(VkFFTApplication* app);
+",
+      backend.suffix
+    );
+  let rw = out_dir.join(format!("vkfft_{}_rw.h", backend.name));
+  std::fs::write(&rw, wrapper.as_str())?;
+
+  let mut build = cc::Build::default();
+  build
+    .file(&wrapper_c)
+    .warnings(false)
+    .include(out_dir.clone());
+  for (key, value) in defines.iter() {
+    build.define(*key, Some(*value));
+  }
+  for include_dir in include_dirs.iter() {
+    build.include(include_dir);
   }
+  build.compile(&format!("vkfft_{}", backend.name));
 
+  let clang_args = defines
+    .iter()
+    .map(|(k, v)| format!("-D{}={}", k, v))
+    .chain(include_dirs.iter().map(|s| format!("-I{}", s)));
+  let bindings = backend
+    .functions
+    .iter()
+    .fold(bindgen::Builder::default(), |builder, function| {
+      builder.allowlist_function(function)
+    })
+    .clang_args(clang_args)
+    .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+    .header(rw.to_str().unwrap())
+    .allowlist_recursively(true)
+    .allowlist_type("VkFFTConfiguration")
+    .allowlist_type("VkFFTLaunchParams")
+    .allowlist_type("VkFFTResult")
+    .allowlist_type("VkFFTApplication")
+    .allowlist_function(format!("initializeVkFFT{}", backend.suffix))
+    .allowlist_function(format!("VkFFTAppend{}", backend.suffix))
+    .allowlist_function(format!("deleteVkFFT{}", backend.suffix))
+    .generate()
+    .map_err(|_| format!("failed to generate the {} bindings", backend.name))?;
+  bindings.write_to_file(out_dir.join(format!("{}_bindings.rs", backend.name)))?;
+
+  for lib_dir in &backend.lib_dirs {
+    println!("cargo:rustc-link-search=native={}", lib_dir.display());
+  }
+  for lib in backend.libs {
+    println!("cargo:rustc-link-lib={lib}");
+  }
   Ok(())
 }
 
+/// iOS has no Vulkan loader, so MoltenVK is linked statically together with the system
+/// frameworks it depends on. The library is taken from `MOLTENVK_LIB_DIR` if set, otherwise from
+/// the MoltenVK xcframework shipped with the Vulkan SDK.
+fn link_moltenvk() {
+  println!("cargo:rerun-if-env-changed=MOLTENVK_LIB_DIR");
+  println!("cargo:rerun-if-env-changed=VULKAN_SDK");
+
+  if let Ok(dir) = env::var("MOLTENVK_LIB_DIR") {
+    println!("cargo:rustc-link-search=native={dir}");
+  } else if let Ok(sdk) = env::var("VULKAN_SDK") {
+    let slice = if is_ios_simulator() {
+      "ios-arm64_x86_64-simulator"
+    } else {
+      "ios-arm64"
+    };
+    let dir = Path::new(&sdk)
+      .join("..")
+      .join("iOS")
+      .join("lib")
+      .join("MoltenVK.xcframework")
+      .join(slice);
+    println!("cargo:rustc-link-search=native={}", dir.display());
+  }
+
+  println!("cargo:rustc-link-lib=static=MoltenVK");
+  println!("cargo:rustc-link-lib=c++");
+  for framework in [
+    "Metal",
+    "Foundation",
+    "QuartzCore",
+    "CoreGraphics",
+    "IOSurface",
+    "UIKit",
+  ] {
+    println!("cargo:rustc-link-lib=framework={framework}");
+  }
+}
+
 fn link_vulkan(){
+  if is_ios() {
+    link_moltenvk();
+    return;
+  }
+
   //logic copied from ash-rs
   let target_family = env::var("CARGO_CFG_TARGET_FAMILY").unwrap();
   let target_pointer_width = env::var("CARGO_CFG_TARGET_POINTER_WIDTH").unwrap();
@@ -227,12 +815,29 @@ fn link_vulkan(){
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+  println!("cargo:rustc-check-cfg=cfg(vkfft_generated_bindings)");
+  println!("cargo:rerun-if-env-changed=ANDROID_NDK_HOME");
+  println!("cargo:rerun-if-env-changed=ANDROID_NDK_ROOT");
+  println!("cargo:rerun-if-env-changed=NDK_HOME");
+  println!("cargo:rerun-if-env-changed=ANDROID_PLATFORM");
+  println!("cargo:rerun-if-env-changed=ANDROID_API_LEVEL");
+  println!("cargo:rustc-env=VKFFT_MAX_FFT_DIMENSIONS={}", max_fft_dimensions()?);
   if env::var("DOCS_RS").is_ok() {
       println!("cargo:warning=Skipping glslang native build for docs.rs.");
       return Ok(());
   }
-  build_glslang();
+  if !link_system_glslang() {
+    build_glslang();
+  }
   build_vkfft()?;
-  link_vulkan();
+  if !dynamic_vulkan() {
+    link_vulkan();
+  }
+  if env::var("CARGO_FEATURE_CUDA").is_ok() {
+    build_vkfft_backend(cuda_backend()?)?;
+  }
+  if env::var("CARGO_FEATURE_LEVEL_ZERO").is_ok() {
+    build_vkfft_backend(level_zero_backend())?;
+  }
   Ok(())
 }
@@ -0,0 +1,129 @@
+//! Declarations for the subset of glslang's C interface (`glslang_c_interface.h`) needed to
+//! compile auxiliary compute shaders with the same glslang build VkFFT links against.
+
+use std::os::raw::{c_char, c_int, c_void};
+
+pub type glslang_stage_t = ::std::os::raw::c_uint;
+pub const glslang_stage_t_GLSLANG_STAGE_COMPUTE: glslang_stage_t = 5;
+
+pub type glslang_source_t = ::std::os::raw::c_uint;
+pub const glslang_source_t_GLSLANG_SOURCE_GLSL: glslang_source_t = 1;
+
+pub type glslang_client_t = ::std::os::raw::c_uint;
+pub const glslang_client_t_GLSLANG_CLIENT_VULKAN: glslang_client_t = 1;
+
+pub type glslang_target_client_version_t = ::std::os::raw::c_uint;
+pub const glslang_target_client_version_t_GLSLANG_TARGET_VULKAN_1_0:
+  glslang_target_client_version_t = 1 << 22;
+
+pub type glslang_target_language_t = ::std::os::raw::c_uint;
+pub const glslang_target_language_t_GLSLANG_TARGET_SPV: glslang_target_language_t = 1;
+
+pub type glslang_target_language_version_t = ::std::os::raw::c_uint;
+pub const glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_0:
+  glslang_target_language_version_t = 1 << 16;
+
+pub type glslang_profile_t = ::std::os::raw::c_uint;
+pub const glslang_profile_t_GLSLANG_NO_PROFILE: glslang_profile_t = 1 << 0;
+
+pub type glslang_messages_t = ::std::os::raw::c_uint;
+pub const glslang_messages_t_GLSLANG_MSG_DEFAULT_BIT: glslang_messages_t = 0;
+pub const glslang_messages_t_GLSLANG_MSG_SPV_RULES_BIT: glslang_messages_t = 1 << 3;
+pub const glslang_messages_t_GLSLANG_MSG_VULKAN_RULES_BIT: glslang_messages_t = 1 << 4;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct glslang_shader_s {
+  _unused: [u8; 0],
+}
+pub type glslang_shader_t = glslang_shader_s;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct glslang_program_s {
+  _unused: [u8; 0],
+}
+pub type glslang_program_t = glslang_program_s;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct glslang_resource_s {
+  _unused: [u8; 0],
+}
+pub type glslang_resource_t = glslang_resource_s;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct glsl_include_result_t {
+  pub header_name: *const c_char,
+  pub header_data: *const c_char,
+  pub header_length: usize,
+}
+
+pub type glsl_include_local_func = ::std::option::Option<
+  unsafe extern "C" fn(
+    ctx: *mut c_void,
+    header_name: *const c_char,
+    includer_name: *const c_char,
+    include_depth: usize,
+  ) -> *mut glsl_include_result_t,
+>;
+pub type glsl_include_system_func = glsl_include_local_func;
+pub type glsl_free_include_result_func = ::std::option::Option<
+  unsafe extern "C" fn(ctx: *mut c_void, result: *mut glsl_include_result_t) -> c_int,
+>;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct glsl_include_callbacks_t {
+  pub include_system: glsl_include_system_func,
+  pub include_local: glsl_include_local_func,
+  pub free_include_result: glsl_free_include_result_func,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct glslang_input_t {
+  pub language: glslang_source_t,
+  pub stage: glslang_stage_t,
+  pub client: glslang_client_t,
+  pub client_version: glslang_target_client_version_t,
+  pub target_language: glslang_target_language_t,
+  pub target_language_version: glslang_target_language_version_t,
+  pub code: *const c_char,
+  pub default_version: c_int,
+  pub default_profile: glslang_profile_t,
+  pub force_default_version_and_profile: c_int,
+  pub forward_compatible: c_int,
+  pub messages: glslang_messages_t,
+  pub resource: *const glslang_resource_t,
+  pub callbacks: glsl_include_callbacks_t,
+  pub callbacks_ctx: *mut c_void,
+}
+
+extern "C" {
+  pub fn glslang_initialize_process() -> c_int;
+  pub fn glslang_finalize_process();
+  pub fn glslang_default_resource() -> *const glslang_resource_t;
+
+  pub fn glslang_shader_create(input: *const glslang_input_t) -> *mut glslang_shader_t;
+  pub fn glslang_shader_delete(shader: *mut glslang_shader_t);
+  pub fn glslang_shader_preprocess(
+    shader: *mut glslang_shader_t,
+    input: *const glslang_input_t,
+  ) -> c_int;
+  pub fn glslang_shader_parse(
+    shader: *mut glslang_shader_t,
+    input: *const glslang_input_t,
+  ) -> c_int;
+  pub fn glslang_shader_get_info_log(shader: *mut glslang_shader_t) -> *const c_char;
+
+  pub fn glslang_program_create() -> *mut glslang_program_t;
+  pub fn glslang_program_delete(program: *mut glslang_program_t);
+  pub fn glslang_program_add_shader(program: *mut glslang_program_t, shader: *mut glslang_shader_t);
+  pub fn glslang_program_link(program: *mut glslang_program_t, messages: c_int) -> c_int;
+  pub fn glslang_program_get_info_log(program: *mut glslang_program_t) -> *const c_char;
+  pub fn glslang_program_SPIRV_generate(program: *mut glslang_program_t, stage: glslang_stage_t);
+  pub fn glslang_program_SPIRV_get_size(program: *mut glslang_program_t) -> usize;
+  pub fn glslang_program_SPIRV_get(program: *mut glslang_program_t, out: *mut u32);
+}
@@ -2,5 +2,57 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+// Generated by the build script from the bundled VkFFT, unless the `prebuilt-bindings` feature
+// selects the committed ones
+#[cfg(not(vkfft_generated_bindings))]
 include!("bindings.rs");
 
+#[cfg(vkfft_generated_bindings)]
+include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+/// `VKFFT_MAX_FFT_DIMENSIONS` VkFFT is built with, which sizes the per-axis arrays of its
+/// configuration. Set at build time by the environment variable of the same name, 4 by default.
+pub const MAX_FFT_DIMENSIONS: usize = parse_usize(env!("VKFFT_MAX_FFT_DIMENSIONS"));
+
+const fn parse_usize(s: &str) -> usize {
+  let bytes = s.as_bytes();
+  let mut value = 0;
+  let mut i = 0;
+  while i < bytes.len() {
+    value = value * 10 + (bytes[i] - b'0') as usize;
+    i += 1;
+  }
+  value
+}
+
+/// The version of the VkFFT sources the library was compiled from, as
+/// `major * 10000 + minor * 100 + patch`. Unset when the native build is skipped.
+pub const SOURCE_VERSION: Option<&str> = option_env!("VKFFT_SOURCE_VERSION");
+
+/// The version of the VkFFT sources the bindings were generated from, in the same format. Unset
+/// for committed bindings whose version wasn't recorded.
+pub const BINDINGS_VERSION: Option<&str> = option_env!("VKFFT_BINDINGS_VERSION");
+
+pub mod glslang;
+
+/// Bindings of VkFFT's CUDA backend, whose configuration and launch parameters take CUDA
+/// devices, streams and device pointers instead of Vulkan handles.
+#[cfg(feature = "cuda")]
+pub mod cuda {
+  #![allow(non_upper_case_globals)]
+  #![allow(non_camel_case_types)]
+  #![allow(non_snake_case)]
+
+  include!(concat!(env!("OUT_DIR"), "/cuda_bindings.rs"));
+}
+
+/// Bindings of VkFFT's Level Zero backend, whose configuration and launch parameters take Level
+/// Zero devices, contexts, command queues and lists instead of Vulkan handles.
+#[cfg(feature = "level-zero")]
+pub mod level_zero {
+  #![allow(non_upper_case_globals)]
+  #![allow(non_camel_case_types)]
+  #![allow(non_snake_case)]
+
+  include!(concat!(env!("OUT_DIR"), "/level_zero_bindings.rs"));
+}
@@ -0,0 +1,13 @@
+#include <vkfft_cuda_rw.h>
+
+VkFFTResult initializeVkFFTCuda(VkFFTApplication* app, VkFFTConfiguration inputLaunchConfiguration) {
+  return initializeVkFFT(app, inputLaunchConfiguration);
+}
+
+VkFFTResult VkFFTAppendCuda(VkFFTApplication* app, int inverse, VkFFTLaunchParams* launchParams) {
+  return VkFFTAppend(app, inverse, launchParams);
+}
+
+void deleteVkFFTCuda(VkFFTApplication* app) {
+  deleteVkFFT(app);
+}
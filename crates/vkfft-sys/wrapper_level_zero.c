@@ -0,0 +1,13 @@
+#include <vkfft_level_zero_rw.h>
+
+VkFFTResult initializeVkFFTLevelZero(VkFFTApplication* app, VkFFTConfiguration inputLaunchConfiguration) {
+  return initializeVkFFT(app, inputLaunchConfiguration);
+}
+
+VkFFTResult VkFFTAppendLevelZero(VkFFTApplication* app, int inverse, VkFFTLaunchParams* launchParams) {
+  return VkFFTAppend(app, inverse, launchParams);
+}
+
+void deleteVkFFTLevelZero(VkFFTApplication* app) {
+  deleteVkFFT(app);
+}
@@ -0,0 +1,57 @@
+//! Transforms a signal in half precision and back, and reports how far the round trip drifted
+//! from the original. Half precision halves the memory traffic of a transform, at the cost of
+//! about three significant digits.
+
+use std::error::Error;
+
+use vkfft::config::{Config, Precision};
+use vkfft::context::{Context, FftType};
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let context = Context::new_standalone()?;
+  if let Some(feature) = Precision::Half.missing_feature(&context.physical) {
+    println!("Half precision transforms need {}, skipping", feature);
+    return Ok(());
+  }
+
+  // 1024 complex values of two tones
+  let size = 1024u32;
+  let signal: Vec<f32> = (0..2 * size as usize)
+    .map(|i| {
+      let x = (i / 2) as f32 * std::f32::consts::TAU / size as f32;
+      if i % 2 == 0 {
+        (3.0 * x).cos() + 0.5 * (40.0 * x).cos()
+      } else {
+        (3.0 * x).sin()
+      }
+    })
+    .collect();
+  let buffer = context.new_f16_buffer_from_f32_iter(signal.iter().copied())?;
+
+  let config_builder = Config::builder()
+    .dim(&[size])
+    .buffer(buffer.clone())
+    .precision(Precision::Half)
+    .normalize();
+  context.single_fft(config_builder.clone(), FftType::Forward)?;
+
+  let spectrum = context.read_f16_as_f32(&buffer)?;
+  let peak = spectrum
+    .chunks_exact(2)
+    .map(|c| c[0].hypot(c[1]))
+    .enumerate()
+    .fold(
+      (0, 0.0f32),
+      |max, (i, m)| if m > max.1 { (i, m) } else { max },
+    );
+  println!("Strongest bin: {} with magnitude {}", peak.0, peak.1);
+
+  context.single_fft(config_builder, FftType::Inverse)?;
+  let round_trip = context.read_f16_as_f32(&buffer)?;
+  let max_error = signal
+    .iter()
+    .zip(&round_trip)
+    .fold(0.0f32, |max, (a, b)| max.max((a - b).abs()));
+  println!("Largest round trip error: {:e}", max_error);
+  Ok(())
+}
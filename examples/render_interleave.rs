@@ -0,0 +1,102 @@
+//! Runs a transform every frame on the queue of an existing "renderer".
+//!
+//! The renderer owns the device and queue; the transforms go through a context created on the
+//! same queue with `Context::from_queue`. Both submit through vulkano, which serializes access to
+//! the queue, so frames and transforms interleave without further synchronization. Here the
+//! renderer only clears a buffer, standing in for drawing a frame from the last spectrum.
+
+use std::{error::Error, sync::Arc};
+
+use vkfft::{
+  config::Config,
+  context::{instance_create_info, Context, FftType},
+};
+use vulkano::{
+  command_buffer::{
+    allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+    PrimaryCommandBufferAbstract,
+  },
+  device::{Device, DeviceCreateInfo, QueueCreateInfo, QueueFlags},
+  instance::Instance,
+  sync::GpuFuture,
+  VulkanLibrary,
+};
+
+const LEN: u32 = 1024;
+const FRAMES: u32 = 8;
+
+fn main() -> Result<(), Box<dyn Error>> {
+  // The renderer's setup: a device with a graphics queue
+  let library = VulkanLibrary::new()?;
+  let instance = Instance::new(library.clone(), instance_create_info(&library))?;
+  let physical = instance
+    .enumerate_physical_devices()?
+    .next()
+    .ok_or("No device available")?;
+  let queue_family_index = physical
+    .queue_family_properties()
+    .iter()
+    .position(|q| {
+      q.queue_flags
+        .contains(QueueFlags::GRAPHICS | QueueFlags::COMPUTE)
+    })
+    .ok_or("No graphics queue with compute support")? as u32;
+  let (device, mut queues) = Device::new(
+    physical,
+    DeviceCreateInfo {
+      queue_create_infos: vec![QueueCreateInfo {
+        queue_family_index,
+        ..Default::default()
+      }],
+      ..Default::default()
+    },
+  )?;
+  let queue = queues.next().unwrap();
+  let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
+    device.clone(),
+    Default::default(),
+  ));
+
+  // Transforms share the renderer's queue
+  let context = Context::from_queue(&instance, queue.clone())?;
+  let signal = context.new_buffer_from_iter((0..LEN).map(|_| [0f32; 2]))?;
+  let frame = context.new_buffer_from_iter((0..LEN).map(|_| 0u32))?;
+  let mut fft = context.fft_stage(
+    Config::builder().buffer(signal.clone()).dim(&[LEN]),
+    FftType::Forward,
+  )?;
+
+  for i in 0..FRAMES {
+    // A tone whose frequency changes every frame
+    let k = (i + 1) as f32 * 8.0;
+    for (n, value) in signal.write()?.iter_mut().enumerate() {
+      let phase = 2.0 * std::f32::consts::PI * k * n as f32 / LEN as f32;
+      *value = [phase.cos(), phase.sin()];
+    }
+    context.run(&mut fft)?;
+
+    let peak = signal
+      .read()?
+      .iter()
+      .enumerate()
+      .max_by(|(_, a), (_, b)| a[0].hypot(a[1]).total_cmp(&b[0].hypot(b[1])))
+      .map(|(bin, _)| bin)
+      .unwrap_or(0);
+
+    // The renderer's frame, submitted to the same queue
+    let mut builder = AutoCommandBufferBuilder::primary(
+      command_buffer_allocator.clone(),
+      queue_family_index,
+      CommandBufferUsage::OneTimeSubmit,
+    )?;
+    builder.fill_buffer(frame.clone(), peak as u32)?;
+    builder
+      .build()?
+      .execute(queue.clone())?
+      .then_signal_fence_and_flush()?
+      .wait(None)?;
+
+    println!("frame {}: peak at bin {}", i, peak);
+  }
+  Ok(())
+}
@@ -1,43 +1,25 @@
 use std::error::Error;
-use vkfft::config::Config;
+use vkfft::config::{Config, R2RKind};
 use vkfft::context::{Context, FftType};
 use vulkano::buffer::subbuffer::Subbuffer;
-use vulkano::instance::{Instance, InstanceCreateFlags, InstanceCreateInfo, InstanceExtensions};
 
 fn main() -> Result<(), Box<dyn Error>> {
   println!("VkFFT version: {}", vkfft::version());
 
-  // These first steps will always take place in a Vulkan program; first the library has to be loaded, then the instance created
-  let library = vulkano::VulkanLibrary::new().expect("no local Vulkan library/DLL");
-
-  // The instance created here has the ENUMERATE_PORTABILITY flag enabled, as well as two Instance extensions. These
-  // are required for the instance to be used on MacOS via the MoltenVK compatibility layer.
-  // This means that Vulkan will accept non-fully-conformant targets; if it is using the wrong hardware on your target
-  // system, removing them might help.
-  let instance = Instance::new(
-    library,
-    InstanceCreateInfo {
-      flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
-      enabled_extensions: InstanceExtensions {
-        khr_get_physical_device_properties2: true,
-        khr_portability_enumeration: true,
-        ..Default::default()
-      },
-      ..Default::default()
-    },
-  )
-  .expect("failed to create instance");
-
   ///The Context struct is provided to contain a number of required elements for the Vulkan instance
-  ///to be used with VkFFT. The new() function creates one with reasonable defaults. However, it is not
-  ///required to use this struct: one can independently create the required elements, e.g. if integrating
-  ///VkFFT in a Vulkano toolchain.
-  let context = Context::new(&instance)?;
+  ///to be used with VkFFT. The new_standalone() function loads Vulkan, creates an instance that also
+  ///works with MoltenVK on macOS, and picks a device with reasonable defaults. If the application
+  ///already uses Vulkan, Context::new() takes an existing instance instead. It is not required to use
+  ///this struct: one can independently create the required elements, e.g. if integrating VkFFT in a
+  ///Vulkano toolchain.
+  let context = Context::new_standalone()?;
 
   //Example ffts:
   complex_to_complex_1d(&context)?;
   real_to_complex_2d(&context)?;
   convolution(&context)?;
+  complex_to_complex_4d(&context)?;
+  real_to_real(&context)?;
   Ok(())
 }
 
@@ -285,6 +267,121 @@ fn convolution(context: &Context) -> Result<(), Box<dyn Error>> {
   Ok(())
 }
 
+/// # 4D complex-to-complex FFT
+///
+/// Transforms of up to four axes, as used for lattice or spacetime data, are planned the same
+/// way, with a four-element size. A 4D plane wave localizes to a single point of the 4D grid,
+/// which we look up by its linear index, x varying fastest.
+fn complex_to_complex_4d(context: &Context) -> Result<(), Box<dyn Error>> {
+  let size = [4u32, 4, 4, 6];
+  let k = [1usize, 2, 3, 5];
+  println!("================================================================================");
+  println!("Performing 4D complex-to-complex FFT.\nThe plane wave should localize to position {k:?}\n");
+
+  let points = size.iter().product::<u32>() as usize;
+  let mut data = Vec::with_capacity(2 * points);
+  for i in 0..points {
+    let mut rest = i;
+    let mut phase = 0.0;
+    for axis in 0..4 {
+      let len = size[axis] as usize;
+      phase += std::f32::consts::TAU * (k[axis] * (rest % len)) as f32 / len as f32;
+      rest /= len;
+    }
+    data.extend_from_slice(&[phase.cos(), phase.sin()]);
+  }
+
+  let spectrum = context.transform(Config::builder().dim(&size), &data, FftType::Forward)?;
+  let peak = spectrum
+    .chunks(2)
+    .enumerate()
+    .max_by(|(_, a), (_, b)| a[0].hypot(a[1]).total_cmp(&b[0].hypot(b[1])))
+    .map(|(i, _)| i)
+    .unwrap_or(0);
+  let expected = k[0] + 4 * (k[1] + 4 * (k[2] + 4 * k[3]));
+  println!("Peak at linear index {peak}, expected {expected}");
+  assert_eq!(peak, expected);
+  Ok(())
+}
+
+/// # Real-to-real transforms
+///
+/// Every DCT and DST type is compared with a direct evaluation of its definition, using FFTW's
+/// conventions. Both sides are scaled to unit peak first, so only the shape of the result is
+/// checked, not VkFFT's choice of normalization.
+fn real_to_real(context: &Context) -> Result<(), Box<dyn Error>> {
+  let n = 16usize;
+  println!("================================================================================");
+  println!("Performing {n}-point DCTs and DSTs of every type.\n");
+
+  let data = (0..n)
+    .map(|j| ((j * 7 % n) as f32 - 7.5) / 8.0)
+    .collect::<Vec<_>>();
+  let kinds = [
+    R2RKind::Dct1,
+    R2RKind::Dct2,
+    R2RKind::Dct3,
+    R2RKind::Dct4,
+    R2RKind::Dst1,
+    R2RKind::Dst2,
+    R2RKind::Dst3,
+    R2RKind::Dst4,
+  ];
+  for kind in kinds {
+    let output = context.transform(
+      Config::builder().dim(&[n as u32]).r2r(kind),
+      &data,
+      FftType::Forward,
+    )?;
+    let expected = r2r_reference(kind, &data);
+    let error = max_error(&unit_peak(&output), &unit_peak(&expected));
+    println!("{kind:?}: max error {error:.2e}");
+    assert!(error < 1e-4, "{:?} differs from its definition", kind);
+  }
+  Ok(())
+}
+
+/// Evaluates FFTW's unnormalized definition of `kind` directly.
+fn r2r_reference(kind: R2RKind, x: &[f32]) -> Vec<f32> {
+  use std::f64::consts::PI;
+  let n = x.len();
+  let nf = n as f64;
+  let x = x.iter().map(|v| *v as f64).collect::<Vec<_>>();
+  let sign = |k: usize| (-1.0f64).powi(k as i32);
+  (0..n)
+    .map(|k| {
+      let kf = k as f64;
+      let sum = |term: &dyn Fn(f64) -> f64, range: std::ops::Range<usize>| {
+        range.map(|j| 2.0 * x[j] * term(j as f64)).sum::<f64>()
+      };
+      let y = match kind {
+        R2RKind::Dct1 => {
+          x[0] + sign(k) * x[n - 1] + sum(&|j| (PI * j * kf / (nf - 1.0)).cos(), 1..n - 1)
+        }
+        R2RKind::Dct2 => sum(&|j| (PI * (j + 0.5) * kf / nf).cos(), 0..n),
+        R2RKind::Dct3 => x[0] + sum(&|j| (PI * j * (kf + 0.5) / nf).cos(), 1..n),
+        R2RKind::Dct4 => sum(&|j| (PI * (j + 0.5) * (kf + 0.5) / nf).cos(), 0..n),
+        R2RKind::Dst1 => sum(&|j| (PI * (j + 1.0) * (kf + 1.0) / (nf + 1.0)).sin(), 0..n),
+        R2RKind::Dst2 => sum(&|j| (PI * (j + 0.5) * (kf + 1.0) / nf).sin(), 0..n),
+        R2RKind::Dst3 => {
+          sign(k) * x[n - 1] + sum(&|j| (PI * (j + 1.0) * (kf + 0.5) / nf).sin(), 0..n - 1)
+        }
+        R2RKind::Dst4 => sum(&|j| (PI * (j + 0.5) * (kf + 0.5) / nf).sin(), 0..n),
+      };
+      y as f32
+    })
+    .collect()
+}
+
+fn unit_peak(values: &[f32]) -> Vec<f32> {
+  let peak = values.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+  values.iter().map(|v| v / peak).collect()
+}
+
+fn max_error(a: &[f32], b: &[f32]) -> f32 {
+  a.iter().zip(b).fold(0.0, |m, (a, b)| m.max((a - b).abs()))
+}
+
 /// Prints a 2D matrix contained in a Vulkano buffer
 fn print_matrix_buffer(buffer: &Subbuffer<[f32]>, shape: &[u32; 2]) {
   buffer
@@ -0,0 +1,109 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use vkfft::{
+  config::{ConfigBuilder, Conjugate, R2RKind},
+  fuzzing::{marshal_config, StubBuffers},
+};
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+  Dim1(u32),
+  Dim2(u32, u32),
+  Dim3(u32, u32, u32),
+  Normalize,
+  KernelConvolution,
+  SymmetricKernel,
+  ConjugateConvolution(bool),
+  CrossPowerSpectrumNormalization,
+  Convolution,
+  R2c,
+  R2r(u8),
+  UseLut,
+  CoordinateFeatures(u32),
+  MatrixConvolution(u64),
+  DisableReorderFourStep,
+  ZeroPadding(bool, bool, bool),
+  ZeropadLeft(u32, u32, u32),
+  ZeropadRight(u32, u32, u32),
+  BatchCount(u32),
+  InputFormatted(bool),
+  OutputFormatted(bool),
+  InverseReturnToInput,
+  SaveApplication,
+  PrintMemoryLayout,
+  KeepShaderCode,
+  FixMaxRadixBluestein(u64),
+  FixMaxRaderPrimeFft(u64),
+  BluesteinPadding(u64, u64),
+}
+
+const R2R_KINDS: [R2RKind; 8] = [
+  R2RKind::Dct1,
+  R2RKind::Dct2,
+  R2RKind::Dct3,
+  R2RKind::Dct4,
+  R2RKind::Dst1,
+  R2RKind::Dst2,
+  R2RKind::Dst3,
+  R2RKind::Dst4,
+];
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+  ops: Vec<Op>,
+  buffers: [(u64, u64); 5],
+}
+
+fn apply(builder: ConfigBuilder, op: &Op) -> ConfigBuilder {
+  match *op {
+    Op::Dim1(x) => builder.dim(&[x]),
+    Op::Dim2(x, y) => builder.dim(&[x, y]),
+    Op::Dim3(x, y, z) => builder.dim(&[x, y, z]),
+    Op::Normalize => builder.normalize(),
+    Op::KernelConvolution => builder.kernel_convolution(),
+    Op::SymmetricKernel => builder.symmetric_kernel(),
+    Op::ConjugateConvolution(kernel) => builder.conjugate_convolution(if kernel {
+      Conjugate::Kernel
+    } else {
+      Conjugate::Input
+    }),
+    Op::CrossPowerSpectrumNormalization => builder.cross_power_spectrum_normalization(),
+    Op::Convolution => builder.convolution(),
+    Op::R2c => builder.r2c(),
+    Op::R2r(v) => builder.r2r(R2R_KINDS[v as usize % R2R_KINDS.len()]),
+    Op::UseLut => builder.use_lut(),
+    Op::CoordinateFeatures(v) => builder.coordinate_features(v),
+    Op::MatrixConvolution(v) => builder.matrix_convolution(v),
+    Op::DisableReorderFourStep => builder.disable_reorder_four_step(),
+    Op::ZeroPadding(x, y, z) => builder.zero_padding(&[x, y, z]),
+    Op::ZeropadLeft(x, y, z) => builder.zeropad_left(&[x, y, z]),
+    Op::ZeropadRight(x, y, z) => builder.zeropad_right(&[x, y, z]),
+    Op::BatchCount(v) => builder.batch_count(v),
+    Op::InputFormatted(v) => builder.input_formatted(v),
+    Op::OutputFormatted(v) => builder.output_formatted(v),
+    Op::InverseReturnToInput => builder.inverse_return_to_input(),
+    Op::SaveApplication => builder.save_application(),
+    Op::PrintMemoryLayout => builder.print_memory_layout(),
+    Op::KeepShaderCode => builder.keep_shader_code(),
+    Op::FixMaxRadixBluestein(v) => builder.fix_max_radix_bluestein(v),
+    Op::FixMaxRaderPrimeFft(v) => builder.fix_max_rader_prime_fft(v),
+    Op::BluesteinPadding(len, padded) => builder.bluestein_padding_pattern(&[(len, padded)]),
+  }
+}
+
+fuzz_target!(|input: Input| {
+  let builder = input.ops.iter().fold(ConfigBuilder::new(), apply);
+  let [buffer, temp_buffer, input_buffer, output_buffer, kernel] = input.buffers;
+  let buffers = StubBuffers {
+    buffer,
+    temp_buffer,
+    input_buffer,
+    output_buffer,
+    kernel,
+  };
+
+  let _ = marshal_config(&builder, &buffers);
+  let _ = builder.plan_key().to_string();
+});
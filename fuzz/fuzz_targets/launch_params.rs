@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vkfft::fuzzing::{marshal_launch, StubBuffers};
+
+fn stub(buffers: [(u64, u64); 5]) -> StubBuffers {
+  let [buffer, temp_buffer, input_buffer, output_buffer, kernel] = buffers;
+  StubBuffers {
+    buffer,
+    temp_buffer,
+    input_buffer,
+    output_buffer,
+    kernel,
+  }
+}
+
+fuzz_target!(|input: (u64, [(u64, u64); 5], [(u64, u64); 5])| {
+  let (command_buffer, config, launch) = input;
+  let _ = marshal_launch(command_buffer, &stub(config), &stub(launch));
+});
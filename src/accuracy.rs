@@ -0,0 +1,363 @@
+//! Checking GPU transforms against double precision references computed on the host.
+//!
+//! [`verify`] runs a transform with a [`Context`] and with
+//! [`transform_cpu`](crate::cpu_fallback::transform_cpu) in `f64`, and reports how far the GPU
+//! result is from the reference:
+//!
+//! ```ignore
+//! let report = verify(&context, Config::builder().dim(&[4096]), &data, FftType::Forward)?;
+//! assert!(report.passed(), "{}", report);
+//! ```
+//!
+//! Errors are relative to the largest magnitude of the reference, so values near zero don't
+//! dominate them. The tolerance grows with the machine epsilon of the precision and the
+//! logarithm of the transform length, which bounds the error of an FFT.
+//!
+//! Double precision references can't check [`Precision::QuadDoubleDouble`] plans, so
+//! [`verify_double_double`] compares them with a direct DFT computed in double-double arithmetic.
+
+use crate::{
+  config::{ConfigBuilder, ConfigError, Precision, R2RKind, MAX_FFT_DIMENSIONS},
+  context::{Context, FftType},
+  cpu_fallback::transform_cpu,
+  scalar::{DoubleDouble, FftScalar},
+};
+
+/// How far a result is from its reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccuracyReport {
+  pub max_relative_error: f64,
+  pub mean_relative_error: f64,
+  pub tolerance: f64,
+}
+
+impl AccuracyReport {
+  /// Whether the largest error is within the tolerance.
+  pub fn passed(&self) -> bool {
+    self.max_relative_error <= self.tolerance
+  }
+}
+
+impl std::fmt::Display for AccuracyReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "max relative error {:.3e}, mean {:.3e}, tolerance {:.3e}",
+      self.max_relative_error, self.mean_relative_error, self.tolerance
+    )
+  }
+}
+
+/// The error a transform of `points` points in `precision` may have.
+pub fn tolerance(precision: Precision, points: u64) -> f64 {
+  let epsilon = match precision {
+    Precision::QuadDoubleDouble => 2f64.powi(-104),
+    Precision::Double => f64::EPSILON,
+    Precision::Single => f32::EPSILON as f64,
+    Precision::Half | Precision::HalfMemory => 9.77e-4,
+  };
+  16.0 * epsilon * (points.max(2) as f64).log2()
+}
+
+/// Compares `result` with `reference` element by element.
+pub fn compare<T: FftScalar>(result: &[T], reference: &[f64], tolerance: f64) -> AccuracyReport {
+  let scale = reference.iter().fold(0.0f64, |m, v| m.max(v.abs()));
+  let scale = if scale > 0.0 { scale } else { 1.0 };
+  let errors = result
+    .iter()
+    .zip(reference)
+    .map(|(r, e)| (r.to_f64() - e).abs() / scale)
+    .collect::<Vec<_>>();
+  AccuracyReport {
+    max_relative_error: errors.iter().fold(0.0, |m, e| m.max(*e)),
+    mean_relative_error: errors.iter().sum::<f64>() / errors.len().max(1) as f64,
+    tolerance,
+  }
+}
+
+/// Compares double-double results without rounding them to `f64` first.
+pub fn compare_double_double(
+  result: &[DoubleDouble],
+  reference: &[DoubleDouble],
+  tolerance: f64,
+) -> AccuracyReport {
+  let difference = result
+    .iter()
+    .zip(reference)
+    .map(|(r, e)| (*r - *e).to_f64())
+    .collect::<Vec<_>>();
+  let reference = reference.iter().map(|e| e.to_f64()).collect::<Vec<_>>();
+  let scale = reference.iter().fold(0.0f64, |m, v| m.max(v.abs()));
+  let scale = if scale > 0.0 { scale } else { 1.0 };
+  let errors = difference
+    .iter()
+    .map(|d| d.abs() / scale)
+    .collect::<Vec<_>>();
+  AccuracyReport {
+    max_relative_error: errors.iter().fold(0.0, |m, e| m.max(*e)),
+    mean_relative_error: errors.iter().sum::<f64>() / errors.len().max(1) as f64,
+    tolerance,
+  }
+}
+
+/// Runs the transform of `data` on the GPU and compares it with a reference computed on the
+/// host. R2R results are compared after scaling both sides to unit peak, since VkFFT scales
+/// them differently from the textbook definitions.
+pub fn verify<T: FftScalar>(
+  context: &Context,
+  config_builder: ConfigBuilder,
+  data: &[T],
+  fft_type: FftType,
+) -> Result<AccuracyReport, crate::Error> {
+  let plan_key = config_builder.plan_key();
+  let dims = (plan_key.fft_dim as usize).min(MAX_FFT_DIMENSIONS);
+  let points = plan_key.size[..dims].iter().map(|n| *n as u64).product();
+  let tolerance = tolerance(T::PRECISION, points);
+  let input = data.iter().map(|v| v.to_f64()).collect::<Vec<_>>();
+
+  let result = context.transform(config_builder.clone(), data, fft_type)?;
+  Ok(match plan_key.r2r {
+    Some(kind) => {
+      let kind = match fft_type {
+        FftType::Forward => kind,
+        FftType::Inverse => kind.inverse(),
+      };
+      let reference = r2r_reference(kind, &plan_key.size[..dims], &input);
+      let result = result.iter().map(|v| v.to_f64()).collect::<Vec<_>>();
+      compare(&unit_peak(&result), &unit_peak(&reference), tolerance)
+    }
+    None => compare(
+      &result,
+      &transform_cpu(&config_builder, &input, fft_type)?,
+      tolerance,
+    ),
+  })
+}
+
+/// Convolves `data` with `kernel` on the GPU, preparing the kernel's spectrum first, and compares
+/// the result with a circular convolution computed on the host. `config_builder` describes a C2C
+/// transform of either.
+pub fn verify_convolution<T: FftScalar>(
+  context: &Context,
+  config_builder: ConfigBuilder,
+  data: &[T],
+  kernel: &[T],
+) -> Result<AccuracyReport, crate::Error> {
+  let plan_key = config_builder.plan_key();
+  let dims = (plan_key.fft_dim as usize).min(MAX_FFT_DIMENSIONS);
+  let points = plan_key.size[..dims].iter().map(|n| *n as u64).product();
+  let tolerance = tolerance(T::PRECISION, points);
+
+  let spectrum = context.transform(
+    config_builder.clone().kernel_convolution(),
+    kernel,
+    FftType::Forward,
+  )?;
+  let spectrum = context.new_buffer_from_iter(spectrum)?;
+  let result = context.transform(
+    config_builder.clone().kernel(spectrum).convolution(),
+    data,
+    FftType::Forward,
+  )?;
+
+  let to_f64 = |values: &[T]| values.iter().map(|v| v.to_f64()).collect::<Vec<_>>();
+  let data_spectrum = transform_cpu(&config_builder, &to_f64(data), FftType::Forward)?;
+  let kernel_spectrum = transform_cpu(&config_builder, &to_f64(kernel), FftType::Forward)?;
+  let mut product = data_spectrum.clone();
+  for ((p, a), b) in product
+    .chunks_exact_mut(2)
+    .zip(data_spectrum.chunks_exact(2))
+    .zip(kernel_spectrum.chunks_exact(2))
+  {
+    p[0] = a[0] * b[0] - a[1] * b[1];
+    p[1] = a[0] * b[1] + a[1] * b[0];
+  }
+  let reference = transform_cpu(&config_builder, &product, FftType::Inverse)?;
+  Ok(compare(&result, &reference, tolerance))
+}
+
+/// Runs a double-double transform of `data` on the GPU and compares it with a direct DFT computed
+/// in double-double arithmetic. Only 1D C2C transforms of powers of two of at least 4 points are
+/// supported.
+pub fn verify_double_double(
+  context: &Context,
+  config_builder: ConfigBuilder,
+  data: &[DoubleDouble],
+  fft_type: FftType,
+) -> Result<AccuracyReport, crate::Error> {
+  let plan_key = config_builder
+    .clone()
+    .precision(Precision::QuadDoubleDouble)
+    .plan_key();
+  let n = plan_key.size[0] as usize;
+  if plan_key.fft_dim != 1
+    || n < 4
+    || !n.is_power_of_two()
+    || plan_key.r2c
+    || plan_key.r2r.is_some()
+    || plan_key.convolution
+  {
+    return Err(
+      ConfigError::UnsupportedOnCpu(
+        "double-double references of anything but 1D C2C powers of two",
+      )
+      .into(),
+    );
+  }
+
+  let result = context.transform(config_builder, data, fft_type)?;
+  let roots = roots_of_unity(n);
+  let sign = match fft_type {
+    FftType::Forward => -1.0,
+    FftType::Inverse => 1.0,
+  };
+  let scale = DoubleDouble::from_f64(plan_key.scale_factor(fft_type == FftType::Inverse));
+  let mut reference = data.to_vec();
+  for (line, out) in data
+    .chunks_exact(2 * n)
+    .zip(reference.chunks_exact_mut(2 * n))
+  {
+    for k in 0..n {
+      let (mut re, mut im) = (DoubleDouble::default(), DoubleDouble::default());
+      for j in 0..n {
+        let (c, s) = roots[j * k % n];
+        let s = s * DoubleDouble::from_f64(sign);
+        let (x, y) = (line[2 * j], line[2 * j + 1]);
+        re = re + x * c - y * s;
+        im = im + x * s + y * c;
+      }
+      out[2 * k] = re * scale;
+      out[2 * k + 1] = im * scale;
+    }
+  }
+  Ok(compare_double_double(
+    &result[..reference.len()],
+    &reference,
+    tolerance(Precision::QuadDoubleDouble, n as u64),
+  ))
+}
+
+/// The cosines and sines of `2πk / n` for every `k` below `n`, a power of two of at least 4.
+fn roots_of_unity(n: usize) -> Vec<(DoubleDouble, DoubleDouble)> {
+  let zero = DoubleDouble::default();
+  let one = DoubleDouble::from_f64(1.0);
+  let two = DoubleDouble::from_f64(2.0);
+  // The roots of 2π / 4, 2π / 8, ..., 2π / n by the half angle formulas, avoiding the
+  // cancellation of `1 - cos` in the sines
+  let mut halvings = vec![(zero, one)];
+  while 4 << (halvings.len() - 1) < n {
+    let (c, s) = halvings[halvings.len() - 1];
+    let half_c = ((one + c) / two).sqrt();
+    halvings.push((half_c, s / (two * half_c)));
+  }
+
+  // Every root is a quarter turn times a product of at most log2(n) of them, which keeps the
+  // error far below that of repeated multiplication
+  let quarter = n / 4;
+  (0..n)
+    .map(|k| {
+      let (mut c, mut s) = (one, zero);
+      for (i, (hc, hs)) in halvings.iter().enumerate() {
+        if (k % quarter) & (quarter >> i) != 0 {
+          let product = (c * *hc - s * *hs, c * *hs + s * *hc);
+          c = product.0;
+          s = product.1;
+        }
+      }
+      for _ in 0..k / quarter {
+        let rotated = (-s, c);
+        c = rotated.0;
+        s = rotated.1;
+      }
+      (c, s)
+    })
+    .collect()
+}
+
+/// Applies FFTW's unnormalized definition of `kind` along every axis of `data`, a real array of
+/// shape `dims`, directly.
+pub fn r2r_reference(kind: R2RKind, dims: &[u32], data: &[f64]) -> Vec<f64> {
+  let mut output = data.to_vec();
+  for (axis, len) in dims.iter().enumerate() {
+    let len = *len as usize;
+    let stride = dims[..axis].iter().map(|n| *n as usize).product::<usize>();
+    for block in output.chunks_exact_mut(stride * len) {
+      for offset in 0..stride {
+        let line = (0..len)
+          .map(|i| block[offset + i * stride])
+          .collect::<Vec<_>>();
+        for (i, v) in r2r_1d(kind, &line).into_iter().enumerate() {
+          block[offset + i * stride] = v;
+        }
+      }
+    }
+  }
+  output
+}
+
+fn r2r_1d(kind: R2RKind, x: &[f64]) -> Vec<f64> {
+  use std::f64::consts::PI;
+  let n = x.len();
+  let nf = n as f64;
+  let sign = |k: usize| (-1.0f64).powi(k as i32);
+  (0..n)
+    .map(|k| {
+      let kf = k as f64;
+      let sum = |term: &dyn Fn(f64) -> f64, range: std::ops::Range<usize>| {
+        range.map(|j| 2.0 * x[j] * term(j as f64)).sum::<f64>()
+      };
+      match kind {
+        R2RKind::Dct1 => {
+          x[0] + sign(k) * x[n - 1] + sum(&|j| (PI * j * kf / (nf - 1.0)).cos(), 1..n - 1)
+        }
+        R2RKind::Dct2 => sum(&|j| (PI * (j + 0.5) * kf / nf).cos(), 0..n),
+        R2RKind::Dct3 => x[0] + sum(&|j| (PI * j * (kf + 0.5) / nf).cos(), 1..n),
+        R2RKind::Dct4 => sum(&|j| (PI * (j + 0.5) * (kf + 0.5) / nf).cos(), 0..n),
+        R2RKind::Dst1 => sum(&|j| (PI * (j + 1.0) * (kf + 1.0) / (nf + 1.0)).sin(), 0..n),
+        R2RKind::Dst2 => sum(&|j| (PI * (j + 0.5) * (kf + 1.0) / nf).sin(), 0..n),
+        R2RKind::Dst3 => {
+          sign(k) * x[n - 1] + sum(&|j| (PI * (j + 1.0) * (kf + 0.5) / nf).sin(), 0..n - 1)
+        }
+        R2RKind::Dst4 => sum(&|j| (PI * (j + 0.5) * (kf + 0.5) / nf).sin(), 0..n),
+      }
+    })
+    .collect()
+}
+
+fn unit_peak(values: &[f64]) -> Vec<f64> {
+  let peak = values.iter().fold(0.0f64, |m, v| m.max(v.abs()));
+  values.iter().map(|v| v / peak).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_r2r_reference() {
+    // A DCT-II of a constant only has a DC component, along both axes
+    let reference = r2r_reference(R2RKind::Dct2, &[4, 2], &[1.0; 8]);
+    assert!((reference[0] - 2.0 * 4.0 * 2.0 * 2.0).abs() < 1e-12);
+    assert!(reference[1..].iter().all(|v| v.abs() < 1e-12));
+
+    let report = compare(
+      &[1.0f32, 0.5],
+      &[1.0, 0.5005],
+      tolerance(Precision::Single, 2),
+    );
+    assert!(!report.passed());
+    assert!((report.max_relative_error - 5e-4).abs() < 1e-6);
+  }
+
+  #[test]
+  fn check_roots_of_unity() {
+    let roots = roots_of_unity(64);
+    let one = DoubleDouble::from_f64(1.0);
+    for (c, s) in &roots {
+      assert!((*c * *c + *s * *s - one).to_f64().abs() < 1e-30);
+    }
+    let half = DoubleDouble::from_f64(0.5).sqrt();
+    assert!((roots[8].0 - half).to_f64().abs() < 1e-31);
+    assert_eq!(roots[16], (DoubleDouble::default(), one));
+  }
+}
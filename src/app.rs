@@ -1,14 +1,22 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use error::check_error;
-use vulkano::{buffer::Buffer, Handle, VulkanObject};
+#[cfg(feature = "vulkano")]
+use vulkano::{
+  buffer::{Buffer, BufferCreateInfo, BufferUsage},
+  memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter},
+  VulkanObject,
+};
 
 use crate::{
-  config::{Config, ConfigGuard},
+  buffer::{BufferBinding, FftBuffer},
+  capture,
+  config::{Config, ConfigGuard, MAX_FFT_DIMENSIONS},
   error,
+  plan_info::{KernelInfo, PlanInfo},
 };
 
-use ash::vk;
+use ash::vk::{self, Handle};
 use std::pin::Pin;
 
 use std::ptr::addr_of_mut;
@@ -31,15 +39,24 @@ pub enum LaunchError {
   ConfigSpecifiesInputBuffer,
   ConfigSpecifiesOutputBuffer,
   ConfigSpecifiesKernel,
+  SharedBuffersChanged,
+  OffsetsNotAtLaunch,
+  /// A buffer passed at launch is smaller than the size the application was planned for
+  LaunchBufferTooSmall,
+  /// The result buffer of a stage scaling for `Normalization::Ortho` isn't a vulkano buffer
+  UnscalableBuffer,
+  /// The result range of a stage scaling for `Normalization::Ortho` holds more values than the
+  /// scaling pass can index
+  UnscalableRange,
 }
 
 pub struct LaunchParamsBuilder {
   command_buffer: Option<vk::CommandBuffer>,
-  buffer: Option<Arc<Buffer>>,
-  temp_buffer: Option<Arc<Buffer>>,
-  input_buffer: Option<Arc<Buffer>>,
-  output_buffer: Option<Arc<Buffer>>,
-  kernel: Option<Arc<Buffer>>,
+  buffer: Option<BufferBinding>,
+  temp_buffer: Option<BufferBinding>,
+  input_buffer: Option<BufferBinding>,
+  output_buffer: Option<BufferBinding>,
+  kernel: Option<BufferBinding>,
 }
 
 impl Default for LaunchParamsBuilder {
@@ -60,6 +77,7 @@ impl LaunchParamsBuilder {
     }
   }
 
+  #[cfg(feature = "vulkano")]
   pub fn command_buffer<C>(mut self, command_buffer: &C) -> Self
   where
     C: VulkanObject<Handle = vk::CommandBuffer>,
@@ -68,28 +86,33 @@ impl LaunchParamsBuilder {
     self
   }
 
-  pub fn buffer(mut self, buffer: Arc<Buffer>) -> Self {
-    self.buffer = Some(buffer);
+  pub fn command_buffer_handle(mut self, command_buffer: vk::CommandBuffer) -> Self {
+    self.command_buffer = Some(command_buffer);
+    self
+  }
+
+  pub fn buffer<B: FftBuffer>(mut self, buffer: B) -> Self {
+    self.buffer = Some(buffer.binding());
     self
   }
 
-  pub fn temp_buffer(mut self, temp_buffer: Arc<Buffer>) -> Self {
-    self.temp_buffer = Some(temp_buffer);
+  pub fn temp_buffer<B: FftBuffer>(mut self, temp_buffer: B) -> Self {
+    self.temp_buffer = Some(temp_buffer.binding());
     self
   }
 
-  pub fn input_buffer(mut self, input_buffer: Arc<Buffer>) -> Self {
-    self.input_buffer = Some(input_buffer);
+  pub fn input_buffer<B: FftBuffer>(mut self, input_buffer: B) -> Self {
+    self.input_buffer = Some(input_buffer.binding());
     self
   }
 
-  pub fn output_buffer(mut self, output_buffer: Arc<Buffer>) -> Self {
-    self.output_buffer = Some(output_buffer);
+  pub fn output_buffer<B: FftBuffer>(mut self, output_buffer: B) -> Self {
+    self.output_buffer = Some(output_buffer.binding());
     self
   }
 
-  pub fn kernel(mut self, kernel: Arc<Buffer>) -> Self {
-    self.kernel = Some(kernel);
+  pub fn kernel<B: FftBuffer>(mut self, kernel: B) -> Self {
+    self.kernel = Some(kernel.binding());
     self
   }
 
@@ -119,24 +142,120 @@ pub(crate) struct LaunchParamsGuard {
   pub(crate) input_buffer: Option<u64>,
   pub(crate) output_buffer: Option<u64>,
   pub(crate) kernel: Option<u64>,
+  /// Sizes of the buffer, temp buffer, input buffer, output buffer and kernel ranges, or 0
+  pub(crate) sizes: [u64; 5],
+}
+
+impl LaunchParamsGuard {
+  /// Points the handle fields of `params` at the values stored in this guard.
+  pub(crate) fn wire(&mut self) {
+    self.params.commandBuffer = unsafe {
+      std::mem::transmute::<*mut ash::vk::CommandBuffer, *mut *mut vkfft_sys::VkCommandBuffer_T>(addr_of_mut!(self.command_buffer))
+    };
+
+    if let Some(b) = &self.buffer {
+      self.params.buffer = b as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
+    }
+
+    if let Some(b) = &self.temp_buffer {
+      self.params.tempBuffer = b as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
+    }
+
+    if let Some(b) = &self.input_buffer {
+      self.params.inputBuffer = b as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
+    }
+
+    if let Some(b) = &self.output_buffer {
+      self.params.outputBuffer = b as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
+    }
+
+    if let Some(k) = &self.kernel {
+      self.params.kernel = k as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
+    }
+  }
+
+  /// Rejects buffers that are already fixed by the application's config, and buffers smaller
+  /// than the application was planned for.
+  pub(crate) fn check(&self, config: &ConfigGuard) -> Result<(), LaunchError> {
+    if config.buffer.is_some() && self.buffer.is_some() {
+      return Err(LaunchError::ConfigSpecifiesBuffer);
+    }
+
+    if config.temp_buffer.is_some() && self.temp_buffer.is_some() {
+      return Err(LaunchError::ConfigSpecifiesTempBuffer);
+    }
+
+    if config.input_buffer.is_some() && self.input_buffer.is_some() {
+      return Err(LaunchError::ConfigSpecifiesInputBuffer);
+    }
+
+    if config.output_buffer.is_some() && self.output_buffer.is_some() {
+      return Err(LaunchError::ConfigSpecifiesOutputBuffer);
+    }
+
+    let planned = [
+      config.buffer_size,
+      config.temp_buffer_size,
+      config.input_buffer_size,
+      config.output_buffer_size,
+      config.kernel_size,
+    ];
+    let passed = [
+      self.buffer,
+      self.temp_buffer,
+      self.input_buffer,
+      self.output_buffer,
+      self.kernel,
+    ];
+    let too_small = passed
+      .iter()
+      .zip(self.sizes.iter().zip(planned.iter()))
+      .any(|(buffer, (size, planned))| buffer.is_some() && size < planned);
+    if too_small {
+      return Err(LaunchError::LaunchBufferTooSmall);
+    }
+
+    let params = &self.params;
+    let offsets = [
+      params.bufferOffset,
+      params.tempBufferOffset,
+      params.inputBufferOffset,
+      params.outputBufferOffset,
+      params.kernelOffset,
+    ];
+    if config.config.specifyOffsetsAtLaunch == 0 && offsets.iter().any(|o| *o != 0) {
+      return Err(LaunchError::OffsetsNotAtLaunch);
+    }
+
+    Ok(())
+  }
+}
+
+/// The GLSL of one kernel of an application, see [`App::shader_sources`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderSource {
+  /// Whether the kernel belongs to the inverse transform
+  pub inverse: bool,
+  /// The axis the kernel transforms
+  pub axis: usize,
+  /// The index of the kernel among the uploads of its axis, for axes split over several
+  pub upload: usize,
+  pub code: String,
 }
 
 #[derive(Clone)]
 pub struct LaunchParams {
   pub command_buffer: vk::CommandBuffer,
-  pub buffer: Option<Arc<Buffer>>,
-  pub temp_buffer: Option<Arc<Buffer>>,
-  pub input_buffer: Option<Arc<Buffer>>,
-  pub output_buffer: Option<Arc<Buffer>>,
-  pub kernel: Option<Arc<Buffer>>,
+  pub buffer: Option<BufferBinding>,
+  pub temp_buffer: Option<BufferBinding>,
+  pub input_buffer: Option<BufferBinding>,
+  pub output_buffer: Option<BufferBinding>,
+  pub kernel: Option<BufferBinding>,
 }
 
 impl LaunchParams {
-  fn buffer_object<B>(buffer: B) -> u64
-  where
-    B: AsRef<Buffer>,
-  {
-    buffer.as_ref().handle().as_raw()
+  fn buffer_object(buffer: &BufferBinding) -> u64 {
+    buffer.handle().as_raw()
   }
   // pub fn duplicate(&self) -> Self{
   //   LaunchParams{
@@ -145,43 +264,32 @@ impl LaunchParams {
   //   }
   // }
   pub(crate) fn as_sys(&self) -> Pin<Box<LaunchParamsGuard>> {
-    use std::mem::{transmute, zeroed};
-
-    unsafe {
-      let mut res = Box::pin(LaunchParamsGuard {
-        params: zeroed(),
-        command_buffer: self.command_buffer,
-        buffer: self.buffer.as_ref().map(Self::buffer_object),
-        temp_buffer: self.temp_buffer.as_ref().map(Self::buffer_object),
-        input_buffer: self.input_buffer.as_ref().map(Self::buffer_object),
-        output_buffer: self.output_buffer.as_ref().map(Self::buffer_object),
-        kernel: self.kernel.as_ref().map(Self::buffer_object),
-      });
-
-      res.params.commandBuffer = transmute::<*mut ash::vk::CommandBuffer, *mut *mut vkfft_sys::VkCommandBuffer_T>(addr_of_mut!(res.command_buffer));
-
-      if let Some(b) = &res.buffer {
-        res.params.buffer = b as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
-      }
-
-      if let Some(b) = &res.temp_buffer {
-        res.params.tempBuffer = b as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
-      }
-
-      if let Some(b) = &res.input_buffer {
-        res.params.inputBuffer = b as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
-      }
-
-      if let Some(b) = &res.output_buffer {
-        res.params.outputBuffer = b as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
-      }
-
-      if let Some(k) = &res.kernel {
-        res.params.kernel = k as *const u64 as *mut *mut vkfft_sys::VkBuffer_T;
-      }
-
-      res
-    }
+    let mut res = Box::pin(LaunchParamsGuard {
+      params: unsafe { std::mem::zeroed() },
+      command_buffer: self.command_buffer,
+      buffer: self.buffer.as_ref().map(Self::buffer_object),
+      temp_buffer: self.temp_buffer.as_ref().map(Self::buffer_object),
+      input_buffer: self.input_buffer.as_ref().map(Self::buffer_object),
+      output_buffer: self.output_buffer.as_ref().map(Self::buffer_object),
+      kernel: self.kernel.as_ref().map(Self::buffer_object),
+      sizes: [
+        &self.buffer,
+        &self.temp_buffer,
+        &self.input_buffer,
+        &self.output_buffer,
+        &self.kernel,
+      ]
+      .map(|b| b.as_ref().map(|b| b.size()).unwrap_or(0)),
+    });
+    res.wire();
+
+    let offset = |b: &Option<BufferBinding>| b.as_ref().map(|b| b.offset()).unwrap_or(0);
+    res.params.bufferOffset = offset(&self.buffer);
+    res.params.tempBufferOffset = offset(&self.temp_buffer);
+    res.params.inputBufferOffset = offset(&self.input_buffer);
+    res.params.outputBufferOffset = offset(&self.output_buffer);
+    res.params.kernelOffset = offset(&self.kernel);
+    res
   }
 
   pub fn builder() -> LaunchParamsBuilder {
@@ -189,51 +297,149 @@ impl LaunchParams {
   }
 }
 
+/// An initialized VkFFT plan.
+///
+/// Appending a transform writes to the application's state and, when buffers are passed in the
+/// launch parameters, rebinds the buffers of its descriptor sets. Appends therefore need `&mut`
+/// access, and command buffers recorded from one application must all use the same launch
+/// buffers while any of them is pending. To record into several command buffers from different
+/// threads, either give each thread its own [`App::fork`] or share one through [`SharedApp`].
 pub struct App {
   app: vkfft_sys::VkFFTApplication,
 
   // Safety: We must keep a copy of the config to ensure our resources are kept alive
   config: Pin<Box<ConfigGuard>>,
+
+  memory_layout: Option<String>,
 }
 
 impl App {
   pub fn new(config: Config) -> error::Result<Pin<Box<Self>>> {
+    #[cfg(feature = "vulkano")]
+    if let Some(allocator) = config.temp_buffer_allocator.clone() {
+      if config.temp_buffer.is_none() && config.launch_buffer_sizes[1].is_none() {
+        return Self::with_temp_buffer(config, allocator);
+      }
+    }
+    Self::from_sys(config.as_sys()?)
+  }
+
+  /// Plans `config` to learn the temp buffer it needs, then creates the application again, from
+  /// the same kernels, with a temp buffer allocated from `allocator`.
+  #[cfg(feature = "vulkano")]
+  fn with_temp_buffer(
+    mut config: Config,
+    allocator: Arc<dyn MemoryAllocator>,
+  ) -> error::Result<Pin<Box<Self>>> {
+    let mut probe_config = config.as_sys()?;
+    probe_config.config.saveApplicationToString = 1;
+    let probe = Self::from_sys(probe_config)?;
+    let size = match probe.temp_buffer_size() {
+      Some(size) if probe.app.configuration.allocateTempBuffer != 0 => size,
+      _ => return Ok(probe),
+    };
+    let application = probe.save_to_bytes();
+    drop(probe);
+
+    let temp_buffer = Buffer::new_slice::<u8>(
+      allocator,
+      BufferCreateInfo {
+        usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST,
+        ..Default::default()
+      },
+      AllocationCreateInfo {
+        memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+        ..Default::default()
+      },
+      size,
+    )
+    .map_err(|_| error::VkfftError::FailedToAllocateMemory)?;
+    config.temp_buffer = Some(temp_buffer.binding());
+
+    let mut sys_config = config.as_sys()?;
+    if let Some(application) = application {
+      sys_config.load_application(application);
+    }
+    Self::from_sys(sys_config)
+  }
+
+  /// Creates an `App` from kernels previously serialized with [`App::save_to_bytes`] instead of
+  /// generating and compiling them.
+  ///
+  /// # Safety
+  ///
+  /// VkFFT doesn't validate the bytes. They must be unmodified output of
+  /// [`App::save_to_bytes`], saved on the same device, driver and VkFFT version from an app of
+  /// the same plan as `config`.
+  pub unsafe fn from_bytes(config: Config, bytes: &[u8]) -> error::Result<Pin<Box<Self>>> {
+    let mut sys_config = config.as_sys()?;
+    sys_config.load_application(bytes.to_vec());
+    Self::from_sys(sys_config)
+  }
+
+  pub(crate) fn from_sys(sys_config: Pin<Box<ConfigGuard>>) -> error::Result<Pin<Box<Self>>> {
     use vkfft_sys::*;
 
-    let app: VkFFTApplication = unsafe { std::mem::zeroed() };
+    // VkFFT would read a config laid out for other bindings
+    crate::version::check_version()?;
 
-    let sys_config = config.as_sys()?;
+    let app: VkFFTApplication = unsafe { std::mem::zeroed() };
 
     let mut res = Box::pin(Self {
       app,
       config: sys_config,
+      memory_layout: None,
     });
 
-    check_error(unsafe { initializeVkFFT(std::ptr::addr_of_mut!(res.app), res.config.config) })?;
+    let print_memory_layout = res.config.config.printMemoryLayout != 0;
+    let config = res.config.config;
+    let app = std::ptr::addr_of_mut!(res.app);
+    let initialize = || unsafe { initializeVkFFT(app, config) };
+    // VkFFT submits its lookup table uploads to the queue, which others may be submitting to
+    #[cfg(feature = "vulkano")]
+    let queue = res.config.vulkano_queue.clone();
+    #[cfg(feature = "vulkano")]
+    let initialize = || match &queue {
+      Some(queue) => queue.with(|_| initialize()),
+      None => initialize(),
+    };
+    let result = if print_memory_layout {
+      let (result, output) = capture::stdout(initialize);
+      res.memory_layout = output;
+      result
+    } else {
+      initialize()
+    };
+    check_error(result).map_err(|e| e.diagnose(res.config.summary()))?;
 
     Ok(res)
   }
 
-  pub fn launch(&mut self, params: &mut LaunchParams, inverse: bool) -> error::Result<()> {
-    use vkfft_sys::VkFFTAppend;
-
-    let mut params = params.as_sys();
+  /// The memory layout VkFFT reported while creating this application, if its config enabled
+  /// `print_memory_layout`. Always `None` on platforms where the output can't be captured, in
+  /// which case it goes to stdout.
+  pub fn memory_layout(&self) -> Option<&str> {
+    self.memory_layout.as_deref()
+  }
 
-    if self.config.buffer.is_some() && params.buffer.is_some() {
-      return Err(LaunchError::ConfigSpecifiesBuffer.into());
+  /// The size in bytes of the temp buffer this application uses, or `None` if its plan doesn't
+  /// need one. This is the size VkFFT computed if it allocated the buffer itself, and the size
+  /// given in the config or planned for launch otherwise.
+  pub fn temp_buffer_size(&self) -> Option<u64> {
+    let configuration = &self.app.configuration;
+    let has_temp_buffer = configuration.userTempBuffer != 0 || configuration.allocateTempBuffer != 0;
+    if !has_temp_buffer || configuration.tempBufferSize.is_null() {
+      return None;
     }
+    Some(unsafe { *configuration.tempBufferSize })
+  }
 
-    if self.config.temp_buffer.is_some() && params.temp_buffer.is_some() {
-      return Err(LaunchError::ConfigSpecifiesTempBuffer.into());
-    }
+  pub fn launch(&mut self, params: &mut LaunchParams, inverse: bool) -> error::Result<()> {
+    use vkfft_sys::VkFFTAppend;
 
-    if self.config.input_buffer.is_some() && params.input_buffer.is_some() {
-      return Err(LaunchError::ConfigSpecifiesInputBuffer.into());
-    }
+    let mut params = params.as_sys();
 
-    if self.config.output_buffer.is_some() && params.output_buffer.is_some() {
-      return Err(LaunchError::ConfigSpecifiesOutputBuffer.into());
-    }
+    params.check(&self.config)?;
 
     check_error(unsafe {
       VkFFTAppend(
@@ -241,11 +447,102 @@ impl App {
         if inverse { 1 } else { -1 },
         std::ptr::addr_of_mut!(params.params),
       )
-    })?;
+    })
+    .map_err(|e| e.diagnose(self.config.summary()))?;
 
     Ok(())
   }
 
+  /// Creates an independent application for the same plan and resources. If this application
+  /// was loaded from or saves its kernels, the copy reuses them instead of recompiling.
+  pub fn fork(&self) -> error::Result<Pin<Box<Self>>> {
+    let mut sys_config = self.config.duplicate();
+    if sys_config.application.is_none() {
+      if let Some(bytes) = self.save_to_bytes() {
+        sys_config.load_application(bytes);
+      }
+    }
+    Self::from_sys(sys_config)
+  }
+
+  /// The GLSL VkFFT generated for each kernel of this application, forward transform first, if
+  /// its config enabled `keep_shader_code`. Applications loaded from serialized kernels have
+  /// no sources.
+  pub fn shader_sources(&self) -> Vec<ShaderSource> {
+    if self.app.configuration.keepShaderCode == 0 {
+      return Vec::new();
+    }
+
+    self
+      .kernels()
+      .filter(|(_, _, _, fft_axis)| !fft_axis.specializationConstants.code0.is_null())
+      .map(|(inverse, axis, upload, fft_axis)| ShaderSource {
+        inverse,
+        axis,
+        upload,
+        code: unsafe { std::ffi::CStr::from_ptr(fft_axis.specializationConstants.code0) }
+          .to_string_lossy()
+          .into_owned(),
+      })
+      .collect()
+  }
+
+  /// How VkFFT decomposed this application's plan: the kernels of each axis, their radices and
+  /// workgroup sizes, and the buffers the plan needs.
+  pub fn plan_info(&self) -> PlanInfo {
+    let axes = (self.app.configuration.FFTdim as usize).min(MAX_FFT_DIMENSIONS);
+    let axis_uploads = match unsafe { self.app.localFFTPlan.as_ref() } {
+      Some(plan) => plan.numAxisUploads[..axes].to_vec(),
+      None => vec![0; axes],
+    };
+    PlanInfo {
+      axis_uploads,
+      bluestein_axes: (0..axes)
+        .filter(|axis| self.app.useBluesteinFFT[*axis] != 0)
+        .collect(),
+      kernels: self
+        .kernels()
+        .map(|(inverse, axis, upload, fft_axis)| KernelInfo::from_axis(inverse, axis, upload, fft_axis))
+        .collect(),
+      buffer_size: self.config.buffer_size,
+      temp_buffer_size: self.temp_buffer_size(),
+    }
+  }
+
+  /// Every kernel of the forward plan, then of the inverse one, with its direction, axis and
+  /// upload index.
+  fn kernels(&self) -> impl Iterator<Item = (bool, usize, usize, &vkfft_sys::VkFFTAxis)> {
+    let plans = vec![(false, self.app.localFFTPlan), (true, self.app.localFFTPlan_inverse)];
+    plans
+      .into_iter()
+      .filter_map(|(inverse, plan)| unsafe { plan.as_ref() }.map(|plan| (inverse, plan)))
+      .flat_map(|(inverse, plan)| {
+        plan.axes.iter().enumerate().flat_map(move |(axis, uploads)| {
+          let count = (plan.numAxisUploads[axis] as usize).min(uploads.len());
+          uploads[..count]
+            .iter()
+            .enumerate()
+            .map(move |(upload, fft_axis)| (inverse, axis, upload, fft_axis))
+        })
+      })
+  }
+
+  /// Returns the serialized kernels of this application, if its config enabled
+  /// `save_application`.
+  pub fn save_to_bytes(&self) -> Option<Vec<u8>> {
+    if self.app.saveApplicationString.is_null() {
+      return None;
+    }
+
+    let bytes = unsafe {
+      std::slice::from_raw_parts(
+        self.app.saveApplicationString as *const u8,
+        self.app.applicationStringSize as usize,
+      )
+    };
+    Some(bytes.to_vec())
+  }
+
   pub fn forward(&mut self, params: &mut LaunchParams) -> error::Result<()> {
     self.launch(params, false)
   }
@@ -264,3 +561,54 @@ impl Drop for App {
     }
   }
 }
+
+struct SharedState {
+  app: Pin<Box<App>>,
+  buffers: Option<[Option<u64>; 5]>,
+}
+
+// Safety: VkFFT keeps no thread-local state, and all access to the application goes through the
+// mutex in `SharedApp`.
+unsafe impl Send for SharedState {}
+
+/// An [`App`] that can be appended into command buffers from several threads.
+///
+/// Appends are serialized, and every append must use the launch buffers of the first one, since
+/// rebinding them would change what already recorded command buffers read.
+#[derive(Clone)]
+pub struct SharedApp {
+  state: Arc<Mutex<SharedState>>,
+}
+
+impl SharedApp {
+  pub fn new(app: Pin<Box<App>>) -> Self {
+    Self {
+      state: Arc::new(Mutex::new(SharedState { app, buffers: None })),
+    }
+  }
+
+  pub fn launch(&self, params: &mut LaunchParams, inverse: bool) -> error::Result<()> {
+    let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+    let buffers = [
+      params.buffer.as_ref().map(LaunchParams::buffer_object),
+      params.temp_buffer.as_ref().map(LaunchParams::buffer_object),
+      params.input_buffer.as_ref().map(LaunchParams::buffer_object),
+      params.output_buffer.as_ref().map(LaunchParams::buffer_object),
+      params.kernel.as_ref().map(LaunchParams::buffer_object),
+    ];
+    if *state.buffers.get_or_insert(buffers) != buffers {
+      return Err(LaunchError::SharedBuffersChanged.into());
+    }
+
+    state.app.launch(params, inverse)
+  }
+
+  pub fn forward(&self, params: &mut LaunchParams) -> error::Result<()> {
+    self.launch(params, false)
+  }
+
+  pub fn inverse(&self, params: &mut LaunchParams) -> error::Result<()> {
+    self.launch(params, true)
+  }
+}
@@ -0,0 +1,380 @@
+//! Picking [`TuningOptions`] by measurement.
+//!
+//! An [`Autotuner`] benchmarks a small set of candidate options for a transform, timed on the GPU
+//! where the queue supports timestamps, and keeps the fastest in a cache file, keyed by the
+//! device's UUID and the transform's plan, so later runs on the same machine get the tuned plan
+//! without measuring again:
+//!
+//! ```ignore
+//! let mut tuner = Autotuner::new(cache_dir.join("tuning.txt"))?;
+//! let builder = tuner.tuned(&context, Config::builder().dim(&[4096, 4096]))?;
+//! let app = context.app(builder.buffer(buffer))?;
+//! ```
+//!
+//! Candidates VkFFT can't plan are skipped. Failing to write the cache file doesn't fail tuning.
+
+use std::{
+  collections::BTreeMap,
+  convert::TryInto,
+  fmt::Write as _,
+  fs, io,
+  path::{Path, PathBuf},
+  time::{Duration, Instant},
+};
+
+use ash::vk;
+use vulkano::{
+  buffer::{Buffer, BufferCreateInfo, BufferUsage},
+  device::DeviceOwned,
+  memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+  query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType},
+  VulkanObject,
+};
+
+use crate::{
+  config::{ConfigBuilder, PlanKey},
+  context::{Context, FftType},
+  dry_run::DryRun,
+  error,
+  stage::{FftStage, Stage},
+  tuning::TuningOptions,
+};
+
+/// The options tried by default: VkFFT's own choice, then a few workgroup sizes, coalescing
+/// widths and register boosts around it.
+pub fn default_candidates() -> Vec<TuningOptions> {
+  let mut candidates = vec![TuningOptions::default()];
+  for aim_threads in [64, 128, 256] {
+    candidates.push(TuningOptions {
+      aim_threads: Some(aim_threads),
+      ..Default::default()
+    });
+  }
+  for coalesced_memory in [32, 64, 128] {
+    candidates.push(TuningOptions {
+      coalesced_memory: Some(coalesced_memory),
+      ..Default::default()
+    });
+  }
+  for register_boost in [2, 4] {
+    candidates.push(TuningOptions {
+      register_boost: Some(register_boost),
+      ..Default::default()
+    });
+  }
+  candidates
+}
+
+/// Benchmarks candidate options and remembers the fastest per device and transform.
+pub struct Autotuner {
+  path: PathBuf,
+  entries: BTreeMap<String, TuningOptions>,
+  candidates: Vec<TuningOptions>,
+  iterations: u32,
+}
+
+impl Autotuner {
+  /// Uses the cache file at `path`, loading the entries it already holds. A missing file is
+  /// created on the first tuning; unreadable lines are ignored.
+  pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    let path = path.as_ref().to_path_buf();
+    let entries = match fs::read_to_string(&path) {
+      Ok(text) => text.lines().filter_map(parse_entry).collect(),
+      Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+      Err(e) => return Err(e),
+    };
+    Ok(Self {
+      path,
+      entries,
+      candidates: default_candidates(),
+      iterations: 5,
+    })
+  }
+
+  /// Replaces the options tried, [`default_candidates`] by default.
+  pub fn candidates(mut self, candidates: Vec<TuningOptions>) -> Self {
+    self.candidates = candidates;
+    self
+  }
+
+  /// Sets how many times each candidate is run, 5 by default. The fastest run counts.
+  pub fn iterations(mut self, iterations: u32) -> Self {
+    self.iterations = iterations.max(1);
+    self
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// The best options for `config_builder` on the device of `context`, measured now unless the
+  /// cache already holds them. Buffers set on `config_builder` are replaced by scratch buffers,
+  /// laid out with its strides, while measuring.
+  pub fn tune(
+    &mut self,
+    context: &Context,
+    config_builder: &ConfigBuilder,
+  ) -> Result<TuningOptions, Box<dyn std::error::Error>> {
+    let plan_key = config_builder.plan_key();
+    let properties = context.physical.properties();
+    let device = match properties.device_uuid {
+      Some(uuid) => uuid.iter().map(|b| format!("{:02x}", b)).collect(),
+      None => properties.device_name.clone(),
+    };
+    let key = cache_key(&device, &plan_key);
+    if let Some(tuning) = self.entries.get(&key) {
+      return Ok(tuning.clone());
+    }
+
+    let dry_run = DryRun::new(&plan_key)?;
+    let scratch = |len: u64| {
+      Buffer::new_slice::<u8>(
+        context.allocator.clone(),
+        BufferCreateInfo {
+          usage: BufferUsage::STORAGE_BUFFER
+            | BufferUsage::TRANSFER_SRC
+            | BufferUsage::TRANSFER_DST,
+          ..Default::default()
+        },
+        AllocationCreateInfo {
+          memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+          ..Default::default()
+        },
+        len,
+      )
+    };
+    let mut builder = config_builder.clone().buffer(scratch(dry_run.buffer_size)?);
+    if let Some(len) = dry_run.input_buffer_size {
+      builder = builder.input_buffer(scratch(len)?);
+    }
+    if let Some(len) = dry_run.output_buffer_size {
+      builder = builder.output_buffer(scratch(len)?);
+    }
+    if let Some(len) = dry_run.kernel_size {
+      builder = builder.kernel(scratch(len)?);
+    }
+
+    let mut best: Option<(Duration, TuningOptions)> = None;
+    for candidate in &self.candidates {
+      let builder = builder.clone().tuning(candidate.clone());
+      let time = match self.measure(context, builder) {
+        Ok(time) => time,
+        Err(_) => continue,
+      };
+      if best.as_ref().is_none_or(|(fastest, _)| time < *fastest) {
+        best = Some((time, candidate.clone()));
+      }
+    }
+    let (_, tuning) = best.ok_or("No tuning candidate could be planned")?;
+
+    self.entries.insert(key, tuning.clone());
+    let _ = self.save();
+    Ok(tuning)
+  }
+
+  /// `config_builder` with the options [`Autotuner::tune`] picks for it.
+  pub fn tuned(
+    &mut self,
+    context: &Context,
+    config_builder: ConfigBuilder,
+  ) -> Result<ConfigBuilder, Box<dyn std::error::Error>> {
+    let tuning = self.tune(context, &config_builder)?;
+    Ok(config_builder.tuning(tuning))
+  }
+
+  /// The fastest of `iterations` forward transforms, after one untimed warm-up. Runs are timed
+  /// with timestamps on the GPU, or on the host if the queue doesn't write them.
+  fn measure(
+    &self,
+    context: &Context,
+    config_builder: ConfigBuilder,
+  ) -> Result<Duration, Box<dyn std::error::Error>> {
+    let mut stage = context.fft_stage(config_builder, FftType::Forward)?;
+    context.run(&mut stage)?;
+
+    let family = context.queue.queue_family_index() as usize;
+    let valid_bits = context.physical.queue_family_properties()[family].timestamp_valid_bits;
+    let queries = match valid_bits {
+      Some(_) => Some(QueryPool::new(
+        context.device.clone(),
+        QueryPoolCreateInfo {
+          query_count: 2,
+          ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+        },
+      )?),
+      None => None,
+    };
+
+    let mut fastest = Duration::MAX;
+    for _ in 0..self.iterations {
+      let time = match (&queries, valid_bits) {
+        (Some(queries), Some(valid_bits)) => {
+          context.run(&mut Timestamped {
+            stage: &mut stage,
+            queries,
+          })?;
+          let mut ticks = [0u64; 2];
+          queries.get_results(0..2, &mut ticks, QueryResultFlags::WAIT)?;
+          let mask = u64::MAX >> (64 - valid_bits.min(64));
+          let elapsed = ticks[1].wrapping_sub(ticks[0]) & mask;
+          let period = context.physical.properties().timestamp_period as f64;
+          Duration::from_nanos((elapsed as f64 * period) as u64)
+        }
+        _ => {
+          let start = Instant::now();
+          context.run(&mut stage)?;
+          start.elapsed()
+        }
+      };
+      fastest = fastest.min(time);
+    }
+    Ok(fastest)
+  }
+
+  /// Writes every entry to the cache file, through a temporary file.
+  pub fn save(&self) -> io::Result<()> {
+    if let Some(dir) = self.path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    let mut text = String::new();
+    for (key, tuning) in &self.entries {
+      let _ = writeln!(text, "{}={}", key, format_tuning(tuning));
+    }
+    let temp = self
+      .path
+      .with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&temp, text)?;
+    fs::rename(temp, &self.path)
+  }
+}
+
+/// Identifies a transform on a device: the device UUID (or name, without one), then every
+/// parameter of the plan but the tuning being chosen.
+fn cache_key(device: &str, plan_key: &PlanKey) -> String {
+  let plan_key = PlanKey {
+    tuning: TuningOptions::default(),
+    ..plan_key.clone()
+  };
+  format!("{} {:?}", device, plan_key)
+}
+
+/// Records a stage between two timestamps, written to the queries of `queries`.
+struct Timestamped<'a> {
+  stage: &'a mut FftStage,
+  queries: &'a QueryPool,
+}
+
+impl Stage for Timestamped<'_> {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    let fns = self.queries.device().fns();
+    let queries = self.queries.handle();
+    unsafe {
+      (fns.v1_0.cmd_reset_query_pool)(command_buffer, queries, 0, 2);
+      (fns.v1_0.cmd_write_timestamp)(
+        command_buffer,
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        queries,
+        0,
+      );
+    }
+    self.stage.record(command_buffer)?;
+    unsafe {
+      (fns.v1_0.cmd_write_timestamp)(
+        command_buffer,
+        vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        queries,
+        1,
+      );
+    }
+    Ok(())
+  }
+}
+
+fn format_tuning(tuning: &TuningOptions) -> String {
+  fn field<T: ToString>(value: Option<T>) -> String {
+    value
+      .map(|v| v.to_string())
+      .unwrap_or_else(|| "-".to_string())
+  }
+  let grouped_batch = tuning.grouped_batch.map(|g| {
+    g.iter()
+      .map(|b| b.to_string())
+      .collect::<Vec<_>>()
+      .join(":")
+  });
+  [
+    field(tuning.coalesced_memory),
+    field(tuning.aim_threads),
+    field(tuning.num_shared_banks),
+    field(tuning.register_boost),
+    field(tuning.register_boost_4_step),
+    field(grouped_batch),
+    field(tuning.bandwidth_boost),
+  ]
+  .join(",")
+}
+
+fn parse_entry(line: &str) -> Option<(String, TuningOptions)> {
+  fn field<T: std::str::FromStr>(value: &str) -> Option<Option<T>> {
+    match value {
+      "-" => Some(None),
+      value => value.parse().ok().map(Some),
+    }
+  }
+
+  let (key, value) = line.rsplit_once('=')?;
+  let fields = value.trim().split(',').collect::<Vec<_>>();
+  if fields.len() != 7 {
+    return None;
+  }
+  let grouped_batch = match fields[5] {
+    "-" => None,
+    value => {
+      let batches = value
+        .split(':')
+        .map(|b| b.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+      Some(batches.try_into().ok()?)
+    }
+  };
+  let tuning = TuningOptions {
+    coalesced_memory: field(fields[0])?,
+    aim_threads: field(fields[1])?,
+    num_shared_banks: field(fields[2])?,
+    register_boost: field(fields[3])?,
+    register_boost_4_step: field(fields[4])?,
+    grouped_batch,
+    bandwidth_boost: field(fields[6])?,
+  };
+  Some((key.to_string(), tuning))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_entry_round_trip() {
+    let mut grouped_batch = [1; crate::config::MAX_FFT_DIMENSIONS];
+    grouped_batch[..2].copy_from_slice(&[4, 2]);
+    let tuning = TuningOptions {
+      aim_threads: Some(128),
+      grouped_batch: Some(grouped_batch),
+      bandwidth_boost: Some(-1),
+      ..Default::default()
+    };
+    let builder = ConfigBuilder::new().dim(&[64, 32]).r2c();
+    let key = cache_key("abab", &builder.plan_key());
+    assert!(key.starts_with("abab PlanKey {"));
+    // Tuning doesn't change the key, but everything else about the plan does
+    let tuned = builder.clone().tuning(tuning.clone());
+    assert_eq!(cache_key("abab", &tuned.plan_key()), key);
+    let strided = builder.buffer_stride(&[33, 33 * 32]);
+    assert_ne!(cache_key("abab", &strided.plan_key()), key);
+
+    let line = format!("{}={}", key, format_tuning(&tuning));
+    assert_eq!(parse_entry(&line), Some((key, tuning)));
+    assert_eq!(parse_entry("key=1,2,3"), None);
+    assert_eq!(parse_entry("key=x,-,-,-,-,-,-"), None);
+  }
+}
@@ -0,0 +1,441 @@
+//! A common interface over the APIs VkFFT runs on, so application code can be written once and
+//! the API chosen at runtime.
+//!
+//! [`Backend`] is implemented for Vulkan [`Context`]s and, with the `cuda` and `level-zero`
+//! features, for [`CudaBackend`] and [`LevelZeroBackend`]. [`AnyBackend`] picks one of them at
+//! runtime and implements the trait too:
+//!
+//! ```ignore
+//! fn spectrum<B: Backend>(backend: &B, buffer: &B::Buffer, n: u32) -> Result<(), vkfft::Error> {
+//!   let mut app = backend.plan(Config::builder().dim(&[n]), buffer)?;
+//!   backend.append(&mut app, false)?;
+//!   backend.sync()
+//! }
+//!
+//! let backend = AnyBackend::detect()?;
+//! ```
+
+#[cfg(any(feature = "cuda", feature = "level-zero"))]
+use std::{ffi::c_void, pin::Pin};
+#[cfg(feature = "cuda")]
+use std::sync::Arc;
+
+use crate::config::ConfigBuilder;
+#[cfg(feature = "cuda")]
+use crate::cuda::CudaApp;
+#[cfg(feature = "level-zero")]
+use crate::level_zero::LevelZeroApp;
+#[cfg(feature = "vulkano")]
+use crate::{
+  buffer::BufferBinding,
+  context::{Context, FftType},
+  stage::FftStage,
+};
+
+/// An API transforms run on.
+pub trait Backend {
+  /// A transform planned for one buffer
+  type App;
+  /// A buffer on the backend's device
+  type Buffer;
+
+  /// Plans the transform of `config_builder` in `buffer`, in place. The builder's device
+  /// handles and buffers are replaced by the backend's.
+  fn plan(
+    &self,
+    config_builder: ConfigBuilder,
+    buffer: &Self::Buffer,
+  ) -> Result<Self::App, crate::Error>;
+
+  /// Submits or enqueues a transform of `app` on the backend's device.
+  fn append(&self, app: &mut Self::App, inverse: bool) -> Result<(), crate::Error>;
+
+  /// Waits until every appended transform has completed.
+  fn sync(&self) -> Result<(), crate::Error>;
+}
+
+/// Transforms run with [`Context::run`], which waits for them, so syncing does nothing.
+#[cfg(feature = "vulkano")]
+impl Backend for Context {
+  type App = FftStage;
+  type Buffer = BufferBinding;
+
+  fn plan(
+    &self,
+    config_builder: ConfigBuilder,
+    buffer: &BufferBinding,
+  ) -> Result<FftStage, crate::Error> {
+    self.fft_stage(config_builder.buffer(buffer.clone()), FftType::Forward)
+  }
+
+  fn append(&self, app: &mut FftStage, inverse: bool) -> Result<(), crate::Error> {
+    if inverse {
+      self.run(&mut app.reversed())
+    } else {
+      self.run(app)
+    }
+  }
+
+  fn sync(&self) -> Result<(), crate::Error> {
+    Ok(())
+  }
+}
+
+/// A CUDA device and the stream transforms are enqueued on.
+#[cfg(feature = "cuda")]
+pub struct CudaBackend {
+  stream: Arc<CudaStream>,
+}
+
+/// The stream of a [`CudaBackend`], shared with every app planned on it so it outlives them.
+#[cfg(feature = "cuda")]
+struct CudaStream {
+  device: i32,
+  stream: *mut c_void,
+  /// The primary context retained by [`CudaBackend::new_default`], which owns the stream
+  primary_context: Option<*mut c_void>,
+}
+
+/// A transform planned on a [`CudaBackend`], keeping its stream alive.
+#[cfg(feature = "cuda")]
+pub struct CudaBackendApp {
+  app: Pin<Box<CudaApp>>,
+  _stream: Arc<CudaStream>,
+}
+
+/// A device allocation of a [`CudaBackend`].
+#[cfg(feature = "cuda")]
+#[derive(Debug, Clone, Copy)]
+pub struct CudaBuffer {
+  ptr: u64,
+  size: u64,
+}
+
+#[cfg(feature = "cuda")]
+impl CudaBackend {
+  /// Transforms on `stream` of `device`.
+  ///
+  /// # Safety
+  ///
+  /// `device` must be a valid device whose context is current on every thread planning
+  /// transforms, and `stream` must belong to it and outlive the backend and every app planned on
+  /// it.
+  pub unsafe fn from_raw(device: i32, stream: *mut c_void) -> Self {
+    Self {
+      stream: Arc::new(CudaStream {
+        device,
+        stream,
+        primary_context: None,
+      }),
+    }
+  }
+
+  /// Initializes CUDA and creates a stream on the first device, in its primary context, which
+  /// is made current on the calling thread and on threads planning transforms. The stream is
+  /// destroyed and the context released once the backend and every app planned on it have been
+  /// dropped.
+  pub fn new_default() -> Result<Self, crate::Error> {
+    use vkfft_sys::cuda::*;
+
+    let check = |name, result: CUresult| match result {
+      0 => Ok(()),
+      code => Err(crate::Error::Backend(name, code as i64)),
+    };
+    let mut device = 0;
+    let mut context = std::ptr::null_mut();
+    let mut stream = std::ptr::null_mut();
+    unsafe {
+      check("cuInit", cuInit(0))?;
+      check("cuDeviceGet", cuDeviceGet(&mut device, 0))?;
+      check(
+        "cuDevicePrimaryCtxRetain",
+        cuDevicePrimaryCtxRetain(&mut context, device),
+      )?;
+    }
+    // Releases the context if the stream can't be created
+    let mut owner = CudaStream {
+      device,
+      stream: std::ptr::null_mut(),
+      primary_context: Some(context as *mut c_void),
+    };
+    unsafe {
+      check("cuCtxSetCurrent", cuCtxSetCurrent(context))?;
+      check("cuStreamCreate", cuStreamCreate(&mut stream, 0))?;
+    }
+    owner.stream = stream as *mut c_void;
+    Ok(Self {
+      stream: Arc::new(owner),
+    })
+  }
+}
+
+#[cfg(feature = "cuda")]
+impl Drop for CudaStream {
+  fn drop(&mut self) {
+    use vkfft_sys::cuda::*;
+
+    // Streams passed to `from_raw` belong to the caller
+    let context = match self.primary_context {
+      Some(context) => context,
+      None => return,
+    };
+    unsafe {
+      cuCtxSetCurrent(context as _);
+      if !self.stream.is_null() {
+        // Work still queued would fail once the context is released
+        cuStreamSynchronize(self.stream as _);
+        cuStreamDestroy_v2(self.stream as _);
+      }
+      cuDevicePrimaryCtxRelease_v2(self.device);
+    }
+  }
+}
+
+#[cfg(feature = "cuda")]
+impl CudaBuffer {
+  /// # Safety
+  ///
+  /// `ptr` must be a device allocation of at least `size` bytes, outliving every app planned for
+  /// it.
+  pub unsafe fn from_raw(ptr: u64, size: u64) -> Self {
+    Self { ptr, size }
+  }
+}
+
+#[cfg(feature = "cuda")]
+impl Backend for CudaBackend {
+  type App = CudaBackendApp;
+  type Buffer = CudaBuffer;
+
+  fn plan(
+    &self,
+    config_builder: ConfigBuilder,
+    buffer: &CudaBuffer,
+  ) -> Result<CudaBackendApp, crate::Error> {
+    let stream = &self.stream;
+    if let Some(context) = stream.primary_context {
+      match unsafe { vkfft_sys::cuda::cuCtxSetCurrent(context as _) } {
+        0 => {}
+        code => return Err(crate::Error::Backend("cuCtxSetCurrent", code as i64)),
+      }
+    }
+    // The app holds on to the stream, and the buffer outlives it by `CudaBuffer::from_raw`
+    let app = unsafe {
+      CudaApp::new(
+        &config_builder,
+        stream.device,
+        stream.stream,
+        buffer.ptr,
+        buffer.size,
+      )
+    }?;
+    Ok(CudaBackendApp {
+      app,
+      _stream: stream.clone(),
+    })
+  }
+
+  fn append(&self, app: &mut CudaBackendApp, inverse: bool) -> Result<(), crate::Error> {
+    app.app.as_mut().launch(inverse)
+  }
+
+  fn sync(&self) -> Result<(), crate::Error> {
+    match unsafe { vkfft_sys::cuda::cuStreamSynchronize(self.stream.stream as _) } {
+      0 => Ok(()),
+      code => Err(crate::Error::Backend("cuStreamSynchronize", code as i64)),
+    }
+  }
+}
+
+/// A Level Zero device with an immediate command list transforms are appended to, so they start
+/// right away.
+#[cfg(feature = "level-zero")]
+pub struct LevelZeroBackend {
+  device: *mut c_void,
+  context: *mut c_void,
+  command_queue: *mut c_void,
+  command_queue_ordinal: u32,
+  command_list: *mut c_void,
+}
+
+/// A device allocation of a [`LevelZeroBackend`].
+#[cfg(feature = "level-zero")]
+#[derive(Debug, Clone, Copy)]
+pub struct LevelZeroBuffer {
+  ptr: *mut c_void,
+  size: u64,
+}
+
+#[cfg(feature = "level-zero")]
+impl LevelZeroBackend {
+  /// Transforms appended to the immediate `command_list`, with lookup tables uploaded through
+  /// `command_queue` of the queue group `command_queue_ordinal`.
+  ///
+  /// # Safety
+  ///
+  /// As for [`LevelZeroApp::new`], with `command_list` an immediate command list of `device`.
+  /// The handles must outlive the backend and every app planned on it.
+  pub unsafe fn from_raw(
+    device: *mut c_void,
+    context: *mut c_void,
+    command_queue: *mut c_void,
+    command_queue_ordinal: u32,
+    command_list: *mut c_void,
+  ) -> Self {
+    Self {
+      device,
+      context,
+      command_queue,
+      command_queue_ordinal,
+      command_list,
+    }
+  }
+}
+
+#[cfg(feature = "level-zero")]
+impl LevelZeroBuffer {
+  /// # Safety
+  ///
+  /// `ptr` must be a device allocation of at least `size` bytes, outliving every app planned for
+  /// it.
+  pub unsafe fn from_raw(ptr: *mut c_void, size: u64) -> Self {
+    Self { ptr, size }
+  }
+}
+
+#[cfg(feature = "level-zero")]
+impl Backend for LevelZeroBackend {
+  type App = Pin<Box<LevelZeroApp>>;
+  type Buffer = LevelZeroBuffer;
+
+  fn plan(
+    &self,
+    config_builder: ConfigBuilder,
+    buffer: &LevelZeroBuffer,
+  ) -> Result<Self::App, crate::Error> {
+    unsafe {
+      LevelZeroApp::new(
+        &config_builder,
+        self.device,
+        self.context,
+        self.command_queue,
+        self.command_queue_ordinal,
+        buffer.ptr,
+        buffer.size,
+      )
+    }
+  }
+
+  fn append(&self, app: &mut Self::App, inverse: bool) -> Result<(), crate::Error> {
+    unsafe { app.as_mut().append(self.command_list, inverse) }
+  }
+
+  fn sync(&self) -> Result<(), crate::Error> {
+    let result = unsafe {
+      vkfft_sys::level_zero::zeCommandListHostSynchronize(self.command_list as _, u64::MAX)
+    };
+    match result {
+      0 => Ok(()),
+      code => Err(crate::Error::Backend(
+        "zeCommandListHostSynchronize",
+        code as i64,
+      )),
+    }
+  }
+}
+
+/// One of the backends the crate was built with, chosen at runtime.
+pub enum AnyBackend {
+  #[cfg(feature = "vulkano")]
+  Vulkan(Context),
+  #[cfg(feature = "cuda")]
+  Cuda(CudaBackend),
+  #[cfg(feature = "level-zero")]
+  LevelZero(LevelZeroBackend),
+}
+
+/// A buffer of an [`AnyBackend`], which must match the backend it is used with.
+pub enum AnyBuffer {
+  #[cfg(feature = "vulkano")]
+  Vulkan(BufferBinding),
+  #[cfg(feature = "cuda")]
+  Cuda(CudaBuffer),
+  #[cfg(feature = "level-zero")]
+  LevelZero(LevelZeroBuffer),
+}
+
+/// A transform planned on an [`AnyBackend`].
+pub enum AnyApp {
+  #[cfg(feature = "vulkano")]
+  Vulkan(Box<FftStage>),
+  #[cfg(feature = "cuda")]
+  Cuda(CudaBackendApp),
+  #[cfg(feature = "level-zero")]
+  LevelZero(Pin<Box<LevelZeroApp>>),
+}
+
+impl AnyBackend {
+  /// The first backend available on this machine: a context on the first Vulkan device, or the
+  /// first CUDA device. Level Zero needs handles from the application, so it is never detected.
+  pub fn detect() -> Result<Self, crate::Error> {
+    #[cfg(feature = "vulkano")]
+    let backend = Context::new_standalone().map(Self::Vulkan);
+    #[cfg(not(feature = "vulkano"))]
+    let backend = Err(crate::Error::NoBackend);
+    #[cfg(feature = "cuda")]
+    let backend = backend.or_else(|_| CudaBackend::new_default().map(Self::Cuda));
+    backend
+  }
+}
+
+impl Backend for AnyBackend {
+  type App = AnyApp;
+  type Buffer = AnyBuffer;
+
+  #[allow(unreachable_patterns, unused_variables)]
+  fn plan(
+    &self,
+    config_builder: ConfigBuilder,
+    buffer: &AnyBuffer,
+  ) -> Result<AnyApp, crate::Error> {
+    match (self, buffer) {
+      #[cfg(feature = "vulkano")]
+      (Self::Vulkan(backend), AnyBuffer::Vulkan(buffer)) => Ok(AnyApp::Vulkan(Box::new(
+        backend.plan(config_builder, buffer)?,
+      ))),
+      #[cfg(feature = "cuda")]
+      (Self::Cuda(backend), AnyBuffer::Cuda(buffer)) => {
+        Ok(AnyApp::Cuda(backend.plan(config_builder, buffer)?))
+      }
+      #[cfg(feature = "level-zero")]
+      (Self::LevelZero(backend), AnyBuffer::LevelZero(buffer)) => {
+        Ok(AnyApp::LevelZero(backend.plan(config_builder, buffer)?))
+      }
+      _ => Err(crate::Error::BackendMismatch),
+    }
+  }
+
+  #[allow(unreachable_patterns, unused_variables)]
+  fn append(&self, app: &mut AnyApp, inverse: bool) -> Result<(), crate::Error> {
+    match (self, app) {
+      #[cfg(feature = "vulkano")]
+      (Self::Vulkan(backend), AnyApp::Vulkan(app)) => backend.append(app, inverse),
+      #[cfg(feature = "cuda")]
+      (Self::Cuda(backend), AnyApp::Cuda(app)) => backend.append(app, inverse),
+      #[cfg(feature = "level-zero")]
+      (Self::LevelZero(backend), AnyApp::LevelZero(app)) => backend.append(app, inverse),
+      _ => Err(crate::Error::BackendMismatch),
+    }
+  }
+
+  fn sync(&self) -> Result<(), crate::Error> {
+    match self {
+      #[cfg(feature = "vulkano")]
+      Self::Vulkan(backend) => backend.sync(),
+      #[cfg(feature = "cuda")]
+      Self::Cuda(backend) => backend.sync(),
+      #[cfg(feature = "level-zero")]
+      Self::LevelZero(backend) => backend.sync(),
+    }
+  }
+}
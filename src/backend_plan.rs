@@ -0,0 +1,57 @@
+//! Marshalling plans for VkFFT's backends other than Vulkan, whose configurations share the
+//! fields describing the plan with the Vulkan one.
+
+use crate::config::{ConfigBuilder, ConfigError, Normalization};
+
+/// Checks and marshals the plan of `config_builder` as for Vulkan, for copying it over with
+/// [`copy_plan`]. Fails if the plan needs features `backend` doesn't support yet.
+pub(crate) fn vulkan_plan(
+  config_builder: &ConfigBuilder,
+  backend: &'static str,
+) -> Result<vkfft_sys::VkFFTConfiguration, ConfigError> {
+  let plan_key = config_builder.plan_key();
+  let unsupported = [
+    (
+      "input and output buffers",
+      plan_key.input_buffer || plan_key.output_buffer,
+    ),
+    ("temp buffers", plan_key.temp_buffer),
+    ("convolutions", plan_key.convolution),
+    (
+      "orthonormal scaling",
+      plan_key.normalization == Normalization::Ortho,
+    ),
+    (
+      "custom Bluestein padding",
+      !plan_key.primes.bluestein_padding.is_empty(),
+    ),
+  ];
+  if let Some((name, _)) = unsupported.iter().find(|(_, unsupported)| *unsupported) {
+    return Err(ConfigError::UnsupportedOnBackend(backend, name));
+  }
+
+  let mut config = unsafe { std::mem::zeroed::<vkfft_sys::VkFFTConfiguration>() };
+  plan_key.write_sys(&mut config)?;
+  Ok(config)
+}
+
+/// Copies the fields describing the plan from a Vulkan configuration.
+macro_rules! copy_plan {
+  ($to:expr, $from:expr) => {
+    copy_plan!($to, $from;
+      FFTdim, size, normalize, performZeropadding, fft_zeropad_left, fft_zeropad_right,
+      omitDimension, performR2C, performDCT, performDST, coordinateFeatures,
+      disableReorderFourStep, isInputFormatted, isOutputFormatted, inverseReturnToInputBuffer,
+      doublePrecision, quadDoubleDoublePrecision, halfPrecision, halfPrecisionMemoryOnly,
+      numberBatches, bufferOffset, specifyOffsetsAtLaunch, bufferStride, fixMaxRadixBluestein,
+      fixMinRaderPrimeMult, fixMaxRaderPrimeMult, fixMinRaderPrimeFFT, fixMaxRaderPrimeFFT,
+      aimThreads, coalescedMemory, groupedBatch, numSharedBanks, performBandwidthBoost,
+      registerBoost, registerBoost4Step, useLUT, keepShaderCode, printMemoryLayout
+    )
+  };
+  ($to:expr, $from:expr; $($field:ident),*) => {
+    $($to.$field = $from.$field;)*
+  };
+}
+
+pub(crate) use copy_plan;
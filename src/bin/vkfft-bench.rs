@@ -0,0 +1,265 @@
+//! Measures transform throughput on the local device, sweeping sizes, batch counts and
+//! precisions, and prints a table of GFLOP/s and bandwidth per configuration.
+//!
+//! ```text
+//! vkfft-bench [--device NAME] [--batch N,...] [--precision single,double,half,quad]
+//!             [--iterations N] [--r2c] [SIZE...]
+//! ```
+//!
+//! Each `SIZE` is a dimension list such as `1024`, `512x512` or `64x64x64`; without any, powers
+//! of two from 2^8 to 2^24 points are swept in 1D. Precisions the device doesn't support are
+//! skipped.
+//!
+//! Timings cover a command buffer of `--iterations` back-to-back in-place forward transforms, so
+//! submission overhead is amortized. GFLOP/s use the conventional `5 N log2 N` operation count
+//! (half of it for R2C), and bandwidth assumes each transform reads and writes the buffer once,
+//! which is a lower bound for multi-pass plans.
+
+use std::{error::Error, time::Instant};
+
+use ash::vk;
+use vkfft::{
+  config::{ConfigBuilder, Precision},
+  context::{Context, FftType},
+  error,
+  stage::{FftStage, Stage},
+};
+use vulkano::{
+  buffer::{Buffer, BufferCreateInfo, BufferUsage},
+  instance::Instance,
+  memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+  VulkanLibrary,
+};
+
+struct Options {
+  device: Option<String>,
+  batch_counts: Vec<u32>,
+  precisions: Vec<Precision>,
+  iterations: u32,
+  r2c: bool,
+  sizes: Vec<Vec<u32>>,
+}
+
+fn usage() -> ! {
+  eprintln!(
+    "usage: vkfft-bench [--device NAME] [--batch N,...] [--precision single,double,half,quad] \
+     [--iterations N] [--r2c] [SIZE...]"
+  );
+  std::process::exit(2);
+}
+
+fn parse_list<T, F: Fn(&str) -> Option<T>>(value: Option<String>, parse: F) -> Vec<T> {
+  let list: Option<Vec<T>> = value
+    .unwrap_or_else(|| usage())
+    .split(',')
+    .map(|v| parse(v.trim()))
+    .collect();
+  match list {
+    Some(list) if !list.is_empty() => list,
+    _ => usage(),
+  }
+}
+
+fn parse_options() -> Options {
+  let mut options = Options {
+    device: None,
+    batch_counts: vec![1],
+    precisions: vec![Precision::Single],
+    iterations: 20,
+    r2c: false,
+    sizes: Vec::new(),
+  };
+
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--device" => options.device = Some(args.next().unwrap_or_else(|| usage())),
+      "--batch" => options.batch_counts = parse_list(args.next(), |v| v.parse().ok()),
+      "--precision" => {
+        options.precisions = parse_list(args.next(), |v| match v {
+          "single" => Some(Precision::Single),
+          "double" => Some(Precision::Double),
+          "half" => Some(Precision::Half),
+          "quad" => Some(Precision::QuadDoubleDouble),
+          _ => None,
+        })
+      }
+      "--iterations" => {
+        options.iterations = args
+          .next()
+          .and_then(|v| v.parse().ok())
+          .filter(|n| *n > 0)
+          .unwrap_or_else(|| usage())
+      }
+      "--r2c" => options.r2c = true,
+      "--help" | "-h" => usage(),
+      size => {
+        let dims: Option<Vec<u32>> = size.split('x').map(|d| d.parse().ok()).collect();
+        match dims {
+          Some(dims) if (1..=3).contains(&dims.len()) => options.sizes.push(dims),
+          _ => usage(),
+        }
+      }
+    }
+  }
+
+  if options.sizes.is_empty() {
+    options.sizes = (8..=24).map(|p| vec![1u32 << p]).collect();
+  }
+
+  options
+}
+
+/// A transform recorded several times into the same command buffer.
+struct Repeat {
+  stage: FftStage,
+  count: u32,
+}
+
+impl Stage for Repeat {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    for _ in 0..self.count {
+      self.stage.record(command_buffer)?;
+    }
+    Ok(())
+  }
+}
+
+/// Bytes of the in-place buffer a plan of `dims` needs.
+fn buffer_bytes(dims: &[u32], batch_count: u32, precision: Precision, r2c: bool) -> u64 {
+  let scalar = precision.compute_scalar_bytes();
+  let mut points = batch_count as u64;
+  for (i, dim) in dims.iter().enumerate() {
+    points *= if i == 0 && r2c {
+      (*dim / 2 + 1) as u64
+    } else {
+      *dim as u64
+    };
+  }
+  2 * scalar * points
+}
+
+fn builder(dims: &[u32]) -> ConfigBuilder {
+  match dims.len() {
+    1 => ConfigBuilder::new().dim(&[dims[0]]),
+    2 => ConfigBuilder::new().dim(&[dims[0], dims[1]]),
+    _ => ConfigBuilder::new().dim(&[dims[0], dims[1], dims[2]]),
+  }
+}
+
+/// Seconds per transform of `dims`, and the size of its buffer in bytes.
+fn measure(
+  context: &Context,
+  options: &Options,
+  dims: &[u32],
+  batch_count: u32,
+  precision: Precision,
+) -> Result<(f64, u64), Box<dyn Error>> {
+  let bytes = buffer_bytes(dims, batch_count, precision, options.r2c);
+  let buffer = Buffer::new_slice::<u8>(
+    context.allocator.clone(),
+    BufferCreateInfo {
+      usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST,
+      ..Default::default()
+    },
+    AllocationCreateInfo {
+      memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+      ..Default::default()
+    },
+    bytes,
+  )?;
+
+  let mut config_builder = builder(dims)
+    .buffer(buffer)
+    .batch_count(batch_count)
+    .precision(precision);
+  if options.r2c {
+    config_builder = config_builder.r2c();
+  }
+  let stage = context.fft_stage(config_builder, FftType::Forward)?;
+
+  // One untimed run pays for pipeline warm-up
+  let mut warm_up = Repeat { stage, count: 1 };
+  context.run(&mut warm_up)?;
+
+  let mut repeat = Repeat {
+    stage: warm_up.stage,
+    count: options.iterations,
+  };
+  let start = Instant::now();
+  context.run(&mut repeat)?;
+  Ok((
+    start.elapsed().as_secs_f64() / options.iterations as f64,
+    bytes,
+  ))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let options = parse_options();
+
+  let library = VulkanLibrary::new()?;
+  let instance = Instance::new(
+    library.clone(),
+    vkfft::context::instance_create_info(&library),
+  )?;
+  let mut context_builder = Context::builder(&instance);
+  if let Some(device) = &options.device {
+    context_builder = context_builder.device_name(device.clone());
+  }
+  let context = context_builder.build()?;
+
+  println!(
+    "{} (VkFFT {}), {} iterations per configuration",
+    context.physical.properties().device_name,
+    vkfft::version(),
+    options.iterations
+  );
+  println!(
+    "{:>16} {:>8} {:>9} {:>12} {:>10} {:>10}",
+    "size", "batch", "precision", "time (us)", "GFLOP/s", "GB/s"
+  );
+
+  for precision in &options.precisions {
+    if let Some(feature) = precision.missing_feature(&context.physical) {
+      println!(
+        "Skipping {:?} precision: {} is not supported",
+        precision, feature
+      );
+      continue;
+    }
+
+    for dims in &options.sizes {
+      for batch_count in &options.batch_counts {
+        let size = dims
+          .iter()
+          .map(|d| d.to_string())
+          .collect::<Vec<_>>()
+          .join("x");
+        match measure(&context, &options, dims, *batch_count, *precision) {
+          Ok((seconds, bytes)) => {
+            let points = dims.iter().map(|d| *d as f64).product::<f64>() * *batch_count as f64;
+            let mut flops = 5.0 * points * (points / *batch_count as f64).log2();
+            if options.r2c {
+              flops /= 2.0;
+            }
+            println!(
+              "{:>16} {:>8} {:>9} {:>12.2} {:>10.1} {:>10.1}",
+              size,
+              batch_count,
+              format!("{:?}", precision),
+              seconds * 1e6,
+              flops / seconds * 1e-9,
+              2.0 * bytes as f64 / seconds * 1e-9
+            );
+          }
+          Err(e) => println!(
+            "{:>16} {:>8} {:>9?} failed: {}",
+            size, batch_count, precision, e
+          ),
+        }
+      }
+    }
+  }
+
+  Ok(())
+}
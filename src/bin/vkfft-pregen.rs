@@ -0,0 +1,118 @@
+//! Pre-generates VkFFT applications on the local device and writes them to a bundle that can be
+//! embedded with `include_bytes!` and loaded with `vkfft::bundle::Bundle`.
+//!
+//! ```text
+//! vkfft-pregen [--output FILE] [--batch N] [--r2c] [--normalize] SIZE...
+//! ```
+//!
+//! Each `SIZE` is a dimension list such as `1024`, `512x512` or `64x64x64`. Plans are generated
+//! in place on a single buffer, matching configs built with `ConfigBuilder::buffer`.
+
+use std::error::Error;
+
+use vkfft::{bundle::BundleWriter, config::ConfigBuilder, context::Context};
+
+struct Options {
+  output: String,
+  batch_count: u32,
+  r2c: bool,
+  normalize: bool,
+  sizes: Vec<Vec<u32>>,
+}
+
+fn usage() -> ! {
+  eprintln!("usage: vkfft-pregen [--output FILE] [--batch N] [--r2c] [--normalize] SIZE...");
+  std::process::exit(2);
+}
+
+fn parse_options() -> Options {
+  let mut options = Options {
+    output: "kernels.vkfft".to_string(),
+    batch_count: 1,
+    r2c: false,
+    normalize: false,
+    sizes: Vec::new(),
+  };
+
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--output" | "-o" => options.output = args.next().unwrap_or_else(|| usage()),
+      "--batch" => {
+        options.batch_count = args
+          .next()
+          .and_then(|v| v.parse().ok())
+          .unwrap_or_else(|| usage())
+      }
+      "--r2c" => options.r2c = true,
+      "--normalize" => options.normalize = true,
+      "--help" | "-h" => usage(),
+      size => {
+        let dims: Option<Vec<u32>> = size.split('x').map(|d| d.parse().ok()).collect();
+        match dims {
+          Some(dims) if (1..=3).contains(&dims.len()) => options.sizes.push(dims),
+          _ => usage(),
+        }
+      }
+    }
+  }
+
+  if options.sizes.is_empty() {
+    usage();
+  }
+
+  options
+}
+
+/// Number of complex single precision values in the in-place buffer a plan of `dims` needs.
+fn buffer_len(options: &Options, dims: &[u32]) -> usize {
+  let mut count = options.batch_count as usize;
+  for (i, dim) in dims.iter().enumerate() {
+    count *= if i == 0 && options.r2c {
+      (*dim / 2 + 1) as usize
+    } else {
+      *dim as usize
+    };
+  }
+  count
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let options = parse_options();
+
+  let context = Context::new_standalone()?;
+
+  println!(
+    "Generating {} plan(s) on {}",
+    options.sizes.len(),
+    context.physical.properties().device_name
+  );
+
+  let mut writer = BundleWriter::new(&context.physical);
+  for dims in &options.sizes {
+    let len = buffer_len(&options, dims);
+    let buffer = context.new_buffer_from_iter((0..len).map(|_| [0f32; 2]))?;
+
+    let mut builder = ConfigBuilder::new()
+      .buffer(buffer.buffer().clone())
+      .batch_count(options.batch_count);
+    builder = match dims.len() {
+      1 => builder.dim(&[dims[0]]),
+      2 => builder.dim(&[dims[0], dims[1]]),
+      _ => builder.dim(&[dims[0], dims[1], dims[2]]),
+    };
+    if options.r2c {
+      builder = builder.r2c();
+    }
+    if options.normalize {
+      builder = builder.normalize();
+    }
+
+    writer.generate(&context, builder)?;
+    println!("  {:?}", dims);
+  }
+
+  writer.write(&options.output)?;
+  println!("Wrote {} plan(s) to {}", writer.len(), options.output);
+  Ok(())
+}
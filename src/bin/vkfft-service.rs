@@ -0,0 +1,117 @@
+//! Serves FFTs to other processes over a Unix socket, keeping plans warm between requests. See
+//! `vkfft::service` for the protocol.
+//!
+//! ```text
+//! vkfft-service [--socket PATH] [--data-dir PATH] [--max-buffer BYTES] [--max-plans N]
+//!               [--plan SPEC]...
+//! ```
+//!
+//! Each `--plan` generates a plan at startup, e.g. `--plan "1024x1024 batch=4 r2c"`. Clients
+//! may only transform files in the data directory, `/dev/shm` by default, and only the user
+//! running the service can connect to the socket. `--max-buffer` limits the buffer size of a
+//! plan in bytes, and `--max-plans` the number of plans kept warm.
+
+use std::{
+  error::Error,
+  os::unix::{fs::FileTypeExt, net::UnixListener},
+};
+
+use vkfft::{
+  context::Context,
+  service::{PlanSpec, Server},
+};
+
+struct Options {
+  socket: String,
+  data_dir: String,
+  max_buffer_len: Option<u64>,
+  max_plans: Option<usize>,
+  plans: Vec<PlanSpec>,
+}
+
+fn usage() -> ! {
+  eprintln!(
+    "usage: vkfft-service [--socket PATH] [--data-dir PATH] [--max-buffer BYTES] \
+     [--max-plans N] [--plan SPEC]..."
+  );
+  std::process::exit(2);
+}
+
+fn parse_options() -> Options {
+  let mut options = Options {
+    socket: "/tmp/vkfft.sock".to_string(),
+    data_dir: "/dev/shm".to_string(),
+    max_buffer_len: None,
+    max_plans: None,
+    plans: Vec::new(),
+  };
+
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--socket" | "-s" => options.socket = args.next().unwrap_or_else(|| usage()),
+      "--data-dir" => options.data_dir = args.next().unwrap_or_else(|| usage()),
+      "--max-buffer" => {
+        options.max_buffer_len = Some(
+          args
+            .next()
+            .and_then(|len| len.parse().ok())
+            .unwrap_or_else(|| usage()),
+        )
+      }
+      "--max-plans" => {
+        options.max_plans = Some(
+          args
+            .next()
+            .and_then(|count| count.parse().ok())
+            .unwrap_or_else(|| usage()),
+        )
+      }
+      "--plan" => options.plans.push(
+        args
+          .next()
+          .and_then(|spec| spec.parse().ok())
+          .unwrap_or_else(|| usage()),
+      ),
+      _ => usage(),
+    }
+  }
+
+  options
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+  let options = parse_options();
+
+  let context = Context::new_standalone()?;
+  println!("Serving on {}", context.physical.properties().device_name);
+
+  let mut server = Server::new(context, &options.data_dir)?;
+  if let Some(len) = options.max_buffer_len {
+    server = server.with_max_buffer_len(len);
+  }
+  if let Some(count) = options.max_plans {
+    server = server.with_max_plans(count);
+  }
+  for spec in &options.plans {
+    let response = server.handle(&format!("PLAN {}", spec));
+    println!("  {}: {}", spec, response);
+  }
+
+  // A socket left behind by a previous run would make binding fail, but anything else at the
+  // path isn't ours to remove
+  if let Ok(metadata) = std::fs::symlink_metadata(&options.socket) {
+    if !metadata.file_type().is_socket() {
+      return Err(format!("{} exists and isn't a socket", options.socket).into());
+    }
+    std::fs::remove_file(&options.socket)?;
+  }
+  // Created without permissions for anyone but the user, so other users can't connect
+  let umask = unsafe { libc::umask(0o177) };
+  let listener = UnixListener::bind(&options.socket);
+  unsafe { libc::umask(umask) };
+  let listener = listener?;
+  println!("Listening on {}", options.socket);
+  server.serve(&listener)?;
+  Ok(())
+}
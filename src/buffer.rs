@@ -0,0 +1,175 @@
+//! Buffers handed to VkFFT, independent of the Vulkan wrapper or allocator that created them.
+
+use std::{any::Any, fmt, sync::Arc};
+
+use ash::vk;
+
+/// A range of a Vulkan buffer that VkFFT can read and write.
+///
+/// Implemented for vulkano's `Arc<Buffer>` and `Subbuffer`, and for [`BufferBinding`], which
+/// wraps raw ash handles. Other allocators can implement it for their own buffer types.
+///
+/// # Safety
+///
+/// `handle` must be a valid buffer containing the `size` bytes starting at `offset`, and must
+/// stay alive as long as the token returned by `keep_alive`, or, if there is none, until every
+/// application and command buffer using it has been destroyed or has completed.
+pub unsafe trait FftBuffer {
+  fn handle(&self) -> vk::Buffer;
+
+  /// Size of the range in bytes.
+  fn size(&self) -> u64;
+
+  /// Start of the range in bytes.
+  fn offset(&self) -> u64 {
+    0
+  }
+
+  /// A value keeping the buffer alive while VkFFT may access it.
+  fn keep_alive(&self) -> Option<Arc<dyn Any + Send + Sync>>;
+
+  fn binding(&self) -> BufferBinding {
+    BufferBinding {
+      handle: self.handle(),
+      offset: self.offset(),
+      size: self.size(),
+      keep_alive: self.keep_alive(),
+    }
+  }
+}
+
+/// A buffer bound to a config or launch, together with whatever keeps it alive.
+#[derive(Clone)]
+pub struct BufferBinding {
+  handle: vk::Buffer,
+  offset: u64,
+  size: u64,
+  keep_alive: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl BufferBinding {
+  /// Binds the first `size` bytes of a raw buffer.
+  ///
+  /// # Safety
+  ///
+  /// `handle` must be a valid buffer of at least `size` bytes, and must stay alive until every
+  /// application and command buffer using it has been destroyed or has completed.
+  pub unsafe fn from_raw(handle: vk::Buffer, size: u64) -> Self {
+    Self::from_raw_range(handle, 0, size)
+  }
+
+  /// Binds the `size` bytes of a raw buffer starting at `offset`.
+  ///
+  /// # Safety
+  ///
+  /// As for [`BufferBinding::from_raw`], with the buffer holding at least `offset + size` bytes.
+  pub unsafe fn from_raw_range(handle: vk::Buffer, offset: u64, size: u64) -> Self {
+    Self {
+      handle,
+      offset,
+      size,
+      keep_alive: None,
+    }
+  }
+
+  /// Binds a raw buffer owned by `owner`, which is kept alive as long as the binding is.
+  ///
+  /// # Safety
+  ///
+  /// `handle` must be a valid buffer of at least `size` bytes that lives as long as `owner`.
+  pub unsafe fn owned<T>(handle: vk::Buffer, size: u64, owner: Arc<T>) -> Self
+  where
+    T: Any + Send + Sync,
+  {
+    Self {
+      handle,
+      offset: 0,
+      size,
+      keep_alive: Some(owner),
+    }
+  }
+
+  pub fn handle(&self) -> vk::Buffer {
+    self.handle
+  }
+
+  pub fn offset(&self) -> u64 {
+    self.offset
+  }
+
+  pub fn size(&self) -> u64 {
+    self.size
+  }
+
+  /// The size VkFFT is given for the buffer. Offsets are applied by the kernels, so this covers
+  /// everything up to the end of the range. `None` if the end of the range overflows.
+  pub(crate) fn bound_size(&self) -> Option<u64> {
+    self.offset.checked_add(self.size)
+  }
+}
+
+unsafe impl FftBuffer for BufferBinding {
+  fn handle(&self) -> vk::Buffer {
+    self.handle
+  }
+
+  fn size(&self) -> u64 {
+    self.size
+  }
+
+  fn offset(&self) -> u64 {
+    self.offset
+  }
+
+  fn keep_alive(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+    self.keep_alive.clone()
+  }
+
+  fn binding(&self) -> BufferBinding {
+    self.clone()
+  }
+}
+
+impl fmt::Debug for BufferBinding {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("BufferBinding")
+      .field("handle", &self.handle)
+      .field("offset", &self.offset)
+      .field("size", &self.size)
+      .finish()
+  }
+}
+
+#[cfg(feature = "vulkano")]
+unsafe impl FftBuffer for Arc<vulkano::buffer::Buffer> {
+  fn handle(&self) -> vk::Buffer {
+    vulkano::VulkanObject::handle(&**self)
+  }
+
+  fn size(&self) -> u64 {
+    vulkano::buffer::Buffer::size(self)
+  }
+
+  fn keep_alive(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+    Some(self.clone())
+  }
+}
+
+#[cfg(feature = "vulkano")]
+unsafe impl<T: ?Sized> FftBuffer for vulkano::buffer::Subbuffer<T> {
+  fn handle(&self) -> vk::Buffer {
+    vulkano::VulkanObject::handle(&**self.buffer())
+  }
+
+  fn size(&self) -> u64 {
+    vulkano::buffer::Subbuffer::size(self)
+  }
+
+  fn offset(&self) -> u64 {
+    vulkano::buffer::Subbuffer::offset(self)
+  }
+
+  fn keep_alive(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+    Some(self.buffer().clone())
+  }
+}
@@ -0,0 +1,300 @@
+//! Bundles of pre-generated VkFFT applications.
+//!
+//! Generating and compiling kernels dominates the cost of creating an [`App`]. A bundle stores
+//! the serialized kernels of a set of plans, generated ahead of time on the target device (see
+//! the `vkfft-pregen` binary), so they can be embedded with `include_bytes!` and loaded at
+//! runtime without recompiling.
+//!
+//! Every entry carries a checksum of its kernels, which [`Bundle::parse`] verifies. It catches
+//! corruption, not tampering: VkFFT loads the kernels without validating them, so bundles must
+//! come from a trusted source, like one embedded in the binary.
+//!
+//! ```ignore
+//! static KERNELS: &[u8] = include_bytes!("kernels.vkfft");
+//!
+//! let bundle = Bundle::parse(KERNELS)?;
+//! let app = bundle.app(config)?;
+//! ```
+
+use std::{error::Error, fs, io, path::Path, pin::Pin};
+
+use derive_more::{Display, Error};
+use vulkano::device::physical::PhysicalDevice;
+
+use crate::{
+  app::App,
+  config::{Config, ConfigBuilder, PlanKey},
+  context::Context,
+  disk_cache::fnv1a,
+  error,
+};
+
+const MAGIC: &[u8; 8] = b"VKFFTBND";
+const FORMAT_VERSION: u32 = 2;
+
+#[derive(Display, Debug, Error)]
+pub enum BundleError {
+  InvalidMagic,
+  #[display("Unsupported bundle format version {}", _0)]
+  UnsupportedVersion(#[error(not(source))] u32),
+  Truncated,
+  InvalidKey,
+  ChecksumMismatch,
+}
+
+/// Identifies the device and VkFFT build a bundle was generated with. Serialized kernels are
+/// only valid on a matching device, driver and library version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BundleTarget {
+  pub vkfft_version: u32,
+  pub vendor_id: u32,
+  pub device_id: u32,
+  pub driver_version: u32,
+}
+
+impl BundleTarget {
+  pub fn of(physical_device: &PhysicalDevice) -> Self {
+    let properties = physical_device.properties();
+    Self {
+      vkfft_version: unsafe { vkfft_sys::VkFFTGetVersion() } as u32,
+      vendor_id: properties.vendor_id,
+      device_id: properties.device_id,
+      driver_version: properties.driver_version,
+    }
+  }
+}
+
+/// A parsed bundle, borrowing the kernels from the underlying bytes.
+pub struct Bundle<'a> {
+  target: BundleTarget,
+  entries: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> Bundle<'a> {
+  pub fn parse(bytes: &'a [u8]) -> Result<Self, BundleError> {
+    let mut reader = Reader { bytes };
+
+    if reader.take(MAGIC.len())? != MAGIC {
+      return Err(BundleError::InvalidMagic);
+    }
+
+    let version = reader.u32()?;
+    if version != FORMAT_VERSION {
+      return Err(BundleError::UnsupportedVersion(version));
+    }
+
+    let target = BundleTarget {
+      vkfft_version: reader.u32()?,
+      vendor_id: reader.u32()?,
+      device_id: reader.u32()?,
+      driver_version: reader.u32()?,
+    };
+
+    let count = reader.u32()?;
+    // The count isn't trusted, but every entry takes at least the 20 bytes of its lengths and
+    // checksum
+    let mut entries = Vec::with_capacity((count as usize).min(reader.bytes.len() / 20));
+    for _ in 0..count {
+      let key_len = reader.u32()? as usize;
+      let key = std::str::from_utf8(reader.take(key_len)?).map_err(|_| BundleError::InvalidKey)?;
+      let blob_len = reader.u64()? as usize;
+      let checksum = reader.u64()?;
+      let blob = reader.take(blob_len)?;
+      if fnv1a(blob) != checksum {
+        return Err(BundleError::ChecksumMismatch);
+      }
+      entries.push((key, blob));
+    }
+
+    Ok(Self { target, entries })
+  }
+
+  pub fn target(&self) -> BundleTarget {
+    self.target
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// Whether the kernels in this bundle can be loaded on `physical_device`.
+  pub fn is_compatible(&self, physical_device: &PhysicalDevice) -> bool {
+    self.target == BundleTarget::of(physical_device)
+  }
+
+  /// Serialized kernels for `key`, if the bundle contains them.
+  pub fn get(&self, key: &PlanKey) -> Option<&'a [u8]> {
+    let key = key.to_string();
+    self
+      .entries
+      .iter()
+      .find(|(k, _)| *k == key)
+      .map(|(_, blob)| *blob)
+  }
+
+  /// Creates an `App` for `config`, loading its kernels from the bundle when they are present
+  /// and compatible with the config's device, and generating them otherwise.
+  pub fn app(&self, config: Config) -> error::Result<Pin<Box<App>>> {
+    let compatible = config
+      .vulkano_physical_device
+      .as_ref()
+      .is_some_and(|physical_device| self.is_compatible(physical_device));
+    if compatible {
+      if let Some(blob) = self.get(&config.plan_key()) {
+        // The target matches, and the checksum verified while parsing
+        return unsafe { App::from_bytes(config, blob) };
+      }
+    }
+
+    App::new(config)
+  }
+}
+
+/// Collects pre-generated applications into a bundle.
+pub struct BundleWriter {
+  target: BundleTarget,
+  entries: Vec<(String, Vec<u8>)>,
+}
+
+impl BundleWriter {
+  pub fn new(physical_device: &PhysicalDevice) -> Self {
+    Self {
+      target: BundleTarget::of(physical_device),
+      entries: Vec::new(),
+    }
+  }
+
+  /// Adds serialized kernels for `key`, replacing any previous entry for it.
+  pub fn insert(&mut self, key: &PlanKey, blob: Vec<u8>) {
+    let key = key.to_string();
+    self.entries.retain(|(k, _)| *k != key);
+    self.entries.push((key, blob));
+  }
+
+  /// Generates the application described by `config_builder` on `context` and adds it.
+  pub fn generate(
+    &mut self,
+    context: &Context,
+    config_builder: ConfigBuilder,
+  ) -> Result<(), Box<dyn Error>> {
+    context.acquire_fence()?;
+    let config = config_builder
+      .physical_device(context.physical.clone())
+      .device(context.device.clone())
+      .fence(context.fence.clone())
+      .queue(context.queue.clone())
+      .command_pool(context.command_pool())
+      .save_application()
+      .build()?;
+
+    let key = config.plan_key();
+    let app = App::new(config)?;
+    let blob = app
+      .save_to_bytes()
+      .ok_or(error::VkfftError::EmptyApplicationString)?;
+    self.insert(&key, blob);
+    Ok(())
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    for word in [
+      FORMAT_VERSION,
+      self.target.vkfft_version,
+      self.target.vendor_id,
+      self.target.device_id,
+      self.target.driver_version,
+      self.entries.len() as u32,
+    ] {
+      bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    for (key, blob) in &self.entries {
+      bytes.extend_from_slice(&(key.len() as u32).to_le_bytes());
+      bytes.extend_from_slice(key.as_bytes());
+      bytes.extend_from_slice(&(blob.len() as u64).to_le_bytes());
+      bytes.extend_from_slice(&fnv1a(blob).to_le_bytes());
+      bytes.extend_from_slice(blob);
+    }
+
+    bytes
+  }
+
+  pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    fs::write(path, self.to_bytes())
+  }
+}
+
+struct Reader<'a> {
+  bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, len: usize) -> Result<&'a [u8], BundleError> {
+    if self.bytes.len() < len {
+      return Err(BundleError::Truncated);
+    }
+
+    let (head, tail) = self.bytes.split_at(len);
+    self.bytes = tail;
+    Ok(head)
+  }
+
+  fn u32(&mut self) -> Result<u32, BundleError> {
+    let mut word = [0; 4];
+    word.copy_from_slice(self.take(4)?);
+    Ok(u32::from_le_bytes(word))
+  }
+
+  fn u64(&mut self) -> Result<u64, BundleError> {
+    let mut word = [0; 8];
+    word.copy_from_slice(self.take(8)?);
+    Ok(u64::from_le_bytes(word))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_truncated_entries() {
+    let mut bytes = MAGIC.to_vec();
+    for word in [FORMAT_VERSION, 10304, 0x10de, 1, 1, u32::MAX] {
+      bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    assert!(matches!(Bundle::parse(&bytes), Err(BundleError::Truncated)));
+  }
+
+  #[test]
+  fn check_corrupt_entries() {
+    let writer = BundleWriter {
+      target: BundleTarget {
+        vkfft_version: 10304,
+        vendor_id: 0x10de,
+        device_id: 1,
+        driver_version: 1,
+      },
+      entries: vec![("key".to_string(), vec![1, 2, 3])],
+    };
+    let bytes = writer.to_bytes();
+    assert_eq!(Bundle::parse(&bytes).unwrap().entries, [("key", &[1u8, 2, 3][..])]);
+
+    let mut corrupt = bytes.clone();
+    *corrupt.last_mut().unwrap() ^= 1;
+    assert!(matches!(Bundle::parse(&corrupt), Err(BundleError::ChecksumMismatch)));
+  }
+}
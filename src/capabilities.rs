@@ -0,0 +1,125 @@
+//! Limits of a device that decide which transforms it can run, read with
+//! [`Context::capabilities`](crate::context::Context::capabilities) so applications can pick
+//! sizes and precisions before creating a plan:
+//!
+//! ```ignore
+//! let capabilities = context.capabilities();
+//! let precision = if capabilities.precisions.contains(&Precision::Double) {
+//!   Precision::Double
+//! } else {
+//!   Precision::Single
+//! };
+//! assert!(n <= capabilities.max_axis_length(precision));
+//! ```
+
+use vulkano::{device::Device, memory::MemoryHeapFlags};
+
+use crate::config::Precision;
+
+const PRECISIONS: [Precision; 5] = [
+  Precision::Single,
+  Precision::Double,
+  Precision::Half,
+  Precision::HalfMemory,
+  Precision::QuadDoubleDouble,
+];
+
+/// What a device offers to transforms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+  pub max_workgroup_size: [u32; 3],
+  pub max_workgroup_invocations: u32,
+  /// Bytes of shared memory a workgroup can use, which bounds the sequences VkFFT transforms
+  /// in a single kernel
+  pub max_shared_memory: u32,
+  /// Precisions whose features the device was created with
+  pub precisions: Vec<Precision>,
+  /// Bytes of the largest single allocation, if the driver reports it
+  pub max_allocation_size: Option<u64>,
+  /// Bytes of the largest range a kernel can bind as a storage buffer
+  pub max_storage_buffer_range: u32,
+  /// Bytes of the largest device-local memory heap
+  pub device_local_memory: u64,
+}
+
+impl Capabilities {
+  pub(crate) fn new(device: &Device) -> Self {
+    let physical = device.physical_device();
+    let properties = physical.properties();
+    Self {
+      max_workgroup_size: properties.max_compute_work_group_size,
+      max_workgroup_invocations: properties.max_compute_work_group_invocations,
+      max_shared_memory: properties.max_compute_shared_memory_size,
+      precisions: PRECISIONS
+        .iter()
+        .copied()
+        .filter(|p| p.missing_enabled_feature(device).is_none())
+        .collect(),
+      max_allocation_size: properties.max_memory_allocation_size,
+      max_storage_buffer_range: properties.max_storage_buffer_range,
+      device_local_memory: physical
+        .memory_properties()
+        .memory_heaps
+        .iter()
+        .filter(|heap| heap.flags.intersects(MemoryHeapFlags::DEVICE_LOCAL))
+        .map(|heap| heap.size)
+        .max()
+        .unwrap_or(0),
+    }
+  }
+
+  /// An estimate of the longest power of two C2C transform of a single axis the device can run
+  /// in `precision`: the largest whose buffer and equally sized temp buffer fit the device, and
+  /// whose buffer fits one allocation and one storage buffer binding.
+  pub fn max_axis_length(&self, precision: Precision) -> u64 {
+    let complex_bytes: u64 = match precision {
+      Precision::QuadDoubleDouble => 32,
+      Precision::Double => 16,
+      Precision::Single => 8,
+      Precision::Half | Precision::HalfMemory => 4,
+    };
+    let buffer_limit = [
+      Some(self.max_storage_buffer_range as u64),
+      self.max_allocation_size,
+      Some(self.device_local_memory / 2),
+    ]
+    .iter()
+    .flatten()
+    .copied()
+    .min()
+    .unwrap_or(0);
+    let points = buffer_limit / complex_bytes;
+    if points == 0 {
+      0
+    } else {
+      1 << (63 - points.leading_zeros())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_max_axis_length() {
+    let capabilities = Capabilities {
+      max_workgroup_size: [1024, 1024, 64],
+      max_workgroup_invocations: 1024,
+      max_shared_memory: 48 * 1024,
+      precisions: vec![Precision::Single],
+      max_allocation_size: Some(1 << 32),
+      max_storage_buffer_range: u32::MAX,
+      device_local_memory: 6 << 30,
+    };
+    // The storage buffer range just misses 4 GiB, so it limits single precision to 2^28 points
+    assert_eq!(capabilities.max_axis_length(Precision::Single), 1 << 28);
+    assert_eq!(capabilities.max_axis_length(Precision::Double), 1 << 27);
+
+    let small = Capabilities {
+      device_local_memory: 1 << 20,
+      ..capabilities
+    };
+    assert_eq!(small.max_axis_length(Precision::Half), 1 << 17);
+  }
+}
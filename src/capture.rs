@@ -0,0 +1,99 @@
+//! Capture of text VkFFT writes to the C library's stdout.
+
+use std::sync::Mutex;
+
+// Redirecting stdout affects the whole process, so only one capture may be active at a time.
+static CAPTURE: Mutex<()> = Mutex::new(());
+
+/// Runs `f`, returning its result together with everything written to stdout while it ran, or
+/// `None` if stdout couldn't be redirected.
+#[cfg(unix)]
+pub(crate) fn stdout<T, F: FnOnce() -> T>(f: F) -> (T, Option<String>) {
+  use std::{
+    fs::File,
+    io::{Read, Write},
+    os::{
+      raw::{c_int, c_void},
+      unix::io::{AsRawFd, FromRawFd},
+    },
+  };
+
+  extern "C" {
+    fn pipe(fds: *mut c_int) -> c_int;
+    fn dup(fd: c_int) -> c_int;
+    fn dup2(src: c_int, dst: c_int) -> c_int;
+    fn close(fd: c_int) -> c_int;
+    fn fflush(stream: *mut c_void) -> c_int;
+  }
+
+  const STDOUT: c_int = 1;
+
+  let _guard = CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+
+  let mut fds = [0; 2];
+  if unsafe { pipe(fds.as_mut_ptr()) } < 0 {
+    return (f(), None);
+  }
+  let (mut reader, writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+  let _ = std::io::stdout().flush();
+  let saved = unsafe {
+    fflush(std::ptr::null_mut());
+    dup(STDOUT)
+  };
+  if saved < 0 || unsafe { dup2(writer.as_raw_fd(), STDOUT) } < 0 {
+    if saved >= 0 {
+      unsafe { close(saved) };
+    }
+    return (f(), None);
+  }
+  // Stdout holds the only write end now, so the reader sees the end of the output once it is
+  // restored. It drains the pipe meanwhile, so long output can't fill it and block `f`.
+  drop(writer);
+  let drain = std::thread::spawn(move || {
+    let mut output = String::new();
+    reader.read_to_string(&mut output).map(|_| output).ok()
+  });
+
+  let result = f();
+
+  unsafe {
+    fflush(std::ptr::null_mut());
+    dup2(saved, STDOUT);
+    close(saved);
+  }
+
+  (result, drain.join().ok().flatten())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn stdout<T, F: FnOnce() -> T>(f: F) -> (T, Option<String>) {
+  let _guard = CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+  (f(), None)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use std::io::Write;
+
+  #[test]
+  fn check_stdout_capture() {
+    let (result, output) = super::stdout(|| {
+      let mut stdout = std::io::stdout();
+      stdout.write_all(b"layout\n").unwrap();
+      stdout.flush().unwrap();
+      7
+    });
+    assert_eq!(result, 7);
+    assert_eq!(output.as_deref(), Some("layout\n"));
+
+    // More than a pipe holds
+    let long = "x".repeat(1 << 20);
+    let ((), output) = super::stdout(|| {
+      let mut stdout = std::io::stdout();
+      stdout.write_all(long.as_bytes()).unwrap();
+      stdout.flush().unwrap();
+    });
+    assert_eq!(output, Some(long));
+  }
+}
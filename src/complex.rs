@@ -0,0 +1,194 @@
+//! Complex buffers typed with [num-complex](https://docs.rs/num-complex).
+//!
+//! `Complex<T>` is laid out as a `re, im` pair, which is VkFFT's interleaved format, so complex
+//! data can be uploaded and read back without packing it into even and odd indices:
+//!
+//! ```ignore
+//! let data = (0..4096).map(|i| Complex32::from_polar(1.0, i as f32 * 0.1)).collect::<Vec<_>>();
+//! let spectrum = context.transform_complex(Config::builder().dim(&[4096]), &data, FftType::Forward)?;
+//! ```
+//!
+//! `Subbuffer<[Complex<T>]>` can be bound to a config like any other buffer.
+//!
+//! For prototyping, [`Context::fft_forward`], [`Context::fft_inverse`], [`Context::rfft`] and
+//! [`Context::irfft`] transform slices without any builder:
+//!
+//! ```ignore
+//! let spectrum = context.rfft(&samples)?;
+//! let samples = context.irfft(&spectrum, samples.len())?;
+//! ```
+
+use num_complex::Complex;
+use vulkano::{
+  buffer::{AllocateBufferError, BufferContents, Subbuffer},
+  Validated,
+};
+
+use crate::{
+  config::{Config, ConfigBuilder, ConfigError},
+  context::{Context, FftType},
+  scalar::FftScalar,
+};
+
+impl Context {
+  /// Creates a host-visible buffer of complex values, like [`Context::new_buffer_from_iter`].
+  pub fn new_complex_buffer_from_iter<T, I>(
+    &self,
+    iter: I,
+  ) -> Result<Subbuffer<[Complex<T>]>, Validated<AllocateBufferError>>
+  where
+    Complex<T>: BufferContents,
+    I: IntoIterator<Item = Complex<T>>,
+    I::IntoIter: ExactSizeIterator,
+  {
+    self.new_buffer_from_iter(iter)
+  }
+
+  /// Like [`Context::transform`], for complex data.
+  pub fn transform_complex<T: FftScalar>(
+    &self,
+    config_builder: ConfigBuilder,
+    data: &[Complex<T>],
+    fft_type: FftType,
+  ) -> Result<Vec<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let buffer = self.new_complex_buffer_from_iter(data.iter().copied())?;
+    let config_builder = config_builder
+      .buffer(buffer.clone())
+      .precision(T::PRECISION);
+    let mut stage = self.fft_stage(config_builder, fft_type)?;
+    self.run(&mut stage)?;
+    self.read_complex(&buffer)
+  }
+
+  /// Like [`Context::read`], for complex buffers.
+  pub fn read_complex<T>(
+    &self,
+    buffer: &Subbuffer<[Complex<T>]>,
+  ) -> Result<Vec<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents + Clone,
+  {
+    Ok(buffer.read()?.to_vec())
+  }
+
+  /// The 1D transform of `data`, planned, run and read back in one call.
+  pub fn fft_forward<T: FftScalar>(
+    &self,
+    data: &[Complex<T>],
+  ) -> Result<Vec<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let config_builder = Config::builder().dim(&[data.len() as u32]);
+    self.transform_complex(config_builder, data, FftType::Forward)
+  }
+
+  /// The normalized inverse 1D transform of `data`, which undoes [`Context::fft_forward`].
+  pub fn fft_inverse<T: FftScalar>(
+    &self,
+    data: &[Complex<T>],
+  ) -> Result<Vec<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let config_builder = Config::builder().dim(&[data.len() as u32]).normalize();
+    self.transform_complex(config_builder, data, FftType::Inverse)
+  }
+
+  /// The `data.len() / 2 + 1` non-redundant values of the 1D transform of the real `data`.
+  pub fn rfft<T: FftScalar>(&self, data: &[T]) -> Result<Vec<Complex<T>>, crate::Error> {
+    let padded = 2 * (data.len() / 2 + 1);
+    let mut values = data.to_vec();
+    values.resize(padded, T::default());
+    let config_builder = Config::builder().dim(&[data.len() as u32]);
+    let spectrum = self.transform_real(config_builder, values, FftType::Forward)?;
+    Ok(interleaved_to_complex(&spectrum))
+  }
+
+  /// The normalized inverse of [`Context::rfft`], for real data of `len` values. `spectrum` must
+  /// have `len / 2 + 1` values.
+  pub fn irfft<T: FftScalar>(
+    &self,
+    spectrum: &[Complex<T>],
+    len: usize,
+  ) -> Result<Vec<T>, crate::Error> {
+    if spectrum.len() != len / 2 + 1 {
+      return Err(ConfigError::LengthMismatch("spectrum", spectrum.len()).into());
+    }
+    let config_builder = Config::builder().dim(&[len as u32]);
+    let mut values = self.transform_real(
+      config_builder,
+      complex_to_interleaved(spectrum),
+      FftType::Inverse,
+    )?;
+    values.truncate(len);
+    Ok(values)
+  }
+
+  /// Runs the real-to-complex transform of `config_builder` in place on `data`, laid out with
+  /// each row padded to make room for its spectrum. Inverse transforms are normalized.
+  pub(crate) fn transform_real<T: FftScalar>(
+    &self,
+    config_builder: ConfigBuilder,
+    data: Vec<T>,
+    fft_type: FftType,
+  ) -> Result<Vec<T>, crate::Error> {
+    let buffer = self.new_buffer_from_iter(data)?;
+    let config_builder = config_builder
+      .r2c()
+      .buffer(buffer.clone())
+      .precision(T::PRECISION);
+    let config_builder = match fft_type {
+      FftType::Forward => config_builder,
+      FftType::Inverse => config_builder.normalize(),
+    };
+    self.run(&mut self.fft_stage(config_builder, fft_type)?)?;
+    self.read(&buffer)
+  }
+}
+
+/// Pairs of real and imaginary parts as complex values.
+pub(crate) fn interleaved_to_complex<T: Copy>(values: &[T]) -> Vec<Complex<T>> {
+  values
+    .chunks_exact(2)
+    .map(|pair| Complex::new(pair[0], pair[1]))
+    .collect()
+}
+
+/// The inverse of [`interleaved_to_complex`].
+pub(crate) fn complex_to_interleaved<T: Copy>(values: &[Complex<T>]) -> Vec<T> {
+  values
+    .iter()
+    .flat_map(|value| [value.re, value.im])
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use num_complex::{Complex32, Complex64};
+
+  #[test]
+  fn check_interleaved_round_trip() {
+    let values = [1.0, 2.0, 3.0, 4.0];
+    let complex = super::interleaved_to_complex(&values);
+    assert_eq!(
+      complex,
+      [Complex32::new(1.0, 2.0), Complex32::new(3.0, 4.0)]
+    );
+    assert_eq!(super::complex_to_interleaved(&complex), values);
+  }
+
+  #[test]
+  fn check_interleaved_layout() {
+    let values = [Complex32::new(1.0, 2.0), Complex32::new(3.0, 4.0)];
+    let floats = unsafe { std::slice::from_raw_parts(values.as_ptr() as *const f32, 4) };
+    assert_eq!(floats, [1.0, 2.0, 3.0, 4.0]);
+    assert_eq!(
+      std::mem::size_of::<Complex64>(),
+      2 * std::mem::size_of::<f64>()
+    );
+  }
+}
@@ -0,0 +1,164 @@
+//! Element-wise arithmetic on complex spectra, for frequency-domain operations the fused
+//! convolution path doesn't cover.
+//!
+//! Each [`ComplexArithmetic`] updates a spectrum in place. They are stages, so a sequence of them
+//! pushed to a [`Chain`](crate::stage::Chain), between a forward and an inverse transform, is
+//! separated by the barriers each step needs:
+//!
+//! ```ignore
+//! let layout = SpectrumLayout::new(&[1024], false)?;
+//! let correlate = ComplexArithmetic::new(&context, a.clone(), ComplexOp::ConjugateMultiply(b), layout)?;
+//! let scale = ComplexArithmetic::new(&context, a, ComplexOp::Scale([0.5, 0.0]), layout)?;
+//! context.run(&mut Chain::new(context.device.clone()).push(forward).push(correlate).push(scale))?;
+//! ```
+
+use std::{error::Error, sync::Arc};
+
+use ash::vk;
+use derive_more::{Display, Error};
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  filter::SpectrumLayout,
+  shader::{group_counts, with_defines, Kernel},
+  stage::Stage,
+};
+
+const ARITHMETIC: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) buffer Data { vec2 data[]; };
+#if !defined(SCALE)
+layout(std430, binding = 1) readonly buffer Operand { vec2 operand[]; };
+#endif
+layout(push_constant) uniform Params {
+  uint total;
+  uint operand_len;
+  vec2 scalar;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= total) {
+    return;
+  }
+
+  vec2 a = data[i];
+#if defined(SCALE)
+  vec2 b = scalar;
+#else
+  vec2 b = operand[i % operand_len];
+#endif
+#if defined(ADD)
+  data[i] = a + b;
+#else
+#if defined(CONJUGATE)
+  b.y = -b.y;
+#endif
+  data[i] = vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+#endif
+}
+"#;
+
+#[derive(Display, Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum ComplexOpError {
+  #[display("The spectrum holds more than 2^32 complex values")]
+  TooManyValues,
+}
+
+/// An operation applied to every complex value `a` of a spectrum. Operand buffers hold
+/// interleaved complex `f32` values, either one per value of the spectrum or one per value of a
+/// single batch, repeated across batches.
+#[derive(Clone)]
+pub enum ComplexOp {
+  /// `a * b`
+  Multiply(Arc<Buffer>),
+  /// `a * conj(b)`, as cross-correlations need
+  ConjugateMultiply(Arc<Buffer>),
+  /// `a + b`
+  Add(Arc<Buffer>),
+  /// `a * s` for the complex scalar `s`, as `[re, im]`
+  Scale([f32; 2]),
+}
+
+/// Applies a [`ComplexOp`] in place to a complex `f32` spectrum.
+pub struct ComplexArithmetic {
+  kernel: Kernel,
+}
+
+impl ComplexArithmetic {
+  pub fn new(
+    context: &Context,
+    spectrum: Arc<Buffer>,
+    op: ComplexOp,
+    layout: SpectrumLayout,
+  ) -> Result<Self, Box<dyn Error>> {
+    if layout.total() > u32::MAX as u64 {
+      return Err(ComplexOpError::TooManyValues.into());
+    }
+
+    let (defines, operand, scalar): (&[&str], _, _) = match op {
+      ComplexOp::Multiply(operand) => (&[], Some(operand), [0.0; 2]),
+      ComplexOp::ConjugateMultiply(operand) => (&["CONJUGATE"], Some(operand), [0.0; 2]),
+      ComplexOp::Add(operand) => (&["ADD"], Some(operand), [0.0; 2]),
+      ComplexOp::Scale(scalar) => (&["SCALE"], None, scalar),
+    };
+    let operand_len = match &operand {
+      Some(operand) => {
+        operand_len(operand.size(), &layout).ok_or(ConfigError::BufferTooSmall("operand"))?
+      }
+      None => 0,
+    };
+
+    let kernel = Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      &with_defines(ARITHMETIC, defines),
+      Some(spectrum).into_iter().chain(operand),
+      vec![
+        layout.total() as u32,
+        operand_len as u32,
+        scalar[0].to_bits(),
+        scalar[1].to_bits(),
+      ],
+      group_counts(layout.total()),
+    )?;
+
+    Ok(Self { kernel })
+  }
+}
+
+impl Stage for ComplexArithmetic {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+/// The number of complex values of an operand of `size` bytes used before repeating: the whole
+/// spectrum if it is large enough, or else one batch.
+fn operand_len(size: u64, layout: &SpectrumLayout) -> Option<u64> {
+  let values = size / 8;
+  if values >= layout.total() {
+    Some(layout.total())
+  } else if values >= layout.mask_len() {
+    Some(layout.mask_len())
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_operand_len() {
+    let layout = SpectrumLayout::new(&[8], true).unwrap().batch_count(3);
+    assert_eq!(operand_len(15 * 8, &layout), Some(15));
+    assert_eq!(operand_len(6 * 8, &layout), Some(5));
+    assert_eq!(operand_len(4 * 8, &layout), None);
+  }
+}
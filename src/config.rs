@@ -1,85 +1,228 @@
-use std::sync::Arc;
+use std::{any::Any, sync::Arc};
 
+use ash::vk;
 use derive_more::{Display, Error};
 use std::pin::Pin;
+#[cfg(feature = "vulkano")]
 use vulkano::{
-  buffer::Buffer,
   command_buffer::pool::CommandPool,
   device::physical::PhysicalDevice,
-  device::{Device, Queue},
-  sync::fence::Fence,
-  VulkanObject,
+  device::{Device, DeviceFeatures, Queue},
+  memory::allocator::MemoryAllocator,
+  sync::fence::{Fence, FenceCreateInfo},
+  Validated, VulkanError, VulkanObject,
 };
 
 use std::ptr::addr_of_mut;
 
+use crate::{
+  buffer::{BufferBinding, FftBuffer},
+  dry_run::DryRun,
+  overrides::EnvOverrides,
+  primes::PrimeOptions,
+  tuning::TuningOptions,
+};
+
 #[derive(Display, Debug, Error)]
 pub enum BuildError {
   NoPhysicalDevice,
   NoDevice,
   NoQueue,
   NoFence,
+  #[cfg(feature = "vulkano")]
+  #[display("Failed to create a fence: {}", _0)]
+  CreateFence(Validated<VulkanError>),
   NoCommandPool,
   NoBuffer,
+  #[display("{:?} precision needs the {} device feature, which is not supported", _0, _1)]
+  UnsupportedPrecision(Precision, #[error(not(source))] &'static str),
+  #[display("{:?} precision needs the {} device feature, which the device was created without", _0, _1)]
+  PrecisionNotEnabled(Precision, #[error(not(source))] &'static str),
+  #[display("Ortho normalization isn't supported in {:?} precision", _0)]
+  OrthoPrecision(#[error(not(source))] Precision),
+}
+
+/// The most axes a transform can have. This is the `VKFFT_MAX_FFT_DIMENSIONS` VkFFT is built
+/// with, which also sizes the per-axis arrays of the bindings. It is 4 unless the
+/// `VKFFT_MAX_FFT_DIMENSIONS` environment variable raises it at build time.
+pub const MAX_FFT_DIMENSIONS: usize = vkfft_sys::MAX_FFT_DIMENSIONS;
+
+/// Raw handles of the device an application runs on, for use without vulkano.
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceHandles {
+  pub physical_device: vk::PhysicalDevice,
+  pub device: vk::Device,
+  pub queue: vk::Queue,
+  pub command_pool: vk::CommandPool,
+  pub fence: vk::Fence,
+}
+
+/// Raw buffers of a config with their sizes in bytes, for [`Config::from_raw_handles`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RawBuffers {
+  pub buffer: Option<(vk::Buffer, u64)>,
+  pub input_buffer: Option<(vk::Buffer, u64)>,
+  pub output_buffer: Option<(vk::Buffer, u64)>,
+  pub temp_buffer: Option<(vk::Buffer, u64)>,
+  pub kernel: Option<(vk::Buffer, u64)>,
+}
+
+/// Keeps a command pool alive for a config, which can be sent across threads.
+#[cfg(feature = "vulkano")]
+struct PoolOwner {
+  _pool: Arc<CommandPool>,
+}
+
+// SAFETY: The pool is never used through the owner, only dropped, and destroying it when the
+// last `Arc` goes away needs no synchronization with the threads holding the others.
+#[cfg(feature = "vulkano")]
+unsafe impl Send for PoolOwner {}
+#[cfg(feature = "vulkano")]
+unsafe impl Sync for PoolOwner {}
+
+/// The plan described by the fields `ConfigBuilder` and `Config` share, with the overrides
+/// captured from the environment. Invalid overrides are left out, and fail the plan's `write_sys`.
+macro_rules! plan_key {
+  ($config:expr) => {{
+    let config = $config;
+    PlanKey {
+      fft_dim: config.fft_dim,
+      size: config.size,
+      batch_count: config.batch_count,
+      precision: config.precision,
+      normalization: config.normalization,
+      r2c: config.r2c,
+      r2r: config.r2r,
+      zero_padding: config.zero_padding,
+      zeropad_left: config.zeropad_left,
+      zeropad_right: config.zeropad_right,
+      omit_dimension: config.omit_dimension,
+      convolution: config.convolution,
+      kernel_convolution: config.kernel_convolution,
+      coordinate_features: config.coordinate_features,
+      matrix_convolution: config.matrix_convolution,
+      symmetric_kernel: config.symmetric_kernel,
+      conjugate_convolution: config.conjugate_convolution,
+      cross_power_spectrum_normalization: config.cross_power_spectrum_normalization,
+      disable_reorder_four_step: config.disable_reorder_four_step,
+      use_lut: config.use_lut,
+      input_formatted: config.input_formatted,
+      output_formatted: config.output_formatted,
+      inverse_return_to_input: config.inverse_return_to_input,
+      input_buffer: config.input_buffer.is_some() || config.launch_buffer_sizes[2].is_some(),
+      output_buffer: config.output_buffer.is_some() || config.launch_buffer_sizes[3].is_some(),
+      temp_buffer: config.temp_buffer.is_some() || config.launch_buffer_sizes[1].is_some(),
+      offsets: buffer_offsets(
+        [
+          &config.buffer,
+          &config.temp_buffer,
+          &config.input_buffer,
+          &config.output_buffer,
+          &config.kernel,
+        ],
+        config.offsets_at_launch,
+      ),
+      offsets_at_launch: config.offsets_at_launch,
+      buffer_stride: config.buffer_stride,
+      input_buffer_stride: config.input_buffer_stride,
+      output_buffer_stride: config.output_buffer_stride,
+      tuning: config.tuning.clone(),
+      primes: config.primes.clone(),
+      overrides: EnvOverrides::captured().cloned().unwrap_or_default(),
+    }
+  }};
 }
 
-pub struct ConfigBuilder<'a> {
+#[derive(Clone)]
+pub struct ConfigBuilder {
   fft_dim: u32,
-  size: [u32; 4usize],
-
-  physical_device: Option<Arc<PhysicalDevice>>,
-  device: Option<Arc<Device>>,
-  queue: Option<Arc<Queue>>,
-  fence: Option<&'a Fence>,
-  command_pool: Option<Arc<CommandPool>>,
-  buffer: Option<Arc<Buffer>>,
-  input_buffer: Option<Arc<Buffer>>,
-  output_buffer: Option<Arc<Buffer>>,
-  temp_buffer: Option<Arc<Buffer>>,
-  kernel: Option<Arc<Buffer>>,
-  normalize: bool,
-  zero_padding: [bool; 3usize],
-  zeropad_left: [u32; 4usize],
-  zeropad_right: [u32; 4usize],
+  size: [u32; MAX_FFT_DIMENSIONS],
+
+  physical_device: Option<vk::PhysicalDevice>,
+  device: Option<vk::Device>,
+  queue: Option<vk::Queue>,
+  fence: Option<vk::Fence>,
+  command_pool: Option<vk::CommandPool>,
+  keep_alive: Vec<Arc<dyn Any + Send + Sync>>,
+  #[cfg(feature = "vulkano")]
+  vulkano_physical_device: Option<Arc<PhysicalDevice>>,
+  #[cfg(feature = "vulkano")]
+  vulkano_device: Option<Arc<Device>>,
+  #[cfg(feature = "vulkano")]
+  vulkano_queue: Option<Arc<Queue>>,
+  #[cfg(feature = "vulkano")]
+  temp_buffer_allocator: Option<Arc<dyn MemoryAllocator>>,
+  buffer: Option<BufferBinding>,
+  input_buffer: Option<BufferBinding>,
+  output_buffer: Option<BufferBinding>,
+  temp_buffer: Option<BufferBinding>,
+  kernel: Option<BufferBinding>,
+  normalization: Normalization,
+  zero_padding: [bool; MAX_FFT_DIMENSIONS],
+  zeropad_left: [u32; MAX_FFT_DIMENSIONS],
+  zeropad_right: [u32; MAX_FFT_DIMENSIONS],
+  omit_dimension: [bool; MAX_FFT_DIMENSIONS],
   kernel_convolution: bool,
   convolution: bool,
   r2c: bool,
-  dct: Option<u64>,
-  dst: Option<u64>,
+  r2r: Option<R2RKind>,
   coordinate_features: u32,
   disable_reorder_four_step: bool,
   batch_count: Option<u32>,
   precision: Precision,
   use_lut: bool,
   symmetric_kernel: bool,
+  conjugate_convolution: Option<Conjugate>,
+  cross_power_spectrum_normalization: bool,
   input_formatted: Option<bool>,
   inverse_return_to_input: Option<bool>,
   output_formatted: Option<bool>,
   matrix_convolution: Option<u64>,
+  save_application: bool,
+  print_memory_layout: bool,
+  keep_shader_code: bool,
+  offsets_at_launch: bool,
+  launch_buffer_sizes: [Option<u64>; 5],
+  buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+  input_buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+  output_buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+  tuning: TuningOptions,
+  primes: PrimeOptions,
 }
-impl<'a> Default for ConfigBuilder<'a> {
+impl Default for ConfigBuilder {
   fn default() -> Self {
       Self::new()
   }
 }
-impl<'a> ConfigBuilder<'a> {
+impl ConfigBuilder {
   pub fn new() -> Self {
+    let mut size = [0; MAX_FFT_DIMENSIONS];
+    size[..3].fill(1);
     Self {
       fft_dim: 1,
-      size: [1, 1, 1, 0],
+      size,
       physical_device: None,
       device: None,
       queue: None,
       fence: None,
       command_pool: None,
-      normalize: false,
-      zero_padding: [false, false, false],
-      zeropad_left: [0, 0, 0, 0],
-      zeropad_right: [0, 0, 0, 0],
+      keep_alive: Vec::new(),
+      #[cfg(feature = "vulkano")]
+      vulkano_physical_device: None,
+      #[cfg(feature = "vulkano")]
+      vulkano_device: None,
+      #[cfg(feature = "vulkano")]
+      vulkano_queue: None,
+      #[cfg(feature = "vulkano")]
+      temp_buffer_allocator: None,
+      normalization: Normalization::None,
+      zero_padding: [false; MAX_FFT_DIMENSIONS],
+      zeropad_left: [0; MAX_FFT_DIMENSIONS],
+      zeropad_right: [0; MAX_FFT_DIMENSIONS],
+      omit_dimension: [false; MAX_FFT_DIMENSIONS],
       kernel_convolution: false,
       r2c: false,
-      dct: None,
-      dst: None,
+      r2r: None,
       coordinate_features: 1,
       disable_reorder_four_step: false,
       buffer: None,
@@ -91,86 +234,174 @@ impl<'a> ConfigBuilder<'a> {
       convolution: false,
       use_lut: false,
       symmetric_kernel: false,
+      conjugate_convolution: None,
+      cross_power_spectrum_normalization: false,
       input_formatted: None,
       output_formatted: None,
       inverse_return_to_input: None,
       kernel: None,
       matrix_convolution: None,
+      save_application: false,
+      print_memory_layout: false,
+      keep_shader_code: false,
+      offsets_at_launch: false,
+      launch_buffer_sizes: [None; 5],
+      buffer_stride: None,
+      input_buffer_stride: None,
+      output_buffer_stride: None,
+      tuning: TuningOptions::default(),
+      primes: PrimeOptions::default(),
     }
   }
 
+  /// Sets the size of each axis, fastest first, for transforms of up to
+  /// [`MAX_FFT_DIMENSIONS`] dimensions.
   pub fn dim<const N: usize>(mut self, dim: &[u32; N]) -> Self {
-    let len = dim.len();
-    assert!(len <= 3);
+    assert!(N <= MAX_FFT_DIMENSIONS);
 
-    self.fft_dim = len as u32;
-    if len > 0 {
-      self.size[0] = dim[0];
-    }
-    if len > 1 {
-      self.size[1] = dim[1];
-    }
-    if len > 2 {
-      self.size[2] = dim[2];
-    }
+    self.fft_dim = N as u32;
+    self.size[..N].copy_from_slice(dim);
     self
   }
 
+  #[cfg(feature = "vulkano")]
   pub fn physical_device(mut self, physical_device: Arc<PhysicalDevice>) -> Self {
-    self.physical_device = Some(physical_device);
+    self.physical_device = Some(physical_device.handle());
+    self.vulkano_physical_device = Some(physical_device);
     self
   }
 
+  #[cfg(feature = "vulkano")]
   pub fn device(mut self, device: Arc<Device>) -> Self {
-    self.device = Some(device);
+    self.device = Some(device.handle());
+    self.vulkano_device = Some(device.clone());
+    self.keep_alive.push(device);
     self
   }
 
+  /// The queue VkFFT uploads lookup tables through during initialization. It is locked while the
+  /// application is created, like vulkano locks it for submissions.
+  #[cfg(feature = "vulkano")]
   pub fn queue(mut self, queue: Arc<Queue>) -> Self {
-    self.queue = Some(queue);
+    self.queue = Some(queue.handle());
+    self.vulkano_queue = Some(queue.clone());
+    self.keep_alive.push(queue);
     self
   }
 
+  #[cfg(feature = "vulkano")]
   pub fn command_pool(mut self, command_pool: Arc<CommandPool>) -> Self {
-    self.command_pool = Some(command_pool);
+    self.command_pool = Some(command_pool.handle());
+    self.keep_alive.push(Arc::new(PoolOwner {
+      _pool: command_pool,
+    }));
+    self
+  }
+
+  /// The fence VkFFT waits on while uploading lookup tables during initialization. When unset, a
+  /// fence is created on the device given to [`ConfigBuilder::device`].
+  #[cfg(feature = "vulkano")]
+  pub fn fence(mut self, fence: Arc<Fence>) -> Self {
+    self.fence = Some(fence.handle());
+    self.keep_alive.push(fence);
+    self
+  }
+
+  /// Sets the device, queue, command pool and fence from raw handles.
+  ///
+  /// # Safety
+  ///
+  /// The handles must be valid, belong to the same device, and outlive every application
+  /// created from the built config.
+  pub unsafe fn device_handles(mut self, handles: DeviceHandles) -> Self {
+    self.physical_device = Some(handles.physical_device);
+    self.device = Some(handles.device);
+    self.queue = Some(handles.queue);
+    self.command_pool = Some(handles.command_pool);
+    self.fence = Some(handles.fence);
+    self
+  }
+
+  pub fn buffer<B: FftBuffer>(mut self, buffer: B) -> Self {
+    self.buffer = Some(buffer.binding());
+    self
+  }
+
+  pub fn temp_buffer<B: FftBuffer>(mut self, temp_buffer: B) -> Self {
+    self.temp_buffer = Some(temp_buffer.binding());
+    self
+  }
+
+  pub fn input_buffer<B: FftBuffer>(mut self, input_buffer: B) -> Self {
+    self.input_buffer = Some(input_buffer.binding());
+    self
+  }
+
+  pub fn output_buffer<B: FftBuffer>(mut self, output_buffer: B) -> Self {
+    self.output_buffer = Some(output_buffer.binding());
+    self
+  }
+
+  pub fn kernel<B: FftBuffer>(mut self, kernel: B) -> Self {
+    self.kernel = Some(kernel.binding());
     self
   }
 
-  pub fn fence(mut self, fence: &'a Fence) -> Self {
-    self.fence = Some(fence);
+  /// Has [`App::new`](crate::app::App::new) allocate the temp buffer the plan needs from
+  /// `allocator`, if no temp buffer is given. Otherwise VkFFT allocates it itself, with its own
+  /// allocator.
+  ///
+  /// The size is only known once VkFFT has planned the transform, so the application is created
+  /// twice, the second time from the kernels of the first.
+  #[cfg(feature = "vulkano")]
+  pub fn temp_buffer_allocator(mut self, allocator: Arc<dyn MemoryAllocator>) -> Self {
+    self.temp_buffer_allocator = Some(allocator);
     self
   }
 
-  pub fn buffer(mut self, buffer: Arc<Buffer>) -> Self {
-    self.buffer = Some(buffer);
+  /// Plans for a buffer of `size` bytes passed with each launch instead of in the config, so
+  /// the application can transform any buffer of that size. VkFFT rebinds the buffer when it
+  /// changes between launches.
+  pub fn buffer_size(mut self, size: u64) -> Self {
+    self.launch_buffer_sizes[0] = Some(size);
     self
   }
 
-  pub fn temp_buffer(mut self, temp_buffer: Arc<Buffer>) -> Self {
-    self.temp_buffer = Some(temp_buffer);
+  /// Like [`ConfigBuilder::buffer_size`], for the temp buffer.
+  pub fn temp_buffer_size(mut self, size: u64) -> Self {
+    self.launch_buffer_sizes[1] = Some(size);
     self
   }
 
-  pub fn input_buffer(mut self, input_buffer: Arc<Buffer>) -> Self {
-    self.input_buffer = Some(input_buffer);
+  /// Like [`ConfigBuilder::buffer_size`], for the input buffer.
+  pub fn input_buffer_size(mut self, size: u64) -> Self {
+    self.launch_buffer_sizes[2] = Some(size);
     self
   }
 
-  pub fn output_buffer(mut self, output_buffer: Arc<Buffer>) -> Self {
-    self.output_buffer = Some(output_buffer);
+  /// Like [`ConfigBuilder::buffer_size`], for the output buffer.
+  pub fn output_buffer_size(mut self, size: u64) -> Self {
+    self.launch_buffer_sizes[3] = Some(size);
     self
   }
 
-  pub fn kernel(mut self, kernel: Arc<Buffer>) -> Self {
-    self.kernel = Some(kernel);
+  /// Like [`ConfigBuilder::buffer_size`], for the convolution kernel.
+  pub fn kernel_size(mut self, size: u64) -> Self {
+    self.launch_buffer_sizes[4] = Some(size);
     self
   }
 
-  pub fn normalize(mut self) -> Self {
-    self.normalize = true;
+  /// Sets how transforms are scaled, see [`Normalization`].
+  pub fn normalization(mut self, normalization: Normalization) -> Self {
+    self.normalization = normalization;
     self
   }
 
+  /// Shorthand for [`Normalization::Backward`], scaling inverse transforms by `1 / n`.
+  pub fn normalize(self) -> Self {
+    self.normalization(Normalization::Backward)
+  }
+
   pub fn kernel_convolution(mut self) -> Self {
     self.kernel_convolution = true;
     self
@@ -181,6 +412,19 @@ impl<'a> ConfigBuilder<'a> {
     self
   }
 
+  /// Conjugates one side of the product in a convolution, turning it into a cross-correlation.
+  pub fn conjugate_convolution(mut self, conjugate: Conjugate) -> Self {
+    self.conjugate_convolution = Some(conjugate);
+    self
+  }
+
+  /// Normalizes each element of the product in a convolution to unit magnitude, giving the
+  /// cross-power spectrum used for phase correlation.
+  pub fn cross_power_spectrum_normalization(mut self) -> Self {
+    self.cross_power_spectrum_normalization = true;
+    self
+  }
+
   pub fn convolution(mut self) -> Self {
     self.convolution = true;
     self
@@ -191,13 +435,16 @@ impl<'a> ConfigBuilder<'a> {
     self
   }
 
-  pub fn dct(mut self, dct: u64) -> Self {
-    self.dct = Some(dct);
+  /// Performs a real-to-real transform of `kind` instead of a complex one.
+  pub fn r2r(mut self, kind: R2RKind) -> Self {
+    self.r2r = Some(kind);
     self
   }
 
-  pub fn dst(mut self, dst: u64) -> Self {
-    self.dct = Some(dst);
+  /// Sets the precision of the transform, single by default. [`ConfigBuilder::build`] checks
+  /// that the physical device supports it, if it was set through vulkano.
+  pub fn precision(mut self, precision: Precision) -> Self {
+    self.precision = precision;
     self
   }
 
@@ -222,50 +469,28 @@ impl<'a> ConfigBuilder<'a> {
   }
 
   pub fn zero_padding<const N: usize>(mut self, zero_padding: &[bool; N]) -> Self {
-    let len = zero_padding.len();
-    assert!(len <= 3);
-
-    if len > 0 {
-      self.zero_padding[0] = zero_padding[0];
-    }
-    if len > 1 {
-      self.zero_padding[1] = zero_padding[1];
-    }
-    if len > 2 {
-      self.zero_padding[2] = zero_padding[2];
-    }
+    assert!(N <= MAX_FFT_DIMENSIONS);
+    self.zero_padding[..N].copy_from_slice(zero_padding);
     self
   }
 
   pub fn zeropad_left<const N: usize>(mut self, zeropad_left: &[u32; N]) -> Self {
-    let len = zeropad_left.len();
-    assert!(len <= 3);
-
-    if len > 0 {
-      self.zeropad_left[0] = zeropad_left[0];
-    }
-    if len > 1 {
-      self.zeropad_left[1] = zeropad_left[1];
-    }
-    if len > 2 {
-      self.zeropad_left[2] = zeropad_left[2];
-    }
+    assert!(N <= MAX_FFT_DIMENSIONS);
+    self.zeropad_left[..N].copy_from_slice(zeropad_left);
     self
   }
 
   pub fn zeropad_right<const N: usize>(mut self, zeropad_right: &[u32; N]) -> Self {
-    let len = zeropad_right.len();
-    assert!(len <= 3);
+    assert!(N <= MAX_FFT_DIMENSIONS);
+    self.zeropad_right[..N].copy_from_slice(zeropad_right);
+    self
+  }
 
-    if len > 0 {
-      self.zeropad_right[0] = zeropad_right[0];
-    }
-    if len > 1 {
-      self.zeropad_right[1] = zeropad_right[1];
-    }
-    if len > 2 {
-      self.zeropad_right[2] = zeropad_right[2];
-    }
+  /// Skips the transform along the axes set, e.g. `&[false, true]` for batched 1D transforms
+  /// along x of a 2D array. At least one axis of the transform must be kept.
+  pub fn omit_dimension<const N: usize>(mut self, omit_dimension: &[bool; N]) -> Self {
+    assert!(N <= MAX_FFT_DIMENSIONS);
+    self.omit_dimension[..N].copy_from_slice(omit_dimension);
     self
   }
 
@@ -274,6 +499,75 @@ impl<'a> ConfigBuilder<'a> {
     self
   }
 
+  /// Strides of the buffer in elements, for transforming data embedded in a larger array.
+  /// `buffer_stride[0]` is the distance between consecutive rows, `buffer_stride[1]` between
+  /// consecutive planes, and so on; the stride after the last axis of the transform separates
+  /// batches and coordinate features. Strides left out or set to 0 default to a contiguous
+  /// layout.
+  pub fn buffer_stride<const N: usize>(mut self, buffer_stride: &[u64; N]) -> Self {
+    self.buffer_stride = Some(strides(buffer_stride));
+    self
+  }
+
+  /// Like [`ConfigBuilder::buffer_stride`], for the input buffer, which is used when
+  /// `input_formatted` is set.
+  pub fn input_buffer_stride<const N: usize>(mut self, input_buffer_stride: &[u64; N]) -> Self {
+    self.input_buffer_stride = Some(strides(input_buffer_stride));
+    self
+  }
+
+  /// Like [`ConfigBuilder::buffer_stride`], for the output buffer, which is used when
+  /// `output_formatted` is set.
+  pub fn output_buffer_stride<const N: usize>(mut self, output_buffer_stride: &[u64; N]) -> Self {
+    self.output_buffer_stride = Some(strides(output_buffer_stride));
+    self
+  }
+
+  /// Sets the performance options of VkFFT's kernel generator, see [`TuningOptions`].
+  pub fn tuning(mut self, tuning: TuningOptions) -> Self {
+    self.tuning = tuning;
+    self
+  }
+
+  /// Caps the radices of the sequences Bluestein's algorithm pads to, see
+  /// [`PrimeOptions::max_radix_bluestein`].
+  pub fn fix_max_radix_bluestein(mut self, max_radix: u64) -> Self {
+    self.primes.max_radix_bluestein = Some(max_radix);
+    self
+  }
+
+  /// See [`PrimeOptions::min_rader_prime_mult`].
+  pub fn fix_min_rader_prime_mult(mut self, prime: u64) -> Self {
+    self.primes.min_rader_prime_mult = Some(prime);
+    self
+  }
+
+  /// See [`PrimeOptions::max_rader_prime_mult`].
+  pub fn fix_max_rader_prime_mult(mut self, prime: u64) -> Self {
+    self.primes.max_rader_prime_mult = Some(prime);
+    self
+  }
+
+  /// See [`PrimeOptions::min_rader_prime_fft`].
+  pub fn fix_min_rader_prime_fft(mut self, prime: u64) -> Self {
+    self.primes.min_rader_prime_fft = Some(prime);
+    self
+  }
+
+  /// Primes above `prime` use Bluestein's algorithm instead of Rader's, see
+  /// [`PrimeOptions::max_rader_prime_fft`].
+  pub fn fix_max_rader_prime_fft(mut self, prime: u64) -> Self {
+    self.primes.max_rader_prime_fft = Some(prime);
+    self
+  }
+
+  /// Pads sequences of each length to the paired length in Bluestein's algorithm, which must be
+  /// at least twice the length minus one.
+  pub fn bluestein_padding_pattern(mut self, pattern: &[(u64, u64)]) -> Self {
+    self.primes.bluestein_padding = pattern.to_vec();
+    self
+  }
+
   pub fn input_formatted(mut self, input_formatted: bool) -> Self {
     self.input_formatted = Some(input_formatted);
     self
@@ -288,7 +582,76 @@ impl<'a> ConfigBuilder<'a> {
     self
   }
 
-  pub fn build(self) -> Result<Config<'a>, BuildError> {
+  pub fn save_application(mut self) -> Self {
+    self.save_application = true;
+    self
+  }
+
+  pub fn print_memory_layout(mut self) -> Self {
+    self.print_memory_layout = true;
+    self
+  }
+
+  /// Keeps the GLSL VkFFT generates, retrievable with `App::shader_sources`. VkFFT also prints
+  /// the code of every kernel it launches to stdout.
+  pub fn keep_shader_code(mut self) -> Self {
+    self.keep_shader_code = true;
+    self
+  }
+
+  /// Takes buffer offsets from the launch parameters instead of baking the offsets of the
+  /// config's buffers into the kernels.
+  pub fn offsets_at_launch(mut self) -> Self {
+    self.offsets_at_launch = true;
+    self
+  }
+
+  /// The buffer slot transforms in the given direction write their result to, with the buffer
+  /// bound to it, if any.
+  #[cfg(feature = "vulkano")]
+  pub(crate) fn result_buffer(&self, inverse: bool) -> (BufferSlot, Option<BufferBinding>) {
+    if inverse && self.input_formatted == Some(true) && self.inverse_return_to_input == Some(true)
+    {
+      (BufferSlot::Input, self.input_buffer.clone())
+    } else if self.output_formatted == Some(true) {
+      (BufferSlot::Output, self.output_buffer.clone())
+    } else {
+      (BufferSlot::Buffer, self.buffer.clone())
+    }
+  }
+
+  /// The plan the built config will describe.
+  pub fn plan_key(&self) -> PlanKey {
+    plan_key!(self)
+  }
+
+  /// Checks the config and computes the sizes of its buffers without a device, see
+  /// [`dry_run`](crate::dry_run). Buffers bound or sized for launch must be large enough.
+  pub fn dry_run(&self) -> Result<DryRun, ConfigError> {
+    let dry_run = DryRun::new(&self.plan_key())?;
+    let buffers = [
+      ("buffer", &self.buffer, 0, Some(dry_run.buffer_size)),
+      ("input buffer", &self.input_buffer, 2, dry_run.input_buffer_size),
+      ("output buffer", &self.output_buffer, 3, dry_run.output_buffer_size),
+      ("kernel", &self.kernel, 4, dry_run.kernel_size),
+    ];
+    for (name, binding, index, needed) in buffers {
+      let size = binding
+        .as_ref()
+        .map(|b| b.size())
+        .or(self.launch_buffer_sizes[index]);
+      if let (Some(size), Some(needed)) = (size, needed) {
+        if size < needed {
+          return Err(ConfigError::BufferTooSmall(name));
+        }
+      }
+    }
+    Ok(dry_run)
+  }
+
+  // Only the vulkano setters fill in missing handles
+  #[cfg_attr(not(feature = "vulkano"), allow(unused_mut))]
+  pub fn build(mut self) -> Result<Config, BuildError> {
     let physical_device = match self.physical_device {
       Some(v) => v,
       None => return Err(BuildError::NoPhysicalDevice),
@@ -304,6 +667,12 @@ impl<'a> ConfigBuilder<'a> {
       None => return Err(BuildError::NoQueue),
     };
 
+    #[cfg(feature = "vulkano")]
+    if let (None, Some(device)) = (self.fence, self.vulkano_device.clone()) {
+      let fence = Fence::new(device, FenceCreateInfo::default()).map_err(BuildError::CreateFence)?;
+      self = self.fence(Arc::new(fence));
+    }
+
     let fence = match self.fence {
       Some(v) => v,
       None => return Err(BuildError::NoFence),
@@ -314,6 +683,21 @@ impl<'a> ConfigBuilder<'a> {
       None => return Err(BuildError::NoCommandPool),
     };
 
+    #[cfg(feature = "vulkano")]
+    if let Some(physical_device) = &self.vulkano_physical_device {
+      if let Some(feature) = self.precision.missing_feature(physical_device) {
+        return Err(BuildError::UnsupportedPrecision(self.precision, feature));
+      }
+    }
+
+    // A device created elsewhere may lack features its physical device supports
+    #[cfg(feature = "vulkano")]
+    if let Some(device) = &self.vulkano_device {
+      if let Some(feature) = self.precision.missing_enabled_feature(device) {
+        return Err(BuildError::PrecisionNotEnabled(self.precision, feature));
+      }
+    }
+
     Ok(Config {
       fft_dim: self.fft_dim,
       size: self.size,
@@ -322,14 +706,21 @@ impl<'a> ConfigBuilder<'a> {
       queue,
       fence,
       command_pool,
-      normalize: self.normalize,
+      keep_alive: self.keep_alive,
+      #[cfg(feature = "vulkano")]
+      vulkano_physical_device: self.vulkano_physical_device,
+      #[cfg(feature = "vulkano")]
+      vulkano_queue: self.vulkano_queue,
+      #[cfg(feature = "vulkano")]
+      temp_buffer_allocator: self.temp_buffer_allocator,
+      normalization: self.normalization,
       zero_padding: self.zero_padding,
       zeropad_left: self.zeropad_left,
       zeropad_right: self.zeropad_right,
+      omit_dimension: self.omit_dimension,
       kernel_convolution: self.kernel_convolution,
       r2c: self.r2c,
-      dct: self.dct,
-      dst: self.dst,
+      r2r: self.r2r,
       coordinate_features: self.coordinate_features,
       disable_reorder_four_step: self.disable_reorder_four_step,
       buffer: self.buffer,
@@ -338,6 +729,8 @@ impl<'a> ConfigBuilder<'a> {
       convolution: self.convolution,
       use_lut: self.use_lut,
       symmetric_kernel: self.symmetric_kernel,
+      conjugate_convolution: self.conjugate_convolution,
+      cross_power_spectrum_normalization: self.cross_power_spectrum_normalization,
       input_formatted: self.input_formatted,
       output_formatted: self.output_formatted,
       kernel: self.kernel,
@@ -346,10 +739,56 @@ impl<'a> ConfigBuilder<'a> {
       inverse_return_to_input: self.inverse_return_to_input,
       output_buffer: self.output_buffer,
       matrix_convolution: self.matrix_convolution,
+      save_application: self.save_application,
+      print_memory_layout: self.print_memory_layout,
+      keep_shader_code: self.keep_shader_code,
+      offsets_at_launch: self.offsets_at_launch,
+      launch_buffer_sizes: self.launch_buffer_sizes,
+      buffer_stride: self.buffer_stride,
+      input_buffer_stride: self.input_buffer_stride,
+      output_buffer_stride: self.output_buffer_stride,
+      tuning: self.tuning.clone(),
+      primes: self.primes.clone(),
     })
   }
 }
 
+/// The buffers of a config or launch.
+#[cfg(feature = "vulkano")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BufferSlot {
+  Buffer,
+  Input,
+  Output,
+}
+
+/// How transforms are scaled, following the `norm` argument of NumPy's FFT functions. `n` is the
+/// number of points transformed, the product of the sizes of the transformed axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Normalization {
+  /// Neither direction is scaled, as in FFTW and VkFFT
+  #[default]
+  None,
+  /// Inverse transforms are scaled by `1 / n`, so they undo forward transforms
+  Backward,
+  /// Both directions are scaled by `1 / sqrt(n)`, making the transform unitary. VkFFT can't
+  /// scale forward transforms, so stages record a scaling pass after each transform.
+  Ortho,
+}
+
+impl Normalization {
+  /// The factor transforms of `n` points in the given direction are scaled by.
+  pub fn scale_factor(&self, n: f64, inverse: bool) -> f64 {
+    match self {
+      Normalization::None => 1.0,
+      Normalization::Backward if inverse => 1.0 / n,
+      Normalization::Backward => 1.0,
+      Normalization::Ortho => 1.0 / n.sqrt(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Precision {
   /// Perform calculations in single precision (32-bit)
   Single,
@@ -360,35 +799,175 @@ pub enum Precision {
   /// Use half precision only as input/output buffer. Input/Output have to be allocated as half,
   /// buffer/tempBuffer have to be allocated as float (out of place mode only).
   HalfMemory,
+  /// Perform calculations in double-double precision, which emulates 128-bit floats with pairs
+  /// of doubles for about 106 bits of mantissa. Buffers hold
+  /// [`DoubleDouble`](crate::scalar::DoubleDouble)s.
+  QuadDoubleDouble,
 }
 
-pub struct Config<'a> {
-  pub fft_dim: u32,
-  pub size: [u32; 4usize],
+impl Precision {
+  /// Bytes of a real value in the buffers an application reads and writes: the input and output
+  /// buffers of [`Precision::HalfMemory`] plans hold halves.
+  pub fn scalar_bytes(&self) -> u64 {
+    match self {
+      Precision::QuadDoubleDouble => 16,
+      Precision::Double => 8,
+      Precision::Single => 4,
+      Precision::Half | Precision::HalfMemory => 2,
+    }
+  }
+
+  /// Bytes of a real value in the buffers a transform computes in, which for
+  /// [`Precision::HalfMemory`] plans are the `f32` buffer and temp buffer.
+  pub fn compute_scalar_bytes(&self) -> u64 {
+    match self {
+      Precision::HalfMemory => 4,
+      precision => precision.scalar_bytes(),
+    }
+  }
+
+  /// The first device feature this precision needs that `physical_device` doesn't support.
+  #[cfg(feature = "vulkano")]
+  pub fn missing_feature(&self, physical_device: &PhysicalDevice) -> Option<&'static str> {
+    self.missing(physical_device.supported_features())
+  }
 
-  pub physical_device: Arc<PhysicalDevice>,
-  pub device: Arc<Device>,
-  pub queue: Arc<Queue>,
-  pub fence: &'a Fence,
-  pub command_pool: Arc<CommandPool>,
+  /// The first device feature this precision needs that `device` wasn't created with.
+  #[cfg(feature = "vulkano")]
+  pub fn missing_enabled_feature(&self, device: &Device) -> Option<&'static str> {
+    self.missing(device.enabled_features())
+  }
+
+  #[cfg(feature = "vulkano")]
+  fn missing(&self, features: &DeviceFeatures) -> Option<&'static str> {
+    let required: &[(bool, &'static str)] = match self {
+      Precision::Single => &[],
+      Precision::Double | Precision::QuadDoubleDouble => {
+        &[(features.shader_float64, "shaderFloat64")]
+      }
+      Precision::Half => &[
+        (features.shader_float16, "shaderFloat16"),
+        (features.storage_buffer16_bit_access, "storageBuffer16BitAccess"),
+      ],
+      Precision::HalfMemory => &[(
+        features.storage_buffer16_bit_access,
+        "storageBuffer16BitAccess",
+      )],
+    };
+    required
+      .iter()
+      .find(|(features, _)| !features)
+      .map(|(_, feature)| *feature)
+  }
+}
+
+/// The side of a convolution that is conjugated before the product.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Conjugate {
+  /// Conjugate the spectrum of the input.
+  Input,
+  /// Conjugate the spectrum of the kernel.
+  Kernel,
+}
+
+/// The type of a real-to-real transform, with the same definitions as FFTW's `REDFT` and
+/// `RODFT` kinds. VkFFT doesn't normalize them, so an inverse is a transform of the matching
+/// kind scaled by `1 / (2 * N)`, or `1 / (2 * (N - 1))` for DCT-I and `1 / (2 * (N + 1))` for
+/// DST-I.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum R2RKind {
+  /// DCT-I, its own inverse. Needs at least two points per axis.
+  Dct1,
+  /// DCT-II, the inverse of DCT-III.
+  Dct2,
+  /// DCT-III, the inverse of DCT-II.
+  Dct3,
+  /// DCT-IV, its own inverse.
+  Dct4,
+  /// DST-I, its own inverse.
+  Dst1,
+  /// DST-II, the inverse of DST-III.
+  Dst2,
+  /// DST-III, the inverse of DST-II.
+  Dst3,
+  /// DST-IV, its own inverse.
+  Dst4,
+}
+
+impl R2RKind {
+  /// The `performDCT` and `performDST` values selecting this transform.
+  pub fn perform(&self) -> (u64, u64) {
+    match self {
+      R2RKind::Dct1 => (1, 0),
+      R2RKind::Dct2 => (2, 0),
+      R2RKind::Dct3 => (3, 0),
+      R2RKind::Dct4 => (4, 0),
+      R2RKind::Dst1 => (0, 1),
+      R2RKind::Dst2 => (0, 2),
+      R2RKind::Dst3 => (0, 3),
+      R2RKind::Dst4 => (0, 4),
+    }
+  }
 
-  pub buffer: Option<Arc<Buffer>>,
-  pub input_buffer: Option<Arc<Buffer>>,
-  pub output_buffer: Option<Arc<Buffer>>,
-  pub temp_buffer: Option<Arc<Buffer>>,
-  pub kernel: Option<Arc<Buffer>>,
+  /// The kind whose transform undoes this one, up to scaling.
+  pub fn inverse(&self) -> R2RKind {
+    match self {
+      R2RKind::Dct2 => R2RKind::Dct3,
+      R2RKind::Dct3 => R2RKind::Dct2,
+      R2RKind::Dst2 => R2RKind::Dst3,
+      R2RKind::Dst3 => R2RKind::Dst2,
+      kind => *kind,
+    }
+  }
 
-  /// Normalize inverse transform
-  pub normalize: bool,
+  /// The fewest points a transformed axis can have.
+  pub fn min_size(&self) -> u32 {
+    match self {
+      R2RKind::Dct1 => 2,
+      _ => 1,
+    }
+  }
+}
+
+pub struct Config {
+  pub fft_dim: u32,
+  pub size: [u32; MAX_FFT_DIMENSIONS],
+
+  pub physical_device: vk::PhysicalDevice,
+  pub device: vk::Device,
+  pub queue: vk::Queue,
+  pub fence: vk::Fence,
+  pub command_pool: vk::CommandPool,
+
+  pub buffer: Option<BufferBinding>,
+  pub input_buffer: Option<BufferBinding>,
+  pub output_buffer: Option<BufferBinding>,
+  pub temp_buffer: Option<BufferBinding>,
+  pub kernel: Option<BufferBinding>,
+
+  /// Owners of the handles above
+  pub(crate) keep_alive: Vec<Arc<dyn Any + Send + Sync>>,
+  #[cfg(feature = "vulkano")]
+  pub(crate) vulkano_physical_device: Option<Arc<PhysicalDevice>>,
+  #[cfg(feature = "vulkano")]
+  pub(crate) vulkano_queue: Option<Arc<Queue>>,
+  #[cfg(feature = "vulkano")]
+  pub(crate) temp_buffer_allocator: Option<Arc<dyn MemoryAllocator>>,
+
+  /// How transforms are scaled
+  pub normalization: Normalization,
 
   /// Don't read some data/perform computations if some input sequences are zeropadded for each axis
-  pub zero_padding: [bool; 3usize],
+  pub zero_padding: [bool; MAX_FFT_DIMENSIONS],
 
   /// Specify start boundary of zero block in the system for each axis
-  pub zeropad_left: [u32; 4usize],
+  pub zeropad_left: [u32; MAX_FFT_DIMENSIONS],
 
   /// Specify end boundary of zero block in the system for each axis
-  pub zeropad_right: [u32; 4usize],
+  pub zeropad_right: [u32; MAX_FFT_DIMENSIONS],
+
+  /// Skip the transform along each axis
+  pub omit_dimension: [bool; MAX_FFT_DIMENSIONS],
 
   /// Specify if this application is used to create kernel for convolution, so it has the same properties
   pub kernel_convolution: bool,
@@ -399,11 +978,8 @@ pub struct Config<'a> {
   /// Perform R2C/C2R decomposition
   pub r2c: bool,
 
-  /// Perform discrete cos transform (R2R) of type 1-4
-  pub dct: Option<u64>,
-
-  /// Perform discrete sin transform (R2R) of type 1-4
-  pub dst: Option<u64>,
+  /// Perform a discrete cosine or sine transform (R2R)
+  pub r2r: Option<R2RKind>,
 
   /// C - coordinate, or dimension of features vector. In matrix convolution - size of vector
   pub coordinate_features: u32,
@@ -422,6 +998,12 @@ pub struct Config<'a> {
   /// Specify if kernel in 2x2 or 3x3 matrix convolution is symmetric
   pub symmetric_kernel: bool,
 
+  /// Conjugate the input or the kernel in a convolution, for cross-correlation
+  pub conjugate_convolution: Option<Conjugate>,
+
+  /// Normalize the product in a convolution to unit magnitude, for phase correlation
+  pub cross_power_spectrum_normalization: bool,
+
   /// specify if input buffer is padded - false is padded, true is not padded.
   /// For example if it is not padded for R2C if out-of-place mode is selected
   /// (only if numberBatches==1 and numberKernels==1)
@@ -440,28 +1022,138 @@ pub struct Config<'a> {
   /// convolving with a 3x3 matrix, matrix_convolution is 3, and coordinate_features
   /// should also be 3
   pub matrix_convolution: Option<u64>,
+
+  /// Keep a serialized copy of the compiled kernels, retrievable with `App::save_to_bytes`
+  pub save_application: bool,
+
+  /// Have VkFFT describe the memory layout of each kernel it generates. The output is captured
+  /// and available from `App::memory_layout`.
+  pub print_memory_layout: bool,
+
+  /// Keep the generated GLSL, available from `App::shader_sources`
+  pub keep_shader_code: bool,
+
+  /// Take buffer offsets from the launch parameters
+  pub offsets_at_launch: bool,
+
+  /// Sizes of the buffer, temp buffer, input buffer, output buffer and kernel passed at launch
+  /// instead of in the config
+  pub launch_buffer_sizes: [Option<u64>; 5],
+
+  /// Strides of the buffer in elements, or `None` for a contiguous layout
+  pub buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+
+  /// Strides of the input buffer in elements, or `None` for a contiguous layout
+  pub input_buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+
+  /// Strides of the output buffer in elements, or `None` for a contiguous layout
+  pub output_buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+
+  /// Performance options of the kernel generator
+  pub tuning: TuningOptions,
+
+  /// Algorithm choices for large primes
+  pub primes: PrimeOptions,
+}
+
+/// The parameters of a `Config` that determine the kernels VkFFT generates for it. Two configs
+/// with equal keys can share serialized applications.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlanKey {
+  pub fft_dim: u32,
+  pub size: [u32; MAX_FFT_DIMENSIONS],
+  pub batch_count: Option<u32>,
+  pub precision: Precision,
+  pub normalization: Normalization,
+  pub r2c: bool,
+  pub r2r: Option<R2RKind>,
+  pub zero_padding: [bool; MAX_FFT_DIMENSIONS],
+  pub zeropad_left: [u32; MAX_FFT_DIMENSIONS],
+  pub zeropad_right: [u32; MAX_FFT_DIMENSIONS],
+  pub omit_dimension: [bool; MAX_FFT_DIMENSIONS],
+  pub convolution: bool,
+  pub kernel_convolution: bool,
+  pub coordinate_features: u32,
+  pub matrix_convolution: Option<u64>,
+  pub symmetric_kernel: bool,
+  pub conjugate_convolution: Option<Conjugate>,
+  pub cross_power_spectrum_normalization: bool,
+  pub disable_reorder_four_step: bool,
+  pub use_lut: bool,
+  pub input_formatted: Option<bool>,
+  pub output_formatted: Option<bool>,
+  pub inverse_return_to_input: Option<bool>,
+  pub input_buffer: bool,
+  pub output_buffer: bool,
+  pub temp_buffer: bool,
+  /// Offsets of the buffer, temp buffer, input buffer, output buffer and kernel
+  pub offsets: [u64; 5],
+  pub offsets_at_launch: bool,
+  pub buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+  pub input_buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+  pub output_buffer_stride: Option<[u64; MAX_FFT_DIMENSIONS]>,
+  pub tuning: TuningOptions,
+  pub primes: PrimeOptions,
+  pub overrides: EnvOverrides,
+}
+
+/// Copies up to four strides, leaving the rest unset.
+fn strides<const N: usize>(strides: &[u64; N]) -> [u64; MAX_FFT_DIMENSIONS] {
+  assert!(N <= MAX_FFT_DIMENSIONS);
+  let mut res = [0; MAX_FFT_DIMENSIONS];
+  res[..N].copy_from_slice(strides);
+  res
+}
+
+/// Offsets baked into the kernels. With offsets given at launch the kernels don't depend on them,
+/// so plans for different ranges of the same shape are the same.
+fn buffer_offsets(buffers: [&Option<BufferBinding>; 5], offsets_at_launch: bool) -> [u64; 5] {
+  if offsets_at_launch {
+    return [0; 5];
+  }
+  buffers.map(|b| b.as_ref().map(|b| b.offset()).unwrap_or(0))
+}
+
+impl std::fmt::Display for PlanKey {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{:?}", self)
+  }
 }
 
 #[derive(Display, Debug, Error)]
 pub enum ConfigError {
   InvalidConfig,
-}
-
-#[allow(dead_code)]
-pub(crate) struct KeepAlive {
-  pub device: Arc<Device>,
-  pub queue: Arc<Queue>,
-  pub command_pool: Arc<CommandPool>,
-  pub buffer: Option<Arc<Buffer>>,
-  pub input_buffer: Option<Arc<Buffer>>,
-  pub output_buffer: Option<Arc<Buffer>>,
-  pub temp_buffer: Option<Arc<Buffer>>,
-  pub kernel: Option<Arc<Buffer>>,
+  #[display("The {} of the transform is zero", _0)]
+  ZeroSize(#[error(not(source))] &'static str),
+  #[display("The {} does not fit in 64 bits", _0)]
+  SizeOverflow(#[error(not(source))] &'static str),
+  #[display("The {} is too small for the axes it spans", _0)]
+  InvalidStride(#[error(not(source))] &'static str),
+  #[display("A {:?} needs at least {} points along each transformed axis", _0, _1)]
+  R2RTooSmall(R2RKind, #[error(not(source))] u32),
+  #[display("The {} is smaller than the transform needs", _0)]
+  BufferTooSmall(#[error(not(source))] &'static str),
+  #[display("The {} holds {} values, which doesn't match the transform", _0, _1)]
+  LengthMismatch(&'static str, #[error(not(source))] usize),
+  #[display("Arrays of {} axes can't be transformed", _0)]
+  AxisCount(#[error(not(source))] usize),
+  #[display("The CPU fallback doesn't support {}", _0)]
+  UnsupportedOnCpu(#[error(not(source))] &'static str),
+  /// VkFFT can't scale forward transforms, so only the stages of `Context::fft_stage` and
+  /// `Context::planned_stage` run `Ortho` plans
+  #[display("Ortho normalization needs the scaling pass of an FftStage")]
+  UnscaledOrtho,
+  #[cfg(any(feature = "cuda", feature = "level-zero"))]
+  #[display("The {} backend doesn't support {}", _0, _1)]
+  UnsupportedOnBackend(&'static str, #[error(not(source))] &'static str),
+  /// See [`overrides`](crate::overrides)
+  #[display("{} is set to the invalid value {:?}", _0, _1)]
+  InvalidOverride(&'static str, #[error(not(source))] String),
 }
 
 #[repr(C)]
 pub(crate) struct ConfigGuard {
-  pub(crate) keep_alive: KeepAlive,
+  pub(crate) keep_alive: Vec<Arc<dyn Any + Send + Sync>>,
   pub(crate) config: vkfft_sys::VkFFTConfiguration,
   pub(crate) physical_device: ash::vk::PhysicalDevice,
   pub(crate) device: ash::vk::Device,
@@ -478,30 +1170,66 @@ pub(crate) struct ConfigGuard {
   pub(crate) temp_buffer: Option<ash::vk::Buffer>,
   pub(crate) kernel_size: u64,
   pub(crate) kernel: Option<ash::vk::Buffer>,
+  pub(crate) application: Option<Vec<u8>>,
+  /// The queue to lock while VkFFT submits to it
+  #[cfg(feature = "vulkano")]
+  pub(crate) vulkano_queue: Option<Arc<Queue>>,
 }
 
-impl<'a> Config<'a> {
-  pub fn builder() -> ConfigBuilder<'a> {
+impl Config {
+  pub fn builder() -> ConfigBuilder {
     ConfigBuilder::new()
   }
 
+  /// Builds a config for a C2C transform of `dim` from raw handles, for Vulkan objects created
+  /// by engines wrapping neither vulkano nor ash. Use [`ConfigBuilder::device_handles`] and
+  /// [`BufferBinding::from_raw`] to set further options.
+  ///
+  /// # Safety
+  ///
+  /// The handles must be valid, belong to the same device, and outlive every application
+  /// created from the config. Each buffer must hold at least the given number of bytes.
+  pub unsafe fn from_raw_handles<const N: usize>(
+    dim: &[u32; N],
+    handles: DeviceHandles,
+    buffers: RawBuffers,
+  ) -> Result<Self, BuildError> {
+    let bind = |buffer: Option<(vk::Buffer, u64)>| {
+      buffer.map(|(handle, size)| BufferBinding::from_raw(handle, size))
+    };
+    let mut builder = ConfigBuilder::new().dim(dim).device_handles(handles);
+    builder.buffer = bind(buffers.buffer);
+    builder.input_buffer = bind(buffers.input_buffer);
+    builder.output_buffer = bind(buffers.output_buffer);
+    builder.temp_buffer = bind(buffers.temp_buffer);
+    builder.kernel = bind(buffers.kernel);
+    builder.build()
+  }
+
+  /// Size of the buffer in bytes, whether it is bound in the config or passed at launch.
   pub fn buffer_size(&self) -> usize {
-    self.buffer.as_ref().map(|b| b.size() as usize).unwrap_or(0)
+    self
+      .buffer
+      .as_ref()
+      .and_then(|b| b.bound_size())
+      .or(self.launch_buffer_sizes[0])
+      .map(|s| std::convert::TryFrom::try_from(s).unwrap_or(usize::MAX))
+      .unwrap_or(0)
   }
 
-  pub fn buffer(&self) -> Option<&Arc<Buffer>> {
+  pub fn buffer(&self) -> Option<&BufferBinding> {
     self.buffer.as_ref()
   }
 
-  pub fn temp_buffer(&self) -> Option<&Arc<Buffer>> {
+  pub fn temp_buffer(&self) -> Option<&BufferBinding> {
     self.temp_buffer.as_ref()
   }
 
-  pub fn input_buffer(&self) -> Option<&Arc<Buffer>> {
+  pub fn input_buffer(&self) -> Option<&BufferBinding> {
     self.input_buffer.as_ref()
   }
 
-  pub fn output_buffer(&self) -> Option<&Arc<Buffer>> {
+  pub fn output_buffer(&self) -> Option<&BufferBinding> {
     self.output_buffer.as_ref()
   }
 
@@ -513,6 +1241,14 @@ impl<'a> Config<'a> {
     self.symmetric_kernel
   }
 
+  pub fn conjugate_convolution(&self) -> Option<Conjugate> {
+    self.conjugate_convolution
+  }
+
+  pub fn cross_power_spectrum_normalization(&self) -> bool {
+    self.cross_power_spectrum_normalization
+  }
+
   pub fn convolution(&self) -> bool {
     self.convolution
   }
@@ -521,8 +1257,17 @@ impl<'a> Config<'a> {
     self.r2c
   }
 
-  pub fn normalize(&self) -> bool {
-    self.normalize
+  pub fn r2r(&self) -> Option<R2RKind> {
+    self.r2r
+  }
+
+  pub fn normalization(&self) -> Normalization {
+    self.normalization
+  }
+
+  /// The factor transforms in the given direction are scaled by.
+  pub fn scale_factor(&self, inverse: bool) -> f64 {
+    self.plan_key().scale_factor(inverse)
   }
 
   pub fn coordinate_features(&self) -> u32 {
@@ -537,154 +1282,682 @@ impl<'a> Config<'a> {
     self.use_lut
   }
 
+  pub fn plan_key(&self) -> PlanKey {
+    plan_key!(self)
+  }
+
   pub(crate) fn as_sys(&self) -> Result<Pin<Box<ConfigGuard>>, ConfigError> {
-    use std::mem::{transmute, zeroed};
+    if self.normalization == Normalization::Ortho {
+      return Err(ConfigError::UnscaledOrtho);
+    }
+    let buffers = [&self.buffer, &self.input_buffer, &self.output_buffer, &self.kernel, &self.temp_buffer];
+    let mut keep_alive = self.keep_alive.clone();
+    for owner in buffers.iter().filter_map(|b| b.as_ref().and_then(|b| b.keep_alive())) {
+      keep_alive.push(owner);
+    }
 
-    unsafe {
-      let keep_alive = KeepAlive {
-        device: self.device.clone(),
-        buffer: self.buffer.as_ref().map(|b| b.clone()),
-        input_buffer: self.input_buffer.as_ref().map(|b| b.clone()),
-        output_buffer: self.output_buffer.as_ref().map(|b| b.clone()),
-        kernel: self.kernel.as_ref().map(|b| b.clone()),
-        command_pool: self.command_pool.clone(),
-        queue: self.queue.clone(),
-        temp_buffer: self.temp_buffer.as_ref().map(|b| b.clone()),
-      };
+    let bound_size = |buffer: &Option<BufferBinding>, launch_size: Option<u64>| match buffer {
+      Some(b) => b
+        .bound_size()
+        .ok_or(ConfigError::SizeOverflow("end of a buffer range")),
+      None => Ok(launch_size.unwrap_or(0)),
+    };
+    let sizes = &self.launch_buffer_sizes;
+
+    let mut res = Box::pin(ConfigGuard {
+      keep_alive,
+      config: unsafe { std::mem::zeroed() },
+      physical_device: self.physical_device,
+      device: self.device,
+      queue: self.queue,
+      command_pool: self.command_pool,
+      fence: self.fence,
+      buffer_size: bound_size(&self.buffer, sizes[0])?,
+      temp_buffer_size: bound_size(&self.temp_buffer, sizes[1])?,
+      input_buffer_size: bound_size(&self.input_buffer, sizes[2])?,
+      output_buffer_size: bound_size(&self.output_buffer, sizes[3])?,
+      kernel_size: bound_size(&self.kernel, sizes[4])?,
+      buffer: self.buffer.as_ref().map(|b| b.handle()),
+      temp_buffer: self.temp_buffer.as_ref().map(|b| b.handle()),
+      input_buffer: self.input_buffer.as_ref().map(|b| b.handle()),
+      output_buffer: self.output_buffer.as_ref().map(|b| b.handle()),
+      kernel: self.kernel.as_ref().map(|b| b.handle()),
+      application: None,
+      #[cfg(feature = "vulkano")]
+      vulkano_queue: self.vulkano_queue.clone(),
+    });
+
+    res.wire();
+    self.plan_key().write_sys(&mut res.config)?;
+    if !self.primes.bluestein_padding.is_empty() {
+      let (mut prime_sizes, mut padded_sizes) = self.primes.padding_arrays();
+      res.config.useCustomBluesteinPaddingPattern = prime_sizes.len() as u64;
+      res.config.primeSizes = prime_sizes.as_mut_ptr();
+      res.config.paddedSizes = padded_sizes.as_mut_ptr();
+      res.keep_alive.push(Arc::new(prime_sizes));
+      res.keep_alive.push(Arc::new(padded_sizes));
+    }
+    res.config.saveApplicationToString = self.save_application.into();
+    res.config.printMemoryLayout = self.print_memory_layout.into();
+    res.config.keepShaderCode = self.keep_shader_code.into();
 
-      let mut res = Box::pin(ConfigGuard {
-        keep_alive,
-        config: zeroed(),
-        physical_device: self.physical_device.handle(),
-        device: self.device.handle(),
-        queue: self.queue.handle(),
-        command_pool: self.command_pool.handle(),
-        fence: self.fence.handle(),
-        buffer_size: self.buffer.as_ref().map(|b| b.size()).unwrap_or(0),
-        temp_buffer_size: self.temp_buffer.as_ref().map(|b| b.size()).unwrap_or(0),
-        input_buffer_size: self.input_buffer.as_ref().map(|b| b.size()).unwrap_or(0),
-        output_buffer_size: self.output_buffer.as_ref().map(|b| b.size()).unwrap_or(0),
-        kernel_size: self.kernel.as_ref().map(|b| b.size()).unwrap_or(0),
-        buffer: self.buffer.as_ref().map(|b| b.handle()),
-        temp_buffer: self.temp_buffer.as_ref().map(|b| b.handle()),
-        input_buffer: self.input_buffer.as_ref().map(|b| b.handle()),
-        output_buffer: self.output_buffer.as_ref().map(|b| b.handle()),
-        kernel: self.kernel.as_ref().map(|b| b.handle()),
-      });
-
-      res.config.FFTdim = self.fft_dim as u64;
-      res.config.size = self.size.map(u64::from);
-
-      res.config.physicalDevice = transmute::<*mut ash::vk::PhysicalDevice, *mut *mut vkfft_sys::VkPhysicalDevice_T>(addr_of_mut!(res.physical_device));
-      res.config.device = transmute::<*mut ash::vk::Device, *mut *mut vkfft_sys::VkDevice_T>(addr_of_mut!(res.device));
-      res.config.queue = transmute::<*mut ash::vk::Queue, *mut *mut vkfft_sys::VkQueue_T>(addr_of_mut!(res.queue));
-      res.config.commandPool = transmute::<*mut ash::vk::CommandPool, *mut *mut vkfft_sys::VkCommandPool_T>(addr_of_mut!(res.command_pool));
-      res.config.fence = transmute::<*mut ash::vk::Fence, *mut *mut vkfft_sys::VkFence_T>(addr_of_mut!(res.fence));
-      res.config.normalize = self.normalize.into();
-
-      if res.kernel_size != 0 {
-        res.config.kernelSize = addr_of_mut!(res.kernel_size);
-      }
+    Ok(res)
+  }
+}
 
-      if let Some(t) = &res.kernel {
-        res.config.kernel = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
-      }
+/// The parameters of a config VkFFT was given, attached to errors for diagnosis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigSummary {
+  pub fft_dim: u64,
+  pub size: [u64; MAX_FFT_DIMENSIONS],
+  pub number_batches: u64,
+  pub coordinate_features: u64,
+  pub precision: Precision,
+  /// Names of the enabled options, such as `r2c` or `convolution`
+  pub flags: Vec<&'static str>,
+  pub buffer_size: u64,
+  pub temp_buffer_size: u64,
+  pub input_buffer_size: u64,
+  pub output_buffer_size: u64,
+  pub kernel_size: u64,
+}
 
-      if res.buffer_size != 0 {
-        res.config.bufferSize = addr_of_mut!(res.buffer_size);
-      }
+impl std::fmt::Display for ConfigSummary {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let size = self.size[..(self.fft_dim as usize).clamp(1, MAX_FFT_DIMENSIONS)]
+      .iter()
+      .map(|s| s.to_string())
+      .collect::<Vec<_>>()
+      .join("x");
+    write!(f, "{} {:?}", size, self.precision)?;
+    if self.number_batches > 1 {
+      write!(f, ", {} batches", self.number_batches)?;
+    }
+    if self.coordinate_features > 1 {
+      write!(f, ", {} coordinate features", self.coordinate_features)?;
+    }
+    for flag in &self.flags {
+      write!(f, ", {}", flag)?;
+    }
 
-      if let Some(t) = &res.buffer {
-        res.config.buffer = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
-      }
+    let buffers = [
+      ("buffer", self.buffer_size),
+      ("temp_buffer", self.temp_buffer_size),
+      ("input_buffer", self.input_buffer_size),
+      ("output_buffer", self.output_buffer_size),
+      ("kernel", self.kernel_size),
+    ];
+    for (name, size) in buffers.iter().filter(|(_, size)| *size != 0) {
+      write!(f, ", {} of {} bytes", name, size)?;
+    }
+    Ok(())
+  }
+}
 
-      if res.temp_buffer_size != 0 {
-        res.config.userTempBuffer = 1;
-        res.config.tempBufferSize = addr_of_mut!(res.temp_buffer_size);
-      }
+impl ConfigGuard {
+  pub(crate) fn summary(&self) -> ConfigSummary {
+    let config = &self.config;
+    let precision = if config.quadDoubleDoublePrecision != 0 {
+      Precision::QuadDoubleDouble
+    } else if config.doublePrecision != 0 {
+      Precision::Double
+    } else if config.halfPrecisionMemoryOnly != 0 {
+      Precision::HalfMemory
+    } else if config.halfPrecision != 0 {
+      Precision::Half
+    } else {
+      Precision::Single
+    };
 
-      if let Some(t) = &res.temp_buffer {
-        res.config.tempBuffer = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
-      }
+    let options = [
+      ("r2c", config.performR2C),
+      ("dct", config.performDCT),
+      ("dst", config.performDST),
+      ("normalize", config.normalize),
+      ("convolution", config.performConvolution),
+      ("kernel_convolution", config.kernelConvolution),
+      ("conjugate_convolution", config.conjugateConvolution),
+      ("cross_power_spectrum_normalization", config.crossPowerSpectrumNormalization),
+      ("zero_padding", config.performZeropadding.iter().any(|z| *z != 0) as u64),
+      ("omit_dimension", config.omitDimension.iter().any(|o| *o != 0) as u64),
+      ("disable_reorder_four_step", config.disableReorderFourStep),
+      ("use_lut", (config.useLUT > 0) as u64),
+      ("input_formatted", config.isInputFormatted),
+      ("output_formatted", config.isOutputFormatted),
+      ("load_application", config.loadApplicationFromString),
+    ];
+
+    ConfigSummary {
+      fft_dim: config.FFTdim,
+      size: config.size,
+      number_batches: config.numberBatches,
+      coordinate_features: config.coordinateFeatures,
+      precision,
+      flags: options
+        .iter()
+        .filter(|(_, value)| *value != 0)
+        .map(|(name, _)| *name)
+        .collect(),
+      buffer_size: self.buffer_size,
+      temp_buffer_size: self.temp_buffer_size,
+      input_buffer_size: self.input_buffer_size,
+      output_buffer_size: self.output_buffer_size,
+      kernel_size: self.kernel_size,
+    }
+  }
 
-      if res.input_buffer_size != 0 {
-        res.config.inputBufferSize = addr_of_mut!(res.input_buffer_size);
-      }
+  /// A copy of this guard with its own storage for the values `config` points at.
+  pub(crate) fn duplicate(&self) -> Pin<Box<ConfigGuard>> {
+    let mut res = Box::pin(ConfigGuard {
+      keep_alive: self.keep_alive.clone(),
+      config: self.config,
+      physical_device: self.physical_device,
+      device: self.device,
+      queue: self.queue,
+      command_pool: self.command_pool,
+      fence: self.fence,
+      buffer_size: self.buffer_size,
+      buffer: self.buffer,
+      input_buffer_size: self.input_buffer_size,
+      input_buffer: self.input_buffer,
+      output_buffer_size: self.output_buffer_size,
+      output_buffer: self.output_buffer,
+      temp_buffer_size: self.temp_buffer_size,
+      temp_buffer: self.temp_buffer,
+      kernel_size: self.kernel_size,
+      kernel: self.kernel,
+      application: None,
+      #[cfg(feature = "vulkano")]
+      vulkano_queue: self.vulkano_queue.clone(),
+    });
+    res.wire();
+    if let Some(application) = &self.application {
+      res.load_application(application.clone());
+    }
+    res
+  }
 
-      if let Some(t) = &res.input_buffer {
-        res.config.inputBuffer = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
-      }
+  /// Makes VkFFT load its kernels from `application` instead of generating them.
+  pub(crate) fn load_application(&mut self, application: Vec<u8>) {
+    let application = self.application.insert(application);
+    self.config.loadApplicationFromString = 1;
+    self.config.loadApplicationString = application.as_mut_ptr() as *mut std::ffi::c_void;
+  }
 
-      if res.output_buffer_size != 0 {
-        res.config.outputBufferSize = addr_of_mut!(res.output_buffer_size);
-      }
+  /// Points the handle and size fields of `config` at the values stored in this guard.
+  pub(crate) fn wire(&mut self) {
+    use std::mem::transmute;
 
-      if let Some(t) = &res.output_buffer {
-        res.config.outputBuffer = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
-      }
+    unsafe {
+      self.config.physicalDevice = transmute::<*mut ash::vk::PhysicalDevice, *mut *mut vkfft_sys::VkPhysicalDevice_T>(addr_of_mut!(self.physical_device));
+      self.config.device = transmute::<*mut ash::vk::Device, *mut *mut vkfft_sys::VkDevice_T>(addr_of_mut!(self.device));
+      self.config.queue = transmute::<*mut ash::vk::Queue, *mut *mut vkfft_sys::VkQueue_T>(addr_of_mut!(self.queue));
+      self.config.commandPool = transmute::<*mut ash::vk::CommandPool, *mut *mut vkfft_sys::VkCommandPool_T>(addr_of_mut!(self.command_pool));
+      self.config.fence = transmute::<*mut ash::vk::Fence, *mut *mut vkfft_sys::VkFence_T>(addr_of_mut!(self.fence));
+    }
 
-      res.config.performZeropadding[0] = self.zero_padding[0].into();
-      res.config.performZeropadding[1] = self.zero_padding[1].into();
-      res.config.performZeropadding[2] = self.zero_padding[2].into();
+    if self.kernel_size != 0 {
+      self.config.kernelSize = addr_of_mut!(self.kernel_size);
+    }
 
-      res.config.fft_zeropad_left = self.zeropad_left.map(u64::from);
-      res.config.fft_zeropad_right = self.zeropad_right.map(u64::from);
-      res.config.performConvolution = self.convolution.into();
-      if self.convolution {
-        res.config.numberKernels = 1;
-      }
-      res.config.kernelConvolution = self.kernel_convolution as u64;
-      res.config.performR2C = self.r2c.into();
-      res.config.performDCT = self.dct.unwrap_or(0);
-      res.config.performDST = self.dst.unwrap_or(0);
-      res.config.coordinateFeatures = self.coordinate_features as u64;
-      res.config.disableReorderFourStep = self.disable_reorder_four_step.into();
+    if let Some(t) = &self.kernel {
+      self.config.kernel = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
+    }
 
-      res.config.symmetricKernel = self.symmetric_kernel.into();
+    if self.buffer_size != 0 {
+      self.config.bufferSize = addr_of_mut!(self.buffer_size);
+    }
 
-      if let Some(input_formatted) = self.input_formatted {
-        res.config.isInputFormatted = input_formatted.into();
-      }
+    if let Some(t) = &self.buffer {
+      self.config.buffer = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
+    }
 
-      if let Some(inverse_return_to_input) = self.inverse_return_to_input {
-        res.config.inverseReturnToInputBuffer = inverse_return_to_input.into();
-      }
+    if self.temp_buffer_size != 0 {
+      self.config.userTempBuffer = 1;
+      self.config.tempBufferSize = addr_of_mut!(self.temp_buffer_size);
+    }
 
-      if let Some(output_formatted) = self.output_formatted {
-        res.config.isOutputFormatted = output_formatted.into();
-      }
+    if let Some(t) = &self.temp_buffer {
+      self.config.tempBuffer = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
+    }
 
-      match self.precision {
-        Precision::Double => {
-          res.config.doublePrecision = true.into();
-        }
-        Precision::Half => res.config.halfPrecision = true.into(),
-        Precision::HalfMemory => {
-          res.config.halfPrecisionMemoryOnly = true.into();
+    if self.input_buffer_size != 0 {
+      self.config.inputBufferSize = addr_of_mut!(self.input_buffer_size);
+    }
 
-          if let Some(false) = self.input_formatted {
-            return Err(ConfigError::InvalidConfig);
-          }
+    if let Some(t) = &self.input_buffer {
+      self.config.inputBuffer = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
+    }
 
-          if let Some(false) = self.output_formatted {
-            return Err(ConfigError::InvalidConfig);
+    if self.output_buffer_size != 0 {
+      self.config.outputBufferSize = addr_of_mut!(self.output_buffer_size);
+    }
+
+    if let Some(t) = &self.output_buffer {
+      self.config.outputBuffer = t as *const ash::vk::Buffer as *mut *mut vkfft_sys::VkBuffer_T;
+    }
+  }
+}
+
+impl PlanKey {
+  /// The number of points transformed, the product of the sizes of the transformed axes.
+  pub fn transform_len(&self) -> f64 {
+    let axes = (self.fft_dim as usize).min(MAX_FFT_DIMENSIONS);
+    self.size[..axes]
+      .iter()
+      .zip(self.omit_dimension)
+      .filter(|(_, omit)| !omit)
+      .map(|(len, _)| *len as f64)
+      .product()
+  }
+
+  /// The factor transforms in the given direction are scaled by.
+  pub fn scale_factor(&self, inverse: bool) -> f64 {
+    self
+      .normalization
+      .scale_factor(self.transform_len(), inverse)
+  }
+
+  /// Checks that no axis or count is zero and that the size of the transform in bytes fits in
+  /// 64 bits, returning that size.
+  pub(crate) fn checked_len(&self) -> Result<u64, ConfigError> {
+    let axes = &self.size[..(self.fft_dim as usize).min(MAX_FFT_DIMENSIONS)];
+    if axes.contains(&0) {
+      return Err(ConfigError::ZeroSize("size"));
+    }
+    if self.batch_count == Some(0) {
+      return Err(ConfigError::ZeroSize("batch count"));
+    }
+    if self.coordinate_features == 0 {
+      return Err(ConfigError::ZeroSize("coordinate feature count"));
+    }
+
+    let scalar_bytes = self.precision.compute_scalar_bytes();
+    axes
+      .iter()
+      .chain(self.batch_count.as_ref())
+      .chain(Some(&self.coordinate_features))
+      .try_fold(2 * scalar_bytes, |len, n| len.checked_mul(*n as u64))
+      .ok_or(ConfigError::SizeOverflow("size of the transform in bytes"))
+  }
+
+  /// The buffer, input and output strides set, in that order, with the entries left at 0 filled
+  /// in for a contiguous layout, paired with the number of elements in a row of each buffer.
+  pub(crate) fn resolved_strides(
+    &self,
+  ) -> [(&'static str, Option<[u64; MAX_FFT_DIMENSIONS]>, u64); 3] {
+    let dims = (self.fft_dim as usize).clamp(1, MAX_FFT_DIMENSIONS);
+    let row = if self.r2c {
+      self.size[0] as u64 / 2 + 1
+    } else {
+      self.size[0] as u64
+    };
+    // The input of an R2C transform is real and unpadded
+    let input_row = if self.r2c { self.size[0] as u64 } else { row };
+    let resolve = |stride: Option<[u64; MAX_FFT_DIMENSIONS]>, row: u64| {
+      stride.map(|mut stride| {
+        let mut span = row;
+        for (axis, stride) in stride[..dims].iter_mut().enumerate() {
+          if *stride == 0 {
+            *stride = span;
           }
+          span = match self.size.get(axis + 1).filter(|_| axis + 1 < dims) {
+            Some(len) => stride.saturating_mul(*len as u64),
+            None => *stride,
+          };
+        }
+        stride
+      })
+    };
+
+    [
+      ("buffer stride", resolve(self.buffer_stride, row), row),
+      (
+        "input buffer stride",
+        resolve(self.input_buffer_stride, input_row),
+        input_row,
+      ),
+      ("output buffer stride", resolve(self.output_buffer_stride, row), row),
+    ]
+  }
 
-          res.config.isInputFormatted = true.into();
-          res.config.isOutputFormatted = true.into();
+  /// Checks that every stride set leaves room for the axes before it, and that the span of a
+  /// strided buffer fits in 64 bits.
+  pub(crate) fn check_strides(&self) -> Result<(), ConfigError> {
+    let dims = (self.fft_dim as usize).clamp(1, MAX_FFT_DIMENSIONS);
+    let batches = self.batch_count.unwrap_or(1) as u64 * self.coordinate_features as u64;
+
+    for (name, stride, row) in self.resolved_strides() {
+      let stride = match stride {
+        Some(stride) => stride,
+        None => continue,
+      };
+      let mut span = row;
+      for (axis, stride) in stride[..dims].iter().enumerate() {
+        if *stride < span {
+          return Err(ConfigError::InvalidStride(name));
         }
-        _ => {}
+        span = match self.size.get(axis + 1).filter(|_| axis + 1 < dims) {
+          Some(len) => stride
+            .checked_mul(*len as u64)
+            .ok_or(ConfigError::SizeOverflow(name))?,
+          None => *stride,
+        };
       }
+      span
+        .checked_mul(batches)
+        .ok_or(ConfigError::SizeOverflow(name))?;
+    }
+    Ok(())
+  }
+
+  /// Checks that a real-to-real transform of `kind` is neither combined with R2C or a
+  /// convolution, nor shorter than the transform type allows along any transformed axis.
+  pub(crate) fn check_r2r(&self, kind: R2RKind) -> Result<(), ConfigError> {
+    if self.r2c || self.convolution || self.kernel_convolution {
+      return Err(ConfigError::InvalidConfig);
+    }
+    let axes = (self.fft_dim as usize).min(MAX_FFT_DIMENSIONS);
+    let min_size = kind.min_size();
+    let too_small = self.size[..axes]
+      .iter()
+      .zip(self.omit_dimension)
+      .any(|(len, omit)| !omit && *len < min_size);
+    if too_small {
+      return Err(ConfigError::R2RTooSmall(kind, min_size));
+    }
+    Ok(())
+  }
+
+  /// Writes the plan parameters into `config`, leaving handles and buffers untouched.
+  pub(crate) fn write_sys(&self, config: &mut vkfft_sys::VkFFTConfiguration) -> Result<(), ConfigError> {
+    EnvOverrides::captured()?;
+    self.checked_len()?;
+    self.check_strides()?;
+
+    config.FFTdim = self.fft_dim as u64;
+    config.size = self.size.map(u64::from);
+    config.normalize = (self.normalization == Normalization::Backward).into();
+
+    config.performZeropadding = self.zero_padding.map(u64::from);
+
+    config.fft_zeropad_left = self.zeropad_left.map(u64::from);
+    config.fft_zeropad_right = self.zeropad_right.map(u64::from);
+
+    let axes = (self.fft_dim as usize).min(MAX_FFT_DIMENSIONS);
+    if self.omit_dimension[..axes].iter().all(|o| *o) {
+      return Err(ConfigError::InvalidConfig);
+    }
+    for (omit, omit_dimension) in config.omitDimension.iter_mut().zip(self.omit_dimension) {
+      *omit = omit_dimension.into();
+    }
+    config.performConvolution = self.convolution.into();
+    if self.convolution {
+      config.numberKernels = 1;
+    }
+    config.kernelConvolution = self.kernel_convolution as u64;
+    config.performR2C = self.r2c.into();
+    if let Some(kind) = self.r2r {
+      self.check_r2r(kind)?;
+      let (dct, dst) = kind.perform();
+      config.performDCT = dct;
+      config.performDST = dst;
+    }
+    config.coordinateFeatures = self.coordinate_features as u64;
+    config.disableReorderFourStep = self.disable_reorder_four_step.into();
+
+    config.symmetricKernel = self.symmetric_kernel.into();
+
+    let conjugates = self.conjugate_convolution.is_some() || self.cross_power_spectrum_normalization;
+    if conjugates && !self.convolution {
+      return Err(ConfigError::InvalidConfig);
+    }
+    config.conjugateConvolution = match self.conjugate_convolution {
+      None => 0,
+      Some(Conjugate::Input) => 1,
+      Some(Conjugate::Kernel) => 2,
+    };
+    config.crossPowerSpectrumNormalization = self.cross_power_spectrum_normalization.into();
+
+    if let Some(input_formatted) = self.input_formatted {
+      config.isInputFormatted = input_formatted.into();
+    }
+
+    if let Some(inverse_return_to_input) = self.inverse_return_to_input {
+      config.inverseReturnToInputBuffer = inverse_return_to_input.into();
+    }
+
+    if let Some(output_formatted) = self.output_formatted {
+      config.isOutputFormatted = output_formatted.into();
+    }
 
-      if let Some(batch_count) = self.batch_count {
-        res.config.numberBatches = batch_count as u64;
+    match self.precision {
+      Precision::Double => {
+        config.doublePrecision = true.into();
       }
+      Precision::QuadDoubleDouble => {
+        config.quadDoubleDoublePrecision = true.into();
+      }
+      Precision::Half => config.halfPrecision = true.into(),
+      Precision::HalfMemory => {
+        config.halfPrecisionMemoryOnly = true.into();
+
+        if let Some(false) = self.input_formatted {
+          return Err(ConfigError::InvalidConfig);
+        }
 
-      if let Some(matrix_convolution) = self.matrix_convolution {
-        res.config.matrixConvolution = matrix_convolution;
+        if let Some(false) = self.output_formatted {
+          return Err(ConfigError::InvalidConfig);
+        }
+
+        config.isInputFormatted = true.into();
+        config.isOutputFormatted = true.into();
       }
+      _ => {}
+    }
 
-      Ok(res)
+    if let Some(batch_count) = self.batch_count {
+      config.numberBatches = batch_count as u64;
+    }
+
+    if let Some(matrix_convolution) = self.matrix_convolution {
+      config.matrixConvolution = matrix_convolution;
+    }
+
+    config.bufferOffset = self.offsets[0];
+    config.tempBufferOffset = self.offsets[1];
+    config.inputBufferOffset = self.offsets[2];
+    config.outputBufferOffset = self.offsets[3];
+    config.kernelOffset = self.offsets[4];
+    config.specifyOffsetsAtLaunch = self.offsets_at_launch.into();
+
+    let [(_, buffer_stride, _), (_, input_buffer_stride, _), (_, output_buffer_stride, _)] =
+      self.resolved_strides();
+    if let Some(buffer_stride) = buffer_stride {
+      config.bufferStride = buffer_stride;
+    }
+    if let Some(input_buffer_stride) = input_buffer_stride {
+      config.inputBufferStride = input_buffer_stride;
     }
+    if let Some(output_buffer_stride) = output_buffer_stride {
+      config.outputBufferStride = output_buffer_stride;
+    }
+
+    self.primes.check()?;
+    self.primes.write_sys(config);
+    self.tuning.write_sys(config);
+    self.overrides.write_sys(config);
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_size_guards() {
+    let key = ConfigBuilder::new().dim(&[1024, 1024]).batch_count(4).plan_key();
+    assert_eq!(key.checked_len().unwrap(), 1024 * 1024 * 4 * 8);
+
+    let key = ConfigBuilder::new().dim(&[0, 16]).plan_key();
+    assert!(matches!(key.checked_len(), Err(ConfigError::ZeroSize("size"))));
+
+    let key = ConfigBuilder::new()
+      .dim(&[u32::MAX, u32::MAX, u32::MAX])
+      .plan_key();
+    assert!(matches!(key.checked_len(), Err(ConfigError::SizeOverflow(_))));
+
+    let key = ConfigBuilder::new().dim(&[8, 8, 8, 16]).plan_key();
+    assert_eq!(key.checked_len().unwrap(), 8 * 8 * 8 * 16 * 8);
+
+    let key = ConfigBuilder::new().dim(&[8, 8, 8, 0]).plan_key();
+    assert!(matches!(key.checked_len(), Err(ConfigError::ZeroSize("size"))));
+
+    let range = unsafe { BufferBinding::from_raw_range(vk::Buffer::null(), u64::MAX, 1) };
+    assert_eq!(range.bound_size(), None);
+  }
+
+  #[test]
+  fn check_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ConfigBuilder>();
+    assert_send_sync::<Config>();
+  }
+
+  #[test]
+  fn check_scale_factors() {
+    let key = ConfigBuilder::new()
+      .dim(&[16, 4])
+      .normalization(Normalization::Ortho)
+      .plan_key();
+    assert_eq!(key.transform_len(), 64.0);
+    assert_eq!(key.scale_factor(false), 0.125);
+    assert_eq!(key.scale_factor(true), 0.125);
+
+    let key = ConfigBuilder::new().dim(&[16, 4]).normalize().plan_key();
+    assert_eq!((key.scale_factor(false), key.scale_factor(true)), (1.0, 1.0 / 64.0));
+  }
+
+  #[test]
+  fn check_from_raw_handles() {
+    let handles = DeviceHandles {
+      physical_device: vk::PhysicalDevice::null(),
+      device: vk::Device::null(),
+      queue: vk::Queue::null(),
+      command_pool: vk::CommandPool::null(),
+      fence: vk::Fence::null(),
+    };
+    let buffers = RawBuffers {
+      buffer: Some((vk::Buffer::null(), 64 * 8)),
+      ..Default::default()
+    };
+    let config = unsafe { Config::from_raw_handles(&[64], handles, buffers) }.unwrap();
+    assert_eq!(config.buffer_size(), 64 * 8);
+    assert!(config.input_buffer.is_none());
+  }
+
+  #[test]
+  fn check_launch_buffer_sizes() {
+    let builder = ConfigBuilder::new()
+      .dim(&[256])
+      .input_buffer_size(2048)
+      .buffer_size(4096);
+    let key = builder.plan_key();
+    assert!(key.input_buffer && !key.output_buffer && !key.temp_buffer);
+    assert_eq!(builder.launch_buffer_sizes, [Some(4096), None, Some(2048), None, None]);
+  }
+
+  #[test]
+  fn check_buffer_offsets() {
+    let range = |offset| unsafe { BufferBinding::from_raw_range(vk::Buffer::null(), offset, 2048) };
+    let builder = ConfigBuilder::new().dim(&[256]).buffer(range(512));
+    assert_eq!(builder.plan_key().offsets, [512, 0, 0, 0, 0]);
+    assert_ne!(
+      builder.plan_key(),
+      ConfigBuilder::new().dim(&[256]).buffer(range(0)).plan_key()
+    );
+
+    let at_launch = |offset| {
+      ConfigBuilder::new()
+        .dim(&[256])
+        .buffer(range(offset))
+        .offsets_at_launch()
+        .plan_key()
+    };
+    assert_eq!(at_launch(512), at_launch(0));
+  }
+
+  #[test]
+  fn check_strides() {
+    let key = |stride: &[u64; 2]| {
+      ConfigBuilder::new()
+        .dim(&[64, 32])
+        .batch_count(4)
+        .buffer_stride(stride)
+        .plan_key()
+        .check_strides()
+    };
+    assert!(key(&[64, 64 * 32]).is_ok());
+    assert!(key(&[80, 80 * 40]).is_ok());
+    assert!(matches!(key(&[63, 64 * 32]), Err(ConfigError::InvalidStride("buffer stride"))));
+    assert!(matches!(key(&[64, 64 * 31]), Err(ConfigError::InvalidStride(_))));
+    assert!(matches!(key(&[64, u64::MAX]), Err(ConfigError::SizeOverflow(_))));
+
+    let r2c = ConfigBuilder::new().dim(&[64]).r2c().buffer_stride(&[33]).plan_key();
+    assert!(r2c.check_strides().is_ok());
+
+    // Strides left out are contiguous after the ones given
+    let padded = ConfigBuilder::new()
+      .dim(&[64, 32])
+      .buffer_stride(&[80])
+      .plan_key();
+    assert!(padded.check_strides().is_ok());
+    assert_eq!(
+      padded.resolved_strides()[0].1.map(|stride| [stride[0], stride[1]]),
+      Some([80, 80 * 32])
+    );
+  }
+
+  #[test]
+  fn check_r2r() {
+    let key = |kind, size: &[u32; 2]| ConfigBuilder::new().dim(size).r2r(kind).plan_key();
+    assert!(key(R2RKind::Dst2, &[16, 1]).check_r2r(R2RKind::Dst2).is_ok());
+    assert!(matches!(
+      key(R2RKind::Dct1, &[16, 1]).check_r2r(R2RKind::Dct1),
+      Err(ConfigError::R2RTooSmall(R2RKind::Dct1, 2))
+    ));
+    let omitted = ConfigBuilder::new()
+      .dim(&[16, 1])
+      .omit_dimension(&[false, true])
+      .plan_key();
+    assert!(omitted.check_r2r(R2RKind::Dct1).is_ok());
+
+    let r2c = ConfigBuilder::new().dim(&[16]).r2c().plan_key();
+    assert!(matches!(r2c.check_r2r(R2RKind::Dct2), Err(ConfigError::InvalidConfig)));
+
+    assert_eq!(R2RKind::Dst3.perform(), (0, 3));
+    assert_eq!(R2RKind::Dct2.inverse(), R2RKind::Dct3);
+    assert_eq!(R2RKind::Dst4.inverse(), R2RKind::Dst4);
+  }
+
+  #[test]
+  fn check_conjugate_convolution() {
+    let mut sys = unsafe { std::mem::zeroed::<vkfft_sys::VkFFTConfiguration>() };
+    let key = ConfigBuilder::new()
+      .dim(&[64])
+      .convolution()
+      .conjugate_convolution(Conjugate::Kernel)
+      .cross_power_spectrum_normalization()
+      .plan_key();
+    assert!(key.write_sys(&mut sys).is_ok());
+    assert_eq!((sys.conjugateConvolution, sys.crossPowerSpectrumNormalization), (2, 1));
+
+    let key = ConfigBuilder::new()
+      .dim(&[64])
+      .cross_power_spectrum_normalization()
+      .plan_key();
+    assert!(matches!(key.write_sys(&mut sys), Err(ConfigError::InvalidConfig)));
   }
 }
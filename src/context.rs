@@ -1,13 +1,38 @@
+#[cfg(feature = "async")]
+use crate::future::FenceFuture;
 use crate::{
   app::{App, LaunchParams},
-  config::ConfigBuilder,
+  capabilities::Capabilities,
+  config::{Config, ConfigBuilder, ConfigError, Normalization, Precision},
+  disk_cache::DiskCache,
+  dry_run::DryRun,
+  gpu_future::{FftExecFuture, Fenced},
+  plan_cache::PlanCache,
+  planner::FftPlanner,
+  scalar::FftScalar,
+  stage::{FftStage, OrthoScale, Stage},
 };
 use ash::vk::Result as ash_Result;
-use std::{pin::Pin, sync::Arc};
+use derive_more::{Display, Error};
+use std::{
+  cell::{Cell, RefCell},
+  pin::Pin,
+  sync::Arc,
+  time::Duration,
+};
 use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferInheritanceInfo, PrimaryAutoCommandBuffer, SecondaryAutoCommandBuffer};
-use vulkano::device::{physical::PhysicalDevice, Device, Queue};
-use vulkano::instance::Instance;
-use vulkano::sync::fence::Fence;
+use vulkano::command_buffer::{CommandBufferBeginInfo, CommandBufferLevel, RecordingCommandBuffer};
+use vulkano::device::{
+  physical::{PhysicalDevice, PhysicalDeviceType},
+  Device, Queue,
+};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::instance::{Instance, InstanceCreateFlags, InstanceCreateInfo, InstanceExtensions};
+use vulkano::sync::{
+  fence::Fence,
+  semaphore::{Semaphore, SemaphoreCreateInfo, SemaphoreType, SemaphoreWaitInfo},
+  GpuFuture,
+};
 use vulkano::{
   buffer::{AllocateBufferError, Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
   memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter},
@@ -19,78 +44,665 @@ use vulkano::{
     pool::{CommandPool, CommandPoolCreateFlags, CommandPoolCreateInfo},
     CommandBufferUsage,
   },
-  device::{DeviceCreateInfo, QueueCreateInfo, QueueFlags},
+  device::{DeviceCreateInfo, DeviceExtensions, DeviceFeatures, QueueCreateInfo, QueueFlags},
   sync::fence::FenceCreateInfo,
-  VulkanObject,
+  VulkanError, VulkanLibrary, VulkanObject,
 };
 
+/// Instance parameters that work with the Vulkan loader of the current platform.
+///
+/// Portability enumeration (needed to see MoltenVK devices on macOS) is only requested when the
+/// loader supports it, so the same code path also works with loaders that reject the extension,
+/// such as Android's.
+pub fn instance_create_info(library: &VulkanLibrary) -> InstanceCreateInfo {
+  let supported = library.supported_extensions();
+  let portability = supported.khr_portability_enumeration;
+
+  InstanceCreateInfo {
+    flags: if portability {
+      InstanceCreateFlags::ENUMERATE_PORTABILITY
+    } else {
+      InstanceCreateFlags::empty()
+    },
+    enabled_extensions: InstanceExtensions {
+      khr_get_physical_device_properties2: supported.khr_get_physical_device_properties2,
+      khr_portability_enumeration: portability,
+      ..Default::default()
+    },
+    ..Default::default()
+  }
+}
+
+#[derive(Display, Debug, Error)]
+pub enum SubmitError {
+  #[display("Submission to the Vulkan queue failed with {:?}", _0)]
+  Submit(#[error(not(source))] ash_Result),
+  #[display("Submitted work did not complete within {:?}", _0)]
+  Timeout(#[error(not(source))] Duration),
+  DeviceLost,
+  Wait(VulkanError),
+  /// [`Context::submit_timeline`] was called on a device without timeline semaphores
+  #[display("The device doesn't support timeline semaphores")]
+  NoTimeline,
+}
+
+/// Creates a device on `physical` with the extensions and features transforms use, and returns
+/// its queue of family `queue_family_index`, and one of `transfer_family` if given.
+fn create_queue(
+  physical: Arc<PhysicalDevice>,
+  queue_family_index: u32,
+  transfer_family: Option<u32>,
+) -> Result<(Arc<Queue>, Option<Arc<Queue>>), crate::Error> {
+  // Non-conformant implementations such as MoltenVK advertise VK_KHR_portability_subset,
+  // which must be enabled whenever it is supported.
+  let supported_extensions = physical.supported_extensions();
+  // Half precision features are core from Vulkan 1.1 and 1.2. Older devices provide them through
+  // extensions, which need the instance to query extended features.
+  let instance = physical.instance();
+  let extended_features = instance.api_version() >= vulkano::Version::V1_1
+    || instance.enabled_extensions().khr_get_physical_device_properties2;
+  let core_1_1 = physical.api_version() >= vulkano::Version::V1_1;
+  let core_1_2 = physical.api_version() >= vulkano::Version::V1_2;
+  let float16_int8 =
+    !core_1_2 && extended_features && supported_extensions.khr_shader_float16_int8;
+  let storage_16bit = !core_1_1
+    && extended_features
+    && supported_extensions.khr_16bit_storage
+    && supported_extensions.khr_storage_buffer_storage_class;
+  let timeline_semaphore =
+    !core_1_2 && extended_features && supported_extensions.khr_timeline_semaphore;
+  let enabled_extensions = DeviceExtensions {
+    khr_portability_subset: supported_extensions.khr_portability_subset,
+    khr_shader_float16_int8: float16_int8,
+    khr_16bit_storage: storage_16bit,
+    khr_storage_buffer_storage_class: storage_16bit,
+    khr_timeline_semaphore: timeline_semaphore,
+    ..Default::default()
+  };
+  // Every precision's features are enabled where available, so plans of any precision the
+  // device supports can run on it. Portability implementations often lack 64-bit shader types.
+  let supported_features = physical.supported_features();
+  let enabled_features = DeviceFeatures {
+    shader_float64: supported_features.shader_float64,
+    shader_int64: supported_features.shader_int64,
+    shader_float16: (core_1_2 || float16_int8) && supported_features.shader_float16,
+    storage_buffer16_bit_access: (core_1_1 || storage_16bit)
+      && supported_features.storage_buffer16_bit_access,
+    timeline_semaphore: (core_1_2 || timeline_semaphore) && supported_features.timeline_semaphore,
+    // Only defined on portability subset devices, where chains need it for event
+    // synchronization
+    events: supported_features.events,
+    ..Default::default()
+  };
+  let (_, mut queues) = Device::new(
+    physical,
+    DeviceCreateInfo {
+      queue_create_infos: Some(queue_family_index)
+        .into_iter()
+        .chain(transfer_family)
+        .map(|queue_family_index| QueueCreateInfo {
+          queue_family_index,
+          ..Default::default()
+        })
+        .collect(),
+      enabled_extensions,
+      enabled_features,
+      ..Default::default()
+    },
+  )?;
+  let queue = queues.next().unwrap();
+  Ok((queue, queues.next()))
+}
+
+/// The index of a queue family of `physical` supporting transfers but neither graphics nor
+/// compute, which GPUs usually back with dedicated copy engines.
+pub fn transfer_family(physical: &PhysicalDevice) -> Option<u32> {
+  physical
+    .queue_family_properties()
+    .iter()
+    .position(|family| {
+      family.queue_flags.contains(QueueFlags::TRANSFER)
+        && !family
+          .queue_flags
+          .intersects(QueueFlags::GRAPHICS | QueueFlags::COMPUTE)
+    })
+    .map(|index| index as u32)
+}
+
+pub(crate) fn wait_fence(fence: &Fence, timeout: Option<Duration>) -> Result<(), SubmitError> {
+  match fence.wait(timeout) {
+    Ok(()) => Ok(()),
+    Err(VulkanError::Timeout) => Err(SubmitError::Timeout(timeout.unwrap_or_default())),
+    Err(VulkanError::DeviceLost) => Err(SubmitError::DeviceLost),
+    Err(e) => Err(SubmitError::Wait(e)),
+  }
+}
+
+pub(crate) fn check_submit(result: ash_Result) -> Result<(), SubmitError> {
+  match result {
+    ash_Result::SUCCESS => Ok(()),
+    ash_Result::ERROR_DEVICE_LOST => Err(SubmitError::DeviceLost),
+    e => Err(SubmitError::Submit(e)),
+  }
+}
+
+/// A semaphore a submission of [`Context::submit_with_semaphores`] waits on or signals, such as
+/// one a renderer's graphics pass waits on before reading the transformed buffer.
+#[derive(Debug, Clone)]
+pub struct SubmitSemaphore {
+  pub semaphore: Arc<Semaphore>,
+  /// The value to wait for or signal, if `semaphore` is a timeline semaphore
+  pub value: u64,
+}
+
+impl SubmitSemaphore {
+  pub fn binary(semaphore: Arc<Semaphore>) -> Self {
+    Self {
+      semaphore,
+      value: 0,
+    }
+  }
+
+  pub fn timeline(semaphore: Arc<Semaphore>, value: u64) -> Self {
+    Self { semaphore, value }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceStatus {
+  /// The last submission has completed, or nothing was submitted
+  Idle,
+  /// The last submission is still executing
+  Busy,
+  /// The device was lost and must be recreated
+  Lost,
+  /// The fence could not be queried
+  Unknown,
+}
+
+/// Work submitted with [`Context::submit_async`] that may still be executing.
+///
+/// Dropping a pending `FftFuture` blocks until the work has completed, since the command buffer
+/// it keeps alive may still be in use.
+pub struct FftFuture {
+  fence: Arc<Fence>,
+  keep_alive: Option<Box<dyn std::any::Any + Send + Sync>>,
+}
+
+impl FftFuture {
+  /// Whether the work has completed, without blocking.
+  pub fn is_complete(&self) -> Result<bool, SubmitError> {
+    match self.fence.is_signaled() {
+      Ok(signaled) => Ok(signaled),
+      Err(VulkanError::DeviceLost) => Err(SubmitError::DeviceLost),
+      Err(e) => Err(SubmitError::Wait(e)),
+    }
+  }
+
+  /// Blocks until the work has completed.
+  pub fn wait(self) -> Result<(), SubmitError> {
+    self.wait_for(None)
+  }
+
+  /// Blocks until the work has completed or `timeout` has passed. On
+  /// [`SubmitError::Timeout`] the work is still pending, and the future can be waited on again.
+  pub fn wait_timeout(&self, timeout: Duration) -> Result<(), SubmitError> {
+    self.wait_for(Some(timeout))
+  }
+
+  fn wait_for(&self, timeout: Option<Duration>) -> Result<(), SubmitError> {
+    wait_fence(&self.fence, timeout)
+  }
+}
+
+impl Drop for FftFuture {
+  fn drop(&mut self) {
+    if let Some(keep_alive) = self.keep_alive.take() {
+      if !matches!(self.fence.is_signaled(), Ok(true)) {
+        let _ = self.fence.wait(None);
+      }
+      drop(keep_alive);
+    }
+  }
+}
+
+/// Awaiting an `FftFuture` doesn't block the executor; the fence is waited on by a helper
+/// thread.
+#[cfg(feature = "async")]
+impl std::future::IntoFuture for FftFuture {
+  type Output = Result<(), VulkanError>;
+  type IntoFuture = FenceFuture;
+
+  fn into_future(mut self) -> FenceFuture {
+    let keep_alive = self.keep_alive.take();
+    FenceFuture::new(self.fence.clone(), keep_alive)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FftType {
   Forward,
   Inverse,
 }
 
-pub struct Context<'a> {
-  pub instance: &'a Arc<Instance>,
+/// Which queue family a context submits to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueFamilyPreference {
+  /// A family with graphics and compute support, or a compute-only family on devices without
+  /// one, such as headless datacenter GPUs
+  #[default]
+  Any,
+  /// A compute family without graphics support, failing if the device has none. These are
+  /// often served by dedicated hardware, so transforms don't compete with rendering.
+  ComputeOnly,
+}
+
+impl QueueFamilyPreference {
+  /// The index of the queue family `physical` would be used with, if it has a suitable one.
+  pub fn select(&self, physical: &PhysicalDevice) -> Option<u32> {
+    let families = physical.queue_family_properties();
+    let compute_family = |graphics: bool| {
+      families.iter().position(|family| {
+        family.queue_flags.contains(QueueFlags::COMPUTE)
+          && family.queue_flags.contains(QueueFlags::GRAPHICS) == graphics
+      })
+    };
+    let index = match self {
+      Self::Any => compute_family(true).or_else(|| compute_family(false)),
+      Self::ComputeOnly => compute_family(false),
+    };
+    index.map(|index| index as u32)
+  }
+}
+
+/// Why [`ContextBuilder::select_physical_device`] found no device.
+#[derive(Display, Debug, Error)]
+pub enum DeviceSelectionError {
+  #[display("Failed to enumerate devices: {}", _0)]
+  Enumerate(VulkanError),
+  #[display("No device matches the selection; available devices: {}", _0.join(", "))]
+  NoMatchingDevice(#[error(not(source))] Vec<String>),
+}
+
+/// Chooses the device and queue family of a new context.
+///
+/// Devices are filtered by every criterion set, then ranked by type, discrete GPUs first unless
+/// another type is preferred. Ties keep the enumeration order.
+pub struct ContextBuilder {
+  instance: Arc<Instance>,
+  device_index: Option<usize>,
+  device_name: Option<String>,
+  device_type: PhysicalDeviceType,
+  precisions: Vec<Precision>,
+  queue_family_preference: QueueFamilyPreference,
+  submit_timeout: Option<Duration>,
+  transfer_queue: bool,
+}
+
+impl ContextBuilder {
+  pub fn new(instance: &Arc<Instance>) -> Self {
+    Self {
+      instance: instance.clone(),
+      device_index: None,
+      device_name: None,
+      device_type: PhysicalDeviceType::DiscreteGpu,
+      precisions: Vec::new(),
+      queue_family_preference: QueueFamilyPreference::Any,
+      submit_timeout: None,
+      transfer_queue: false,
+    }
+  }
+
+  /// Only considers the device at `index` in enumeration order.
+  pub fn device_index(mut self, index: usize) -> Self {
+    self.device_index = Some(index);
+    self
+  }
+
+  /// Only considers devices whose name contains `name`, ignoring case.
+  pub fn device_name<S: Into<String>>(mut self, name: S) -> Self {
+    self.device_name = Some(name.into());
+    self
+  }
+
+  /// Ranks devices of `device_type` first.
+  pub fn prefer_device_type(mut self, device_type: PhysicalDeviceType) -> Self {
+    self.device_type = device_type;
+    self
+  }
+
+  /// Only considers devices supporting transforms in `precision`. Can be called several times.
+  pub fn require_precision(mut self, precision: Precision) -> Self {
+    self.precisions.push(precision);
+    self
+  }
+
+  /// Only considers devices with a queue family matching `preference`.
+  pub fn queue_family_preference(mut self, preference: QueueFamilyPreference) -> Self {
+    self.queue_family_preference = preference;
+    self
+  }
+
+  /// The device a context would be created on.
+  pub fn select_physical_device(&self) -> Result<Arc<PhysicalDevice>, DeviceSelectionError> {
+    let devices = self
+      .instance
+      .enumerate_physical_devices()
+      .map_err(DeviceSelectionError::Enumerate)?
+      .collect::<Vec<_>>();
+
+    let name = self.device_name.as_ref().map(|n| n.to_lowercase());
+    let mut candidates = devices
+      .iter()
+      .enumerate()
+      .filter(|(i, _)| self.device_index.is_none_or(|index| index == *i))
+      .filter(|(_, d)| {
+        name
+          .as_ref()
+          .is_none_or(|n| d.properties().device_name.to_lowercase().contains(n))
+      })
+      .filter(|(_, d)| self.precisions.iter().all(|p| p.missing_feature(d).is_none()))
+      .filter(|(_, d)| self.queue_family_preference.select(d).is_some())
+      .map(|(_, d)| d)
+      .collect::<Vec<_>>();
+    candidates.sort_by_key(|d| type_rank(d.properties().device_type, self.device_type));
+
+    candidates.first().map(|d| (*d).clone()).ok_or_else(|| {
+      DeviceSelectionError::NoMatchingDevice(
+        devices
+          .iter()
+          .map(|d| {
+            let properties = d.properties();
+            format!("{} ({:?})", properties.device_name, properties.device_type)
+          })
+          .collect(),
+      )
+    })
+  }
+
+  /// Sets the [`Context::with_submit_timeout`] timeout of the context.
+  pub fn submit_timeout(mut self, timeout: Duration) -> Self {
+    self.submit_timeout = Some(timeout);
+    self
+  }
+
+  /// Also creates a queue of a dedicated transfer family, if the device has one, which
+  /// [`StreamingFft`](crate::streaming::StreamingFft) uploads batches on while the previous one
+  /// is transformed.
+  pub fn transfer_queue(mut self) -> Self {
+    self.transfer_queue = true;
+    self
+  }
+
+  pub fn build(self) -> Result<Context, crate::Error> {
+    let physical = self.select_physical_device()?;
+    let queue_family_index = self
+      .queue_family_preference
+      .select(&physical)
+      .ok_or(crate::Error::NoQueueFamily(self.queue_family_preference))?;
+    let transfer_family = if self.transfer_queue {
+      transfer_family(&physical)
+    } else {
+      None
+    };
+    let (queue, transfer_queue) = create_queue(physical, queue_family_index, transfer_family)?;
+    let mut context = Context::from_queue(&self.instance, queue)?;
+    context.transfer_queue = transfer_queue;
+    context.submit_timeout = self.submit_timeout;
+    Ok(context)
+  }
+}
+
+/// Position of `device_type` when devices are ranked with `preferred` first, then discrete,
+/// integrated, virtual and CPU devices.
+fn type_rank(device_type: PhysicalDeviceType, preferred: PhysicalDeviceType) -> usize {
+  if device_type == preferred {
+    return 0;
+  }
+  match device_type {
+    PhysicalDeviceType::DiscreteGpu => 1,
+    PhysicalDeviceType::IntegratedGpu => 2,
+    PhysicalDeviceType::VirtualGpu => 3,
+    PhysicalDeviceType::Cpu => 4,
+    _ => 5,
+  }
+}
+
+/// The command pool of a [`Context`]. vulkano's pools aren't `Sync`, so an `Arc` of one isn't
+/// `Send`.
+struct ContextPool(Arc<CommandPool>);
+
+// SAFETY: VkFFT only allocates from the pool while planning, which goes through `&Context`, and
+// `Context` isn't `Sync`, so the pool is never used from two threads at once. Apps, including the
+// `Send` ones behind a `SharedApp`, only hold clones to keep it alive: once planned, the only
+// operation they expose is `launch`, which records into the caller's command buffer without
+// touching the pool, and `SharedApp` serializes it.
+unsafe impl Send for ContextPool {}
+
+/// The device, queue and allocator transforms run with.
+///
+/// A context keeps its instance alive, so it can be stored in long-lived structs or moved into a
+/// background worker thread. It isn't `Sync`, since its command pool and fence must not be used
+/// from several threads at once; threads transforming concurrently should each own a context,
+/// e.g. one made with [`Context::new_standalone`].
+pub struct Context {
+  pub instance: Arc<Instance>,
   pub physical: Arc<PhysicalDevice>,
   pub device: Arc<Device>,
   pub queue: Arc<Queue>,
-  pub pool: Arc<CommandPool>,
+  pool: ContextPool,
   pub allocator: Arc<dyn MemoryAllocator>,
-  pub fence: Fence,
+  /// Allocates the descriptor sets of the compute passes created on the context
+  pub descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+  pub fence: Arc<Fence>,
+  /// Where [`Context::app`] persists generated kernels, if anywhere
+  pub disk_cache: Option<DiskCache>,
+  /// How long [`Context::submit`] waits for submitted work, without limit if `None`
+  pub submit_timeout: Option<Duration>,
+  /// A queue of a dedicated transfer family, if one was requested with
+  /// [`ContextBuilder::transfer_queue`] and the device has one
+  pub transfer_queue: Option<Arc<Queue>>,
+  /// The timeline semaphore [`Context::submit_timeline`] signals, if the device supports
+  /// timeline semaphores
+  pub timeline: Option<Arc<Semaphore>>,
+  timeline_value: Cell<u64>,
+  /// Whether a submission signals `fence` and its signal hasn't been observed yet
+  fence_pending: Cell<bool>,
+  /// Command buffers of submissions that timed out, kept alive until `fence` signals
+  parked: RefCell<Vec<Box<dyn std::any::Any + Send>>>,
 }
 
-impl<'a> Context<'a> {
-  pub fn new(instance: &'a Arc<Instance>) -> Result<Self, Box<dyn std::error::Error>> {
+impl Context {
+  /// Creates a context without any Vulkan setup by the caller: loads the Vulkan library,
+  /// creates an instance with [`instance_create_info`] and picks a device as [`Context::new`]
+  /// does.
+  pub fn new_standalone() -> Result<Self, crate::Error> {
+    let library = VulkanLibrary::new()?;
+    let instance = Instance::new(library.clone(), instance_create_info(&library))?;
+    Self::new(&instance)
+  }
+
+  /// Creates a context on the first device. Use [`Context::builder`] to choose another.
+  pub fn new(instance: &Arc<Instance>) -> Result<Self, crate::Error> {
     let physical = instance
       .enumerate_physical_devices()?
       .next()
-      .ok_or("No device available")?;
+      .ok_or(DeviceSelectionError::NoMatchingDevice(Vec::new()))?;
+    Self::with_physical_device(instance, physical)
+  }
 
-    let queue_family_index = physical
-      .queue_family_properties()
-      .iter()
-      .enumerate()
-      .position(|(_queue_family_index, queue_family_properties)| {
-        queue_family_properties
-          .queue_flags
-          .contains(QueueFlags::COMPUTE)
-          && queue_family_properties
-            .queue_flags
-            .contains(QueueFlags::GRAPHICS)
-      })
-      .expect("couldn't find a compute+graphical queue family") as u32;
-    let (device, mut queues) = Device::new(
-      physical.clone(),
-      DeviceCreateInfo {
-        queue_create_infos: vec![QueueCreateInfo {
-          queue_family_index,
-          ..Default::default()
-        }],
-        ..Default::default()
-      },
+  /// Creates a context on every device of `instance`, in enumeration order, skipping devices
+  /// without a queue family transforms can run on.
+  pub fn for_all_devices(instance: &Arc<Instance>) -> Result<Vec<Self>, crate::Error> {
+    let mut contexts = Vec::new();
+    for physical in instance.enumerate_physical_devices()? {
+      match Self::with_physical_device(instance, physical) {
+        Ok(context) => contexts.push(context),
+        Err(crate::Error::NoQueueFamily(_)) => {}
+        Err(e) => return Err(e),
+      }
+    }
+    if contexts.is_empty() {
+      return Err(DeviceSelectionError::NoMatchingDevice(Vec::new()).into());
+    }
+    Ok(contexts)
+  }
+
+  pub fn builder(instance: &Arc<Instance>) -> ContextBuilder {
+    ContextBuilder::new(instance)
+  }
+
+  /// Creates a context on `physical`, which must belong to `instance`, submitting to a queue
+  /// family chosen with [`QueueFamilyPreference::Any`].
+  pub fn with_physical_device(
+    instance: &Arc<Instance>,
+    physical: Arc<PhysicalDevice>,
+  ) -> Result<Self, crate::Error> {
+    Self::with_queue_family_preference(instance, physical, QueueFamilyPreference::Any)
+  }
+
+  /// Like [`Context::with_physical_device`], choosing the queue family with `preference`.
+  pub fn with_queue_family_preference(
+    instance: &Arc<Instance>,
+    physical: Arc<PhysicalDevice>,
+    preference: QueueFamilyPreference,
+  ) -> Result<Self, crate::Error> {
+    let queue_family_index = preference
+      .select(&physical)
+      .ok_or(crate::Error::NoQueueFamily(preference))?;
+    let (queue, _) = create_queue(physical, queue_family_index, None)?;
+    Self::from_queue(instance, queue)
+  }
+
+  /// Replaces the device, queues, command pool, fence and allocator with new ones on the same
+  /// physical device and queue families, keeping the disk cache and submit timeout. This recovers
+  /// from [`SubmitError::DeviceLost`], e.g. after the driver reset the GPU.
+  ///
+  /// Apps, stages and buffers created from the old device can't be used with the new one and
+  /// must be created again. A context made with [`Context::from_queue`] gets a device of its own.
+  pub fn recreate(&mut self) -> Result<(), crate::Error> {
+    let (queue, transfer_queue) = create_queue(
+      self.physical.clone(),
+      self.queue.queue_family_index(),
+      self.transfer_queue.as_ref().map(|q| q.queue_family_index()),
     )?;
-    let queue = queues.next().unwrap();
+    let mut context = Self::from_queue(&self.instance, queue)?;
+    context.transfer_queue = transfer_queue;
+    context.disk_cache = self.disk_cache.take();
+    context.submit_timeout = self.submit_timeout;
+    // The old device is gone, so nothing executes a parked command buffer any more
+    self.fence_pending.set(false);
+    *self = context;
+    Ok(())
+  }
+
+  /// Creates a context submitting to an existing queue, such as the one a renderer draws with,
+  /// instead of creating a device of its own.
+  ///
+  /// Submissions from the context, including the lookup table uploads VkFFT makes while creating
+  /// apps, lock the queue the same way vulkano does, so they can't race with other submissions
+  /// made through vulkano or [`Context::with_queue`]. Buffers shared with
+  /// work on a queue of another family need an
+  /// [`OwnershipTransfer`](crate::queue_share::OwnershipTransfer) unless they were created with
+  /// concurrent sharing.
+  pub fn from_queue(
+    instance: &Arc<Instance>,
+    queue: Arc<Queue>,
+  ) -> Result<Self, crate::Error> {
+    let device = queue.device().clone();
     let pool = Arc::new(CommandPool::new(
       device.clone(),
       CommandPoolCreateInfo {
-        queue_family_index,
+        queue_family_index: queue.queue_family_index(),
         flags: CommandPoolCreateFlags::default(),
         ..Default::default()
       },
     )?);
-    let fence = Fence::new(device.clone(), FenceCreateInfo::default())?;
+    let fence = Arc::new(Fence::new(device.clone(), FenceCreateInfo::default())?);
+    let timeline = if device.enabled_features().timeline_semaphore {
+      Some(Arc::new(Semaphore::new(
+        device.clone(),
+        SemaphoreCreateInfo {
+          semaphore_type: SemaphoreType::Timeline,
+          ..Default::default()
+        },
+      )?))
+    } else {
+      None
+    };
     let allocator =
       Arc::new(vulkano::memory::allocator::StandardMemoryAllocator::new_default(device.clone()));
+    let descriptor_set_allocator = Arc::new(StandardDescriptorSetAllocator::new(
+      device.clone(),
+      Default::default(),
+    ));
     Ok(Self {
-      instance,
-      physical: physical.clone(),
+      instance: instance.clone(),
+      physical: device.physical_device().clone(),
       queue,
       device,
-      pool,
+      pool: ContextPool(pool),
       fence,
       allocator,
+      descriptor_set_allocator,
+      disk_cache: None,
+      submit_timeout: None,
+      transfer_queue: None,
+      timeline,
+      timeline_value: Cell::new(0),
+      fence_pending: Cell::new(false),
+      parked: RefCell::new(Vec::new()),
     })
   }
+
+  /// Persists the kernels of every app this context creates in `dir`, and loads them from
+  /// there when the same plan is created again, in this or a later process.
+  pub fn with_cache_dir<P: AsRef<std::path::Path>>(
+    mut self,
+    dir: P,
+  ) -> Result<Self, crate::Error> {
+    self.disk_cache = Some(DiskCache::new(dir)?);
+    Ok(self)
+  }
+
+  /// Makes [`Context::submit`], and so [`Context::run`], give up waiting for submitted work
+  /// after `timeout`, so a hung GPU can't block the caller forever.
+  pub fn with_submit_timeout(mut self, timeout: Duration) -> Self {
+    self.submit_timeout = Some(timeout);
+    self
+  }
+
+  /// Runs `f` with the raw queue while holding its lock, for submitting work recorded outside
+  /// vulkano, e.g. by a renderer using ash, without racing the context's own submissions.
+  pub fn with_queue<R>(&self, f: impl FnOnce(ash::vk::Queue) -> R) -> R {
+    self.queue.with(|_| f(self.queue.handle()))
+  }
+
+  /// Whether the device is a non-conformant portability implementation such as MoltenVK, on
+  /// which `VK_KHR_portability_subset` is enabled.
+  ///
+  /// Such devices often lack 64-bit floats in shaders, in which case double precision transforms
+  /// are unavailable (see [`Context::supports_double_precision`]). The remaining restrictions of
+  /// the subset concern graphics features and don't affect transforms.
+  pub fn portability_subset(&self) -> bool {
+    self.device.enabled_extensions().khr_portability_subset
+  }
+
+  /// Whether the device supports double precision transforms.
+  pub fn supports_double_precision(&self) -> bool {
+    self.supports_precision(Precision::Double)
+  }
+
+  /// The limits of the device that decide which transforms it can run.
+  pub fn capabilities(&self) -> Capabilities {
+    Capabilities::new(&self.device)
+  }
+
+  /// Whether the device was created with the features transforms of `precision` need.
+  pub fn supports_precision(&self, precision: Precision) -> bool {
+    precision.missing_enabled_feature(&self.device).is_none()
+  }
+
   pub fn new_buffer_from_iter<T, I>(
     &self,
     iter: I,
@@ -103,7 +715,7 @@ impl<'a> Context<'a> {
     Buffer::from_iter(
       self.allocator.clone(),
       BufferCreateInfo {
-        usage: BufferUsage::TRANSFER_DST,
+        usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST,
         ..Default::default()
       },
       AllocationCreateInfo {
@@ -114,96 +726,611 @@ impl<'a> Context<'a> {
     )
   }
 
-  pub fn submit(
-    &self,
-    command_buffer: Arc<SecondaryAutoCommandBuffer>,
-  ) -> Result<(), Box<dyn std::error::Error>> {
+  /// Submits `command_buffer` and blocks until it has completed, or until the
+  /// [`Context::with_submit_timeout`] timeout has passed. A failed submission or wait, such as a
+  /// lost device, is returned as [`SubmitError`]; see [`Context::submit_with_timeout`] for
+  /// handling a timeout. The context keeps a clone of `command_buffer`, such as an `Arc`, alive
+  /// until the work has completed.
+  pub fn submit<C>(&self, command_buffer: C) -> Result<(), crate::Error>
+  where
+    C: VulkanObject<Handle = ash::vk::CommandBuffer> + Clone + Send + 'static,
+  {
+    self.submit_many(std::slice::from_ref(&command_buffer))
+  }
+
+  /// Like [`Context::submit`], submitting all of `command_buffers` in a single queue submission
+  /// with one fence. This saves driver overhead when many independent transforms are
+  /// dispatched per frame. The command buffers may execute in any order or overlap.
+  pub fn submit_many<C>(&self, command_buffers: &[C]) -> Result<(), crate::Error>
+  where
+    C: VulkanObject<Handle = ash::vk::CommandBuffer> + Clone + Send + 'static,
+  {
+    self.acquire_fence()?;
     let fns = self.device.fns();
-    let command_buffer_submit_info = ash::vk::CommandBufferSubmitInfo {
-      command_buffer: command_buffer.handle(),
-      device_mask: 0u32,
-      ..Default::default()
-    };
-    if self.device.enabled_features().synchronization2 {
+    let command_buffer_submit_infos = command_buffers
+      .iter()
+      .map(|command_buffer| ash::vk::CommandBufferSubmitInfo {
+        command_buffer: command_buffer.handle(),
+        device_mask: 0u32,
+        ..Default::default()
+      })
+      .collect::<Vec<_>>();
+    let submit_result = if self.device.enabled_features().synchronization2 {
       let submit_info_vk = ash::vk::SubmitInfo2 {
-        command_buffer_info_count: 1u32,
-        p_command_buffer_infos: &command_buffer_submit_info,
+        command_buffer_info_count: command_buffer_submit_infos.len() as u32,
+        p_command_buffer_infos: command_buffer_submit_infos.as_ptr(),
         ..Default::default()
       };
-      if self.device.api_version() >= vulkano::Version::V1_3 {
-        self.queue.with(|_| unsafe {
-          let submit_result = unsafe {
-            (fns.v1_3.queue_submit2)(
-              self.queue.handle(),
-              1u32,
-              &submit_info_vk,
-              self.fence.handle(),
-            )
-          };
-          if submit_result != ash_Result::SUCCESS {
-            println!(
-              "Submission to Vulkan queue failed with result {:?}",
-              submit_result
-            );
-            panic!("Vulkan in non-handled state, panicking.");
-          }
-          self.fence.wait(None).unwrap();
-          self.fence.reset().unwrap();
-        });
+      let queue_submit2 = if self.device.api_version() >= vulkano::Version::V1_3 {
+        fns.v1_3.queue_submit2
       } else {
-        self.queue.with(|_| unsafe {
-          let submit_result = unsafe {
-            (fns.khr_synchronization2.queue_submit2_khr)(
-              self.queue.handle(),
-              1u32,
-              &submit_info_vk,
-              self.fence.handle(),
-            )
-          };
-          if submit_result != ash_Result::SUCCESS {
-            println!(
-              "Submission to Vulkan queue failed with result {:?}",
-              submit_result
-            );
-            panic!("Vulkan in non-handled state, panicking.");
-          }
-          self.fence.wait(None).unwrap();
-          self.fence.reset().unwrap();
-        });
-      }
+        fns.khr_synchronization2.queue_submit2_khr
+      };
+      self.queue.with(|_| unsafe {
+        queue_submit2(
+          self.queue.handle(),
+          1u32,
+          &submit_info_vk,
+          self.fence.handle(),
+        )
+      })
     } else {
+      let handles = command_buffer_submit_infos
+        .iter()
+        .map(|info| info.command_buffer)
+        .collect::<Vec<_>>();
       let submit_info_vk = ash::vk::SubmitInfo {
-        command_buffer_count: 1u32,
-        p_command_buffers: &command_buffer_submit_info.command_buffer,
+        command_buffer_count: handles.len() as u32,
+        p_command_buffers: handles.as_ptr(),
         ..Default::default()
       };
       self.queue.with(|_| unsafe {
-        let submit_result = unsafe {
-          (fns.v1_0.queue_submit)(
-            self.queue.handle(),
-            1u32,
-            &submit_info_vk,
-            self.fence.handle(),
-          )
-        };
-        if submit_result != ash_Result::SUCCESS {
-          println!(
-            "Submission to Vulkan queue failed with result {:?}",
-            submit_result
-          );
-          panic!("Vulkan in non-handled state, panicking.");
+        (fns.v1_0.queue_submit)(
+          self.queue.handle(),
+          1u32,
+          &submit_info_vk,
+          self.fence.handle(),
+        )
+      })
+    };
+    check_submit(submit_result)?;
+    self.fence_pending.set(true);
+    // Released by `retire_fence` once the work has completed, even after a timeout
+    self.parked.borrow_mut().extend(
+      command_buffers
+        .iter()
+        .map(|command_buffer| Box::new(command_buffer.clone()) as Box<dyn std::any::Any + Send>),
+    );
+
+    Ok(self.wait_and_reset(self.submit_timeout)?)
+  }
+
+  /// Like [`Context::submit`], but returns as soon as the work is submitted. The returned
+  /// [`FftFuture`] can be polled or waited on later, so the CPU can do other work meanwhile,
+  /// and keeps `command_buffer` alive until the work has completed.
+  ///
+  /// Each submission gets a fence of its own, so several can be pending at once.
+  ///
+  /// # Safety
+  ///
+  /// `command_buffer` must be a valid primary command buffer of the context's device in the
+  /// executable state. Everything it refers to other than itself, such as the applications and
+  /// buffers VkFFT binds by handle, must be kept alive until the returned future has resolved or
+  /// been dropped.
+  pub unsafe fn submit_async<C>(&self, command_buffer: C) -> Result<FftFuture, crate::Error>
+  where
+    C: VulkanObject<Handle = ash::vk::CommandBuffer> + Send + Sync + 'static,
+  {
+    // The future keeps the command buffer alive until the fence signals
+    let fence = self.submit_fenced(command_buffer.handle())?;
+    Ok(FftFuture {
+      fence: Arc::new(fence),
+      keep_alive: Some(Box::new(command_buffer)),
+    })
+  }
+
+  /// Records `stage` like [`Context::run`] and submits it with [`Context::submit_async`].
+  ///
+  /// # Safety
+  ///
+  /// `stage`, its applications and the buffers they are bound to must be kept alive until the
+  /// returned future has resolved or been dropped.
+  pub unsafe fn start<S>(&self, stage: &mut S) -> Result<FftFuture, crate::Error>
+  where
+    S: Stage + ?Sized,
+  {
+    let command_buffer = self.record(stage)?;
+    self.submit_async(command_buffer)
+  }
+
+  /// Records `stage` and returns a vulkano future executing it on the context's queue after
+  /// `future`, to chain transforms with an application's own command buffers and presentation.
+  /// Nothing is submitted until the chain is flushed. See [`crate::gpu_future`] for how the
+  /// transform is synchronized with the rest of the chain.
+  ///
+  /// # Safety
+  ///
+  /// vulkano doesn't track the resources of the recorded stage. `stage`, its applications and
+  /// the buffers they are bound to must be kept alive until the returned future, or a future
+  /// chained after it, has been waited on.
+  ///
+  /// # Panics
+  ///
+  /// - Panics if `future` belongs to another device or is bound to another queue.
+  pub unsafe fn then_execute<F, S>(
+    &self,
+    future: F,
+    stage: &mut S,
+  ) -> Result<FftExecFuture<F>, crate::Error>
+  where
+    F: GpuFuture,
+    S: Stage + ?Sized,
+  {
+    let command_buffer = self.record(&mut Fenced {
+      device: self.device.clone(),
+      stage,
+    })?;
+    Ok(FftExecFuture::new(future, self.queue.clone(), command_buffer))
+  }
+
+  /// Like [`Context::submit`], but waits at most `timeout` for the work to complete.
+  ///
+  /// On [`SubmitError::Timeout`] the work is still pending, and the context keeps
+  /// `command_buffer` alive until it has completed. Until then, the next submission through the
+  /// context's fence first waits for the pending one, and fails with [`SubmitError::Timeout`] if
+  /// it still hasn't completed. [`SubmitError::DeviceLost`] means the driver reset the device, for
+  /// example after a watchdog timeout, and the context has to be recreated.
+  pub fn submit_with_timeout(
+    &self,
+    command_buffer: Arc<PrimaryAutoCommandBuffer>,
+    timeout: Duration,
+  ) -> Result<(), SubmitError> {
+    self.acquire_fence()?;
+    let fns = self.device.fns();
+    let handle = command_buffer.handle();
+    let submit_info = ash::vk::SubmitInfo {
+      command_buffer_count: 1u32,
+      p_command_buffers: &handle,
+      ..Default::default()
+    };
+
+    self.queue.with(|_| {
+      check_submit(unsafe {
+        (fns.v1_0.queue_submit)(
+          self.queue.handle(),
+          1u32,
+          &submit_info,
+          self.fence.handle(),
+        )
+      })?;
+      self.fence_pending.set(true);
+      Ok(())
+    })?;
+    self.parked.borrow_mut().push(Box::new(command_buffer));
+    self.wait_and_reset(Some(timeout))
+  }
+
+  /// Makes the context's fence available to a new submission, or to VkFFT, which submits with it
+  /// while planning. A submission that timed out before is waited for up to the submit timeout.
+  pub(crate) fn acquire_fence(&self) -> Result<(), SubmitError> {
+    if self.fence_pending.get() {
+      self.wait_and_reset(self.submit_timeout)?;
+    }
+    Ok(())
+  }
+
+  /// Waits for the context's fence and resets it. A timeout is reported as
+  /// [`SubmitError::DeviceLost`] if the device was lost meanwhile, and leaves the fence pending.
+  fn wait_and_reset(&self, timeout: Option<Duration>) -> Result<(), SubmitError> {
+    if let Err(e) = wait_fence(&self.fence, timeout) {
+      return Err(match e {
+        SubmitError::Timeout(_) if self.device_status() == DeviceStatus::Lost => {
+          SubmitError::DeviceLost
         }
-        self.fence.wait(None).unwrap();
-        self.fence.reset().unwrap();
+        e => e,
       });
     }
+    self.retire_fence()
+  }
+
+  /// Resets the context's fence once its signal has been observed.
+  fn retire_fence(&self) -> Result<(), SubmitError> {
+    unsafe { self.fence.reset_unchecked() }.map_err(SubmitError::Wait)?;
+    self.fence_pending.set(false);
+    self.parked.borrow_mut().clear();
+    Ok(())
+  }
+
+  /// Queries whether work submitted through this context is still running, without blocking.
+  pub fn device_status(&self) -> DeviceStatus {
+    if !self.fence_pending.get() {
+      return DeviceStatus::Idle;
+    }
+    match self.fence.is_signaled() {
+      Ok(true) => match self.retire_fence() {
+        Ok(()) => DeviceStatus::Idle,
+        Err(_) => DeviceStatus::Unknown,
+      },
+      Ok(false) => DeviceStatus::Busy,
+      Err(VulkanError::DeviceLost) => DeviceStatus::Lost,
+      Err(_) => DeviceStatus::Unknown,
+    }
+  }
+
+  /// Creates an `App` for `config_builder`, filling in this context's device, queue, fence and
+  /// command pool. With a [`Context::with_cache_dir`] cache, kernels generated before are
+  /// loaded instead of compiled.
+  pub fn app(&self, config_builder: ConfigBuilder) -> Result<Pin<Box<App>>, crate::Error> {
+    let config = self.config(config_builder)?;
+    Ok(match &self.disk_cache {
+      Some(disk_cache) => disk_cache.app(config)?,
+      None => App::new(config)?,
+    })
+  }
+
+  /// Like [`Context::app`], but reuses kernels from `cache` when the same plan was already
+  /// generated for this device, possibly by another context.
+  pub fn cached_app(
+    &self,
+    cache: &PlanCache,
+    config_builder: ConfigBuilder,
+  ) -> Result<Pin<Box<App>>, crate::Error> {
+    Ok(cache.app(self.config(config_builder)?)?)
+  }
+
+  fn config(&self, config_builder: ConfigBuilder) -> Result<Config, crate::Error> {
+    self.acquire_fence()?;
+    Ok(config_builder
+      .physical_device(self.physical.clone())
+      .device(self.device.clone())
+      .fence(self.fence.clone())
+      .queue(self.queue.clone())
+      .command_pool(self.command_pool())
+      .build()?)
+  }
+
+  /// The pool VkFFT allocates the command buffers uploading lookup tables from.
+  pub(crate) fn command_pool(&self) -> Arc<CommandPool> {
+    self.pool.0.clone()
+  }
+
+  /// Splits the scaling of `Ortho` plans off into a pass of their stage, leaving VkFFT an
+  /// unnormalized plan.
+  fn scaled_plan(
+    &self,
+    config_builder: ConfigBuilder,
+  ) -> Result<(ConfigBuilder, Option<OrthoScale>), crate::Error> {
+    Ok(match OrthoScale::for_plan(&self.descriptor_set_allocator, &config_builder)? {
+      Some(scale) => (config_builder.normalization(Normalization::None), Some(scale)),
+      None => (config_builder, None),
+    })
+  }
+
+  pub fn fft_stage(
+    &self,
+    config_builder: ConfigBuilder,
+    fft_type: FftType,
+  ) -> Result<FftStage, crate::Error> {
+    let (config_builder, scale) = self.scaled_plan(config_builder)?;
+    let app = self.app(config_builder)?;
+    let params = LaunchParams {
+      command_buffer: ash::vk::CommandBuffer::null(),
+      buffer: None,
+      temp_buffer: None,
+      input_buffer: None,
+      output_buffer: None,
+      kernel: None,
+    };
+    Ok(FftStage::new(app, params, fft_type).with_scale(scale))
+  }
+
+  /// Like [`Context::fft_stage`], but reuses the application `planner` already created for the
+  /// same plan, device and buffers.
+  pub fn planned_stage(
+    &self,
+    planner: &mut FftPlanner,
+    config_builder: ConfigBuilder,
+    fft_type: FftType,
+  ) -> Result<FftStage, crate::Error> {
+    let (config_builder, scale) = self.scaled_plan(config_builder)?;
+    let app = planner.plan(self.config(config_builder)?)?;
+    let params = LaunchParams {
+      command_buffer: ash::vk::CommandBuffer::null(),
+      buffer: None,
+      temp_buffer: None,
+      input_buffer: None,
+      output_buffer: None,
+      kernel: None,
+    };
+    Ok(FftStage::shared(app, params, fft_type).with_scale(scale))
+  }
+
+  /// Records `stage` into a fresh primary command buffer and submits it, waiting for completion.
+  ///
+  /// `stage` is only borrowed, so on a [`SubmitError::Timeout`] this keeps waiting until the
+  /// work has completed before returning the timeout. Use [`Context::start`] to stop waiting
+  /// early.
+  pub fn run<S>(&self, stage: &mut S) -> Result<(), crate::Error>
+  where
+    S: Stage + ?Sized,
+  {
+    let command_buffer = self.record(stage)?;
+    // The apps and buffers of `stage` must outlive the work
+    self.wait_out_timeout(self.submit(Arc::new(command_buffer)))
+  }
+
+  /// Waits for the work of a submission that timed out with `result` to complete, for callers
+  /// that only borrow what it refers to. The timeout is still returned.
+  fn wait_out_timeout(&self, result: Result<(), crate::Error>) -> Result<(), crate::Error> {
+    if let Err(crate::Error::Submit(SubmitError::Timeout(_))) = result {
+      self.wait_and_reset(None)?;
+    }
+    result
+  }
+
+  /// Like [`Context::run`], but awaits completion instead of blocking the calling thread.
+  #[cfg(feature = "async")]
+  pub async fn run_async<S>(&self, stage: &mut S) -> Result<(), crate::Error>
+  where
+    S: Stage + ?Sized,
+  {
+    let command_buffer = self.record(stage)?;
+    self.submit_with_fence(command_buffer)?.await?;
     Ok(())
   }
+
+  /// Like [`Context::transform`], but awaits completion instead of blocking the calling thread.
+  #[cfg(feature = "async")]
+  pub async fn fft<T: FftScalar>(
+    &self,
+    config_builder: ConfigBuilder,
+    data: &[T],
+    fft_type: FftType,
+  ) -> Result<Vec<T>, crate::Error> {
+    let config_builder = config_builder.precision(T::PRECISION);
+    check_data_len(&config_builder, data)?;
+    let buffer = self.new_buffer_from_iter(data.iter().copied())?;
+    let config_builder = config_builder.buffer(buffer.clone());
+    let mut stage = self.fft_stage(config_builder, fft_type)?;
+    self.run_async(&mut stage).await?;
+    self.read(&buffer)
+  }
+
+  /// Submits `command_buffer` with a fence of its own, so several submissions can be pending at
+  /// once, and returns a future completing when it has executed.
+  #[cfg(feature = "async")]
+  fn submit_with_fence(
+    &self,
+    command_buffer: vulkano::command_buffer::CommandBuffer,
+  ) -> Result<FenceFuture, crate::Error> {
+    // The future keeps the command buffer alive until the fence signals
+    let fence = unsafe { self.submit_fenced(command_buffer.handle()) }?;
+    Ok(FenceFuture::new(Arc::new(fence), command_buffer))
+  }
+
+  /// Submits `command_buffer` without waiting, returning a new fence signaled once it has
+  /// executed. A command buffer recorded with [`CommandBufferUsage::SimultaneousUse`] can be
+  /// submitted again before the fence signals. [`Context::submit_async`] keeps the command buffer
+  /// alive itself.
+  ///
+  /// # Safety
+  ///
+  /// `command_buffer` must be a valid primary command buffer of the context's device in the
+  /// executable state, and it, along with everything it refers to, must be kept alive until the
+  /// fence signals.
+  pub unsafe fn submit_fenced(
+    &self,
+    command_buffer: ash::vk::CommandBuffer,
+  ) -> Result<Fence, crate::Error> {
+    let fence = Fence::new(self.device.clone(), FenceCreateInfo::default())?;
+    self.submit_signaling(command_buffer, &fence)?;
+    Ok(fence)
+  }
+
+  /// Submits `command_buffer` to the context's queue, signaling `fence`, which must be unsignaled,
+  /// once it has executed.
+  pub(crate) fn submit_signaling(
+    &self,
+    command_buffer: ash::vk::CommandBuffer,
+    fence: &Fence,
+  ) -> Result<(), SubmitError> {
+    let fns = self.device.fns();
+    let submit_info = ash::vk::SubmitInfo {
+      command_buffer_count: 1u32,
+      p_command_buffers: &command_buffer,
+      ..Default::default()
+    };
+
+    check_submit(self.queue.with(|_| unsafe {
+      (fns.v1_0.queue_submit)(self.queue.handle(), 1u32, &submit_info, fence.handle())
+    }))
+  }
+
+  /// Submits `command_buffer` once every semaphore of `wait` is signaled, and signals every
+  /// semaphore of `signal` once it has executed, so other work on the GPU, e.g. a graphics pass
+  /// reading the result, can follow without a round trip to the host. Returns a new fence
+  /// signaled along with `signal`.
+  ///
+  /// Work of all pipeline stages waits on `wait`.
+  ///
+  /// # Safety
+  ///
+  /// `command_buffer` must be a valid primary command buffer of the context's device in the
+  /// executable state, and it, along with everything it refers to, must be kept alive until the
+  /// fence signals. Binary semaphores of `wait` must have a signal pending.
+  pub unsafe fn submit_with_semaphores(
+    &self,
+    command_buffer: ash::vk::CommandBuffer,
+    wait: &[SubmitSemaphore],
+    signal: &[SubmitSemaphore],
+  ) -> Result<Fence, crate::Error> {
+    let handles = |semaphores: &[SubmitSemaphore]| {
+      semaphores
+        .iter()
+        .map(|s| s.semaphore.handle())
+        .collect::<Vec<_>>()
+    };
+    let values = |semaphores: &[SubmitSemaphore]| {
+      semaphores.iter().map(|s| s.value).collect::<Vec<_>>()
+    };
+    let (wait_semaphores, signal_semaphores) = (handles(wait), handles(signal));
+    let (wait_values, signal_values) = (values(wait), values(signal));
+    let wait_stages = vec![ash::vk::PipelineStageFlags::ALL_COMMANDS; wait.len()];
+    let timeline_info = ash::vk::TimelineSemaphoreSubmitInfo {
+      wait_semaphore_value_count: wait_values.len() as u32,
+      p_wait_semaphore_values: wait_values.as_ptr(),
+      signal_semaphore_value_count: signal_values.len() as u32,
+      p_signal_semaphore_values: signal_values.as_ptr(),
+      ..Default::default()
+    };
+    // The values are only chained when needed, since they belong to an extension
+    let any_timeline = wait
+      .iter()
+      .chain(signal)
+      .any(|s| s.semaphore.semaphore_type() == SemaphoreType::Timeline);
+    let submit_info = ash::vk::SubmitInfo {
+      p_next: if any_timeline {
+        &timeline_info as *const _ as *const std::ffi::c_void
+      } else {
+        std::ptr::null()
+      },
+      wait_semaphore_count: wait_semaphores.len() as u32,
+      p_wait_semaphores: wait_semaphores.as_ptr(),
+      p_wait_dst_stage_mask: wait_stages.as_ptr(),
+      command_buffer_count: 1u32,
+      p_command_buffers: &command_buffer,
+      signal_semaphore_count: signal_semaphores.len() as u32,
+      p_signal_semaphores: signal_semaphores.as_ptr(),
+      ..Default::default()
+    };
+
+    let fence = Fence::new(self.device.clone(), FenceCreateInfo::default())?;
+    let fns = self.device.fns();
+    check_submit(self.queue.with(|_| unsafe {
+      (fns.v1_0.queue_submit)(self.queue.handle(), 1u32, &submit_info, fence.handle())
+    }))?;
+    Ok(fence)
+  }
+
+  /// Submits `command_buffer` without a fence, signaling the context's timeline semaphore with
+  /// the next value, which is returned. Pass it to [`Context::wait_for`], or wait on
+  /// [`Context::timeline`] from other work, e.g. an engine's own timeline. Submissions signal
+  /// increasing values, so waiting for one value also waits for every earlier submission.
+  ///
+  /// # Safety
+  ///
+  /// `command_buffer` must be a valid primary command buffer of the context's device in the
+  /// executable state, and it, along with everything it refers to, must be kept alive until the
+  /// returned value is reached.
+  pub unsafe fn submit_timeline(
+    &self,
+    command_buffer: ash::vk::CommandBuffer,
+  ) -> Result<u64, crate::Error> {
+    let timeline = self.timeline.as_ref().ok_or(SubmitError::NoTimeline)?;
+    let value = self.timeline_value.get() + 1;
+    let timeline_info = ash::vk::TimelineSemaphoreSubmitInfo {
+      signal_semaphore_value_count: 1u32,
+      p_signal_semaphore_values: &value,
+      ..Default::default()
+    };
+    let semaphore = timeline.handle();
+    let submit_info = ash::vk::SubmitInfo {
+      p_next: &timeline_info as *const _ as *const std::ffi::c_void,
+      command_buffer_count: 1u32,
+      p_command_buffers: &command_buffer,
+      signal_semaphore_count: 1u32,
+      p_signal_semaphores: &semaphore,
+      ..Default::default()
+    };
+
+    let fns = self.device.fns();
+    check_submit(self.queue.with(|_| unsafe {
+      (fns.v1_0.queue_submit)(
+        self.queue.handle(),
+        1u32,
+        &submit_info,
+        ash::vk::Fence::null(),
+      )
+    }))?;
+    self.timeline_value.set(value);
+    Ok(value)
+  }
+
+  /// Waits until the submission of [`Context::submit_timeline`] that returned `value`, and every
+  /// earlier one, has executed, up to the context's submit timeout.
+  pub fn wait_for(&self, value: u64) -> Result<(), crate::Error> {
+    let timeline = self.timeline.as_ref().ok_or(SubmitError::NoTimeline)?;
+    let wait_info = SemaphoreWaitInfo {
+      value,
+      ..Default::default()
+    };
+    match timeline.wait(wait_info, self.submit_timeout) {
+      Ok(()) => Ok(()),
+      Err(Validated::Error(VulkanError::Timeout)) => {
+        Err(SubmitError::Timeout(self.submit_timeout.unwrap_or_default()).into())
+      }
+      Err(Validated::Error(VulkanError::DeviceLost)) => Err(SubmitError::DeviceLost.into()),
+      Err(Validated::Error(e)) => Err(SubmitError::Wait(e).into()),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  /// The largest timeline value whose submission has executed, without blocking.
+  pub fn completed_value(&self) -> Result<u64, crate::Error> {
+    let timeline = self.timeline.as_ref().ok_or(SubmitError::NoTimeline)?;
+    Ok(timeline.counter_value()?)
+  }
+
+  pub(crate) fn record<S>(
+    &self,
+    stage: &mut S,
+  ) -> Result<vulkano::command_buffer::CommandBuffer, crate::Error>
+  where
+    S: Stage + ?Sized,
+  {
+    self.record_with_usage(stage, CommandBufferUsage::OneTimeSubmit)
+  }
+
+  /// Records `stage`, such as an [`FftStage`] or a whole [`Chain`](crate::stage::Chain), into a
+  /// primary command buffer for the context's queue. With
+  /// [`CommandBufferUsage::MultipleSubmit`], the command buffer can be passed to
+  /// [`Context::submit`] every frame without recording it again; with
+  /// [`CommandBufferUsage::SimultaneousUse`], it can also be pending several times at once
+  /// through [`Context::submit_fenced`].
+  ///
+  /// The command buffer refers to the stage's app and buffers, which must outlive it.
+  pub fn record_with_usage<S>(
+    &self,
+    stage: &mut S,
+    usage: CommandBufferUsage,
+  ) -> Result<vulkano::command_buffer::CommandBuffer, crate::Error>
+  where
+    S: Stage + ?Sized,
+  {
+    let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
+      self.device.clone(),
+      StandardCommandBufferAllocatorCreateInfo::default(),
+    ));
+    let recording = RecordingCommandBuffer::new(
+      command_buffer_allocator,
+      self.queue.queue_family_index(),
+      CommandBufferLevel::Primary,
+      CommandBufferBeginInfo {
+        usage,
+        ..Default::default()
+      },
+    )?;
+    stage.record(recording.handle())?;
+    Ok(unsafe { recording.end()? })
+  }
+
+  /// Starts a chain of transforms in a one-time-submit command buffer. To submit the same
+  /// transforms every frame, record them once with [`Context::record_with_usage`] instead.
   pub fn start_fft_chain(
     &self,
     config_builder: ConfigBuilder,
     fft_type: FftType,
-  ) -> Result<(Pin<Box<App>>, LaunchParams, Arc<SecondaryAutoCommandBuffer>), Box<dyn std::error::Error>>
+  ) -> Result<(Pin<Box<App>>, LaunchParams, Arc<SecondaryAutoCommandBuffer>), crate::Error>
   {
     let command_buffer_allocator = Arc::new(
       StandardCommandBufferAllocator::new(
@@ -221,13 +1348,7 @@ impl<'a> Context<'a> {
     };
 
     let mut params = LaunchParams::builder().command_buffer(&buffer).build()?;
-    let config = config_builder
-      .physical_device(self.physical.clone())
-      .device(self.device.clone())
-      .fence(&self.fence)
-      .queue(self.queue.clone())
-      .command_pool(self.pool.clone())
-      .build()?;
+    let config = self.config(config_builder)?;
     let mut app = App::new(config)?;
     match fft_type {
       FftType::Forward => app.forward(&mut params)?,
@@ -240,7 +1361,7 @@ impl<'a> Context<'a> {
     mut app: Pin<Box<App>>,
     mut params: LaunchParams,
     fft_type: FftType,
-  ) -> Result<(Pin<Box<App>>, LaunchParams), Box<dyn std::error::Error>> {
+  ) -> Result<(Pin<Box<App>>, LaunchParams), crate::Error> {
     match fft_type {
       FftType::Forward => app.forward(&mut params)?,
       FftType::Inverse => app.inverse(&mut params)?,
@@ -252,16 +1373,10 @@ impl<'a> Context<'a> {
     config_builder: ConfigBuilder,
     builder: Arc<SecondaryAutoCommandBuffer>,
     fft_type: FftType,
-  ) -> Result<(Pin<Box<App>>, LaunchParams, Arc<SecondaryAutoCommandBuffer>), Box<dyn std::error::Error>>
+  ) -> Result<(Pin<Box<App>>, LaunchParams, Arc<SecondaryAutoCommandBuffer>), crate::Error>
   {
     let mut params = LaunchParams::builder().command_buffer(&builder).build()?;
-    let config = config_builder
-      .physical_device(self.physical.clone())
-      .device(self.device.clone())
-      .fence(&self.fence)
-      .queue(self.queue.clone())
-      .command_pool(self.pool.clone())
-      .build()?;
+    let config = self.config(config_builder)?;
     let mut app = App::new(config)?;
     match fft_type {
       FftType::Forward => app.forward(&mut params)?,
@@ -269,13 +1384,86 @@ impl<'a> Context<'a> {
     }
     Ok((app, params, builder))
   }
+  /// Uploads `data`, transforms it in place and returns the result. The buffer and precision of
+  /// `config_builder` are set from `data`, so it only needs to describe the transform. `data`
+  /// must hold at least as many values as the plan's buffer.
+  pub fn transform<T: FftScalar>(
+    &self,
+    config_builder: ConfigBuilder,
+    data: &[T],
+    fft_type: FftType,
+  ) -> Result<Vec<T>, crate::Error> {
+    let config_builder = config_builder.precision(T::PRECISION);
+    check_data_len(&config_builder, data)?;
+    let buffer = self.new_buffer_from_iter(data.iter().copied())?;
+    let config_builder = config_builder.buffer(buffer.clone());
+    let mut stage = self.fft_stage(config_builder, fft_type)?;
+    self.run(&mut stage)?;
+    self.read(&buffer)
+  }
+
+  /// Copies the contents of a host-visible buffer, such as one created by
+  /// [`Context::new_buffer_from_iter`], once the work writing it has completed.
+  pub fn read<T: FftScalar>(
+    &self,
+    buffer: &Subbuffer<[T]>,
+  ) -> Result<Vec<T>, crate::Error> {
+    Ok(buffer.read()?.to_vec())
+  }
+
+  /// Creates a buffer of half precision values converted from `f32`s, for
+  /// [`Precision::Half`](crate::config::Precision::Half) plans and the input and output buffers of
+  /// [`Precision::HalfMemory`](crate::config::Precision::HalfMemory) plans.
+  #[cfg(feature = "f16")]
+  pub fn new_f16_buffer_from_f32_iter<I>(
+    &self,
+    iter: I,
+  ) -> Result<Subbuffer<[half::f16]>, Validated<AllocateBufferError>>
+  where
+    I: IntoIterator<Item = f32>,
+    I::IntoIter: ExactSizeIterator,
+  {
+    self.new_buffer_from_iter(iter.into_iter().map(half::f16::from_f32))
+  }
+
+  /// Copies the contents of a host-visible half precision buffer, converted to `f32`s.
+  #[cfg(feature = "f16")]
+  pub fn read_f16_as_f32(&self, buffer: &Subbuffer<[half::f16]>) -> Result<Vec<f32>, crate::Error> {
+    Ok(half::slice::HalfFloatSliceExt::to_f32_vec(&*buffer.read()?))
+  }
+
+  /// Copies the contents of a host-visible half precision buffer, converted to `f64`s.
+  #[cfg(feature = "f16")]
+  pub fn read_f16_as_f64(&self, buffer: &Subbuffer<[half::f16]>) -> Result<Vec<f64>, crate::Error> {
+    Ok(half::slice::HalfFloatSliceExt::to_f64_vec(&*buffer.read()?))
+  }
+
   pub fn single_fft(
     &self,
     config_builder: ConfigBuilder,
     fft_type: FftType,
-  ) -> Result<(), Box<dyn std::error::Error>> {
+  ) -> Result<(), crate::Error> {
     let (_app, _params, builder) = self.start_fft_chain(config_builder, fft_type)?;
-    self.submit(builder)?;
-    Ok(())
+    // `_app` is dropped on return
+    self.wait_out_timeout(self.submit(builder))
+  }
+}
+
+/// Fails with [`ConfigError::LengthMismatch`] unless `data` fills the buffer of the plan
+/// `config_builder` describes.
+fn check_data_len<T>(config_builder: &ConfigBuilder, data: &[T]) -> Result<(), crate::Error> {
+  let needed = DryRun::new(&config_builder.plan_key())?.buffer_size;
+  if (std::mem::size_of_val(data) as u64) < needed {
+    return Err(ConfigError::LengthMismatch("data", data.len()).into());
+  }
+  Ok(())
+}
+
+impl Drop for Context {
+  fn drop(&mut self) {
+    // A parked command buffer may still be executing, and is only released once it has completed
+    if self.fence_pending.get() {
+      let _ = self.fence.wait(None);
+    }
   }
 }
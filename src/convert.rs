@@ -0,0 +1,176 @@
+//! GPU conversion of integer samples to `f32`, so raw ADC or camera data can be uploaded as-is
+//! and widened in the same command buffer as the transform that consumes it.
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  shader::{group_counts, with_defines, Kernel},
+  stage::Stage,
+};
+
+const CONVERT: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) readonly buffer Input { uint src[]; };
+layout(std430, binding = 1) writeonly buffer Output { float dst[]; };
+layout(push_constant) uniform Params {
+  uint total;
+  float offset;
+  float scale;
+  uint row_len;
+  uint row_stride;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= total) {
+    return;
+  }
+
+#if defined(FORMAT_U8)
+  float value = float((src[i / 4] >> (8u * (i % 4u))) & 0xffu);
+#elif defined(FORMAT_I16)
+  float value = float(int(src[i / 2] << (16u * (1u - i % 2u))) >> 16);
+#else
+  float value = float(int(src[i]));
+#endif
+
+  uint j = (i / row_len) * row_stride + i % row_len;
+  value = (value + offset) * scale;
+#if defined(COMPLEX_OUTPUT)
+  dst[2 * j] = value;
+  dst[2 * j + 1] = 0.0;
+#else
+  dst[j] = value;
+#endif
+}
+"#;
+
+/// Sample type of the input buffer of an [`IntegerConversion`]. Samples are tightly packed in
+/// little-endian order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+  U8,
+  I16,
+  I32,
+}
+
+/// How converted samples are laid out in the output buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputLayout {
+  /// Contiguous `f32` values
+  Real,
+  /// Interleaved complex `f32` values with a zero imaginary part, for C2C transforms
+  Complex,
+  /// Rows of `width` `f32` values padded to `2 * (width / 2 + 1)`, the input layout of an
+  /// in-place R2C transform
+  R2cPadded { width: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConversionOptions {
+  /// Added to each sample before scaling, e.g. `-128.0` to center unsigned 8-bit data
+  pub offset: f32,
+  pub scale: f32,
+  pub layout: OutputLayout,
+}
+
+impl Default for ConversionOptions {
+  fn default() -> Self {
+    Self {
+      offset: 0.0,
+      scale: 1.0,
+      layout: OutputLayout::Real,
+    }
+  }
+}
+
+/// Converts `total` integer samples into `f32` values computed as `(sample + offset) * scale`.
+pub struct IntegerConversion {
+  kernel: Kernel,
+}
+
+impl IntegerConversion {
+  pub fn new(
+    context: &Context,
+    input: Arc<Buffer>,
+    output: Arc<Buffer>,
+    format: SampleFormat,
+    total: u32,
+    options: ConversionOptions,
+  ) -> Result<Self, crate::Error> {
+    let mut defines = vec![match format {
+      SampleFormat::U8 => "FORMAT_U8",
+      SampleFormat::I16 => "FORMAT_I16",
+      SampleFormat::I32 => "FORMAT_I32",
+    }];
+    let (row_len, row_stride) = match options.layout {
+      OutputLayout::Real => (total, total as u64),
+      OutputLayout::Complex => {
+        defines.push("COMPLEX_OUTPUT");
+        (total, total as u64)
+      }
+      OutputLayout::R2cPadded { width } => {
+        if width == 0 {
+          return Err(ConfigError::ZeroSize("row width").into());
+        }
+        (width, 2 * (width as u64 / 2 + 1))
+      }
+    };
+
+    let input_words = match format {
+      SampleFormat::U8 => (total as u64).div_ceil(4),
+      SampleFormat::I16 => (total as u64).div_ceil(2),
+      SampleFormat::I32 => total as u64,
+    };
+    // One past the last float the shader writes, which must also fit its 32-bit indices
+    let output_floats = match total.checked_sub(1) {
+      Some(last) => {
+        let j = (last / row_len) as u64 * row_stride + (last % row_len) as u64;
+        match options.layout {
+          OutputLayout::Complex => 2 * j + 2,
+          _ => j + 1,
+        }
+      }
+      None => 0,
+    };
+    if row_stride > u32::MAX as u64 || output_floats > u32::MAX as u64 {
+      return Err(ConfigError::SizeOverflow("output layout").into());
+    }
+    if input.size() / 4 < input_words {
+      return Err(ConfigError::BufferTooSmall("input").into());
+    }
+    if output.size() / 4 < output_floats {
+      return Err(ConfigError::BufferTooSmall("output").into());
+    }
+
+    let kernel = Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      &with_defines(CONVERT, &defines),
+      [input, output],
+      vec![
+        total,
+        options.offset.to_bits(),
+        options.scale.to_bits(),
+        row_len.max(1),
+        (row_stride as u32).max(1),
+      ],
+      group_counts(total as u64),
+    )?;
+
+    Ok(Self { kernel })
+  }
+}
+
+impl Stage for IntegerConversion {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
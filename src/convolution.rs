@@ -0,0 +1,208 @@
+//! Convolutions with a fixed kernel, without matching the flags of two plans by hand.
+//!
+//! A convolution needs the kernel's spectrum, made by a `kernel_convolution` plan, and a
+//! `convolution` plan for the data whose shape, coordinate features and layout agree with it. A
+//! [`ConvolutionPipeline`] builds both from one description, transforms the kernel once and
+//! keeps its spectrum, so every call to [`ConvolutionPipeline::convolve`] only transforms the
+//! data:
+//!
+//! ```ignore
+//! let mut pipeline = ConvolutionPipeline::builder()
+//!   .dim(&[640, 480])
+//!   .r2c()
+//!   .kernel(&blur)
+//!   .build(&context)?;
+//! let blurred = pipeline.convolve(&image)?;
+//! ```
+
+use vulkano::buffer::Subbuffer;
+
+use crate::{
+  config::{Config, ConfigBuilder, ConfigError, Conjugate},
+  context::{Context, FftType},
+  r2c_layout::{pad_rows, unpad_rows},
+  scalar::FftScalar,
+  stage::FftStage,
+};
+
+/// Describes a [`ConvolutionPipeline`].
+///
+/// Data and kernel are given in the same compact layout: interleaved complex values for C2C
+/// transforms, or real values without the row padding of in-place R2C transforms, with
+/// coordinate features stored one after another.
+#[derive(Clone)]
+pub struct ConvolutionPipelineBuilder<T> {
+  config_builder: ConfigBuilder,
+  dims: Vec<u32>,
+  kernel: Vec<T>,
+  r2c: bool,
+  coordinate_features: u32,
+}
+
+impl<T: FftScalar> ConvolutionPipelineBuilder<T> {
+  pub fn dim<const N: usize>(mut self, dims: &[u32; N]) -> Self {
+    self.config_builder = self.config_builder.dim(dims);
+    self.dims = dims.to_vec();
+    self
+  }
+
+  pub fn kernel(mut self, kernel: &[T]) -> Self {
+    self.kernel = kernel.to_vec();
+    self
+  }
+
+  /// Convolves real data through R2C transforms.
+  pub fn r2c(mut self) -> Self {
+    self.config_builder = self.config_builder.r2c();
+    self.r2c = true;
+    self
+  }
+
+  pub fn coordinate_features(mut self, coordinate_features: u32) -> Self {
+    self.config_builder = self.config_builder.coordinate_features(coordinate_features);
+    self.coordinate_features = coordinate_features;
+    self
+  }
+
+  /// Computes a cross-correlation instead, conjugating one side of the product.
+  pub fn conjugate_convolution(mut self, conjugate: Conjugate) -> Self {
+    self.config_builder = self.config_builder.conjugate_convolution(conjugate);
+    self
+  }
+
+  /// The number of values of data and kernel in the compact layout.
+  pub fn len(&self) -> usize {
+    let points = self.dims.iter().map(|dim| *dim as usize).product::<usize>();
+    let values = if self.r2c { points } else { 2 * points };
+    values * self.coordinate_features as usize
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Transforms the kernel and plans the convolution of data.
+  pub fn build(self, context: &Context) -> Result<ConvolutionPipeline<'_, T>, crate::Error> {
+    if self.dims.is_empty() {
+      return Err(ConfigError::ZeroSize("dimension count").into());
+    }
+    if self.coordinate_features == 0 {
+      return Err(ConfigError::ZeroSize("coordinate feature count").into());
+    }
+    let len = self.len();
+    if self.kernel.len() != len {
+      return Err(ConfigError::LengthMismatch("kernel", self.kernel.len()).into());
+    }
+    let cols = if self.r2c {
+      Some(self.dims[0] as usize)
+    } else {
+      None
+    };
+    let to_layout = |values: &[T]| match cols {
+      Some(cols) => pad_rows(values, cols),
+      None => values.to_vec(),
+    };
+
+    let spectrum = context.transform(
+      self.config_builder.clone().kernel_convolution(),
+      &to_layout(&self.kernel),
+      FftType::Forward,
+    )?;
+    let kernel_spectrum = context.new_buffer_from_iter(spectrum)?;
+
+    let data = context.new_buffer_from_iter(to_layout(&vec![T::default(); len]))?;
+    let config_builder = self
+      .config_builder
+      .buffer(data.clone())
+      .kernel(kernel_spectrum.clone())
+      .convolution()
+      .normalize()
+      .precision(T::PRECISION);
+    let stage = context.fft_stage(config_builder, FftType::Forward)?;
+
+    Ok(ConvolutionPipeline {
+      context,
+      stage,
+      data,
+      kernel_spectrum,
+      cols,
+      len,
+    })
+  }
+}
+
+/// A convolution with a kernel transformed once, at build time.
+pub struct ConvolutionPipeline<'c, T: FftScalar> {
+  context: &'c Context,
+  stage: FftStage,
+  data: Subbuffer<[T]>,
+  kernel_spectrum: Subbuffer<[T]>,
+  /// Length of the rows padded for R2C transforms
+  cols: Option<usize>,
+  len: usize,
+}
+
+impl<'c, T: FftScalar> ConvolutionPipeline<'c, T> {
+  pub fn builder() -> ConvolutionPipelineBuilder<T> {
+    ConvolutionPipelineBuilder {
+      config_builder: Config::builder(),
+      dims: Vec::new(),
+      kernel: Vec::new(),
+      r2c: false,
+      coordinate_features: 1,
+    }
+  }
+
+  /// Convolves `data`, in the compact layout of the kernel, and returns the result in the same
+  /// layout.
+  pub fn convolve(&mut self, data: &[T]) -> Result<Vec<T>, crate::Error> {
+    if data.len() != self.len {
+      return Err(ConfigError::LengthMismatch("data", data.len()).into());
+    }
+    match self.cols {
+      Some(cols) => self.data.write()?.copy_from_slice(&pad_rows(data, cols)),
+      None => self.data.write()?.copy_from_slice(data),
+    }
+    self.convolve_in_place()?;
+
+    let result = self.context.read(&self.data)?;
+    Ok(match self.cols {
+      Some(cols) => unpad_rows(&result, cols),
+      None => result,
+    })
+  }
+
+  /// Convolves the data buffer in place, blocking until it has completed.
+  pub fn convolve_in_place(&mut self) -> Result<(), crate::Error> {
+    self.context.run(&mut self.stage)
+  }
+
+  /// The buffer [`ConvolutionPipeline::convolve_in_place`] convolves, in the layout of the
+  /// transform, with padded rows for R2C transforms.
+  pub fn data_buffer(&self) -> &Subbuffer<[T]> {
+    &self.data
+  }
+
+  /// The cached spectrum of the kernel.
+  pub fn kernel_spectrum(&self) -> &Subbuffer<[T]> {
+    &self.kernel_spectrum
+  }
+
+  /// The stage convolving the data buffer, for recording into a larger command buffer.
+  pub fn stage(&mut self) -> &mut FftStage {
+    &mut self.stage
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_compact_len() {
+    let builder = ConvolutionPipeline::<f32>::builder().dim(&[5, 4]);
+    assert_eq!(builder.len(), 40);
+    assert_eq!(builder.clone().r2c().len(), 20);
+    assert_eq!(builder.r2c().coordinate_features(3).len(), 60);
+  }
+}
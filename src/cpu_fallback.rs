@@ -0,0 +1,232 @@
+//! Running transforms on the host with [rustfft](https://docs.rs/rustfft) when there is no
+//! Vulkan device, so applications degrade gracefully on machines without a GPU.
+//!
+//! [`transform_cpu`] takes the same config and data as [`Context::transform`] and returns the
+//! data in the same layout, including the padded rows of R2C transforms. An [`ExecutionTarget`]
+//! picks the GPU when one is available:
+//!
+//! ```ignore
+//! let target = ExecutionTarget::new();
+//! let spectrum = target.transform(Config::builder().dim(&[1024]), &samples, FftType::Forward)?;
+//! ```
+//!
+//! Computations are done in double precision. Convolutions, R2R transforms, zero padding,
+//! strides and separate input or output buffers aren't supported on the host.
+
+use std::sync::Arc;
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::{
+  config::{ConfigBuilder, ConfigError},
+  context::{Context, FftType},
+  dry_run::DryRun,
+  scalar::FftScalar,
+};
+
+/// Where transforms run.
+pub enum ExecutionTarget {
+  Gpu(Context),
+  Cpu,
+}
+
+impl ExecutionTarget {
+  /// A context on the first device, or the host if no context can be created.
+  pub fn new() -> Self {
+    match Context::new_standalone() {
+      Ok(context) => Self::Gpu(context),
+      Err(_) => Self::Cpu,
+    }
+  }
+
+  pub fn is_gpu(&self) -> bool {
+    matches!(self, Self::Gpu(_))
+  }
+
+  /// Transforms `data` with [`Context::transform`] or [`transform_cpu`].
+  pub fn transform<T: FftScalar>(
+    &self,
+    config_builder: ConfigBuilder,
+    data: &[T],
+    fft_type: FftType,
+  ) -> Result<Vec<T>, crate::Error> {
+    match self {
+      Self::Gpu(context) => context.transform(config_builder, data, fft_type),
+      Self::Cpu => transform_cpu(&config_builder, data, fft_type),
+    }
+  }
+}
+
+impl Default for ExecutionTarget {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Transforms `data`, laid out as in a buffer of the config's plan, on the host. The config's
+/// buffers are ignored.
+pub fn transform_cpu<T: FftScalar>(
+  config_builder: &ConfigBuilder,
+  data: &[T],
+  fft_type: FftType,
+) -> Result<Vec<T>, crate::Error> {
+  let plan_key = config_builder.clone().precision(T::PRECISION).plan_key();
+  let unsupported = [
+    ("R2R transforms", plan_key.r2r.is_some()),
+    ("convolutions", plan_key.convolution),
+    ("zero padding", plan_key.zero_padding.contains(&true)),
+    (
+      "strides",
+      plan_key.buffer_stride.is_some()
+        || plan_key.input_buffer_stride.is_some()
+        || plan_key.output_buffer_stride.is_some(),
+    ),
+    (
+      "input and output buffers",
+      plan_key.input_buffer || plan_key.output_buffer,
+    ),
+    (
+      "omitting the first axis of R2C transforms",
+      plan_key.r2c && plan_key.omit_dimension[0],
+    ),
+  ];
+  if let Some((name, _)) = unsupported.iter().find(|(_, unsupported)| *unsupported) {
+    return Err(ConfigError::UnsupportedOnCpu(name).into());
+  }
+
+  let layout = DryRun::new(&plan_key)?;
+  let len = (layout.buffer_size / T::PRECISION.compute_scalar_bytes()) as usize;
+  if data.len() < len {
+    return Err(ConfigError::BufferTooSmall("buffer").into());
+  }
+
+  let dims: Vec<usize> = layout.padded_dims.iter().map(|d| *d as usize).collect();
+  let mut buffer: Vec<Complex<f64>> = data[..len]
+    .chunks_exact(2)
+    .map(|c| Complex::new(c[0].to_f64(), c[1].to_f64()))
+    .collect();
+  let inverse = fft_type == FftType::Inverse;
+  let n = plan_key.size[0] as usize;
+  let mut planner = FftPlanner::new();
+  let plan = |planner: &mut FftPlanner<f64>, len: usize| match fft_type {
+    FftType::Forward => planner.plan_fft_forward(len),
+    FftType::Inverse => planner.plan_fft_inverse(len),
+  };
+
+  let batch_len = dims.iter().product::<usize>();
+  for batch in buffer.chunks_exact_mut(batch_len) {
+    if plan_key.r2c && !inverse {
+      real_rows(batch, n, &plan(&mut planner, n), false);
+    }
+    for (axis, len) in dims.iter().enumerate() {
+      if plan_key.omit_dimension[axis] || (plan_key.r2c && axis == 0) {
+        continue;
+      }
+      fft_axis(batch, &dims, axis, &plan(&mut planner, *len));
+    }
+    if plan_key.r2c && inverse {
+      real_rows(batch, n, &plan(&mut planner, n), true);
+    }
+  }
+
+  let scale = plan_key.scale_factor(inverse);
+  if scale != 1.0 {
+    buffer.iter_mut().for_each(|v| *v *= scale);
+  }
+
+  let mut output = data.to_vec();
+  for (out, v) in output.chunks_exact_mut(2).zip(&buffer) {
+    out[0] = T::from_f64(v.re);
+    out[1] = T::from_f64(v.im);
+  }
+  Ok(output)
+}
+
+/// Transforms every line of a batch of shape `dims` along `axis`.
+fn fft_axis(batch: &mut [Complex<f64>], dims: &[usize], axis: usize, fft: &Arc<dyn Fft<f64>>) {
+  let stride = dims[..axis].iter().product::<usize>();
+  let len = dims[axis];
+  let mut line = vec![Complex::default(); len];
+  for block in batch.chunks_exact_mut(stride * len) {
+    for offset in 0..stride {
+      for (i, v) in line.iter_mut().enumerate() {
+        *v = block[offset + i * stride];
+      }
+      fft.process(&mut line);
+      for (i, v) in line.iter().enumerate() {
+        block[offset + i * stride] = *v;
+      }
+    }
+  }
+}
+
+/// Transforms the first axis of an R2C plan, whose rows hold `n` reals before the forward
+/// transform and `n / 2 + 1` complex numbers after it.
+fn real_rows(batch: &mut [Complex<f64>], n: usize, fft: &Arc<dyn Fft<f64>>, inverse: bool) {
+  let row_len = n / 2 + 1;
+  let mut line = vec![Complex::default(); n];
+  for row in batch.chunks_exact_mut(row_len) {
+    if inverse {
+      // Rebuild the full spectrum from its Hermitian half
+      for (i, v) in line.iter_mut().enumerate() {
+        *v = if i < row_len {
+          row[i]
+        } else {
+          row[n - i].conj()
+        };
+      }
+    } else {
+      for (i, v) in line.iter_mut().enumerate() {
+        let pair = row[i / 2];
+        *v = Complex::new(if i % 2 == 0 { pair.re } else { pair.im }, 0.0);
+      }
+    }
+
+    fft.process(&mut line);
+
+    if inverse {
+      row.iter_mut().for_each(|v| *v = Complex::default());
+      for (i, v) in line.iter().enumerate() {
+        if i % 2 == 0 {
+          row[i / 2].re = v.re;
+        } else {
+          row[i / 2].im = v.re;
+        }
+      }
+    } else {
+      row.copy_from_slice(&line[..row_len]);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_transform_cpu() {
+    // An impulse at 1 transforms to the unit circle
+    let mut data = vec![0.0f64; 2 * 8];
+    data[2] = 1.0;
+    let spectrum = transform_cpu(&ConfigBuilder::new().dim(&[8]), &data, FftType::Forward).unwrap();
+    let angle = -2.0 * std::f64::consts::PI / 8.0;
+    assert!((spectrum[2] - angle.cos()).abs() < 1e-12);
+    assert!((spectrum[3] - angle.sin()).abs() < 1e-12);
+
+    // R2C rows of 6 reals are padded to 4 complex numbers, and come back normalized
+    let builder = ConfigBuilder::new().dim(&[6, 3]).r2c().normalize();
+    let mut data = vec![0.0f32; 8 * 3];
+    for (i, v) in data.iter_mut().enumerate().filter(|(i, _)| i % 8 < 6) {
+      *v = i as f32;
+    }
+    let spectrum = transform_cpu(&builder, &data, FftType::Forward).unwrap();
+    assert_eq!(
+      spectrum[0],
+      (0..6).chain(8..14).chain(16..22).sum::<i32>() as f32
+    );
+    let round_trip = transform_cpu(&builder, &spectrum, FftType::Inverse).unwrap();
+    for (a, b) in data.iter().zip(&round_trip) {
+      assert!((a - b).abs() < 1e-4);
+    }
+  }
+}
@@ -0,0 +1,89 @@
+//! Transforms on NVIDIA GPUs through VkFFT's CUDA backend, without Vulkan.
+//!
+//! A [`CudaApp`] is planned from the same [`ConfigBuilder`] as a Vulkan app. The device, stream
+//! and buffer are raw CUDA handles, e.g. from cudarc or cust, and the buffer is transformed in
+//! place:
+//!
+//! ```ignore
+//! let builder = Config::builder().dim(&[4096]);
+//! let mut app = unsafe { CudaApp::new(&builder, device, stream, buffer, 4096 * 8)? };
+//! app.as_mut().launch(false)?;
+//! stream.synchronize()?;
+//! ```
+//!
+//! Input, output and temp buffers, convolutions and custom Bluestein padding aren't supported
+//! yet.
+
+use std::{ffi::c_void, pin::Pin};
+
+use vkfft_sys::cuda;
+
+use crate::{
+  backend_plan::{copy_plan, vulkan_plan},
+  config::ConfigBuilder,
+  error::check_error,
+};
+
+/// A VkFFT application on a CUDA device.
+pub struct CudaApp {
+  app: cuda::VkFFTApplication,
+  device: cuda::CUdevice,
+  stream: cuda::cudaStream_t,
+  buffer: *mut c_void,
+  buffer_size: u64,
+}
+
+impl CudaApp {
+  /// Plans the transform of `config_builder`, whose device handles and buffers are ignored, in
+  /// `buffer` of `buffer_size` bytes on `device`. Transforms are enqueued on `stream`.
+  ///
+  /// # Safety
+  ///
+  /// `device` must be a valid device whose context is current on the calling thread, and
+  /// `stream` and `buffer` must belong to it and outlive the app.
+  pub unsafe fn new(
+    config_builder: &ConfigBuilder,
+    device: i32,
+    stream: *mut c_void,
+    buffer: u64,
+    buffer_size: u64,
+  ) -> Result<Pin<Box<Self>>, crate::Error> {
+    let vulkan = vulkan_plan(config_builder, "CUDA")?;
+    let mut config = std::mem::zeroed::<cuda::VkFFTConfiguration>();
+    copy_plan!(config, vulkan);
+
+    let mut res = Box::pin(Self {
+      app: std::mem::zeroed(),
+      device,
+      stream: stream as cuda::cudaStream_t,
+      buffer: buffer as *mut c_void,
+      buffer_size,
+    });
+    // VkFFT keeps pointers to the handles, so they live in the pinned app
+    let this = res.as_mut().get_unchecked_mut();
+    config.device = &mut this.device;
+    config.stream = &mut this.stream;
+    config.num_streams = 1;
+    config.buffer = &mut this.buffer;
+    config.bufferSize = &mut this.buffer_size;
+    check_error(cuda::initializeVkFFTCuda(&mut this.app, config))?;
+    Ok(res)
+  }
+
+  /// Enqueues the transform on the app's stream without waiting for it.
+  pub fn launch(self: Pin<&mut Self>, inverse: bool) -> Result<(), crate::Error> {
+    let this = unsafe { self.get_unchecked_mut() };
+    let mut params = unsafe { std::mem::zeroed::<cuda::VkFFTLaunchParams>() };
+    params.buffer = &mut this.buffer;
+    check_error(unsafe {
+      cuda::VkFFTAppendCuda(&mut this.app, if inverse { 1 } else { -1 }, &mut params)
+    })?;
+    Ok(())
+  }
+}
+
+impl Drop for CudaApp {
+  fn drop(&mut self) {
+    unsafe { cuda::deleteVkFFTCuda(&mut self.app) };
+  }
+}
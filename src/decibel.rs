@@ -0,0 +1,145 @@
+//! GPU conversion of spectra to decibels, so visualization and peak detection can consume
+//! log-scaled data without a CPU round trip.
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  shader::{group_counts, with_defines, Kernel},
+  stage::Stage,
+};
+
+const DECIBEL: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) readonly buffer Input { float src[]; };
+layout(std430, binding = 1) writeonly buffer Output { float dst[]; };
+layout(push_constant) uniform Params {
+  uint total;
+  float reference_power;
+  float floor_db;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= total) {
+    return;
+  }
+
+#if defined(COMPLEX_INPUT)
+  vec2 value = vec2(src[2 * i], src[2 * i + 1]);
+  float power = dot(value, value);
+#elif defined(MAGNITUDE_INPUT)
+  float power = src[i] * src[i];
+#else
+  float power = src[i];
+#endif
+
+  float ratio = max(power / reference_power, 1e-37);
+  dst[i] = max(10.0 * log2(ratio) * 0.30102999566, floor_db);
+}
+"#;
+
+/// How values are stored in the input buffer of a [`DecibelConversion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecibelInput {
+  /// Interleaved complex `f32` values, converted as `20 log10(|x| / reference)`
+  Complex,
+  /// Real `f32` magnitudes, converted as `20 log10(x / reference)`
+  Magnitude,
+  /// Real `f32` powers, converted as `10 log10(x / reference^2)`
+  Power,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecibelOptions {
+  /// Amplitude that maps to 0 dB
+  pub reference: f32,
+  /// Lowest value written to the output, in dB
+  pub floor: f32,
+}
+
+impl Default for DecibelOptions {
+  fn default() -> Self {
+    Self {
+      reference: 1.0,
+      floor: -200.0,
+    }
+  }
+}
+
+/// Converts `total` spectrum values into `f32` decibels. `output` may be the same buffer as
+/// `input` for real-valued inputs. The reference must be positive.
+pub struct DecibelConversion {
+  kernel: Kernel,
+}
+
+impl DecibelConversion {
+  pub fn new(
+    context: &Context,
+    input: Arc<Buffer>,
+    output: Arc<Buffer>,
+    format: DecibelInput,
+    total: u32,
+    options: DecibelOptions,
+  ) -> Result<Self, crate::Error> {
+    let kernel = decibel_kernel(context, input, output, format, total, options)?;
+
+    Ok(Self { kernel })
+  }
+}
+
+pub(crate) fn decibel_kernel(
+  context: &Context,
+  input: Arc<Buffer>,
+  output: Arc<Buffer>,
+  format: DecibelInput,
+  total: u32,
+  options: DecibelOptions,
+) -> Result<Kernel, crate::Error> {
+  // Also rejects NaN
+  if options.reference.is_nan() || options.reference <= 0.0 {
+    return Err(crate::Error::InvalidArgument("decibel reference"));
+  }
+  let components = match format {
+    DecibelInput::Complex => 2,
+    DecibelInput::Magnitude | DecibelInput::Power => 1,
+  };
+  // The shader indexes the input's floats with 32-bit integers
+  let values = total as u64 * components;
+  if values > u32::MAX as u64 {
+    return Err(ConfigError::SizeOverflow("value count").into());
+  }
+  if input.size() / 4 < values {
+    return Err(ConfigError::BufferTooSmall("input").into());
+  }
+  if output.size() / 4 < total as u64 {
+    return Err(ConfigError::BufferTooSmall("output").into());
+  }
+
+  let source = match format {
+    DecibelInput::Complex => with_defines(DECIBEL, &["COMPLEX_INPUT"]),
+    DecibelInput::Magnitude => with_defines(DECIBEL, &["MAGNITUDE_INPUT"]),
+    DecibelInput::Power => DECIBEL.to_string(),
+  };
+  let reference_power = options.reference * options.reference;
+  Kernel::new(
+    context.descriptor_set_allocator.clone(),
+    &source,
+    [input, output],
+    vec![total, reference_power.to_bits(), options.floor.to_bits()],
+    group_counts(total as u64),
+  )
+}
+
+impl Stage for DecibelConversion {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
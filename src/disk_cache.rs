@@ -0,0 +1,150 @@
+//! Generated kernels persisted across runs.
+//!
+//! A [`DiskCache`] stores the serialized kernels of every application it creates in a
+//! directory, one file per plan and device, so shader compilation is paid once per machine
+//! rather than once per process. Set one on a context with
+//! [`Context::with_cache_dir`](crate::context::Context::with_cache_dir).
+//!
+//! Entries are only loaded on the device, driver and VkFFT version they were generated with, and
+//! only if the checksum of their kernels matches; anything else, including unreadable or corrupt
+//! files, is regenerated and overwritten. The checksum catches corruption, not tampering: the
+//! directory must only be writable by users trusted to run code on the device.
+
+use std::{
+  convert::TryInto,
+  fs, io,
+  path::{Path, PathBuf},
+  pin::Pin,
+};
+
+use crate::{app::App, bundle::BundleTarget, config::Config, error};
+
+const MAGIC: &[u8; 8] = b"VKFFTCCH";
+
+/// A directory of serialized kernels keyed by device and plan.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+  dir: PathBuf,
+}
+
+impl DiskCache {
+  /// Uses `dir`, creating it if needed.
+  pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+    fs::create_dir_all(&dir)?;
+    Ok(Self {
+      dir: dir.as_ref().to_path_buf(),
+    })
+  }
+
+  pub fn dir(&self) -> &Path {
+    &self.dir
+  }
+
+  /// Creates an `App` for `config`, loading its kernels from the cache when they were generated
+  /// before for the same device and plan, and generating and storing them otherwise. Configs
+  /// built from raw handles can't identify their device and are never cached.
+  ///
+  /// Failing to write the cache entry doesn't fail the call.
+  pub fn app(&self, config: Config) -> error::Result<Pin<Box<App>>> {
+    let physical_device = match config.vulkano_physical_device.clone() {
+      Some(physical_device) => physical_device,
+      None => return App::new(config),
+    };
+    let key = format!(
+      "{:?} {:?} {}",
+      BundleTarget::of(&physical_device),
+      physical_device.properties().device_uuid,
+      config.plan_key()
+    );
+    let path = self.dir.join(format!("{:016x}.vkfft", fnv1a(key.as_bytes())));
+
+    let mut sys_config = config.as_sys()?;
+    // The key and checksum match, so the blob was saved from this plan on this device
+    if let Some(blob) = fs::read(&path).ok().and_then(|bytes| entry(&bytes, &key)) {
+      let mut loading = sys_config.duplicate();
+      loading.load_application(blob);
+      if let Ok(app) = App::from_sys(loading) {
+        return Ok(app);
+      }
+    }
+
+    sys_config.config.saveApplicationToString = 1;
+    let app = App::from_sys(sys_config)?;
+    if let Some(blob) = app.save_to_bytes() {
+      let _ = write_entry(&path, &key, &blob);
+    }
+    Ok(app)
+  }
+
+  /// Removes every entry.
+  pub fn clear(&self) -> io::Result<()> {
+    for entry in fs::read_dir(&self.dir)? {
+      let path = entry?.path();
+      if path.extension().is_some_and(|e| e == "vkfft") {
+        fs::remove_file(path)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hashers is stable across releases.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+    (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+  })
+}
+
+/// The blob of an entry file, if it was written for `key` and matches its checksum.
+fn entry(bytes: &[u8], key: &str) -> Option<Vec<u8>> {
+  let rest = bytes.strip_prefix(MAGIC)?;
+  let key_len = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+  let rest = &rest[4..];
+  if rest.get(..key_len)? != key.as_bytes() {
+    return None;
+  }
+  let rest = &rest[key_len..];
+  let checksum = u64::from_le_bytes(rest.get(..8)?.try_into().ok()?);
+  let blob = &rest[8..];
+  if fnv1a(blob) != checksum {
+    return None;
+  }
+  Some(blob.to_vec())
+}
+
+/// Writes an entry through a temporary file, so concurrent readers never see a partial one.
+fn write_entry(path: &Path, key: &str, blob: &[u8]) -> io::Result<()> {
+  let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + key.len() + 8 + blob.len());
+  bytes.extend_from_slice(MAGIC);
+  bytes.extend_from_slice(&(key.len() as u32).to_le_bytes());
+  bytes.extend_from_slice(key.as_bytes());
+  bytes.extend_from_slice(&fnv1a(blob).to_le_bytes());
+  bytes.extend_from_slice(blob);
+
+  let temp = path.with_extension(format!("tmp{}", std::process::id()));
+  fs::write(&temp, bytes)?;
+  fs::rename(temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_entry_round_trip() {
+    let dir = std::env::temp_dir().join(format!("vkfft-cache-test-{}", std::process::id()));
+    let path = DiskCache::new(&dir).unwrap().dir().join("entry.vkfft");
+
+    write_entry(&path, "key", &[1, 2, 3]).unwrap();
+    let bytes = fs::read(&path).unwrap();
+    assert_eq!(entry(&bytes, "key"), Some(vec![1, 2, 3]));
+    assert_eq!(entry(&bytes, "other"), None);
+    assert_eq!(entry(&bytes[..10], "key"), None);
+
+    let mut corrupt = bytes.clone();
+    *corrupt.last_mut().unwrap() ^= 1;
+    assert_eq!(entry(&corrupt, "key"), None);
+
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
@@ -0,0 +1,171 @@
+//! Checking a config and computing the buffers it needs on the CPU, without a Vulkan device.
+//!
+//! [`ConfigBuilder::dry_run`](crate::config::ConfigBuilder::dry_run) runs the same checks as
+//! creating an app, short of generating kernels, so FFT setup code can be unit-tested on
+//! machines without a GPU:
+//!
+//! ```ignore
+//! let dry_run = Config::builder().dim(&[1000, 1000]).r2c().dry_run()?;
+//! assert_eq!(dry_run.padded_dims, vec![501, 1000]);
+//! let buffer = allocate(dry_run.buffer_size);
+//! ```
+
+use crate::config::{ConfigError, PlanKey, MAX_FFT_DIMENSIONS};
+
+/// The layout of a transform's buffers, derived from its plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DryRun {
+  /// Extent of each axis in the buffer. The first axis of an R2C transform holds `n / 2 + 1`
+  /// complex numbers.
+  pub padded_dims: Vec<u64>,
+  /// Bytes of the buffer the transform runs in, following `buffer_stride` if set
+  pub buffer_size: u64,
+  /// Bytes of the input buffer, if the plan reads from one
+  pub input_buffer_size: Option<u64>,
+  /// Bytes of the output buffer, if the plan writes to one
+  pub output_buffer_size: Option<u64>,
+  /// Bytes of the convolution kernel, if the plan convolves
+  pub kernel_size: Option<u64>,
+  /// Bytes of device memory the buffers take, counting a temp buffer as large as the buffer,
+  /// which VkFFT allocates for plans split into several passes. Bluestein plans can need more.
+  pub memory_estimate: u64,
+}
+
+impl DryRun {
+  /// Checks `plan_key` like VkFFT's config marshalling does and computes its buffer sizes.
+  pub fn new(plan_key: &PlanKey) -> Result<Self, ConfigError> {
+    let mut sys = unsafe { std::mem::zeroed::<vkfft_sys::VkFFTConfiguration>() };
+    plan_key.write_sys(&mut sys)?;
+
+    let dims = (plan_key.fft_dim as usize).min(MAX_FFT_DIMENSIONS);
+    let mut padded_dims: Vec<u64> = plan_key.size[..dims].iter().map(|n| *n as u64).collect();
+    if plan_key.r2c {
+      padded_dims[0] = padded_dims[0] / 2 + 1;
+    }
+
+    // Input and output buffers hold the values the application reads and writes, the buffer
+    // those it computes with
+    let scalar = plan_key.precision.scalar_bytes();
+    let compute_scalar = plan_key.precision.compute_scalar_bytes();
+    // R2R transforms run on real numbers, everything else on complex ones
+    let components = if plan_key.r2r.is_some() { 1 } else { 2 };
+    let (element, compute_element) = (components * scalar, components * compute_scalar);
+    let batches = plan_key.batch_count.unwrap_or(1) as u64 * plan_key.coordinate_features as u64;
+    let bytes = |row: u64, stride: &Option<[u64; MAX_FFT_DIMENSIONS]>, element: u64, batches: u64| {
+      let span = match stride {
+        Some(stride) => stride[dims - 1],
+        None => row * padded_dims[1..].iter().product::<u64>(),
+      };
+      span
+        .checked_mul(batches)
+        .and_then(|n| n.checked_mul(element))
+        .ok_or(ConfigError::SizeOverflow("size of the buffer in bytes"))
+    };
+
+    let [(_, buffer_stride, _), (_, input_buffer_stride, _), (_, output_buffer_stride, _)] =
+      plan_key.resolved_strides();
+    let buffer_size = bytes(
+      padded_dims[0],
+      &buffer_stride,
+      compute_element,
+      batches,
+    )?;
+    let input_buffer_size = if plan_key.input_buffer {
+      // The input of an R2C transform is real and unpadded
+      let (row, element) = if plan_key.r2c {
+        (plan_key.size[0] as u64, scalar)
+      } else {
+        (padded_dims[0], element)
+      };
+      Some(bytes(row, &input_buffer_stride, element, batches)?)
+    } else {
+      None
+    };
+    let output_buffer_size = if plan_key.output_buffer {
+      Some(bytes(
+        padded_dims[0],
+        &output_buffer_stride,
+        element,
+        batches,
+      )?)
+    } else {
+      None
+    };
+    // One kernel serves every batch, with a matrix of coordinate features for matrix
+    // convolutions
+    let kernel_size = if plan_key.convolution {
+      let features = plan_key.coordinate_features as u64;
+      let kernel_features = match plan_key.matrix_convolution {
+        Some(_) => features * features,
+        None => features,
+      };
+      Some(bytes(
+        padded_dims[0],
+        &None,
+        compute_element,
+        kernel_features,
+      )?)
+    } else {
+      None
+    };
+
+    let memory_estimate = [
+      Some(buffer_size),
+      Some(buffer_size),
+      input_buffer_size,
+      output_buffer_size,
+      kernel_size,
+    ]
+    .iter()
+    .flatten()
+    .try_fold(0u64, |total, size| total.checked_add(*size))
+    .ok_or(ConfigError::SizeOverflow("memory estimate"))?;
+
+    Ok(Self {
+      padded_dims,
+      buffer_size,
+      input_buffer_size,
+      output_buffer_size,
+      kernel_size,
+      memory_estimate,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::{ConfigBuilder, ConfigError, Precision};
+
+  #[test]
+  fn check_dry_run() {
+    let dry_run = ConfigBuilder::new()
+      .dim(&[1000, 100])
+      .r2c()
+      .batch_count(2)
+      .dry_run()
+      .unwrap();
+    assert_eq!(dry_run.padded_dims, vec![501, 100]);
+    assert_eq!(dry_run.buffer_size, 501 * 100 * 2 * 8);
+    assert_eq!(dry_run.input_buffer_size, None);
+    assert_eq!(dry_run.memory_estimate, 2 * dry_run.buffer_size);
+
+    let too_small = ConfigBuilder::new().dim(&[64]).buffer_size(256).dry_run();
+    assert!(matches!(
+      too_small,
+      Err(ConfigError::BufferTooSmall("buffer"))
+    ));
+    assert!(ConfigBuilder::new().dim(&[64, 0]).dry_run().is_err());
+
+    // Half precision memory keeps halves in the input and output buffers only
+    let half_memory = ConfigBuilder::new()
+      .dim(&[64])
+      .precision(Precision::HalfMemory)
+      .input_buffer_size(64 * 4)
+      .output_buffer_size(64 * 4)
+      .dry_run()
+      .unwrap();
+    assert_eq!(half_memory.buffer_size, 64 * 8);
+    assert_eq!(half_memory.input_buffer_size, Some(64 * 4));
+    assert_eq!(half_memory.output_buffer_size, Some(64 * 4));
+  }
+}
@@ -2,88 +2,452 @@ use std::convert::{TryFrom, TryInto};
 
 use derive_more::{Display, Error};
 
-use crate::{app::LaunchError, config::ConfigError};
+#[cfg(feature = "vulkano")]
+use vulkano::{
+  buffer::AllocateBufferError, sync::HostAccessError, LoadingError, Validated, ValidationError,
+  VulkanError,
+};
 
+#[cfg(feature = "vulkano")]
+use crate::{
+  context::{DeviceSelectionError, QueueFamilyPreference, SubmitError},
+  shader::ShaderError,
+};
+use crate::{
+  app::{self, LaunchError},
+  config::{self, ConfigError, ConfigSummary},
+  version::VersionMismatch,
+};
+
+/// An error from VkFFT, with a variant for every `VkFFTResult` code, or from the checks this crate
+/// runs before calling it.
 #[derive(Display, Debug, Error)]
 pub enum VkfftError {
+  #[display("VkFFT failed to allocate host memory")]
+  MallocFailed,
+  #[display("The generated kernel code doesn't fit VkFFT's code buffer")]
+  InsufficientCodeBuffer,
+  #[display("VkFFT's temporary string buffer is too small")]
+  InsufficientTempBuffer,
+  #[display("The plan was used before it was initialized")]
+  PlanNotInitialized,
+  #[display("A null temporary was passed to the kernel generator")]
+  NullTempPassed,
+  #[display("The kernel generator failed to compute a constant")]
+  MathFailed,
+  #[display("The number of dimensions exceeds VkFFT's maximum")]
+  FftDimGtMaxFftDimensions,
+  #[display("The application passed to initialization is not zeroed")]
+  NonzeroAppInitialization,
+  #[display("The physical device is missing from the config")]
   InvalidPhysicalDevice,
+  #[display("The device is missing from the config")]
   InvalidDevice,
+  #[display("The queue is missing from the config")]
   InvalidQueue,
+  #[display("The command pool is missing from the config")]
   InvalidCommandPool,
+  #[display("The fence is missing from the config")]
   InvalidFence,
+  #[display("Only the forward transform was initialized")]
   OnlyForwardFftInitialized,
+  #[display("Only the inverse transform was initialized")]
   OnlyInverseFftInitialized,
+  #[display("The context is missing from the config")]
   InvalidContext,
+  #[display("The platform is missing from the config")]
   InvalidPlatform,
+  #[display("Saving the application can't be combined with loading one")]
+  EnabledSaveApplicationToString,
+  #[display("The file to load the application from is empty")]
+  EmptyFile,
+  #[display("The number of dimensions is zero")]
   EmptyFftDim,
+  #[display("The transform size is zero")]
   EmptySize,
+  #[display("The buffer size is zero")]
   EmptyBufferSize,
+  #[display("The buffer is missing")]
   EmptyBuffer,
+  #[display("The temp buffer size is zero")]
   EmptyTempBufferSize,
+  #[display("The temp buffer is missing")]
   EmptyTempBuffer,
+  #[display("The input buffer size is zero")]
   EmptyInputBufferSize,
+  #[display("The input buffer is missing")]
   EmptyInputBuffer,
+  #[display("The output buffer size is zero")]
   EmptyOutputBufferSize,
+  #[display("The output buffer is missing")]
   EmptyOutputBuffer,
+  #[display("The kernel size is zero")]
   EmptyKernelSize,
+  #[display("The convolution kernel is missing")]
   EmptyKernel,
+  #[display("The serialized application is empty")]
+  EmptyApplicationString,
+  #[display("The custom Bluestein padding pattern arrays are missing")]
+  EmptyCustomBluesteinPaddingPatternArrays,
+  #[display("The application is missing")]
+  EmptyApp,
+  #[display("The temp buffer provided is smaller than the plan needs")]
+  UserTempBufferTooSmall,
+  #[display("The size has a prime factor VkFFT can't transform")]
   UnsupportedRadix,
+  #[display("The transform length is not supported")]
   UnsupportedFftLength,
+  #[display("The R2C transform length is not supported")]
   UnsupportedFftLengthR2C,
+  #[display("The R2R transform length is not supported")]
+  UnsupportedFftLengthR2R,
+  #[display("The combination of omitted dimensions is not supported")]
+  UnsupportedFftOmit,
+  #[display("Failed to allocate memory")]
   FailedToAllocate,
+  #[display("Failed to map memory")]
   FailedToMapMemory,
+  #[display("Failed to allocate command buffers")]
   FailedToAllocateCommandBuffers,
+  #[display("Failed to begin a command buffer")]
   FailedToBeginCommandBuffer,
+  #[display("Failed to end a command buffer")]
   FailedToEndCommandBuffer,
+  #[display("Failed to submit to the queue")]
   FailedToSubmitQueue,
+  #[display("Failed to wait for fences")]
   FailedToWaitForFences,
+  #[display("Failed to reset fences")]
   FailedToResetFences,
+  #[display("Failed to create a descriptor pool")]
   FailedToCreateDescriptorPool,
+  #[display("Failed to create a descriptor set layout")]
   FailedToCreatedDescriptorSetLayout,
+  #[display("Failed to allocate descriptor sets")]
   FailedToAllocateDescriptorSets,
+  #[display("Failed to create a pipeline layout")]
   FailedToCreatePipelineLayout,
+  #[display("Failed to preprocess a generated shader")]
   FailedShaderPreprocess,
+  #[display("Failed to parse a generated shader")]
   FailedShaderParse,
+  #[display("Failed to link a generated shader")]
   FailedShaderLink,
+  #[display("Failed to generate SPIR-V")]
   FailedSpirvGenerate,
+  #[display("Failed to create a shader module")]
   FailedToCreateShaderModule,
+  #[display("Failed to create an instance")]
   FailedToCreateInstance,
+  #[display("Failed to set up the debug messenger")]
   FailedToSetupDebugMessenger,
+  #[display("Failed to find a physical device")]
   FailedToFindPhysicalDevice,
+  #[display("Failed to create a device")]
   FailedToCreateDevice,
+  #[display("Failed to create a fence")]
   FailedToCreateFence,
+  #[display("Failed to create a command pool")]
   FailedToCreateCommandPool,
+  #[display("Failed to create a buffer")]
   FailedToCreateBuffer,
+  #[display("Failed to allocate device memory")]
   FailedToAllocateMemory,
+  #[display("Failed to bind buffer memory")]
   FailedToBindBufferMemory,
+  #[display("Failed to find a suitable memory type")]
   FailedToFindMemory,
+  #[display("Failed to synchronize with the device")]
   FailedToSynchronize,
+  #[display("Failed to copy data")]
   FailedToCopy,
+  #[display("Failed to create a program")]
   FailedToCreateProgram,
+  #[display("Failed to compile a program")]
   FailedToCompileProgram,
+  #[display("Failed to get the size of compiled code")]
   FailedToGetCodeSize,
+  #[display("Failed to get compiled code")]
   FailedToGetCode,
+  #[display("Failed to destroy a program")]
   FailedToDestroyProgram,
+  #[display("Failed to load a module")]
   FailedToLoadModule,
+  #[display("Failed to get a kernel function")]
   FailedToGetFunction,
+  #[display("Failed to set the dynamic shared memory size")]
   FailedToSetDynamicSharedMemory,
+  #[display("Failed to get a module global")]
   FailedToModuleGetGlobal,
+  #[display("Failed to launch a kernel")]
   FailedToLaunchKernel,
+  #[display("Failed to record an event")]
   FailedToEventRecord,
+  #[display("Failed to add a name expression")]
   FailedToAddNameExpression,
+  #[display("Failed to initialize")]
   FailedToInitialize,
+  #[display("Failed to set the device ID")]
   FailedToSetDeviceId,
+  #[display("Failed to get the device")]
   FailedToGetDevice,
+  #[display("Failed to create a context")]
   FailedToCreateContext,
+  #[display("Failed to create a pipeline")]
   FailedToCreatePipeline,
+  #[display("Failed to set a kernel argument")]
   FailedToSetKernelArg,
+  #[display("Failed to create a command queue")]
   FailedToCreateCommandQueue,
+  #[display("Failed to release a command queue")]
   FailedToReleaseCommandQueue,
+  #[display("Failed to enumerate devices")]
   FailedToEnumerateDevices,
+  #[display("Failed to get a device attribute")]
+  FailedToGetAttribute,
+  #[display("Failed to create an event")]
+  FailedToCreateEvent,
+  #[display("Failed to create a command list")]
+  FailedToCreateCommandList,
+  #[display("Failed to destroy a command list")]
+  FailedToDestroyCommandList,
+  #[display("Failed to submit a barrier")]
+  FailedToSubmitBarrier,
+  /// A code this version of the bindings doesn't know
+  #[display("Unknown VkFFT error code {}", _0)]
+  Unknown(#[error(not(source))] vkfft_sys::VkFFTResult),
   Config(ConfigError),
   Launch(LaunchError),
+  /// The compiled VkFFT library doesn't match the bindings, so no application is created with it
+  Version(VersionMismatch),
+  Diagnosed(Box<Diagnostic>),
+}
+
+/// An error returned by VkFFT together with the config it occurred with, and a suggested remedy
+/// for known causes.
+#[derive(Debug)]
+pub struct Diagnostic {
+  pub error: VkfftError,
+  pub config: ConfigSummary,
+  pub remedy: Option<&'static str>,
+}
+
+impl std::fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} (config: {})", self.error, self.config)?;
+    if let Some(remedy) = self.remedy {
+      write!(f, "; {}", remedy)?;
+    }
+    Ok(())
+  }
 }
 
+impl std::error::Error for Diagnostic {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    Some(&self.error)
+  }
+}
+
+impl VkfftError {
+  /// The underlying error, without any diagnostic attached.
+  pub fn root(&self) -> &VkfftError {
+    match self {
+      Self::Diagnosed(diagnostic) => diagnostic.error.root(),
+      _ => self,
+    }
+  }
+
+  /// The raw `VkFFTResult` code of the error, or `None` for errors raised by this crate's own
+  /// checks.
+  #[allow(non_upper_case_globals)]
+  pub fn code(&self) -> Option<vkfft_sys::VkFFTResult> {
+    use vkfft_sys::*;
+
+    Some(match self {
+      Self::MallocFailed => VkFFTResult_VKFFT_ERROR_MALLOC_FAILED,
+      Self::InsufficientCodeBuffer => VkFFTResult_VKFFT_ERROR_INSUFFICIENT_CODE_BUFFER,
+      Self::InsufficientTempBuffer => VkFFTResult_VKFFT_ERROR_INSUFFICIENT_TEMP_BUFFER,
+      Self::PlanNotInitialized => VkFFTResult_VKFFT_ERROR_PLAN_NOT_INITIALIZED,
+      Self::NullTempPassed => VkFFTResult_VKFFT_ERROR_NULL_TEMP_PASSED,
+      Self::MathFailed => VkFFTResult_VKFFT_ERROR_MATH_FAILED,
+      Self::FftDimGtMaxFftDimensions => VkFFTResult_VKFFT_ERROR_FFTdim_GT_MAX_FFT_DIMENSIONS,
+      Self::NonzeroAppInitialization => VkFFTResult_VKFFT_ERROR_NONZERO_APP_INITIALIZATION,
+      Self::InvalidPhysicalDevice => VkFFTResult_VKFFT_ERROR_INVALID_PHYSICAL_DEVICE,
+      Self::InvalidDevice => VkFFTResult_VKFFT_ERROR_INVALID_DEVICE,
+      Self::InvalidQueue => VkFFTResult_VKFFT_ERROR_INVALID_QUEUE,
+      Self::InvalidCommandPool => VkFFTResult_VKFFT_ERROR_INVALID_COMMAND_POOL,
+      Self::InvalidFence => VkFFTResult_VKFFT_ERROR_INVALID_FENCE,
+      Self::OnlyForwardFftInitialized => VkFFTResult_VKFFT_ERROR_ONLY_FORWARD_FFT_INITIALIZED,
+      Self::OnlyInverseFftInitialized => VkFFTResult_VKFFT_ERROR_ONLY_INVERSE_FFT_INITIALIZED,
+      Self::InvalidContext => VkFFTResult_VKFFT_ERROR_INVALID_CONTEXT,
+      Self::InvalidPlatform => VkFFTResult_VKFFT_ERROR_INVALID_PLATFORM,
+      Self::EnabledSaveApplicationToString => {
+        VkFFTResult_VKFFT_ERROR_ENABLED_saveApplicationToString
+      }
+      Self::EmptyFile => VkFFTResult_VKFFT_ERROR_EMPTY_FILE,
+      Self::EmptyFftDim => VkFFTResult_VKFFT_ERROR_EMPTY_FFTdim,
+      Self::EmptySize => VkFFTResult_VKFFT_ERROR_EMPTY_size,
+      Self::EmptyBufferSize => VkFFTResult_VKFFT_ERROR_EMPTY_bufferSize,
+      Self::EmptyBuffer => VkFFTResult_VKFFT_ERROR_EMPTY_buffer,
+      Self::EmptyTempBufferSize => VkFFTResult_VKFFT_ERROR_EMPTY_tempBufferSize,
+      Self::EmptyTempBuffer => VkFFTResult_VKFFT_ERROR_EMPTY_tempBuffer,
+      Self::EmptyInputBufferSize => VkFFTResult_VKFFT_ERROR_EMPTY_inputBufferSize,
+      Self::EmptyInputBuffer => VkFFTResult_VKFFT_ERROR_EMPTY_inputBuffer,
+      Self::EmptyOutputBufferSize => VkFFTResult_VKFFT_ERROR_EMPTY_outputBufferSize,
+      Self::EmptyOutputBuffer => VkFFTResult_VKFFT_ERROR_EMPTY_outputBuffer,
+      Self::EmptyKernelSize => VkFFTResult_VKFFT_ERROR_EMPTY_kernelSize,
+      Self::EmptyKernel => VkFFTResult_VKFFT_ERROR_EMPTY_kernel,
+      Self::EmptyApplicationString => VkFFTResult_VKFFT_ERROR_EMPTY_applicationString,
+      Self::EmptyCustomBluesteinPaddingPatternArrays => {
+        VkFFTResult_VKFFT_ERROR_EMPTY_useCustomBluesteinPaddingPattern_arrays
+      }
+      Self::EmptyApp => VkFFTResult_VKFFT_ERROR_EMPTY_app,
+      Self::UserTempBufferTooSmall => VkFFTResult_VKFFT_ERROR_INVALID_user_tempBuffer_too_small,
+      Self::UnsupportedRadix => VkFFTResult_VKFFT_ERROR_UNSUPPORTED_RADIX,
+      Self::UnsupportedFftLength => VkFFTResult_VKFFT_ERROR_UNSUPPORTED_FFT_LENGTH,
+      Self::UnsupportedFftLengthR2C => VkFFTResult_VKFFT_ERROR_UNSUPPORTED_FFT_LENGTH_R2C,
+      Self::UnsupportedFftLengthR2R => VkFFTResult_VKFFT_ERROR_UNSUPPORTED_FFT_LENGTH_R2R,
+      Self::UnsupportedFftOmit => VkFFTResult_VKFFT_ERROR_UNSUPPORTED_FFT_OMIT,
+      Self::FailedToAllocate => VkFFTResult_VKFFT_ERROR_FAILED_TO_ALLOCATE,
+      Self::FailedToMapMemory => VkFFTResult_VKFFT_ERROR_FAILED_TO_MAP_MEMORY,
+      Self::FailedToAllocateCommandBuffers => {
+        VkFFTResult_VKFFT_ERROR_FAILED_TO_ALLOCATE_COMMAND_BUFFERS
+      }
+      Self::FailedToBeginCommandBuffer => VkFFTResult_VKFFT_ERROR_FAILED_TO_BEGIN_COMMAND_BUFFER,
+      Self::FailedToEndCommandBuffer => VkFFTResult_VKFFT_ERROR_FAILED_TO_END_COMMAND_BUFFER,
+      Self::FailedToSubmitQueue => VkFFTResult_VKFFT_ERROR_FAILED_TO_SUBMIT_QUEUE,
+      Self::FailedToWaitForFences => VkFFTResult_VKFFT_ERROR_FAILED_TO_WAIT_FOR_FENCES,
+      Self::FailedToResetFences => VkFFTResult_VKFFT_ERROR_FAILED_TO_RESET_FENCES,
+      Self::FailedToCreateDescriptorPool => {
+        VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_DESCRIPTOR_POOL
+      }
+      Self::FailedToCreatedDescriptorSetLayout => {
+        VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_DESCRIPTOR_SET_LAYOUT
+      }
+      Self::FailedToAllocateDescriptorSets => {
+        VkFFTResult_VKFFT_ERROR_FAILED_TO_ALLOCATE_DESCRIPTOR_SETS
+      }
+      Self::FailedToCreatePipelineLayout => {
+        VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_PIPELINE_LAYOUT
+      }
+      Self::FailedShaderPreprocess => VkFFTResult_VKFFT_ERROR_FAILED_SHADER_PREPROCESS,
+      Self::FailedShaderParse => VkFFTResult_VKFFT_ERROR_FAILED_SHADER_PARSE,
+      Self::FailedShaderLink => VkFFTResult_VKFFT_ERROR_FAILED_SHADER_LINK,
+      Self::FailedSpirvGenerate => VkFFTResult_VKFFT_ERROR_FAILED_SPIRV_GENERATE,
+      Self::FailedToCreateShaderModule => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_SHADER_MODULE,
+      Self::FailedToCreateInstance => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_INSTANCE,
+      Self::FailedToSetupDebugMessenger => VkFFTResult_VKFFT_ERROR_FAILED_TO_SETUP_DEBUG_MESSENGER,
+      Self::FailedToFindPhysicalDevice => VkFFTResult_VKFFT_ERROR_FAILED_TO_FIND_PHYSICAL_DEVICE,
+      Self::FailedToCreateDevice => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_DEVICE,
+      Self::FailedToCreateFence => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_FENCE,
+      Self::FailedToCreateCommandPool => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_COMMAND_POOL,
+      Self::FailedToCreateBuffer => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_BUFFER,
+      Self::FailedToAllocateMemory => VkFFTResult_VKFFT_ERROR_FAILED_TO_ALLOCATE_MEMORY,
+      Self::FailedToBindBufferMemory => VkFFTResult_VKFFT_ERROR_FAILED_TO_BIND_BUFFER_MEMORY,
+      Self::FailedToFindMemory => VkFFTResult_VKFFT_ERROR_FAILED_TO_FIND_MEMORY,
+      Self::FailedToSynchronize => VkFFTResult_VKFFT_ERROR_FAILED_TO_SYNCHRONIZE,
+      Self::FailedToCopy => VkFFTResult_VKFFT_ERROR_FAILED_TO_COPY,
+      Self::FailedToCreateProgram => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_PROGRAM,
+      Self::FailedToCompileProgram => VkFFTResult_VKFFT_ERROR_FAILED_TO_COMPILE_PROGRAM,
+      Self::FailedToGetCodeSize => VkFFTResult_VKFFT_ERROR_FAILED_TO_GET_CODE_SIZE,
+      Self::FailedToGetCode => VkFFTResult_VKFFT_ERROR_FAILED_TO_GET_CODE,
+      Self::FailedToDestroyProgram => VkFFTResult_VKFFT_ERROR_FAILED_TO_DESTROY_PROGRAM,
+      Self::FailedToLoadModule => VkFFTResult_VKFFT_ERROR_FAILED_TO_LOAD_MODULE,
+      Self::FailedToGetFunction => VkFFTResult_VKFFT_ERROR_FAILED_TO_GET_FUNCTION,
+      Self::FailedToSetDynamicSharedMemory => {
+        VkFFTResult_VKFFT_ERROR_FAILED_TO_SET_DYNAMIC_SHARED_MEMORY
+      }
+      Self::FailedToModuleGetGlobal => VkFFTResult_VKFFT_ERROR_FAILED_TO_MODULE_GET_GLOBAL,
+      Self::FailedToLaunchKernel => VkFFTResult_VKFFT_ERROR_FAILED_TO_LAUNCH_KERNEL,
+      Self::FailedToEventRecord => VkFFTResult_VKFFT_ERROR_FAILED_TO_EVENT_RECORD,
+      Self::FailedToAddNameExpression => VkFFTResult_VKFFT_ERROR_FAILED_TO_ADD_NAME_EXPRESSION,
+      Self::FailedToInitialize => VkFFTResult_VKFFT_ERROR_FAILED_TO_INITIALIZE,
+      Self::FailedToSetDeviceId => VkFFTResult_VKFFT_ERROR_FAILED_TO_SET_DEVICE_ID,
+      Self::FailedToGetDevice => VkFFTResult_VKFFT_ERROR_FAILED_TO_GET_DEVICE,
+      Self::FailedToCreateContext => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_CONTEXT,
+      Self::FailedToCreatePipeline => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_PIPELINE,
+      Self::FailedToSetKernelArg => VkFFTResult_VKFFT_ERROR_FAILED_TO_SET_KERNEL_ARG,
+      Self::FailedToCreateCommandQueue => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_COMMAND_QUEUE,
+      Self::FailedToReleaseCommandQueue => VkFFTResult_VKFFT_ERROR_FAILED_TO_RELEASE_COMMAND_QUEUE,
+      Self::FailedToEnumerateDevices => VkFFTResult_VKFFT_ERROR_FAILED_TO_ENUMERATE_DEVICES,
+      Self::FailedToGetAttribute => VkFFTResult_VKFFT_ERROR_FAILED_TO_GET_ATTRIBUTE,
+      Self::FailedToCreateEvent => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_EVENT,
+      Self::FailedToCreateCommandList => VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_COMMAND_LIST,
+      Self::FailedToDestroyCommandList => VkFFTResult_VKFFT_ERROR_FAILED_TO_DESTROY_COMMAND_LIST,
+      Self::FailedToSubmitBarrier => VkFFTResult_VKFFT_ERROR_FAILED_TO_SUBMIT_BARRIER,
+      Self::Unknown(code) => *code,
+      Self::Diagnosed(diagnostic) => return diagnostic.error.code(),
+      Self::Config(_) | Self::Launch(_) | Self::Version(_) => return None,
+    })
+  }
+
+  /// A suggestion for fixing the config or environment, for errors with a known cause.
+  pub fn remedy(&self) -> Option<&'static str> {
+    match self.root() {
+      Self::EmptyBuffer | Self::EmptyBufferSize => {
+        Some("provide `buffer` in the config or the launch parameters")
+      }
+      Self::EmptyTempBuffer | Self::EmptyTempBufferSize | Self::UserTempBufferTooSmall => {
+        Some("provide `temp_buffer`, which this plan needs for its intermediate results")
+      }
+      Self::EmptyInputBuffer | Self::EmptyInputBufferSize => {
+        Some("provide `input_buffer`, or don't set `input_formatted`")
+      }
+      Self::EmptyOutputBuffer | Self::EmptyOutputBufferSize => {
+        Some("provide `output_buffer`, or don't set `output_formatted`")
+      }
+      Self::EmptyKernel | Self::EmptyKernelSize => {
+        Some("provide `kernel` with the spectrum to convolve with")
+      }
+      Self::EmptyFftDim | Self::EmptySize => Some("set the transform size with `dim`"),
+      Self::UnsupportedRadix | Self::UnsupportedFftLength => {
+        Some("use a size whose prime factors are at most 13, or pad the data to such a size")
+      }
+      Self::UnsupportedFftLengthR2C => {
+        Some("use an even size for the first axis, or run a C2C transform instead")
+      }
+      Self::MallocFailed
+      | Self::FailedToAllocate
+      | Self::FailedToAllocateMemory
+      | Self::FailedToFindMemory => {
+        Some("reduce the size or batch count, or provide `temp_buffer` to avoid allocations")
+      }
+      Self::FailedShaderPreprocess
+      | Self::FailedShaderParse
+      | Self::FailedShaderLink
+      | Self::FailedSpirvGenerate
+      | Self::FailedToCreateShaderModule
+      | Self::FailedToCreatePipeline => Some(
+        "the generated kernel may exceed the device's shared memory or register limits; \
+         keep the four-step reordering enabled, or lower `VKFFT_RS_REGISTER_BOOST` and \
+         `VKFFT_RS_COALESCED_MEMORY`",
+      ),
+      Self::EmptyApplicationString => Some("the serialized application passed in is empty"),
+      Self::InvalidPhysicalDevice
+      | Self::InvalidDevice
+      | Self::InvalidQueue
+      | Self::InvalidCommandPool
+      | Self::InvalidFence => Some("set the device, queue, command pool and fence"),
+      _ => None,
+    }
+  }
+
+  /// Attaches `config` and the remedy for this error, if any.
+  pub(crate) fn diagnose(self, config: ConfigSummary) -> Self {
+    if let Self::Diagnosed(_) = self {
+      return self;
+    }
+
+    let remedy = self.remedy();
+    Self::Diagnosed(Box::new(Diagnostic {
+      error: self,
+      config,
+      remedy,
+    }))
+  }
+}
+
+/// Fails only for `VKFFT_SUCCESS`.
 impl TryFrom<vkfft_sys::VkFFTResult> for VkfftError {
   type Error = ();
 
@@ -92,6 +456,14 @@ impl TryFrom<vkfft_sys::VkFFTResult> for VkfftError {
     use vkfft_sys::*;
 
     match value {
+      VkFFTResult_VKFFT_ERROR_MALLOC_FAILED => Ok(Self::MallocFailed),
+      VkFFTResult_VKFFT_ERROR_INSUFFICIENT_CODE_BUFFER => Ok(Self::InsufficientCodeBuffer),
+      VkFFTResult_VKFFT_ERROR_INSUFFICIENT_TEMP_BUFFER => Ok(Self::InsufficientTempBuffer),
+      VkFFTResult_VKFFT_ERROR_PLAN_NOT_INITIALIZED => Ok(Self::PlanNotInitialized),
+      VkFFTResult_VKFFT_ERROR_NULL_TEMP_PASSED => Ok(Self::NullTempPassed),
+      VkFFTResult_VKFFT_ERROR_MATH_FAILED => Ok(Self::MathFailed),
+      VkFFTResult_VKFFT_ERROR_FFTdim_GT_MAX_FFT_DIMENSIONS => Ok(Self::FftDimGtMaxFftDimensions),
+      VkFFTResult_VKFFT_ERROR_NONZERO_APP_INITIALIZATION => Ok(Self::NonzeroAppInitialization),
       VkFFTResult_VKFFT_ERROR_INVALID_PHYSICAL_DEVICE => Ok(Self::InvalidPhysicalDevice),
       VkFFTResult_VKFFT_ERROR_INVALID_DEVICE => Ok(Self::InvalidDevice),
       VkFFTResult_VKFFT_ERROR_INVALID_QUEUE => Ok(Self::InvalidQueue),
@@ -101,6 +473,10 @@ impl TryFrom<vkfft_sys::VkFFTResult> for VkfftError {
       VkFFTResult_VKFFT_ERROR_ONLY_INVERSE_FFT_INITIALIZED => Ok(Self::OnlyInverseFftInitialized),
       VkFFTResult_VKFFT_ERROR_INVALID_CONTEXT => Ok(Self::InvalidContext),
       VkFFTResult_VKFFT_ERROR_INVALID_PLATFORM => Ok(Self::InvalidPlatform),
+      VkFFTResult_VKFFT_ERROR_ENABLED_saveApplicationToString => {
+        Ok(Self::EnabledSaveApplicationToString)
+      }
+      VkFFTResult_VKFFT_ERROR_EMPTY_FILE => Ok(Self::EmptyFile),
       VkFFTResult_VKFFT_ERROR_EMPTY_FFTdim => Ok(Self::EmptyFftDim),
       VkFFTResult_VKFFT_ERROR_EMPTY_size => Ok(Self::EmptySize),
       VkFFTResult_VKFFT_ERROR_EMPTY_bufferSize => Ok(Self::EmptyBufferSize),
@@ -113,9 +489,17 @@ impl TryFrom<vkfft_sys::VkFFTResult> for VkfftError {
       VkFFTResult_VKFFT_ERROR_EMPTY_outputBuffer => Ok(Self::EmptyOutputBuffer),
       VkFFTResult_VKFFT_ERROR_EMPTY_kernelSize => Ok(Self::EmptyKernelSize),
       VkFFTResult_VKFFT_ERROR_EMPTY_kernel => Ok(Self::EmptyKernel),
+      VkFFTResult_VKFFT_ERROR_EMPTY_applicationString => Ok(Self::EmptyApplicationString),
+      VkFFTResult_VKFFT_ERROR_EMPTY_useCustomBluesteinPaddingPattern_arrays => {
+        Ok(Self::EmptyCustomBluesteinPaddingPatternArrays)
+      }
+      VkFFTResult_VKFFT_ERROR_EMPTY_app => Ok(Self::EmptyApp),
+      VkFFTResult_VKFFT_ERROR_INVALID_user_tempBuffer_too_small => Ok(Self::UserTempBufferTooSmall),
       VkFFTResult_VKFFT_ERROR_UNSUPPORTED_RADIX => Ok(Self::UnsupportedRadix),
       VkFFTResult_VKFFT_ERROR_UNSUPPORTED_FFT_LENGTH => Ok(Self::UnsupportedFftLength),
       VkFFTResult_VKFFT_ERROR_UNSUPPORTED_FFT_LENGTH_R2C => Ok(Self::UnsupportedFftLengthR2C),
+      VkFFTResult_VKFFT_ERROR_UNSUPPORTED_FFT_LENGTH_R2R => Ok(Self::UnsupportedFftLengthR2R),
+      VkFFTResult_VKFFT_ERROR_UNSUPPORTED_FFT_OMIT => Ok(Self::UnsupportedFftOmit),
       VkFFTResult_VKFFT_ERROR_FAILED_TO_ALLOCATE => Ok(Self::FailedToAllocate),
       VkFFTResult_VKFFT_ERROR_FAILED_TO_MAP_MEMORY => Ok(Self::FailedToMapMemory),
       VkFFTResult_VKFFT_ERROR_FAILED_TO_ALLOCATE_COMMAND_BUFFERS => {
@@ -190,7 +574,15 @@ impl TryFrom<vkfft_sys::VkFFTResult> for VkfftError {
         Ok(Self::FailedToReleaseCommandQueue)
       }
       VkFFTResult_VKFFT_ERROR_FAILED_TO_ENUMERATE_DEVICES => Ok(Self::FailedToEnumerateDevices),
-      _ => Err(()),
+      VkFFTResult_VKFFT_ERROR_FAILED_TO_GET_ATTRIBUTE => Ok(Self::FailedToGetAttribute),
+      VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_EVENT => Ok(Self::FailedToCreateEvent),
+      VkFFTResult_VKFFT_ERROR_FAILED_TO_CREATE_COMMAND_LIST => Ok(Self::FailedToCreateCommandList),
+      VkFFTResult_VKFFT_ERROR_FAILED_TO_DESTROY_COMMAND_LIST => {
+        Ok(Self::FailedToDestroyCommandList)
+      }
+      VkFFTResult_VKFFT_ERROR_FAILED_TO_SUBMIT_BARRIER => Ok(Self::FailedToSubmitBarrier),
+      VkFFTResult_VKFFT_SUCCESS => Err(()),
+      code => Ok(Self::Unknown(code)),
     }
   }
 }
@@ -207,6 +599,12 @@ impl From<LaunchError> for VkfftError {
   }
 }
 
+impl From<VersionMismatch> for VkfftError {
+  fn from(e: VersionMismatch) -> Self {
+    Self::Version(e)
+  }
+}
+
 pub(crate) fn check_error(result: vkfft_sys::VkFFTResult) -> Result<()> {
   match result.try_into() {
     Ok(err) => Err(err),
@@ -215,3 +613,192 @@ pub(crate) fn check_error(result: vkfft_sys::VkFFTResult) -> Result<()> {
 }
 
 pub type Result<T> = std::result::Result<T, VkfftError>;
+
+/// Any error returned by the crate's contexts, so callers can match on the failure instead of
+/// inspecting a boxed error.
+#[derive(Display, Debug, Error)]
+pub enum Error {
+  /// The Vulkan library could not be loaded
+  #[cfg(feature = "vulkano")]
+  #[display("Failed to load Vulkan: {}", _0)]
+  Loading(LoadingError),
+  /// A config is missing a handle or can't be built
+  Build(config::BuildError),
+  /// Launch parameters are missing a command buffer
+  LaunchParams(app::BuildError),
+  /// VkFFT rejected a config or launch, or failed
+  Vkfft(VkfftError),
+  /// A Vulkan call failed
+  #[cfg(feature = "vulkano")]
+  Vulkan(VulkanError),
+  /// vulkano rejected the parameters of a call
+  #[cfg(feature = "vulkano")]
+  Validation(Box<ValidationError>),
+  #[cfg(feature = "vulkano")]
+  #[display("Failed to allocate a buffer: {}", _0)]
+  AllocateBuffer(AllocateBufferError),
+  #[cfg(feature = "vulkano")]
+  #[display("Failed to access a buffer from the host: {}", _0)]
+  HostAccess(HostAccessError),
+  #[cfg(feature = "vulkano")]
+  Submit(SubmitError),
+  #[cfg(feature = "vulkano")]
+  DeviceSelection(DeviceSelectionError),
+  /// A compute shader failed to compile, or a pass can't move its values
+  #[cfg(feature = "vulkano")]
+  Shader(ShaderError),
+  /// The physical device has no queue family matching the preference
+  #[cfg(feature = "vulkano")]
+  #[display("No queue family matches {:?}", _0)]
+  NoQueueFamily(#[error(not(source))] QueueFamilyPreference),
+  /// The wgpu device doesn't run on the Vulkan backend
+  #[cfg(feature = "wgpu")]
+  #[display("The wgpu device doesn't use the Vulkan backend")]
+  NotVulkan,
+  /// A CUDA or Level Zero driver call failed with the given result code
+  #[cfg(any(feature = "cuda", feature = "level-zero"))]
+  #[display("{} failed with {}", _0, _1)]
+  Backend(&'static str, i64),
+  /// None of the backends the crate was built with is available
+  #[cfg(any(feature = "vulkano", feature = "cuda", feature = "level-zero"))]
+  #[display("No backend is available")]
+  NoBackend,
+  /// A buffer or app was used with a backend it doesn't belong to
+  #[cfg(any(feature = "vulkano", feature = "cuda", feature = "level-zero"))]
+  #[display("The buffer or app belongs to another backend")]
+  BackendMismatch,
+  /// An argument of a GPU pass is out of range
+  #[display("Invalid {}", _0)]
+  InvalidArgument(#[error(not(source))] &'static str),
+  /// The compiled VkFFT library doesn't match the bindings
+  VersionMismatch(VersionMismatch),
+  Io(std::io::Error),
+}
+
+impl From<config::BuildError> for Error {
+  fn from(e: config::BuildError) -> Self {
+    Self::Build(e)
+  }
+}
+
+impl From<VersionMismatch> for Error {
+  fn from(e: VersionMismatch) -> Self {
+    Self::VersionMismatch(e)
+  }
+}
+
+impl From<app::BuildError> for Error {
+  fn from(e: app::BuildError) -> Self {
+    Self::LaunchParams(e)
+  }
+}
+
+impl From<VkfftError> for Error {
+  fn from(e: VkfftError) -> Self {
+    Self::Vkfft(e)
+  }
+}
+
+impl From<ConfigError> for Error {
+  fn from(e: ConfigError) -> Self {
+    Self::Vkfft(e.into())
+  }
+}
+
+impl From<LaunchError> for Error {
+  fn from(e: LaunchError) -> Self {
+    Self::Vkfft(e.into())
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl From<LoadingError> for Error {
+  fn from(e: LoadingError) -> Self {
+    Self::Loading(e)
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl From<VulkanError> for Error {
+  fn from(e: VulkanError) -> Self {
+    Self::Vulkan(e)
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl From<Box<ValidationError>> for Error {
+  fn from(e: Box<ValidationError>) -> Self {
+    Self::Validation(e)
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl<E: Into<Error>> From<Validated<E>> for Error {
+  fn from(e: Validated<E>) -> Self {
+    match e {
+      Validated::Error(e) => e.into(),
+      Validated::ValidationError(e) => Self::Validation(e),
+    }
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl From<AllocateBufferError> for Error {
+  fn from(e: AllocateBufferError) -> Self {
+    Self::AllocateBuffer(e)
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl From<HostAccessError> for Error {
+  fn from(e: HostAccessError) -> Self {
+    Self::HostAccess(e)
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl From<SubmitError> for Error {
+  fn from(e: SubmitError) -> Self {
+    Self::Submit(e)
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl From<DeviceSelectionError> for Error {
+  fn from(e: DeviceSelectionError) -> Self {
+    Self::DeviceSelection(e)
+  }
+}
+
+#[cfg(feature = "vulkano")]
+impl From<ShaderError> for Error {
+  fn from(e: ShaderError) -> Self {
+    Self::Shader(e)
+  }
+}
+
+impl From<std::io::Error> for Error {
+  fn from(e: std::io::Error) -> Self {
+    Self::Io(e)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_codes() {
+    assert!(VkfftError::try_from(vkfft_sys::VkFFTResult_VKFFT_SUCCESS).is_err());
+
+    let mut known = 0;
+    for code in 1..5000 {
+      let error = VkfftError::try_from(code).unwrap();
+      assert_eq!(error.code(), Some(code));
+      if !matches!(error, VkfftError::Unknown(_)) {
+        known += 1;
+      }
+    }
+    assert_eq!(known, 95);
+  }
+}
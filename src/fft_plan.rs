@@ -0,0 +1,132 @@
+//! Plans of a fixed number of axes, which hide configs, launch parameters and command buffers.
+//!
+//! [`Fft1d`], [`Fft2d`] and [`Fft3d`] compile their kernels once, when they are created, and
+//! transform any buffer of the right size in place:
+//!
+//! ```ignore
+//! let mut fft = Fft2d::c2c(&context, [1024, 512])?;
+//! fft.forward(&buffer)?;
+//! fft.inverse(&buffer)?;
+//! ```
+//!
+//! The precision follows the element type of the buffers, and inverse transforms are normalized.
+
+use std::marker::PhantomData;
+
+use vulkano::buffer::Subbuffer;
+
+use crate::{
+  config::{Config, ConfigBuilder},
+  context::{Context, FftType},
+  scalar::FftScalar,
+  stage::FftStage,
+};
+
+/// A planned transform of `N` axes, on buffers of `T`.
+pub struct FftPlan<'c, T, const N: usize> {
+  context: &'c Context,
+  forward: FftStage,
+  inverse: FftStage,
+  len: usize,
+  _scalar: PhantomData<T>,
+}
+
+/// A planned 1D transform.
+pub type Fft1d<'c, T> = FftPlan<'c, T, 1>;
+/// A planned 2D transform.
+pub type Fft2d<'c, T> = FftPlan<'c, T, 2>;
+/// A planned 3D transform.
+pub type Fft3d<'c, T> = FftPlan<'c, T, 3>;
+
+impl<'c, T: FftScalar, const N: usize> FftPlan<'c, T, N> {
+  /// Plans complex transforms of `dims`, fastest axis first, on buffers of interleaved real and
+  /// imaginary parts.
+  pub fn c2c(context: &'c Context, dims: [u32; N]) -> Result<Self, crate::Error> {
+    Self::new(
+      context,
+      Config::builder().dim(&dims),
+      padded_len(dims, false),
+    )
+  }
+
+  /// Plans real transforms of `dims`, fastest axis first. Buffers hold the rows of real values
+  /// along the first axis, each padded to `2 * (dims[0] / 2 + 1)` values to make room for its
+  /// spectrum.
+  pub fn r2c(context: &'c Context, dims: [u32; N]) -> Result<Self, crate::Error> {
+    Self::new(
+      context,
+      Config::builder().dim(&dims).r2c(),
+      padded_len(dims, true),
+    )
+  }
+
+  fn new(
+    context: &'c Context,
+    config_builder: ConfigBuilder,
+    len: usize,
+  ) -> Result<Self, crate::Error> {
+    let config_builder = config_builder
+      .normalize()
+      .precision(T::PRECISION)
+      .buffer_size((len * std::mem::size_of::<T>()) as u64);
+    let forward = context.fft_stage(config_builder, FftType::Forward)?;
+    let inverse = forward.reversed();
+    Ok(Self {
+      context,
+      forward,
+      inverse,
+      len,
+      _scalar: PhantomData,
+    })
+  }
+
+  /// The number of values buffers of the plan hold.
+  pub fn buffer_len(&self) -> usize {
+    self.len
+  }
+
+  /// Transforms `buffer` in place, blocking until the transform has completed. Buffers of fewer
+  /// than [`FftPlan::buffer_len`] values fail with
+  /// [`LaunchError::LaunchBufferTooSmall`](crate::app::LaunchError::LaunchBufferTooSmall).
+  pub fn forward(&mut self, buffer: &Subbuffer<[T]>) -> Result<(), crate::Error> {
+    run(self.context, &mut self.forward, buffer)
+  }
+
+  /// The normalized inverse of [`FftPlan::forward`].
+  pub fn inverse(&mut self, buffer: &Subbuffer<[T]>) -> Result<(), crate::Error> {
+    run(self.context, &mut self.inverse, buffer)
+  }
+}
+
+fn run<T>(
+  context: &Context,
+  stage: &mut FftStage,
+  buffer: &Subbuffer<[T]>,
+) -> Result<(), crate::Error> {
+  stage.set_buffer(buffer.clone());
+  context.run(stage)
+}
+
+/// The number of values in buffers of transforms of `dims`, with real rows padded for their
+/// spectrum.
+fn padded_len<const N: usize>(dims: [u32; N], r2c: bool) -> usize {
+  let mut dims = dims.map(|dim| dim as usize);
+  if r2c {
+    dims[0] = 2 * (dims[0] / 2 + 1);
+  } else {
+    dims[0] *= 2;
+  }
+  dims.iter().product()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::padded_len;
+
+  #[test]
+  fn check_buffer_len() {
+    assert_eq!(padded_len([8, 4], false), 64);
+    assert_eq!(padded_len([8, 4], true), 40);
+    assert_eq!(padded_len([7], true), 8);
+  }
+}
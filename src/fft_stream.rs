@@ -0,0 +1,116 @@
+//! Running the same transform on new data every frame.
+//!
+//! An [`FftStream`] plans a transform once, records it into a command buffer that can be
+//! submitted repeatedly, and owns the fence its submissions signal, so each frame only takes
+//! writing the buffer and calling [`FftStream::run`]:
+//!
+//! ```ignore
+//! let config_builder = Config::builder().dim(&[4096]).buffer(buffer.clone());
+//! let mut stream = FftStream::new(&context, config_builder, FftType::Forward)?;
+//! loop {
+//!   buffer.write()?.copy_from_slice(&next_frame());
+//!   stream.run()?;
+//!   show(&buffer.read()?);
+//! }
+//! ```
+
+use std::sync::Arc;
+
+use vulkano::{
+  command_buffer::{CommandBuffer, CommandBufferUsage},
+  sync::fence::{Fence, FenceCreateInfo},
+  VulkanObject,
+};
+
+use crate::{
+  config::ConfigBuilder,
+  context::{wait_fence, Context, FftType},
+  stage::FftStage,
+};
+
+/// A planned transform with a pre-recorded command buffer and a fence of its own.
+pub struct FftStream<'c> {
+  context: &'c Context,
+  fence: Arc<Fence>,
+  command_buffer: Arc<CommandBuffer>,
+  /// Whether a submission signals the fence and its signal hasn't been observed yet
+  pending: bool,
+  // The command buffer refers to the stage's app, so it is dropped first
+  _stage: FftStage,
+}
+
+impl<'c> FftStream<'c> {
+  /// Plans the transform `config_builder` describes, which must set its buffers, on the
+  /// context's device.
+  pub fn new(
+    context: &'c Context,
+    config_builder: ConfigBuilder,
+    fft_type: FftType,
+  ) -> Result<Self, crate::Error> {
+    Self::from_stage(context, context.fft_stage(config_builder, fft_type)?)
+  }
+
+  /// Records `stage` once, for running it with [`FftStream::run`].
+  pub fn from_stage(context: &'c Context, mut stage: FftStage) -> Result<Self, crate::Error> {
+    let command_buffer =
+      context.record_with_usage(&mut stage, CommandBufferUsage::MultipleSubmit)?;
+    Ok(Self {
+      context,
+      fence: Arc::new(Fence::new(
+        context.device.clone(),
+        FenceCreateInfo::default(),
+      )?),
+      command_buffer: Arc::new(command_buffer),
+      pending: false,
+      _stage: stage,
+    })
+  }
+
+  /// Submits the transform and blocks until it has completed, or until the context's
+  /// [`submit_timeout`](Context::with_submit_timeout) has passed. After a timeout, the next run
+  /// first waits for the pending one.
+  pub fn run(&mut self) -> Result<(), crate::Error> {
+    self.submit()?;
+    wait_fence(&self.fence, self.context.submit_timeout)?;
+    self.reset()
+  }
+
+  /// Like [`FftStream::run`], but awaits completion instead of blocking the calling thread.
+  #[cfg(feature = "async")]
+  pub async fn run_async(&mut self) -> Result<(), crate::Error> {
+    self.submit()?;
+    crate::future::FenceFuture::new(self.fence.clone(), self.command_buffer.clone()).await?;
+    self.reset()
+  }
+
+  /// Submits the command buffer once the previous submission, if it is still pending, has
+  /// completed.
+  fn submit(&mut self) -> Result<(), crate::Error> {
+    if self.pending {
+      wait_fence(&self.fence, self.context.submit_timeout)?;
+      self.reset()?;
+    }
+    self
+      .context
+      .submit_signaling(self.command_buffer.handle(), &self.fence)?;
+    self.pending = true;
+    Ok(())
+  }
+
+  fn reset(&mut self) -> Result<(), crate::Error> {
+    // The fence was waited on, so no submission uses it any more
+    unsafe { self.fence.reset_unchecked() }?;
+    self.pending = false;
+    Ok(())
+  }
+}
+
+impl Drop for FftStream<'_> {
+  fn drop(&mut self) {
+    // The command buffer and the stage's app may still be in use by a submission that timed out
+    // or whose future was dropped
+    if self.pending {
+      let _ = self.fence.wait(None);
+    }
+  }
+}
@@ -0,0 +1,228 @@
+//! GPU passes moving the zero-frequency bin of 1D, 2D and 3D arrays to the center and back, like
+//! numpy's `fftshift` and `ifftshift`.
+//!
+//! Shifts are stages, so they can be recorded into the same command buffer as the transform:
+//!
+//! ```ignore
+//! let geometry = ShiftGeometry::new(&[512, 512], ShiftValues::Complex)?;
+//! let shift = FftShift::new::<f32>(&context, spectrum, centered, geometry)?;
+//! context.run(&mut Chain::new(context.device.clone()).push(fft).push(shift))?;
+//! ```
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  scalar::FftScalar,
+  shader::{group_counts, words_per_value, Kernel},
+  stage::Stage,
+};
+
+const SHIFT: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) readonly buffer Input { uint src[]; };
+layout(std430, binding = 1) writeonly buffer Output { uint dst[]; };
+layout(push_constant) uniform Params {
+  uvec3 size;
+  uint batches;
+  uvec3 shift;
+  uint words;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= size.x * size.y * size.z * batches * words) {
+    return;
+  }
+
+  uint word = i % words;
+  uint value = i / words;
+  uint x = value % size.x;
+  value /= size.x;
+  uint y = value % size.y;
+  value /= size.y;
+  uint z = value % size.z;
+  uint batch = value / size.z;
+
+  uvec3 from = (uvec3(x, y, z) + shift) % size;
+  dst[i] = src[(((batch * size.z + from.z) * size.y + from.y) * size.x + from.x) * words + word];
+}
+"#;
+
+/// Whether shifted arrays hold real or interleaved complex values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftValues {
+  Real,
+  Complex,
+}
+
+/// Shape of the arrays a shift moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShiftGeometry {
+  /// Size of each axis, fastest first, with unused axes of size 1
+  pub dims: [u32; 3],
+  /// Number of arrays stored back to back, each shifted on its own
+  pub batches: u32,
+  pub values: ShiftValues,
+}
+
+impl ShiftGeometry {
+  /// Arrays of up to three axes, fastest first.
+  pub fn new<const N: usize>(dims: &[u32; N], values: ShiftValues) -> Result<Self, ConfigError> {
+    if !(1..=3).contains(&N) {
+      return Err(ConfigError::AxisCount(N));
+    }
+    let mut padded = [1; 3];
+    padded[..N].copy_from_slice(dims);
+    let geometry = Self {
+      dims: padded,
+      batches: 1,
+      values,
+    };
+    geometry.check(1)?;
+    Ok(geometry)
+  }
+
+  pub fn batches(mut self, batches: u32) -> Result<Self, ConfigError> {
+    self.batches = batches;
+    self.check(1)?;
+    Ok(self)
+  }
+
+  /// Number of 32-bit words the shift reads and writes, for values of `words` words each.
+  fn len(&self, words: u32) -> u64 {
+    let [x, y, z] = self.dims;
+    x as u64 * y as u64 * z as u64 * self.batches as u64 * words as u64
+  }
+
+  /// Rejects empty arrays and ones whose indices overflow the shader's 32-bit index math.
+  fn check(&self, words: u32) -> Result<(), ConfigError> {
+    if self.dims.contains(&0) || self.batches == 0 {
+      return Err(ConfigError::ZeroSize("shift geometry"));
+    }
+    let shifted = |dim: u32| dim as u64 + dim.div_ceil(2) as u64;
+    if self.len(words) > u32::MAX as u64
+      || self.dims.iter().any(|&dim| shifted(dim) > u32::MAX as u64)
+    {
+      return Err(ConfigError::SizeOverflow("shift geometry"));
+    }
+    Ok(())
+  }
+
+  /// The offset added to each output index, modulo the axis size, to find the value moved there.
+  fn shifts(&self, inverse: bool) -> [u32; 3] {
+    self
+      .dims
+      .map(|dim| if inverse { dim / 2 } else { dim.div_ceil(2) })
+  }
+
+  fn kernel<T: FftScalar>(
+    &self,
+    context: &Context,
+    input: Arc<Buffer>,
+    output: Arc<Buffer>,
+    inverse: bool,
+  ) -> Result<Kernel, crate::Error> {
+    let components = match self.values {
+      ShiftValues::Real => 1,
+      ShiftValues::Complex => 2,
+    };
+    let words = words_per_value(components * std::mem::size_of::<T>())?;
+    self.check(words)?;
+    let len = self.len(words);
+    if input.size() / 4 < len {
+      return Err(ConfigError::BufferTooSmall("input").into());
+    }
+    if output.size() / 4 < len {
+      return Err(ConfigError::BufferTooSmall("output").into());
+    }
+    let [x, y, z] = self.dims;
+    let [shift_x, shift_y, shift_z] = self.shifts(inverse);
+
+    Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      SHIFT,
+      [input, output],
+      vec![x, y, z, self.batches, shift_x, shift_y, shift_z, words],
+      group_counts(len),
+    )
+  }
+}
+
+/// Moves the zero-frequency bin of spectra in `input` to the center of the arrays in `output`.
+pub struct FftShift {
+  kernel: Kernel,
+}
+
+impl FftShift {
+  /// Shifts values of `T`, e.g. `f32` for single precision spectra. `input` and `output` must be
+  /// different buffers. Complex `f16` spectra can be shifted, but real `f16` arrays fail.
+  pub fn new<T: FftScalar>(
+    context: &Context,
+    input: Arc<Buffer>,
+    output: Arc<Buffer>,
+    geometry: ShiftGeometry,
+  ) -> Result<Self, crate::Error> {
+    Ok(Self {
+      kernel: geometry.kernel::<T>(context, input, output, false)?,
+    })
+  }
+}
+
+impl Stage for FftShift {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+/// The inverse of [`FftShift`], moving the center of the arrays back to index zero, e.g. before
+/// an inverse transform.
+pub struct IfftShift {
+  kernel: Kernel,
+}
+
+impl IfftShift {
+  /// Like [`FftShift::new`].
+  pub fn new<T: FftScalar>(
+    context: &Context,
+    input: Arc<Buffer>,
+    output: Arc<Buffer>,
+    geometry: ShiftGeometry,
+  ) -> Result<Self, crate::Error> {
+    Ok(Self {
+      kernel: geometry.kernel::<T>(context, input, output, true)?,
+    })
+  }
+}
+
+impl Stage for IfftShift {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_shifts() {
+    let geometry = ShiftGeometry::new(&[5, 4], ShiftValues::Real).unwrap();
+    assert_eq!(geometry.dims, [5, 4, 1]);
+    // numpy's fftshift of [0, 1, 2, 3, 4] is [3, 4, 0, 1, 2], so output 0 reads input 3
+    assert_eq!(geometry.shifts(false), [3, 2, 1]);
+    assert_eq!(geometry.shifts(true), [2, 2, 0]);
+
+    assert!(ShiftGeometry::new(&[5, 0], ShiftValues::Real).is_err());
+    assert!(geometry.batches(0).is_err());
+    assert!(geometry.batches(1 << 30).is_err());
+  }
+}
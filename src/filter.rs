@@ -0,0 +1,287 @@
+//! Frequency-domain filtering with real-valued masks.
+//!
+//! A [`SpectralFilter`] multiplies every bin of a spectrum by the matching value of a mask.
+//! Masks are either user-provided buffers or generated from a [`MaskShape`], and can be placed
+//! between a forward and an inverse transform with [`SpectralFilter::between`].
+//!
+//! ```ignore
+//! let layout = SpectrumLayout::new(&[512, 512], true)?;
+//! let config_builder = Config::builder()
+//!   .buffer(data.clone())
+//!   .dim(&[512, 512])
+//!   .r2c()
+//!   .normalize();
+//! let forward = context.fft_stage(config_builder, FftType::Forward)?;
+//! let shape = MaskShape::LowPass { cutoff: 0.1 };
+//! let filter = SpectralFilter::shaped(&context, data, shape, layout)?;
+//! context.run(&mut filter.between(forward))?;
+//! ```
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::{buffer::Buffer, device::Device};
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  shader::{group_counts, Kernel},
+  stage::{Chain, FftStage, Stage},
+};
+
+const APPLY_MASK: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) buffer Data { vec2 data[]; };
+layout(std430, binding = 1) readonly buffer Mask { float mask[]; };
+layout(push_constant) uniform Params { uint mask_len; uint total; };
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= total) {
+    return;
+  }
+  data[i] *= mask[i % mask_len];
+}
+"#;
+
+/// Shape of the spectrum a mask applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpectrumLayout {
+  /// Transform size along each axis, fastest first. Unused axes are 1.
+  pub size: [u32; 3],
+  /// Whether the spectrum is the half spectrum of an R2C transform, storing `size[0] / 2 + 1`
+  /// bins along the first axis
+  pub r2c: bool,
+  pub batch_count: u32,
+}
+
+impl SpectrumLayout {
+  pub fn new<const N: usize>(size: &[u32; N], r2c: bool) -> Result<Self, ConfigError> {
+    if N == 0 || N > 3 {
+      return Err(ConfigError::AxisCount(N));
+    }
+    if size.contains(&0) {
+      return Err(ConfigError::ZeroSize("spectrum size"));
+    }
+
+    let mut res = Self {
+      size: [1, 1, 1],
+      r2c,
+      batch_count: 1,
+    };
+    res.size[..N].copy_from_slice(size);
+    Ok(res)
+  }
+
+  pub fn batch_count(mut self, batch_count: u32) -> Self {
+    self.batch_count = batch_count;
+    self
+  }
+
+  /// Rejects empty layouts and ones whose bin indices overflow the shader's 32-bit index math.
+  fn check(&self) -> Result<(), ConfigError> {
+    if self.size.contains(&0) || self.batch_count == 0 {
+      return Err(ConfigError::ZeroSize("spectrum layout"));
+    }
+    if self.total() > u32::MAX as u64 {
+      return Err(ConfigError::SizeOverflow("spectrum layout"));
+    }
+    Ok(())
+  }
+
+  /// Number of complex bins stored along each axis.
+  pub fn bins(&self) -> [u32; 3] {
+    let mut bins = self.size;
+    if self.r2c {
+      bins[0] = self.size[0] / 2 + 1;
+    }
+    bins
+  }
+
+  /// Number of complex bins in one batch, and the length of a mask for this layout.
+  pub fn mask_len(&self) -> u64 {
+    self.bins().iter().map(|b| *b as u64).product()
+  }
+
+  /// Number of complex bins in all batches.
+  pub fn total(&self) -> u64 {
+    self.mask_len() * self.batch_count as u64
+  }
+
+  /// Normalized frequency of `bin` along `axis`, in cycles per sample between -0.5 and 0.5.
+  fn frequency(&self, axis: usize, bin: u32) -> f64 {
+    let len = self.size[axis];
+    if bin >= len.div_ceil(2) {
+      (bin as f64 - len as f64) / len as f64
+    } else {
+      bin as f64 / len as f64
+    }
+  }
+}
+
+/// A generated mask, selecting bins by their radial normalized frequency in cycles per sample
+/// (0 to 0.5 along each axis).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaskShape {
+  /// Keeps bins at or below `cutoff`
+  LowPass { cutoff: f64 },
+  /// Keeps bins above `cutoff`
+  HighPass { cutoff: f64 },
+  /// Keeps bins between `low` and `high`, inclusive
+  BandPass { low: f64, high: f64 },
+  /// Removes bins between `low` and `high`, inclusive
+  BandStop { low: f64, high: f64 },
+}
+
+impl MaskShape {
+  fn passes(&self, frequency: f64) -> bool {
+    match *self {
+      MaskShape::LowPass { cutoff } => frequency <= cutoff,
+      MaskShape::HighPass { cutoff } => frequency > cutoff,
+      MaskShape::BandPass { low, high } => frequency >= low && frequency <= high,
+      MaskShape::BandStop { low, high } => frequency < low || frequency > high,
+    }
+  }
+
+  /// Rejects negative or NaN cutoffs and bands whose `low` is above `high`.
+  fn check(&self) -> Result<(), crate::Error> {
+    let valid = |cutoff: f64| cutoff >= 0.0;
+    let valid = match *self {
+      MaskShape::LowPass { cutoff } | MaskShape::HighPass { cutoff } => valid(cutoff),
+      MaskShape::BandPass { low, high } | MaskShape::BandStop { low, high } => {
+        valid(low) && valid(high) && low <= high
+      }
+    };
+    if !valid {
+      return Err(crate::Error::InvalidArgument("mask cutoff"));
+    }
+    Ok(())
+  }
+
+  /// The mask values for one batch of `layout`, in storage order.
+  pub fn mask(&self, layout: &SpectrumLayout) -> Result<Vec<f32>, crate::Error> {
+    self.check()?;
+    layout.check()?;
+
+    let bins = layout.bins();
+    let mut mask = Vec::with_capacity(layout.mask_len() as usize);
+    for z in 0..bins[2] {
+      for y in 0..bins[1] {
+        for x in 0..bins[0] {
+          let frequency = [(0, x), (1, y), (2, z)]
+            .iter()
+            .map(|(axis, bin)| layout.frequency(*axis, *bin).powi(2))
+            .sum::<f64>()
+            .sqrt();
+          mask.push(if self.passes(frequency) { 1.0 } else { 0.0 });
+        }
+      }
+    }
+    Ok(mask)
+  }
+}
+
+/// Multiplies a complex spectrum in place by a real mask, repeated across batches.
+pub struct SpectralFilter {
+  kernel: Kernel,
+  device: Arc<Device>,
+}
+
+impl SpectralFilter {
+  /// Filters `spectrum` with `mask`, which holds [`SpectrumLayout::mask_len`] `f32` values in
+  /// the storage order of the spectrum.
+  pub fn new(
+    context: &Context,
+    spectrum: Arc<Buffer>,
+    mask: Arc<Buffer>,
+    layout: SpectrumLayout,
+  ) -> Result<Self, crate::Error> {
+    layout.check()?;
+    if spectrum.size() / 8 < layout.total() {
+      return Err(ConfigError::BufferTooSmall("spectrum").into());
+    }
+    if mask.size() / 4 < layout.mask_len() {
+      return Err(ConfigError::BufferTooSmall("mask").into());
+    }
+
+    let kernel = Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      APPLY_MASK,
+      [spectrum, mask],
+      vec![layout.mask_len() as u32, layout.total() as u32],
+      group_counts(layout.total()),
+    )?;
+
+    Ok(Self {
+      kernel,
+      device: context.device.clone(),
+    })
+  }
+
+  /// Filters `spectrum` with a mask generated from `shape`.
+  pub fn shaped(
+    context: &Context,
+    spectrum: Arc<Buffer>,
+    shape: MaskShape,
+    layout: SpectrumLayout,
+  ) -> Result<Self, crate::Error> {
+    let mask = context.new_buffer_from_iter(shape.mask(&layout)?)?;
+    Self::new(context, spectrum, mask.buffer().clone(), layout)
+  }
+
+  /// A chain running `forward`, this filter, and the inverse of `forward`. The plan of
+  /// `forward` should normalize for the output to match the input's scale.
+  pub fn between(self, forward: FftStage) -> Chain {
+    let inverse = forward.reversed();
+    Chain::new(self.device.clone())
+      .push(forward)
+      .push(self)
+      .push(inverse)
+  }
+}
+
+impl Stage for SpectralFilter {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_r2c_mask() {
+    let layout = SpectrumLayout::new(&[8, 4], true).unwrap().batch_count(3);
+    assert_eq!(layout.bins(), [5, 4, 1]);
+    assert_eq!((layout.mask_len(), layout.total()), (20, 60));
+
+    let mask = MaskShape::LowPass { cutoff: 0.25 }.mask(&layout).unwrap();
+    assert_eq!(
+      mask,
+      vec![
+        1.0, 1.0, 1.0, 0.0, 0.0, // fy = 0
+        1.0, 0.0, 0.0, 0.0, 0.0, // fy = 0.25
+        0.0, 0.0, 0.0, 0.0, 0.0, // fy = -0.5
+        1.0, 0.0, 0.0, 0.0, 0.0, // fy = -0.25
+      ]
+    );
+
+    assert!(SpectrumLayout::new(&[8, 0], true).is_err());
+    assert!(MaskShape::LowPass { cutoff: f64::NAN }
+      .mask(&layout)
+      .is_err());
+    assert!(MaskShape::BandPass {
+      low: 0.3,
+      high: 0.1
+    }
+    .mask(&layout)
+    .is_err());
+    assert!(MaskShape::LowPass { cutoff: 0.25 }
+      .mask(&layout.batch_count(0))
+      .is_err());
+  }
+}
@@ -0,0 +1,301 @@
+//! Mapping between FFT bin indices and physical frequencies.
+//!
+//! [`fftfreq`] and [`rfftfreq`] give the frequencies of 1D spectra like numpy's functions of the
+//! same names. A [`KGrid`] gives the wavevectors of multidimensional spectra in the crate's
+//! layout, fastest axis first and with the reduced first axis of R2C transforms, for building
+//! spectral filters and derivative operators:
+//!
+//! ```ignore
+//! // The Laplacian of a 256 x 128 real field with a grid spacing of 0.5
+//! let grid = KGrid::new(&[256, 128], &[0.5, 0.5], true).angular();
+//! let laplacian = grid.magnitudes_squared().iter().map(|k2| -k2).collect::<Vec<_>>();
+//! ```
+
+use std::ops::Range;
+
+#[cfg(feature = "vulkano")]
+use vulkano::{buffer::Subbuffer, sync::HostAccessError};
+
+#[cfg(feature = "vulkano")]
+use crate::scalar::FftScalar;
+
+/// The frequency axis of a transform of `len` samples taken at `sample_rate`.
+///
+/// A complex axis covers all `len` bins in the usual FFT order, with the upper half holding
+/// negative frequencies. A real axis covers the `len / 2 + 1` non-negative bins produced by an
+/// R2C transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyAxis {
+  len: u32,
+  sample_rate: f64,
+  real: bool,
+}
+
+impl FrequencyAxis {
+  pub fn complex(len: u32, sample_rate: f64) -> Self {
+    assert!(len > 0);
+    Self {
+      len,
+      sample_rate,
+      real: false,
+    }
+  }
+
+  pub fn real(len: u32, sample_rate: f64) -> Self {
+    assert!(len > 0);
+    Self {
+      len,
+      sample_rate,
+      real: true,
+    }
+  }
+
+  /// Length of the transform in samples
+  pub fn transform_len(&self) -> u32 {
+    self.len
+  }
+
+  pub fn sample_rate(&self) -> f64 {
+    self.sample_rate
+  }
+
+  pub fn is_real(&self) -> bool {
+    self.real
+  }
+
+  /// Number of bins stored along this axis
+  pub fn bins(&self) -> u32 {
+    if self.real {
+      self.len / 2 + 1
+    } else {
+      self.len
+    }
+  }
+
+  /// Spacing between adjacent bins
+  pub fn resolution(&self) -> f64 {
+    self.sample_rate / self.len as f64
+  }
+
+  /// Frequency of `bin`, negative for the upper half of a complex axis.
+  pub fn frequency(&self, bin: u32) -> f64 {
+    assert!(bin < self.bins());
+
+    if !self.real && bin >= self.len.div_ceil(2) {
+      (bin as f64 - self.len as f64) * self.resolution()
+    } else {
+      bin as f64 * self.resolution()
+    }
+  }
+
+  /// Bin closest to `frequency`, or `None` if it lies outside the axis.
+  pub fn bin(&self, frequency: f64) -> Option<u32> {
+    let index = (frequency / self.resolution()).round();
+    let (low, high) = self.index_bounds();
+    if index < low || index > high {
+      return None;
+    }
+
+    if index < 0.0 {
+      Some((index + self.len as f64) as u32)
+    } else {
+      Some(index as u32)
+    }
+  }
+
+  /// Frequencies of all bins, in storage order.
+  pub fn frequencies(&self) -> Vec<f64> {
+    (0..self.bins()).map(|bin| self.frequency(bin)).collect()
+  }
+
+  /// Bins whose frequency lies within `[low, high]`, as ascending runs of contiguous bins. A
+  /// band of a complex axis that crosses zero yields two runs.
+  pub fn bin_ranges(&self, low: f64, high: f64) -> Vec<Range<u32>> {
+    let mut ranges: Vec<Range<u32>> = Vec::new();
+
+    for bin in 0..self.bins() {
+      let frequency = self.frequency(bin);
+      if frequency < low || frequency > high {
+        continue;
+      }
+
+      match ranges.last_mut() {
+        Some(range) if range.end == bin => range.end += 1,
+        _ => ranges.push(bin..bin + 1),
+      }
+    }
+
+    ranges
+  }
+
+  fn index_bounds(&self) -> (f64, f64) {
+    if self.real {
+      (0.0, (self.len / 2) as f64)
+    } else {
+      (-((self.len / 2) as f64), (self.len.div_ceil(2) - 1) as f64)
+    }
+  }
+}
+
+/// The frequencies of the bins of a complex transform of `len` samples spaced `spacing` apart,
+/// like `numpy.fft.fftfreq`.
+pub fn fftfreq(len: u32, spacing: f64) -> Vec<f64> {
+  FrequencyAxis::complex(len, 1.0 / spacing).frequencies()
+}
+
+/// Like [`fftfreq`], for the `len / 2 + 1` bins of a real transform, like
+/// `numpy.fft.rfftfreq`.
+pub fn rfftfreq(len: u32, spacing: f64) -> Vec<f64> {
+  FrequencyAxis::real(len, 1.0 / spacing).frequencies()
+}
+
+/// The wavevectors of the bins of a multidimensional spectrum, in storage order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KGrid {
+  axes: Vec<FrequencyAxis>,
+  batches: u32,
+  angular: bool,
+}
+
+impl KGrid {
+  /// The grid of transforms of `dims` samples spaced `spacing` apart, fastest axis first. With
+  /// `r2c`, the first axis holds the `dims[0] / 2 + 1` bins of a real transform.
+  pub fn new<const N: usize>(dims: &[u32; N], spacing: &[f64; N], r2c: bool) -> Self {
+    let axes = dims
+      .iter()
+      .zip(spacing)
+      .enumerate()
+      .map(|(axis, (&len, &spacing))| match axis {
+        0 if r2c => FrequencyAxis::real(len, 1.0 / spacing),
+        _ => FrequencyAxis::complex(len, 1.0 / spacing),
+      })
+      .collect();
+    Self::from_axes(axes)
+  }
+
+  /// The grid of `axes`, fastest first.
+  pub fn from_axes(axes: Vec<FrequencyAxis>) -> Self {
+    assert!(!axes.is_empty());
+    Self {
+      axes,
+      batches: 1,
+      angular: false,
+    }
+  }
+
+  /// Repeats the grid for `batches` spectra stored back to back.
+  pub fn batches(mut self, batches: u32) -> Self {
+    assert!(batches > 0);
+    self.batches = batches;
+    self
+  }
+
+  /// Gives angular wavenumbers, `2π` times the frequencies, as derivative operators need.
+  pub fn angular(mut self) -> Self {
+    self.angular = true;
+    self
+  }
+
+  /// Number of bins along each axis.
+  pub fn shape(&self) -> Vec<u32> {
+    self.axes.iter().map(FrequencyAxis::bins).collect()
+  }
+
+  /// Number of complex values in all batches of the spectrum.
+  pub fn len(&self) -> usize {
+    self
+      .shape()
+      .iter()
+      .map(|&bins| bins as usize)
+      .product::<usize>()
+      * self.batches as usize
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// The component along `axis` of the wavevector of every value, in storage order.
+  pub fn components(&self, axis: usize) -> Vec<f64> {
+    let frequencies = self.axes[axis].frequencies();
+    let scale = if self.angular {
+      std::f64::consts::TAU
+    } else {
+      1.0
+    };
+    let stride = self.shape()[..axis]
+      .iter()
+      .map(|&bins| bins as usize)
+      .product::<usize>();
+    (0..self.len())
+      .map(|index| scale * frequencies[(index / stride) % frequencies.len()])
+      .collect()
+  }
+
+  /// The squared magnitude of the wavevector of every value, in storage order.
+  pub fn magnitudes_squared(&self) -> Vec<f64> {
+    let mut magnitudes = vec![0.0; self.len()];
+    for axis in 0..self.axes.len() {
+      for (magnitude, component) in magnitudes.iter_mut().zip(self.components(axis)) {
+        *magnitude += component * component;
+      }
+    }
+    magnitudes
+  }
+}
+
+/// Writes `values`, such as the result of [`KGrid::magnitudes_squared`], into the start of a
+/// host-visible buffer, converted to `T`.
+#[cfg(feature = "vulkano")]
+pub fn fill_buffer<T: FftScalar>(
+  buffer: &Subbuffer<[T]>,
+  values: &[f64],
+) -> Result<(), HostAccessError> {
+  let mut contents = buffer.write()?;
+  for (value, &source) in contents.iter_mut().zip(values) {
+    *value = T::from_f64(source);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_complex_axis() {
+    let axis = FrequencyAxis::complex(8, 8.0);
+    assert_eq!(
+      axis.frequencies(),
+      vec![0.0, 1.0, 2.0, 3.0, -4.0, -3.0, -2.0, -1.0]
+    );
+    assert_eq!(axis.bin(-1.0), Some(7));
+    assert_eq!(axis.bin(3.2), Some(3));
+    assert_eq!(axis.bin(4.0), None);
+    assert_eq!(axis.bin_ranges(-2.0, 1.0), vec![0..2, 6..8]);
+  }
+
+  #[test]
+  fn check_real_axis() {
+    let axis = FrequencyAxis::real(9, 18.0);
+    assert_eq!(axis.bins(), 5);
+    assert_eq!(axis.frequencies(), vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+    assert_eq!(axis.bin(8.0), Some(4));
+    assert_eq!(axis.bin(-2.0), None);
+    assert_eq!(axis.bin_ranges(3.0, 100.0), vec![2..5]);
+  }
+
+  #[test]
+  fn check_k_grid() {
+    assert_eq!(rfftfreq(4, 0.5), vec![0.0, 0.5, 1.0]);
+    let grid = KGrid::new(&[4, 2], &[0.5, 1.0], true).batches(2);
+    assert_eq!(grid.shape(), vec![3, 2]);
+    assert_eq!(grid.len(), 12);
+    assert_eq!(grid.components(0)[..4], [0.0, 0.5, 1.0, 0.0]);
+    assert_eq!(
+      grid.components(1)[..7],
+      [0.0, 0.0, 0.0, -0.5, -0.5, -0.5, 0.0]
+    );
+    assert_eq!(grid.magnitudes_squared()[3..6], [0.25, 0.5, 1.25]);
+  }
+}
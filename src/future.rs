@@ -0,0 +1,88 @@
+//! Awaiting GPU work without blocking an async executor.
+//!
+//! A [`FenceFuture`] completes when its fence is signaled. The blocking fence wait runs on a
+//! helper thread started on the first poll, so executor threads stay free while the GPU works.
+
+use std::{
+  any::Any,
+  future::Future,
+  pin::Pin,
+  sync::{Arc, Mutex},
+  task::{Context, Poll, Waker},
+  thread,
+};
+
+use vulkano::{sync::fence::Fence, VulkanError};
+
+#[derive(Default)]
+struct WaitState {
+  result: Option<Result<(), VulkanError>>,
+  waker: Option<Waker>,
+  waiting: bool,
+}
+
+/// Completes when the work submitted with its fence has finished executing.
+///
+/// Dropping a pending future blocks until the work has finished, since the resources it keeps
+/// alive may still be in use by the GPU.
+pub struct FenceFuture {
+  fence: Arc<Fence>,
+  state: Arc<Mutex<WaitState>>,
+  _keep_alive: Box<dyn Any + Send + Sync>,
+}
+
+impl FenceFuture {
+  /// Waits on `fence`, keeping `keep_alive` (typically the submitted command buffer) alive until
+  /// it is signaled.
+  pub(crate) fn new<K>(fence: Arc<Fence>, keep_alive: K) -> Self
+  where
+    K: Any + Send + Sync,
+  {
+    Self {
+      fence,
+      state: Arc::default(),
+      _keep_alive: Box::new(keep_alive),
+    }
+  }
+
+  fn state(&self) -> std::sync::MutexGuard<'_, WaitState> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+impl Future for FenceFuture {
+  type Output = Result<(), VulkanError>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let mut state = self.state();
+    if let Some(result) = state.result {
+      return Poll::Ready(result);
+    }
+
+    state.waker = Some(cx.waker().clone());
+    if !state.waiting {
+      state.waiting = true;
+
+      let fence = self.fence.clone();
+      let shared = self.state.clone();
+      thread::spawn(move || {
+        let result = fence.wait(None);
+        let mut state = shared.lock().unwrap_or_else(|e| e.into_inner());
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+          waker.wake();
+        }
+      });
+    }
+
+    Poll::Pending
+  }
+}
+
+impl Drop for FenceFuture {
+  fn drop(&mut self) {
+    if self.state().result.is_none() {
+      let _ = self.fence.wait(None);
+    }
+  }
+}
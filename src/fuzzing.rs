@@ -0,0 +1,144 @@
+//! Entry points for the cargo-fuzz targets in `fuzz/`.
+//!
+//! They run config and launch parameter marshalling against stub handles, so no Vulkan device is
+//! needed, and panic if a pointer in the resulting VkFFT structures doesn't point at the value it
+//! was wired to.
+
+use std::pin::Pin;
+
+use ash::vk::{self, Handle};
+
+use crate::{
+  app::{LaunchError, LaunchParamsGuard},
+  config::{ConfigBuilder, ConfigError, ConfigGuard},
+};
+
+/// Handles and sizes standing in for the buffers of a config or launch. A handle of `0` means
+/// the buffer is absent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StubBuffers {
+  pub buffer: (u64, u64),
+  pub temp_buffer: (u64, u64),
+  pub input_buffer: (u64, u64),
+  pub output_buffer: (u64, u64),
+  pub kernel: (u64, u64),
+}
+
+fn handle(raw: u64) -> Option<vk::Buffer> {
+  if raw == 0 {
+    None
+  } else {
+    Some(vk::Buffer::from_raw(raw))
+  }
+}
+
+fn stub_config(buffers: &StubBuffers) -> Pin<Box<ConfigGuard>> {
+  let mut res = Box::pin(ConfigGuard {
+    keep_alive: Vec::new(),
+    config: unsafe { std::mem::zeroed() },
+    physical_device: vk::PhysicalDevice::from_raw(1),
+    device: vk::Device::from_raw(2),
+    queue: vk::Queue::from_raw(3),
+    command_pool: vk::CommandPool::from_raw(4),
+    fence: vk::Fence::from_raw(5),
+    buffer_size: buffers.buffer.1,
+    buffer: handle(buffers.buffer.0),
+    input_buffer_size: buffers.input_buffer.1,
+    input_buffer: handle(buffers.input_buffer.0),
+    output_buffer_size: buffers.output_buffer.1,
+    output_buffer: handle(buffers.output_buffer.0),
+    temp_buffer_size: buffers.temp_buffer.1,
+    temp_buffer: handle(buffers.temp_buffer.0),
+    kernel_size: buffers.kernel.1,
+    kernel: handle(buffers.kernel.0),
+    application: None,
+    #[cfg(feature = "vulkano")]
+    vulkano_queue: None,
+  });
+  res.wire();
+  res
+}
+
+unsafe fn check_buffer(pointer: *mut *mut vkfft_sys::VkBuffer_T, expected: u64) {
+  if expected == 0 {
+    assert!(pointer.is_null());
+  } else {
+    assert_eq!(*(pointer as *const u64), expected);
+  }
+}
+
+unsafe fn check_size(pointer: *mut u64, expected: u64) {
+  if expected == 0 {
+    assert!(pointer.is_null());
+  } else {
+    assert_eq!(*pointer, expected);
+  }
+}
+
+/// Marshals the plan described by `builder` into a stub config wired to `buffers`.
+pub fn marshal_config(builder: &ConfigBuilder, buffers: &StubBuffers) -> Result<(), ConfigError> {
+  let mut res = stub_config(buffers);
+  builder.plan_key().write_sys(&mut res.config)?;
+
+  let config = &res.config;
+  unsafe {
+    assert_eq!(*(config.physicalDevice as *const u64), 1);
+    assert_eq!(*(config.device as *const u64), 2);
+    assert_eq!(*(config.queue as *const u64), 3);
+    assert_eq!(*(config.commandPool as *const u64), 4);
+    assert_eq!(*(config.fence as *const u64), 5);
+
+    check_buffer(config.buffer, buffers.buffer.0);
+    check_buffer(config.tempBuffer, buffers.temp_buffer.0);
+    check_buffer(config.inputBuffer, buffers.input_buffer.0);
+    check_buffer(config.outputBuffer, buffers.output_buffer.0);
+    check_buffer(config.kernel, buffers.kernel.0);
+
+    check_size(config.bufferSize, buffers.buffer.1);
+    check_size(config.tempBufferSize, buffers.temp_buffer.1);
+    check_size(config.inputBufferSize, buffers.input_buffer.1);
+    check_size(config.outputBufferSize, buffers.output_buffer.1);
+    check_size(config.kernelSize, buffers.kernel.1);
+  }
+
+  Ok(())
+}
+
+/// Marshals launch parameters with `launch` buffers and validates them against an application
+/// configured with `config` buffers.
+pub fn marshal_launch(
+  command_buffer: u64,
+  config: &StubBuffers,
+  launch: &StubBuffers,
+) -> Result<(), LaunchError> {
+  let config = stub_config(config);
+  let mut res = Box::pin(LaunchParamsGuard {
+    params: unsafe { std::mem::zeroed() },
+    command_buffer: vk::CommandBuffer::from_raw(command_buffer),
+    buffer: Some(launch.buffer.0).filter(|b| *b != 0),
+    temp_buffer: Some(launch.temp_buffer.0).filter(|b| *b != 0),
+    input_buffer: Some(launch.input_buffer.0).filter(|b| *b != 0),
+    output_buffer: Some(launch.output_buffer.0).filter(|b| *b != 0),
+    kernel: Some(launch.kernel.0).filter(|b| *b != 0),
+    sizes: [
+      launch.buffer.1,
+      launch.temp_buffer.1,
+      launch.input_buffer.1,
+      launch.output_buffer.1,
+      launch.kernel.1,
+    ],
+  });
+  res.wire();
+
+  let params = &res.params;
+  unsafe {
+    assert_eq!(*(params.commandBuffer as *const u64), command_buffer);
+    check_buffer(params.buffer, launch.buffer.0);
+    check_buffer(params.tempBuffer, launch.temp_buffer.0);
+    check_buffer(params.inputBuffer, launch.input_buffer.0);
+    check_buffer(params.outputBuffer, launch.output_buffer.0);
+    check_buffer(params.kernel, launch.kernel.0);
+  }
+
+  res.check(&config)
+}
@@ -0,0 +1,242 @@
+//! Chaining transforms with vulkano's [`GpuFuture`]s.
+//!
+//! [`Context::then_execute`](crate::context::Context::then_execute) executes a stage after any
+//! vulkano future, so transforms can be chained with an application's own uploads, dispatches
+//! and swapchain presentation instead of going through the context's fence.
+//!
+//! ```ignore
+//! let future = upload
+//!   .execute(queue.clone())?;
+//! unsafe { context.then_execute(future, &mut fft)? }
+//!   .then_execute(queue.clone(), draw)?
+//!   .then_signal_fence_and_flush()?
+//!   .wait(None)?;
+//! ```
+//!
+//! vulkano doesn't see which buffers VkFFT accesses. The stage is recorded between full memory
+//! barriers, ordering it with the work submitted before and after it on the same queue, but
+//! vulkano's own access checks and lifetime tracking don't know about it: the stage and the
+//! buffers the transform uses must be kept alive, and not accessed from the host, until the
+//! chain has been waited on.
+
+use std::{
+  ops::Range,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+};
+
+use ash::vk;
+use vulkano::{
+  buffer::Buffer,
+  command_buffer::CommandBuffer,
+  device::{Device, DeviceOwned, Queue},
+  image::{Image, ImageLayout},
+  swapchain::Swapchain,
+  sync::{
+    future::{AccessCheckError, SubmitAnyBuilder},
+    GpuFuture,
+  },
+  DeviceSize, Validated, VulkanError, VulkanObject,
+};
+
+use crate::{error, stage::Stage};
+
+/// Records a stage between barriers ordering all memory accesses before and after it.
+pub(crate) struct Fenced<'s, S: ?Sized> {
+  pub(crate) device: Arc<Device>,
+  pub(crate) stage: &'s mut S,
+}
+
+impl<S: Stage + ?Sized> Stage for Fenced<'_, S> {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    memory_barrier(&self.device, command_buffer);
+    self.stage.record(command_buffer)?;
+    memory_barrier(&self.device, command_buffer);
+    Ok(())
+  }
+}
+
+fn memory_barrier(device: &Device, command_buffer: vk::CommandBuffer) {
+  let barrier = vk::MemoryBarrier {
+    src_access_mask: vk::AccessFlags::MEMORY_WRITE,
+    dst_access_mask: vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+    ..Default::default()
+  };
+
+  unsafe {
+    (device.fns().v1_0.cmd_pipeline_barrier)(
+      command_buffer,
+      vk::PipelineStageFlags::ALL_COMMANDS,
+      vk::PipelineStageFlags::ALL_COMMANDS,
+      vk::DependencyFlags::empty(),
+      1,
+      &barrier,
+      0,
+      std::ptr::null(),
+      0,
+      std::ptr::null(),
+    );
+  }
+}
+
+/// A transform executed after the future `F`, returned by
+/// [`Context::then_execute`](crate::context::Context::then_execute).
+///
+/// Like vulkano's own futures, nothing is submitted until the chain is flushed, and dropping a
+/// submitted future blocks until its queue is idle.
+#[must_use = "Dropping this object will immediately block the thread until the GPU has finished processing the submission"]
+pub struct FftExecFuture<F: GpuFuture> {
+  previous: F,
+  queue: Arc<Queue>,
+  command_buffer: CommandBuffer,
+  submitted: Mutex<bool>,
+  finished: AtomicBool,
+}
+
+impl<F: GpuFuture> FftExecFuture<F> {
+  /// Executes `command_buffer` on `queue` after `previous`.
+  ///
+  /// # Panics
+  ///
+  /// - Panics if `previous` is bound to another queue or device.
+  pub(crate) fn new(previous: F, queue: Arc<Queue>, command_buffer: CommandBuffer) -> Self {
+    assert_eq!(previous.device().handle(), queue.device().handle());
+    if !previous.queue_change_allowed() {
+      assert!(previous.queue().is_none_or(|q| q == queue));
+    }
+
+    Self {
+      previous,
+      queue,
+      command_buffer,
+      submitted: Mutex::new(false),
+      finished: AtomicBool::new(false),
+    }
+  }
+}
+
+unsafe impl<F: GpuFuture> DeviceOwned for FftExecFuture<F> {
+  fn device(&self) -> &Arc<Device> {
+    self.queue.device()
+  }
+}
+
+unsafe impl<F: GpuFuture> GpuFuture for FftExecFuture<F> {
+  fn cleanup_finished(&mut self) {
+    self.previous.cleanup_finished();
+  }
+
+  /// Submits the transform right away, since the submission can't be merged with vulkano's.
+  unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, Validated<VulkanError>> {
+    self.flush()?;
+    Ok(SubmitAnyBuilder::Empty)
+  }
+
+  fn flush(&self) -> Result<(), Validated<VulkanError>> {
+    let mut submitted = self.submitted.lock().unwrap();
+    if *submitted {
+      return Ok(());
+    }
+
+    // Work on the same queue is ordered by the barriers around the transform, work on other
+    // queues is waited on through its semaphores
+    let wait_semaphores = match unsafe { self.previous.build_submission() }? {
+      SubmitAnyBuilder::Empty => Vec::new(),
+      SubmitAnyBuilder::SemaphoresWait(semaphores) => semaphores.into_vec(),
+      _ => {
+        self.previous.flush()?;
+        Vec::new()
+      }
+    };
+    let semaphore_handles = wait_semaphores
+      .iter()
+      .map(|s| s.handle())
+      .collect::<Vec<_>>();
+    let stage_masks = vec![vk::PipelineStageFlags::ALL_COMMANDS; semaphore_handles.len()];
+    let command_buffer = self.command_buffer.handle();
+    let submit_info = vk::SubmitInfo {
+      wait_semaphore_count: semaphore_handles.len() as u32,
+      p_wait_semaphores: semaphore_handles.as_ptr(),
+      p_wait_dst_stage_mask: stage_masks.as_ptr(),
+      command_buffer_count: 1,
+      p_command_buffers: &command_buffer,
+      ..Default::default()
+    };
+
+    let fns = self.queue.device().fns();
+    self.queue.with(|_| {
+      unsafe { (fns.v1_0.queue_submit)(self.queue.handle(), 1, &submit_info, vk::Fence::null()) }
+        .result()
+        .map_err(VulkanError::from)
+    })?;
+
+    *submitted = true;
+    Ok(())
+  }
+
+  unsafe fn signal_finished(&self) {
+    self.finished.store(true, Ordering::SeqCst);
+    unsafe { self.previous.signal_finished() };
+  }
+
+  fn queue_change_allowed(&self) -> bool {
+    false
+  }
+
+  fn queue(&self) -> Option<Arc<Queue>> {
+    Some(self.queue.clone())
+  }
+
+  fn check_buffer_access(
+    &self,
+    buffer: &Buffer,
+    range: Range<DeviceSize>,
+    exclusive: bool,
+    queue: &Queue,
+  ) -> Result<(), AccessCheckError> {
+    self
+      .previous
+      .check_buffer_access(buffer, range, exclusive, queue)
+  }
+
+  fn check_image_access(
+    &self,
+    image: &Image,
+    range: Range<DeviceSize>,
+    exclusive: bool,
+    expected_layout: ImageLayout,
+    queue: &Queue,
+  ) -> Result<(), AccessCheckError> {
+    self
+      .previous
+      .check_image_access(image, range, exclusive, expected_layout, queue)
+  }
+
+  fn check_swapchain_image_acquired(
+    &self,
+    swapchain: &Swapchain,
+    image_index: u32,
+    before: bool,
+  ) -> Result<(), AccessCheckError> {
+    self
+      .previous
+      .check_swapchain_image_acquired(swapchain, image_index, before)
+  }
+}
+
+impl<F: GpuFuture> Drop for FftExecFuture<F> {
+  fn drop(&mut self) {
+    if std::thread::panicking() {
+      return;
+    }
+
+    let submitted = *self.submitted.get_mut().unwrap();
+    if submitted && !*self.finished.get_mut() {
+      // The command buffer is freed with this future, so it must not be pending anymore
+      self.queue.with(|mut q| q.wait_idle()).unwrap();
+      unsafe { self.previous.signal_finished() };
+    }
+  }
+}
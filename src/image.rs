@@ -0,0 +1,239 @@
+//! Per-channel transforms of interleaved color images.
+//!
+//! An [`ImageFft`] splits the channels of an `RGBRGB...` (or `RGBARGBA...`) `f32` image into
+//! planes on the GPU and transforms all of them with a single batched 2D R2C plan, instead of
+//! one pipeline per channel.
+//!
+//! ```ignore
+//! let geometry = ImageGeometry::new(width, height, 3)?;
+//! let mut fft = ImageFft::new(&context, pixels, planes, geometry)?;
+//! context.run(fft.forward())?;
+//! let red = fft.spectrum(0).read()?;
+//! ```
+
+use std::sync::Arc;
+
+use vulkano::buffer::{Buffer, Subbuffer};
+
+use crate::{
+  config::{Config, ConfigError},
+  context::{Context, FftType},
+  shader::{group_counts, with_defines, Kernel},
+  stage::Chain,
+};
+
+const PLANES: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) buffer Pixels { float pixels[]; };
+layout(std430, binding = 1) buffer Planes { float planes[]; };
+layout(push_constant) uniform Params {
+  uint width;
+  uint height;
+  uint channels;
+  uint row_stride;
+  float scale;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= width * height * channels) {
+    return;
+  }
+
+  uint pixel = i / channels;
+  uint channel = i % channels;
+  uint j = (channel * height + pixel / width) * row_stride + pixel % width;
+
+#if defined(INTERLEAVE)
+  pixels[i] = planes[j] * scale;
+#else
+  planes[j] = pixels[i];
+#endif
+}
+"#;
+
+/// Shape of an interleaved image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageGeometry {
+  pub width: u32,
+  pub height: u32,
+  /// Interleaved values per pixel, e.g. 3 for RGB and 4 for RGBA
+  pub channels: u32,
+}
+
+impl ImageGeometry {
+  pub fn new(width: u32, height: u32, channels: u32) -> Result<Self, ConfigError> {
+    let geometry = Self {
+      width,
+      height,
+      channels,
+    };
+    geometry.check()?;
+    Ok(geometry)
+  }
+
+  /// Number of `f32` values in the interleaved image.
+  pub fn pixels_len(&self) -> u64 {
+    self.width as u64 * self.height as u64 * self.channels as u64
+  }
+
+  /// Number of complex values in each row of a channel's spectrum.
+  pub fn spectrum_width(&self) -> u32 {
+    self.width / 2 + 1
+  }
+
+  /// Distance between the starts of consecutive plane rows, in `f32` values. Rows are padded for
+  /// the in-place R2C transform.
+  pub fn row_stride(&self) -> u32 {
+    self.stride() as u32
+  }
+
+  /// Number of `f32` values in one channel plane.
+  pub fn plane_len(&self) -> u64 {
+    self.stride() * self.height as u64
+  }
+
+  /// Number of `f32` values in the planes buffer.
+  pub fn planes_len(&self) -> u64 {
+    self.plane_len() * self.channels as u64
+  }
+
+  fn stride(&self) -> u64 {
+    2 * (self.width as u64 / 2 + 1)
+  }
+
+  /// Rejects empty images and ones whose pixel or plane indices overflow the shader's 32-bit
+  /// index math.
+  fn check(&self) -> Result<(), ConfigError> {
+    if self.width == 0 || self.height == 0 || self.channels == 0 {
+      return Err(ConfigError::ZeroSize("image geometry"));
+    }
+    if self.pixels_len() > u32::MAX as u64 || self.planes_len() > u32::MAX as u64 {
+      return Err(ConfigError::SizeOverflow("image geometry"));
+    }
+    Ok(())
+  }
+
+  fn kernel(
+    &self,
+    context: &Context,
+    pixels: Arc<Buffer>,
+    planes: Arc<Buffer>,
+    interleave: bool,
+  ) -> Result<Kernel, crate::Error> {
+    let defines: &[&str] = if interleave { &["INTERLEAVE"] } else { &[] };
+    let scale = 1.0 / (self.width as f32 * self.height as f32);
+
+    Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      &with_defines(PLANES, defines),
+      [pixels, planes],
+      vec![
+        self.width,
+        self.height,
+        self.channels,
+        self.row_stride(),
+        scale.to_bits(),
+      ],
+      group_counts(self.pixels_len()),
+    )
+  }
+}
+
+/// A batched 2D R2C transform of every channel of an interleaved image.
+///
+/// The forward chain deinterleaves `pixels` into `planes` and transforms them in place. The
+/// inverse chain transforms the planes back and interleaves them into `pixels`, normalized.
+pub struct ImageFft {
+  geometry: ImageGeometry,
+  planes: Arc<Buffer>,
+  forward: Chain,
+  inverse: Chain,
+}
+
+impl ImageFft {
+  /// `pixels` must hold at least [`ImageGeometry::pixels_len`] and `planes` at least
+  /// [`ImageGeometry::planes_len`] `f32` values.
+  pub fn new(
+    context: &Context,
+    pixels: Arc<Buffer>,
+    planes: Arc<Buffer>,
+    geometry: ImageGeometry,
+  ) -> Result<Self, crate::Error> {
+    geometry.check()?;
+    if pixels.size() / 4 < geometry.pixels_len() {
+      return Err(ConfigError::BufferTooSmall("pixels").into());
+    }
+    if planes.size() / 4 < geometry.planes_len() {
+      return Err(ConfigError::BufferTooSmall("planes").into());
+    }
+
+    let config_builder = Config::builder()
+      .buffer(planes.clone())
+      .dim(&[geometry.width, geometry.height])
+      .r2c()
+      .batch_count(geometry.channels);
+    let forward_fft = context.fft_stage(config_builder, FftType::Forward)?;
+    let inverse_fft = forward_fft.reversed();
+
+    let deinterleave = geometry.kernel(context, pixels.clone(), planes.clone(), false)?;
+    let interleave = geometry.kernel(context, pixels, planes.clone(), true)?;
+
+    Ok(Self {
+      geometry,
+      planes,
+      forward: Chain::new(context.device.clone())
+        .push(deinterleave)
+        .push(forward_fft),
+      inverse: Chain::new(context.device.clone())
+        .push(inverse_fft)
+        .push(interleave),
+    })
+  }
+
+  pub fn geometry(&self) -> ImageGeometry {
+    self.geometry
+  }
+
+  pub fn forward(&mut self) -> &mut Chain {
+    &mut self.forward
+  }
+
+  pub fn inverse(&mut self) -> &mut Chain {
+    &mut self.inverse
+  }
+
+  /// The forward and inverse chains, for embedding into a larger pipeline.
+  pub fn into_chains(self) -> (Chain, Chain) {
+    (self.forward, self.inverse)
+  }
+
+  /// The half spectrum of `channel` after the forward chain: `height` rows of
+  /// [`ImageGeometry::spectrum_width`] complex values.
+  pub fn spectrum(&self, channel: u32) -> Subbuffer<[[f32; 2]]> {
+    assert!(channel < self.geometry.channels);
+
+    let plane_bytes = self.geometry.plane_len() * 4;
+    let start = channel as u64 * plane_bytes;
+    Subbuffer::new(self.planes.clone())
+      .slice(start..start + plane_bytes)
+      .reinterpret::<[[f32; 2]]>()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_plane_layout() {
+    let rgb = ImageGeometry::new(5, 4, 3).unwrap();
+    assert_eq!(rgb.pixels_len(), 60);
+    assert_eq!((rgb.spectrum_width(), rgb.row_stride()), (3, 6));
+    assert_eq!((rgb.plane_len(), rgb.planes_len()), (24, 72));
+
+    assert!(ImageGeometry::new(5, 0, 3).is_err());
+    assert!(ImageGeometry::new(65536, 65536, 1).is_err());
+  }
+}
@@ -0,0 +1,240 @@
+//! GPU passes converting packed multi-channel audio (`LRLR...`) to and from the one-row-per-
+//! channel layout of a batched transform.
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  shader::{group_counts, with_defines, Kernel},
+  stage::Stage,
+};
+
+const INTERLEAVE: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) readonly buffer Input { float src[]; };
+layout(std430, binding = 1) writeonly buffer Output { float dst[]; };
+layout(push_constant) uniform Params {
+  uint channels;
+  uint frames;
+  uint row_stride;
+  float scale;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= channels * frames) {
+    return;
+  }
+
+  uint frame = i / channels;
+  uint channel = i % channels;
+  uint j = channel * row_stride + frame;
+
+#if defined(INTERLEAVE)
+#if defined(COMPLEX_ROWS)
+  dst[i] = src[2 * j] * scale;
+#else
+  dst[i] = src[j] * scale;
+#endif
+#else
+#if defined(COMPLEX_ROWS)
+  dst[2 * j] = src[i] * scale;
+  dst[2 * j + 1] = 0.0;
+#else
+  dst[j] = src[i] * scale;
+#endif
+#endif
+}
+"#;
+
+/// Layout of the per-channel rows, matching the transform run on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelLayout {
+  /// Rows of `frames` contiguous `f32` values
+  Real,
+  /// Rows of `frames` interleaved complex `f32` values, for C2C transforms. Deinterleaving
+  /// zeroes the imaginary parts and interleaving keeps only the real parts.
+  Complex,
+  /// Rows of `frames` `f32` values padded to `2 * (frames / 2 + 1)`, the layout of an in-place
+  /// R2C transform
+  R2cPadded,
+}
+
+/// Shape of a block of packed multi-channel audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelGeometry {
+  pub channels: u32,
+  pub frames: u32,
+  pub layout: ChannelLayout,
+}
+
+impl ChannelGeometry {
+  pub fn new(channels: u32, frames: u32, layout: ChannelLayout) -> Result<Self, ConfigError> {
+    let geometry = Self {
+      channels,
+      frames,
+      layout,
+    };
+    geometry.check()?;
+    Ok(geometry)
+  }
+
+  /// Distance between the starts of consecutive channel rows, in complex values for the
+  /// `Complex` layout and `f32` values otherwise.
+  pub fn row_stride(&self) -> u32 {
+    self.stride() as u32
+  }
+
+  /// Number of `f32` values in the per-channel buffer.
+  pub fn rows_len(&self) -> u64 {
+    let floats = match self.layout {
+      ChannelLayout::Complex => 2,
+      _ => 1,
+    };
+    self.channels as u64 * self.stride() * floats
+  }
+
+  fn stride(&self) -> u64 {
+    match self.layout {
+      ChannelLayout::Real | ChannelLayout::Complex => self.frames as u64,
+      ChannelLayout::R2cPadded => 2 * (self.frames as u64 / 2 + 1),
+    }
+  }
+
+  /// Rejects empty shapes and ones whose sample or row indices overflow the shader's 32-bit
+  /// index math.
+  fn check(&self) -> Result<(), ConfigError> {
+    if self.channels == 0 || self.frames == 0 {
+      return Err(ConfigError::ZeroSize("channel geometry"));
+    }
+    if self.channels as u64 * self.frames as u64 > u32::MAX as u64
+      || self.rows_len() > u32::MAX as u64
+    {
+      return Err(ConfigError::SizeOverflow("channel geometry"));
+    }
+    Ok(())
+  }
+
+  fn kernel(
+    &self,
+    context: &Context,
+    input: Arc<Buffer>,
+    output: Arc<Buffer>,
+    interleave: bool,
+    scale: f32,
+  ) -> Result<Kernel, crate::Error> {
+    self.check()?;
+    let (packed, rows) = if interleave {
+      (&output, &input)
+    } else {
+      (&input, &output)
+    };
+    if packed.size() / 4 < self.channels as u64 * self.frames as u64 {
+      return Err(ConfigError::BufferTooSmall("packed").into());
+    }
+    if rows.size() / 4 < self.rows_len() {
+      return Err(ConfigError::BufferTooSmall("rows").into());
+    }
+
+    let mut defines = Vec::new();
+    if interleave {
+      defines.push("INTERLEAVE");
+    }
+    if self.layout == ChannelLayout::Complex {
+      defines.push("COMPLEX_ROWS");
+    }
+
+    Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      &with_defines(INTERLEAVE, &defines),
+      [input, output],
+      vec![
+        self.channels,
+        self.frames,
+        self.row_stride(),
+        scale.to_bits(),
+      ],
+      group_counts(self.channels as u64 * self.frames as u64),
+    )
+  }
+}
+
+/// Splits packed `f32` audio into one row per channel, ready for a transform batched over
+/// channels.
+pub struct Deinterleave {
+  kernel: Kernel,
+}
+
+impl Deinterleave {
+  pub fn new(
+    context: &Context,
+    packed: Arc<Buffer>,
+    rows: Arc<Buffer>,
+    geometry: ChannelGeometry,
+  ) -> Result<Self, crate::Error> {
+    Ok(Self {
+      kernel: geometry.kernel(context, packed, rows, false, 1.0)?,
+    })
+  }
+}
+
+impl Stage for Deinterleave {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+/// Packs per-channel rows back into interleaved `f32` audio, multiplying by `scale`, e.g.
+/// `1.0 / frames` to normalize an unnormalized inverse transform.
+pub struct Interleave {
+  kernel: Kernel,
+}
+
+impl Interleave {
+  pub fn new(
+    context: &Context,
+    rows: Arc<Buffer>,
+    packed: Arc<Buffer>,
+    geometry: ChannelGeometry,
+    scale: f32,
+  ) -> Result<Self, crate::Error> {
+    Ok(Self {
+      kernel: geometry.kernel(context, rows, packed, true, scale)?,
+    })
+  }
+}
+
+impl Stage for Interleave {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_row_stride() {
+    let real = ChannelGeometry::new(2, 9, ChannelLayout::Real).unwrap();
+    assert_eq!((real.row_stride(), real.rows_len()), (9, 18));
+
+    let complex = ChannelGeometry::new(2, 9, ChannelLayout::Complex).unwrap();
+    assert_eq!((complex.row_stride(), complex.rows_len()), (9, 36));
+
+    let padded = ChannelGeometry::new(2, 9, ChannelLayout::R2cPadded).unwrap();
+    assert_eq!((padded.row_stride(), padded.rows_len()), (10, 20));
+
+    assert!(ChannelGeometry::new(0, 9, ChannelLayout::Real).is_err());
+    assert!(ChannelGeometry::new(2, u32::MAX, ChannelLayout::Real).is_err());
+    assert!(ChannelGeometry::new(1, u32::MAX, ChannelLayout::Complex).is_err());
+  }
+}
@@ -0,0 +1,107 @@
+//! Transforms on Intel GPUs through VkFFT's oneAPI Level Zero backend, without Vulkan.
+//!
+//! A [`LevelZeroApp`] is planned from the same [`ConfigBuilder`] as a Vulkan app, with raw Level
+//! Zero handles, and records transforms of its buffer in place into command lists:
+//!
+//! ```ignore
+//! let builder = Config::builder().dim(&[4096]);
+//! let mut app = unsafe {
+//!   LevelZeroApp::new(&builder, device, context, queue, queue_ordinal, buffer, 4096 * 8)?
+//! };
+//! unsafe { app.as_mut().append(command_list, false)? };
+//! ```
+//!
+//! Input, output and temp buffers, convolutions and custom Bluestein padding aren't supported
+//! yet.
+
+use std::{ffi::c_void, pin::Pin};
+
+use vkfft_sys::level_zero as ze;
+
+use crate::{
+  backend_plan::{copy_plan, vulkan_plan},
+  config::ConfigBuilder,
+  error::check_error,
+};
+
+/// A VkFFT application on a Level Zero device.
+pub struct LevelZeroApp {
+  app: ze::VkFFTApplication,
+  device: ze::ze_device_handle_t,
+  context: ze::ze_context_handle_t,
+  command_queue: ze::ze_command_queue_handle_t,
+  buffer: *mut c_void,
+  buffer_size: u64,
+}
+
+impl LevelZeroApp {
+  /// Plans the transform of `config_builder`, whose device handles and buffers are ignored, in
+  /// the device allocation `buffer` of `buffer_size` bytes. VkFFT uploads its lookup tables
+  /// through `command_queue`, created on the queue group `command_queue_ordinal` of `device`.
+  ///
+  /// # Safety
+  ///
+  /// The handles must be valid, belong to `device` and `context`, and outlive the app.
+  pub unsafe fn new(
+    config_builder: &ConfigBuilder,
+    device: *mut c_void,
+    context: *mut c_void,
+    command_queue: *mut c_void,
+    command_queue_ordinal: u32,
+    buffer: *mut c_void,
+    buffer_size: u64,
+  ) -> Result<Pin<Box<Self>>, crate::Error> {
+    let vulkan = vulkan_plan(config_builder, "Level Zero")?;
+    let mut config = std::mem::zeroed::<ze::VkFFTConfiguration>();
+    copy_plan!(config, vulkan);
+
+    let mut res = Box::pin(Self {
+      app: std::mem::zeroed(),
+      device: device as ze::ze_device_handle_t,
+      context: context as ze::ze_context_handle_t,
+      command_queue: command_queue as ze::ze_command_queue_handle_t,
+      buffer,
+      buffer_size,
+    });
+    // VkFFT keeps pointers to the handles, so they live in the pinned app
+    let this = res.as_mut().get_unchecked_mut();
+    config.device = &mut this.device;
+    config.context = &mut this.context;
+    config.commandQueue = &mut this.command_queue;
+    config.commandQueueID = command_queue_ordinal;
+    config.buffer = &mut this.buffer;
+    config.bufferSize = &mut this.buffer_size;
+    check_error(ze::initializeVkFFTLevelZero(&mut this.app, config))?;
+    Ok(res)
+  }
+
+  /// Appends the transform to `command_list`, which the caller executes on a queue of the
+  /// app's device.
+  ///
+  /// # Safety
+  ///
+  /// `command_list` must be a valid, open command list of the app's device and context.
+  pub unsafe fn append(
+    self: Pin<&mut Self>,
+    command_list: *mut c_void,
+    inverse: bool,
+  ) -> Result<(), crate::Error> {
+    let this = self.get_unchecked_mut();
+    let mut command_list = command_list as ze::ze_command_list_handle_t;
+    let mut params = std::mem::zeroed::<ze::VkFFTLaunchParams>();
+    params.commandList = &mut command_list;
+    params.buffer = &mut this.buffer;
+    check_error(ze::VkFFTAppendLevelZero(
+      &mut this.app,
+      if inverse { 1 } else { -1 },
+      &mut params,
+    ))?;
+    Ok(())
+  }
+}
+
+impl Drop for LevelZeroApp {
+  fn drop(&mut self) {
+    unsafe { ze::deleteVkFFTLevelZero(&mut self.app) };
+  }
+}
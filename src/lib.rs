@@ -1,7 +1,112 @@
+//! The core of the crate (`config`, `app` and `buffer`) only depends on ash handles. Contexts,
+//! stages and the GPU passes built on them use vulkano and are behind the default-on `vulkano`
+//! feature.
+
+#[cfg(feature = "cpu-fallback")]
+pub mod accuracy;
 pub mod app;
+#[cfg(feature = "vulkano")]
+pub mod autotune;
+#[cfg(any(feature = "vulkano", feature = "cuda", feature = "level-zero"))]
+pub mod backend;
+#[cfg(any(feature = "cuda", feature = "level-zero"))]
+mod backend_plan;
+pub mod buffer;
+#[cfg(feature = "vulkano")]
+pub mod capabilities;
+#[cfg(feature = "vulkano")]
+pub mod bundle;
+mod capture;
+#[cfg(feature = "num-complex")]
+pub mod complex;
+#[cfg(feature = "vulkano")]
+pub mod complex_ops;
 pub mod config;
+#[cfg(feature = "vulkano")]
 pub mod context;
+#[cfg(feature = "vulkano")]
+pub mod convert;
+#[cfg(feature = "vulkano")]
+pub mod convolution;
+#[cfg(feature = "cpu-fallback")]
+pub mod cpu_fallback;
+#[cfg(feature = "cuda")]
+pub mod cuda;
+#[cfg(feature = "vulkano")]
+pub mod decibel;
+#[cfg(feature = "vulkano")]
+pub mod disk_cache;
+pub mod dry_run;
 pub mod error;
+#[cfg(feature = "vulkano")]
+pub mod fft_plan;
+#[cfg(feature = "vulkano")]
+pub mod fft_stream;
+#[cfg(feature = "vulkano")]
+pub mod fftshift;
+#[cfg(feature = "vulkano")]
+pub mod filter;
+pub mod frequency;
+#[cfg(feature = "async")]
+pub mod future;
+#[cfg(feature = "vulkano")]
+pub mod gpu_future;
+#[cfg(feature = "vulkano")]
+pub mod image;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+#[cfg(feature = "vulkano")]
+pub mod interleave;
+#[cfg(feature = "level-zero")]
+pub mod level_zero;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod overrides;
+#[cfg(feature = "vulkano")]
+pub mod multi_gpu;
+#[cfg(feature = "nalgebra")]
+pub mod nalgebra_interop;
+#[cfg(feature = "ndarray")]
+pub mod ndarray_interop;
+#[cfg(feature = "vulkano")]
+pub mod peaks;
+#[cfg(feature = "vulkano")]
+pub mod plan_cache;
+pub mod plan_info;
+pub mod planner;
+pub mod primes;
+#[cfg(feature = "vulkano")]
+pub mod queue_share;
+#[cfg(feature = "vulkano")]
+pub mod r2c_layout;
+#[cfg(feature = "vulkano")]
+pub mod real_fft;
+#[cfg(feature = "vulkano")]
+pub mod record_ext;
+#[cfg(feature = "vulkano")]
+pub mod sar;
+pub mod scalar;
+#[cfg(all(feature = "service", unix))]
+pub mod service;
+#[cfg(feature = "vulkano")]
+pub mod shader;
+#[cfg(feature = "snapshots")]
+pub mod snapshot;
+#[cfg(feature = "vulkano")]
+pub mod spectrum;
+#[cfg(feature = "vulkano")]
+pub mod stage;
+#[cfg(feature = "vulkano")]
+pub mod streaming;
+pub mod tuning;
 mod version;
+#[cfg(feature = "vulkano")]
+pub mod welch;
+#[cfg(feature = "vulkano")]
+pub mod window;
+#[cfg(feature = "wgpu")]
+pub mod wgpu_interop;
 
+pub use error::Error;
 pub use version::*;
@@ -0,0 +1,234 @@
+//! A stand-in for [`Context`](crate::context::Context) and [`App`](crate::app::App) that needs
+//! no Vulkan device, for unit testing code that plans and launches FFTs.
+//!
+//! A [`MockContext`] records the plan of every app created from it and every launch of those
+//! apps. Launches succeed unless a result is queued with [`MockContext::queue_result`] or a
+//! handler is installed with [`MockContext::on_launch`].
+
+use std::{
+  collections::VecDeque,
+  sync::{Arc, Mutex, MutexGuard},
+};
+
+use ash::vk;
+
+use crate::{
+  app::LaunchParams,
+  config::{ConfigBuilder, PlanKey},
+  context::FftType,
+  error,
+  stage::Stage,
+};
+
+/// A launch recorded by a [`MockApp`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockLaunch {
+  pub plan: PlanKey,
+  pub fft_type: FftType,
+  pub command_buffer: vk::CommandBuffer,
+}
+
+type LaunchHandler = Arc<Mutex<dyn FnMut(&MockLaunch) -> error::Result<()> + Send>>;
+
+#[derive(Default)]
+struct MockState {
+  plans: Vec<PlanKey>,
+  launches: Vec<MockLaunch>,
+  app_results: VecDeque<error::VkfftError>,
+  results: VecDeque<error::Result<()>>,
+  handler: Option<LaunchHandler>,
+}
+
+#[derive(Clone, Default)]
+pub struct MockContext {
+  state: Arc<Mutex<MockState>>,
+}
+
+impl MockContext {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn state(&self) -> MutexGuard<'_, MockState> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  /// Records the plan described by `config_builder`, failing with the next error queued by
+  /// [`MockContext::fail_next_app`] if there is one.
+  pub fn app(&self, config_builder: ConfigBuilder) -> error::Result<MockApp> {
+    let plan = config_builder.plan_key();
+    let mut state = self.state();
+    if let Some(err) = state.app_results.pop_front() {
+      return Err(err);
+    }
+
+    state.plans.push(plan.clone());
+    Ok(MockApp {
+      context: self.clone(),
+      plan,
+    })
+  }
+
+  pub fn fft_stage(
+    &self,
+    config_builder: ConfigBuilder,
+    fft_type: FftType,
+  ) -> error::Result<MockFftStage> {
+    Ok(MockFftStage {
+      app: self.app(config_builder)?,
+      fft_type,
+    })
+  }
+
+  /// Records `stage` against a null command buffer.
+  pub fn run<S>(&self, stage: &mut S) -> error::Result<()>
+  where
+    S: Stage + ?Sized,
+  {
+    stage.record(vk::CommandBuffer::null())
+  }
+
+  /// Makes the next call to [`MockContext::app`] fail with `err`.
+  pub fn fail_next_app(&self, err: error::VkfftError) {
+    self.state().app_results.push_back(err);
+  }
+
+  /// Queues the result of the next launch. Queued results take precedence over the handler.
+  pub fn queue_result(&self, result: error::Result<()>) {
+    self.state().results.push_back(result);
+  }
+
+  /// Computes the result of launches that have no queued result.
+  pub fn on_launch<F>(&self, handler: F)
+  where
+    F: FnMut(&MockLaunch) -> error::Result<()> + Send + 'static,
+  {
+    self.state().handler = Some(Arc::new(Mutex::new(handler)));
+  }
+
+  /// Plans of all apps created so far, in creation order.
+  pub fn plans(&self) -> Vec<PlanKey> {
+    self.state().plans.clone()
+  }
+
+  /// All launches so far, in launch order.
+  pub fn launches(&self) -> Vec<MockLaunch> {
+    self.state().launches.clone()
+  }
+
+  pub fn clear(&self) {
+    let mut state = self.state();
+    state.plans.clear();
+    state.launches.clear();
+  }
+
+  fn launch(&self, launch: MockLaunch) -> error::Result<()> {
+    let handler = {
+      let mut state = self.state();
+      state.launches.push(launch.clone());
+      if let Some(result) = state.results.pop_front() {
+        return result;
+      }
+      state.handler.clone()
+    };
+    // The handler may call back into the context, so the state isn't locked while it runs
+    match handler {
+      Some(handler) => {
+        let mut handler = handler.lock().unwrap_or_else(|e| e.into_inner());
+        (*handler)(&launch)
+      }
+      None => Ok(()),
+    }
+  }
+}
+
+/// The mock counterpart of [`App`](crate::app::App).
+pub struct MockApp {
+  context: MockContext,
+  plan: PlanKey,
+}
+
+impl MockApp {
+  pub fn plan(&self) -> &PlanKey {
+    &self.plan
+  }
+
+  pub fn launch(&mut self, params: &mut LaunchParams, inverse: bool) -> error::Result<()> {
+    self.context.launch(MockLaunch {
+      plan: self.plan.clone(),
+      fft_type: if inverse {
+        FftType::Inverse
+      } else {
+        FftType::Forward
+      },
+      command_buffer: params.command_buffer,
+    })
+  }
+
+  pub fn forward(&mut self, params: &mut LaunchParams) -> error::Result<()> {
+    self.launch(params, false)
+  }
+
+  pub fn inverse(&mut self, params: &mut LaunchParams) -> error::Result<()> {
+    self.launch(params, true)
+  }
+}
+
+/// The mock counterpart of [`FftStage`](crate::stage::FftStage).
+pub struct MockFftStage {
+  app: MockApp,
+  fft_type: FftType,
+}
+
+impl MockFftStage {
+  pub fn fft_type(&self) -> FftType {
+    self.fft_type
+  }
+}
+
+impl Stage for MockFftStage {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    let mut params = LaunchParams {
+      command_buffer,
+      buffer: None,
+      temp_buffer: None,
+      input_buffer: None,
+      output_buffer: None,
+      kernel: None,
+    };
+    self
+      .app
+      .launch(&mut params, self.fft_type == FftType::Inverse)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_mock_launches() {
+    let context = MockContext::new();
+    let mut stage = context
+      .fft_stage(ConfigBuilder::new().dim(&[64]).r2c(), FftType::Inverse)
+      .unwrap();
+
+    context.queue_result(Err(error::VkfftError::InvalidDevice));
+    assert!(context.run(&mut stage).is_err());
+    assert!(context.run(&mut stage).is_ok());
+
+    let launches = context.launches();
+    assert_eq!(context.plans().len(), 1);
+    assert_eq!(launches.len(), 2);
+    assert_eq!(launches[0].fft_type, FftType::Inverse);
+    assert_eq!(launches[0].plan.size[0], 64);
+    assert!(launches[0].plan.r2c);
+
+    let handler_context = context.clone();
+    context.on_launch(move |_| {
+      assert_eq!(handler_context.launches().len(), 3);
+      Err(error::VkfftError::InvalidDevice)
+    });
+    assert!(context.run(&mut stage).is_err());
+  }
+}
@@ -0,0 +1,575 @@
+//! Transforms split across several GPUs.
+//!
+//! A [`MultiGpu`] holds a context per device and splits work that divides naturally: the
+//! batches of a batched transform, or the slabs of a 3D volume. Each device transforms its
+//! share in its own buffer, and the results are gathered on the host:
+//!
+//! ```ignore
+//! let multi_gpu = MultiGpu::new(&instance)?;
+//! let builder = Config::builder().dim(&[4096]);
+//! let spectra = multi_gpu.transform_batched(builder, &signals, 64, FftType::Forward)?;
+//! let dims = [256, 256, 256];
+//! let spectrum = multi_gpu.transform_3d(Config::builder(), dims, &volume, FftType::Forward)?;
+//! ```
+//!
+//! A [`DistributedFft`] computes a 1D complex transform of `n1 * n2` points with the four-step
+//! decomposition: `n1` transforms of length `n2`, a twiddle multiplication and transpose, and
+//! `n2` transforms of length `n1`. Each device of a [`MultiGpuContext`] runs half of the
+//! transforms of each step, so it only holds half of the data. The transposes are staged through
+//! host memory, where the twiddles are applied in double precision.
+//!
+//! ```ignore
+//! let group = MultiGpuContext::new(&instance)?;
+//! let mut fft = DistributedFft::new(&group, 1 << 14, 1 << 14)?;
+//! let spectrum = fft.transform(&signal, FftType::Forward)?;
+//! ```
+
+use std::{f64::consts::PI, sync::Arc};
+
+use vulkano::{buffer::Subbuffer, instance::Instance, VulkanObject};
+
+use crate::{
+  config::{Config, ConfigBuilder, ConfigError},
+  context::{Context, DeviceSelectionError, FftType},
+  scalar::FftScalar,
+  stage::FftStage,
+};
+
+/// Contexts on any number of devices, which share batched transforms and 3D volumes.
+pub struct MultiGpu {
+  contexts: Vec<Context>,
+}
+
+impl MultiGpu {
+  /// Creates a context on every device of `instance`.
+  pub fn new(instance: &Arc<Instance>) -> Result<Self, crate::Error> {
+    Self::from_contexts(Context::for_all_devices(instance)?)
+  }
+
+  /// Fails if `contexts` is empty.
+  pub fn from_contexts(contexts: Vec<Context>) -> Result<Self, crate::Error> {
+    if contexts.is_empty() {
+      return Err(DeviceSelectionError::NoMatchingDevice(Vec::new()).into());
+    }
+    Ok(Self { contexts })
+  }
+
+  pub fn contexts(&self) -> &[Context] {
+    &self.contexts
+  }
+
+  /// Transforms the `batch_count` batches of `data`, each of the shape `config_builder`
+  /// describes, splitting consecutive batches evenly across the devices. Buffers and the batch
+  /// count of `config_builder` are replaced.
+  pub fn transform_batched<T: FftScalar>(
+    &self,
+    config_builder: ConfigBuilder,
+    data: &[T],
+    batch_count: u32,
+    fft_type: FftType,
+  ) -> Result<Vec<T>, crate::Error> {
+    let batch_len = data.len() / batch_count.max(1) as usize;
+    if batch_len * batch_count as usize != data.len() {
+      return Err(ConfigError::LengthMismatch("data", data.len()).into());
+    }
+
+    let shares = shares(batch_count, self.contexts.len());
+    let mut chunks = Vec::new();
+    let mut rest = data;
+    for share in &shares {
+      let (chunk, tail) = rest.split_at(*share as usize * batch_len);
+      chunks.push(chunk);
+      rest = tail;
+    }
+    let jobs = shares
+      .iter()
+      .zip(chunks)
+      .map(|(share, chunk)| (config_builder.clone().batch_count(*share), chunk))
+      .collect();
+    Ok(self.run_each(jobs, fft_type)?.concat())
+  }
+
+  /// Transforms a complex volume of `dims` points, laid out with the first axis contiguous, by
+  /// slabs: each device transforms the first two axes of a range of planes along the third,
+  /// then the third axis of a range of rows along the second. Buffers, sizes and batch count of
+  /// `config_builder` are replaced.
+  pub fn transform_3d<T: FftScalar>(
+    &self,
+    config_builder: ConfigBuilder,
+    dims: [u32; 3],
+    data: &[T],
+    fft_type: FftType,
+  ) -> Result<Vec<T>, crate::Error> {
+    let slabs = Slabs::new(dims);
+    if data.len() != 2 * slabs.points() {
+      return Err(ConfigError::LengthMismatch("data", data.len()).into());
+    }
+
+    // Planes along the third axis are contiguous, so the first step is a batched 2D transform
+    let planes = self.transform_batched(
+      config_builder.clone().dim(&[dims[0], dims[1]]),
+      data,
+      dims[2],
+      fft_type,
+    )?;
+
+    let shares = shares(dims[1], self.contexts.len());
+    let mut first_row = 0;
+    let mut ranges = Vec::new();
+    for share in &shares {
+      ranges.push(first_row..first_row + *share);
+      first_row += *share;
+    }
+    let slabs_in = ranges
+      .iter()
+      .map(|rows| slabs.rows(&planes, rows.clone()))
+      .collect::<Vec<_>>();
+    let jobs = ranges
+      .iter()
+      .zip(&slabs_in)
+      .map(|(rows, slab)| {
+        let builder = config_builder
+          .clone()
+          .dim(&[dims[0], rows.len() as u32, dims[2]])
+          .omit_dimension(&[true, true, false]);
+        (builder, &slab[..])
+      })
+      .collect();
+    let results = self.run_each(jobs, fft_type)?;
+
+    let mut volume = planes;
+    for (rows, slab) in ranges.into_iter().zip(results) {
+      slabs.write_rows(&slab, rows, &mut volume);
+    }
+    Ok(volume)
+  }
+
+  /// Transforms the data of `jobs[i]` on device `i` in place, skipping empty ones, with every
+  /// device running concurrently, and returns the results in device order.
+  fn run_each<T: FftScalar>(
+    &self,
+    jobs: Vec<(ConfigBuilder, &[T])>,
+    fft_type: FftType,
+  ) -> Result<Vec<Vec<T>>, crate::Error> {
+    let mut running = Vec::new();
+    let mut failure = None;
+    for (context, (config_builder, data)) in self.contexts.iter().zip(jobs) {
+      if data.is_empty() {
+        continue;
+      }
+      let submit = || -> Result<_, crate::Error> {
+        let buffer = context.new_buffer_from_iter(data.iter().copied())?;
+        let config_builder = config_builder
+          .buffer(buffer.clone())
+          .precision(T::PRECISION);
+        let mut stage = context.fft_stage(config_builder, fft_type)?;
+        let command_buffer = context.record(&mut stage)?;
+        // The stage and command buffer are kept until the fence has been waited on
+        let fence = unsafe { context.submit_fenced(command_buffer.handle()) }?;
+        Ok((buffer, stage, command_buffer, fence))
+      };
+      match submit() {
+        Ok(job) => running.push(job),
+        Err(e) => {
+          failure = Some(e);
+          break;
+        }
+      }
+    }
+
+    // Every submission has to complete before its buffers are dropped, even after a failure
+    let mut results = Vec::new();
+    for (buffer, _stage, _command_buffer, fence) in running {
+      let result = fence
+        .wait(None)
+        .map_err(crate::Error::from)
+        .and_then(|()| Ok(buffer.read()?.to_vec()));
+      match result {
+        Ok(result) => results.push(result),
+        Err(e) => failure = failure.or(Some(e)),
+      }
+    }
+    match failure {
+      Some(e) => Err(e),
+      None => Ok(results),
+    }
+  }
+}
+
+/// Splits `total` into `parts` shares differing by at most one, larger ones first.
+fn shares(total: u32, parts: usize) -> Vec<u32> {
+  let parts = parts as u32;
+  (0..parts)
+    .map(|i| total / parts + u32::from(i < total % parts))
+    .collect()
+}
+
+/// Copies between a complex volume and slabs of its rows along the second axis.
+#[derive(Debug, Clone, Copy)]
+struct Slabs {
+  dims: [usize; 3],
+}
+
+impl Slabs {
+  fn new(dims: [u32; 3]) -> Self {
+    Self {
+      dims: [dims[0] as usize, dims[1] as usize, dims[2] as usize],
+    }
+  }
+
+  fn points(&self) -> usize {
+    self.dims.iter().product()
+  }
+
+  /// The volume restricted to `rows` of the second axis, as a contiguous volume.
+  fn rows<T: Copy>(&self, volume: &[T], rows: std::ops::Range<u32>) -> Vec<T> {
+    let [nx, ny, nz] = self.dims;
+    let row_len = 2 * nx;
+    let mut slab = Vec::with_capacity(row_len * rows.len() * nz);
+    for z in 0..nz {
+      for y in rows.clone() {
+        let start = (z * ny + y as usize) * row_len;
+        slab.extend_from_slice(&volume[start..start + row_len]);
+      }
+    }
+    slab
+  }
+
+  /// Writes a slab taken with [`Slabs::rows`] back to its place in `volume`.
+  fn write_rows<T: Copy>(&self, slab: &[T], rows: std::ops::Range<u32>, volume: &mut [T]) {
+    let [nx, ny, nz] = self.dims;
+    let row_len = 2 * nx;
+    let mut chunks = slab.chunks_exact(row_len);
+    for z in 0..nz {
+      for y in rows.clone() {
+        let start = (z * ny + y as usize) * row_len;
+        volume[start..start + row_len].copy_from_slice(chunks.next().unwrap());
+      }
+    }
+  }
+}
+
+/// Two contexts on different devices, used together for a single transform.
+pub struct MultiGpuContext {
+  contexts: [Context; 2],
+}
+
+impl MultiGpuContext {
+  /// Creates contexts on the first two devices of `instance`.
+  pub fn new(instance: &Arc<Instance>) -> Result<Self, crate::Error> {
+    let devices = instance.enumerate_physical_devices()?.collect::<Vec<_>>();
+    let [first, second] = match &devices[..] {
+      [first, second, ..] => [first.clone(), second.clone()],
+      _ => {
+        let names = devices
+          .iter()
+          .map(|d| d.properties().device_name.clone())
+          .collect();
+        return Err(DeviceSelectionError::NoMatchingDevice(names).into());
+      }
+    };
+    Ok(Self::from_contexts([
+      Context::with_physical_device(instance, first)?,
+      Context::with_physical_device(instance, second)?,
+    ]))
+  }
+
+  pub fn from_contexts(contexts: [Context; 2]) -> Self {
+    Self { contexts }
+  }
+
+  pub fn contexts(&self) -> &[Context; 2] {
+    &self.contexts
+  }
+
+  /// Records `stages[i]` for device `i` and runs both submissions concurrently, returning once
+  /// both have completed.
+  fn run(&self, stages: [&mut FftStage; 2]) -> Result<(), crate::Error> {
+    let [first, second] = stages;
+    let command_buffers = [
+      self.contexts[0].record(first)?,
+      self.contexts[1].record(second)?,
+    ];
+
+    // The command buffers outlive the fences, and every submission is waited on below
+    let submitted = self
+      .contexts
+      .iter()
+      .zip(&command_buffers)
+      .map(|(context, command_buffer)| unsafe { context.submit_fenced(command_buffer.handle()) })
+      .collect::<Vec<_>>();
+    for fence in submitted.iter().flatten() {
+      fence.wait(None)?;
+    }
+    for result in submitted {
+      result?;
+    }
+    Ok(())
+  }
+}
+
+/// One device's share of the data, with the batched transforms of both steps over it.
+struct Share {
+  buffer: Subbuffer<[[f32; 2]]>,
+  /// Forward and inverse stages of `n1 / 2` transforms of length `n2`
+  first: [FftStage; 2],
+  /// Forward and inverse stages of `n2 / 2` transforms of length `n1`
+  second: [FftStage; 2],
+}
+
+impl Share {
+  fn new(context: &Context, split: FourStep) -> Result<Self, crate::Error> {
+    let buffer = context.new_buffer_from_iter(vec![[0.0f32; 2]; split.points() / 2])?;
+    let step = |len: u32, rows: u32| -> Result<[FftStage; 2], crate::Error> {
+      let config_builder = Config::builder()
+        .buffer(buffer.clone())
+        .dim(&[len])
+        .batch_count(rows);
+      let forward = context.fft_stage(config_builder, FftType::Forward)?;
+      let inverse = forward.reversed();
+      Ok([forward, inverse])
+    };
+    Ok(Self {
+      first: step(split.n2, split.n1 / 2)?,
+      second: step(split.n1, split.n2 / 2)?,
+      buffer,
+    })
+  }
+}
+
+/// The stage of `stages`, forward and inverse, running in the direction of `fft_type`.
+fn directed(stages: &mut [FftStage; 2], fft_type: FftType) -> &mut FftStage {
+  let [forward, inverse] = stages;
+  match fft_type {
+    FftType::Forward => forward,
+    FftType::Inverse => inverse,
+  }
+}
+
+/// The four-step split of a 1D transform of `n1 * n2` points, which must both be even.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FourStep {
+  pub n1: u32,
+  pub n2: u32,
+}
+
+impl FourStep {
+  pub fn new(n1: u32, n2: u32) -> Result<Self, crate::Error> {
+    if n1 == 0 || n2 == 0 {
+      return Err(ConfigError::ZeroSize("four-step split").into());
+    }
+    if !n1.is_multiple_of(2) || !n2.is_multiple_of(2) {
+      return Err(crate::Error::InvalidArgument("four-step split of odd size"));
+    }
+    Ok(Self { n1, n2 })
+  }
+
+  /// Number of points of the transform.
+  pub fn points(&self) -> usize {
+    self.n1 as usize * self.n2 as usize
+  }
+
+  /// Splits `data` into the inputs of the first step on each device: `n1 / 2` rows of `n2`
+  /// points, where row `r` of device `d` is the subsequence starting at `d * n1 / 2 + r` with
+  /// stride `n1`.
+  fn scatter(&self, data: &[[f32; 2]], device: usize, out: &mut [[f32; 2]]) {
+    let (n1, n2) = (self.n1 as usize, self.n2 as usize);
+    let rows = n1 / 2;
+    for r in 0..rows {
+      let row = device * rows + r;
+      for j in 0..n2 {
+        out[r * n2 + j] = data[row + n1 * j];
+      }
+    }
+  }
+
+  /// Twiddles and transposes the outputs of the first step into the inputs of the second step
+  /// on `device`: `n2 / 2` rows of `n1` points.
+  fn transpose(
+    &self,
+    first: [&[[f32; 2]]; 2],
+    device: usize,
+    fft_type: FftType,
+    out: &mut [[f32; 2]],
+  ) {
+    let (n1, n2) = (self.n1 as usize, self.n2 as usize);
+    let (rows_in, rows_out) = (n1 / 2, n2 / 2);
+    let sign = match fft_type {
+      FftType::Forward => -1.0,
+      FftType::Inverse => 1.0,
+    };
+    for r in 0..rows_out {
+      let k2 = device * rows_out + r;
+      for j in 0..n1 {
+        let [re, im] = first[j / rows_in][(j % rows_in) * n2 + k2];
+        let angle = sign * 2.0 * PI * ((j * k2) % self.points()) as f64 / self.points() as f64;
+        let (sin, cos) = angle.sin_cos();
+        let (re, im) = (re as f64, im as f64);
+        out[r * n1 + j] = [(re * cos - im * sin) as f32, (re * sin + im * cos) as f32];
+      }
+    }
+  }
+
+  /// Writes the outputs of the second step on `device` to their place in the result.
+  fn gather(&self, second: &[[f32; 2]], device: usize, out: &mut [[f32; 2]]) {
+    let (n1, n2) = (self.n1 as usize, self.n2 as usize);
+    let rows = n2 / 2;
+    for r in 0..rows {
+      let k2 = device * rows + r;
+      for k1 in 0..n1 {
+        out[k2 + n2 * k1] = second[r * n1 + k1];
+      }
+    }
+  }
+}
+
+/// A 1D complex transform distributed over the two devices of a [`MultiGpuContext`].
+///
+/// Each device holds a single buffer of `n1 * n2 / 2` points, which both steps transform in
+/// place, so transforms twice as large as fit on either device alone can be computed. Inverse
+/// transforms are not normalized.
+pub struct DistributedFft<'g> {
+  group: &'g MultiGpuContext,
+  split: FourStep,
+  shares: [Share; 2],
+}
+
+impl<'g> DistributedFft<'g> {
+  /// Plans a transform of `n1 * n2` points, where `n1` and `n2` are even. Choosing them close
+  /// to each other keeps both steps' transforms short.
+  pub fn new(group: &'g MultiGpuContext, n1: u32, n2: u32) -> Result<Self, crate::Error> {
+    let split = FourStep::new(n1, n2)?;
+    let [first, second] = group.contexts();
+    Ok(Self {
+      group,
+      split,
+      shares: [Share::new(first, split)?, Share::new(second, split)?],
+    })
+  }
+
+  pub fn split(&self) -> FourStep {
+    self.split
+  }
+
+  /// Transforms `data`, which holds `n1 * n2` complex points, and returns the result in natural
+  /// order.
+  pub fn transform(
+    &mut self,
+    data: &[[f32; 2]],
+    fft_type: FftType,
+  ) -> Result<Vec<[f32; 2]>, crate::Error> {
+    if data.len() != self.split.points() {
+      return Err(ConfigError::LengthMismatch("data", data.len()).into());
+    }
+
+    for (device, share) in self.shares.iter().enumerate() {
+      self.split.scatter(data, device, &mut share.buffer.write()?);
+    }
+    let [a, b] = &mut self.shares;
+    self.group.run([
+      directed(&mut a.first, fft_type),
+      directed(&mut b.first, fft_type),
+    ])?;
+
+    // Each device's second step needs both halves of the first, so they are read back before
+    // the buffers are overwritten
+    let first = [
+      self.shares[0].buffer.read()?.to_vec(),
+      self.shares[1].buffer.read()?.to_vec(),
+    ];
+    for (device, share) in self.shares.iter().enumerate() {
+      self.split.transpose(
+        [&first[0], &first[1]],
+        device,
+        fft_type,
+        &mut share.buffer.write()?,
+      );
+    }
+    let [a, b] = &mut self.shares;
+    self.group.run([
+      directed(&mut a.second, fft_type),
+      directed(&mut b.second, fft_type),
+    ])?;
+
+    let mut res = vec![[0.0; 2]; self.split.points()];
+    for (device, share) in self.shares.iter().enumerate() {
+      self.split.gather(&share.buffer.read()?, device, &mut res);
+    }
+    Ok(res)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_slabs() {
+    assert_eq!(shares(10, 3), vec![4, 3, 3]);
+    assert_eq!(shares(1, 2), vec![1, 0]);
+
+    let slabs = Slabs::new([2, 3, 2]);
+    let volume = (0..2 * slabs.points()).collect::<Vec<_>>();
+    let slab = slabs.rows(&volume, 1..3);
+    // Rows 1 and 2 of each plane, 4 values per row
+    assert_eq!(&slab[..8], &volume[4..12]);
+    assert_eq!(&slab[8..], &volume[16..24]);
+
+    let mut copy = vec![0; volume.len()];
+    slabs.write_rows(&slabs.rows(&volume, 0..1), 0..1, &mut copy);
+    slabs.write_rows(&slab, 1..3, &mut copy);
+    assert_eq!(copy, volume);
+  }
+
+  fn dft(data: &[[f32; 2]], sign: f64) -> Vec<[f32; 2]> {
+    let n = data.len();
+    (0..n)
+      .map(|k| {
+        let (mut re, mut im) = (0.0, 0.0);
+        for (j, [x, y]) in data.iter().enumerate() {
+          let (sin, cos) = (sign * 2.0 * PI * ((j * k) % n) as f64 / n as f64).sin_cos();
+          re += *x as f64 * cos - *y as f64 * sin;
+          im += *x as f64 * sin + *y as f64 * cos;
+        }
+        [re as f32, im as f32]
+      })
+      .collect()
+  }
+
+  /// Runs the host steps with a DFT standing in for each device's batched transforms.
+  #[test]
+  fn check_four_step() {
+    let split = FourStep::new(4, 6).unwrap();
+    assert!(FourStep::new(4, 5).is_err());
+    let data = (0..split.points())
+      .map(|i| [(i as f32 * 0.7).sin(), (i as f32 * 0.3).cos()])
+      .collect::<Vec<_>>();
+    let rows = |buffer: Vec<[f32; 2]>, len: usize| {
+      buffer
+        .chunks(len)
+        .flat_map(|row| dft(row, -1.0))
+        .collect::<Vec<_>>()
+    };
+
+    let mut first = [vec![[0.0; 2]; 12], vec![[0.0; 2]; 12]];
+    for (device, buffer) in first.iter_mut().enumerate() {
+      split.scatter(&data, device, buffer);
+      *buffer = rows(buffer.clone(), 6);
+    }
+    let mut res = vec![[0.0; 2]; split.points()];
+    for device in 0..2 {
+      let mut second = vec![[0.0; 2]; 12];
+      split.transpose(
+        [&first[0], &first[1]],
+        device,
+        FftType::Forward,
+        &mut second,
+      );
+      split.gather(&rows(second, 4), device, &mut res);
+    }
+
+    for (a, b) in res.iter().zip(dft(&data, -1.0)) {
+      assert!((a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4);
+    }
+  }
+}
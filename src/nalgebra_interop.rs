@@ -0,0 +1,151 @@
+//! Transforms of [nalgebra](https://docs.rs/nalgebra) matrices.
+//!
+//! nalgebra stores matrices column by column, so the fastest axis of a transform runs down the
+//! columns and matrices are uploaded without reordering:
+//!
+//! ```ignore
+//! let field = DMatrix::<Complex32>::zeros(256, 512);
+//! let spectrum = context.fft_matrix(&field)?;
+//! let restored = context.ifft_matrix(&spectrum)?;
+//!
+//! let heights = DMatrix::<f32>::zeros(256, 512);
+//! let half_spectrum = context.rfft_matrix(&heights)?; // 129 x 512
+//! ```
+//!
+//! Inverse transforms are normalized, so they undo the forward ones.
+
+use nalgebra::DMatrix;
+use num_complex::Complex;
+use vulkano::buffer::{BufferContents, Subbuffer};
+
+use crate::{
+  complex::{complex_to_interleaved, interleaved_to_complex},
+  config::{Config, ConfigError},
+  context::{Context, FftType},
+  r2c_layout::{pad_rows, unpad_rows},
+  scalar::FftScalar,
+};
+
+impl Context {
+  /// Uploads `matrix` into a buffer laid out for a transform of `[nrows, ncols]`.
+  pub fn new_matrix_buffer<T>(
+    &self,
+    matrix: &DMatrix<Complex<T>>,
+  ) -> Result<Subbuffer<[Complex<T>]>, crate::Error>
+  where
+    T: FftScalar,
+    Complex<T>: BufferContents,
+  {
+    Ok(self.new_complex_buffer_from_iter(matrix.iter().copied())?)
+  }
+
+  /// Reads a buffer created by [`Context::new_matrix_buffer`] back into a matrix of `nrows` rows,
+  /// which must divide its length.
+  pub fn read_matrix<T>(
+    &self,
+    buffer: &Subbuffer<[Complex<T>]>,
+    nrows: usize,
+  ) -> Result<DMatrix<Complex<T>>, crate::Error>
+  where
+    T: FftScalar,
+    Complex<T>: BufferContents,
+  {
+    if nrows == 0 {
+      return Err(ConfigError::ZeroSize("row count").into());
+    }
+    let values = self.read_complex(buffer)?;
+    if values.len() % nrows != 0 {
+      return Err(ConfigError::LengthMismatch("buffer", values.len()).into());
+    }
+    let ncols = values.len() / nrows;
+    Ok(DMatrix::from_vec(nrows, ncols, values))
+  }
+
+  /// The 2D transform of `matrix`.
+  pub fn fft_matrix<T>(
+    &self,
+    matrix: &DMatrix<Complex<T>>,
+  ) -> Result<DMatrix<Complex<T>>, crate::Error>
+  where
+    T: FftScalar,
+    Complex<T>: BufferContents,
+  {
+    self.transform_matrix(matrix, FftType::Forward)
+  }
+
+  /// The normalized inverse 2D transform of `matrix`.
+  pub fn ifft_matrix<T>(
+    &self,
+    matrix: &DMatrix<Complex<T>>,
+  ) -> Result<DMatrix<Complex<T>>, crate::Error>
+  where
+    T: FftScalar,
+    Complex<T>: BufferContents,
+  {
+    self.transform_matrix(matrix, FftType::Inverse)
+  }
+
+  /// The 2D transform of the real `matrix`, with the `nrows / 2 + 1` non-redundant rows of the
+  /// spectrum.
+  pub fn rfft_matrix<T: FftScalar>(
+    &self,
+    matrix: &DMatrix<T>,
+  ) -> Result<DMatrix<Complex<T>>, crate::Error> {
+    let (nrows, ncols) = matrix.shape();
+    let config_builder = Config::builder().dim(&[nrows as u32, ncols as u32]);
+    let data = pad_rows(matrix.as_slice(), nrows);
+    let spectrum = self.transform_real(config_builder, data, FftType::Forward)?;
+    let spectrum = interleaved_to_complex(&spectrum);
+    Ok(DMatrix::from_vec(nrows / 2 + 1, ncols, spectrum))
+  }
+
+  /// The normalized inverse of [`Context::rfft_matrix`], for real matrices of `nrows` rows.
+  /// `matrix` must have `nrows / 2 + 1` rows.
+  pub fn irfft_matrix<T: FftScalar>(
+    &self,
+    matrix: &DMatrix<Complex<T>>,
+    nrows: usize,
+  ) -> Result<DMatrix<T>, crate::Error> {
+    let (spectrum_rows, ncols) = matrix.shape();
+    if spectrum_rows != nrows / 2 + 1 {
+      return Err(ConfigError::LengthMismatch("spectrum column", spectrum_rows).into());
+    }
+    let config_builder = Config::builder().dim(&[nrows as u32, ncols as u32]);
+    let values = self.transform_real(
+      config_builder,
+      complex_to_interleaved(matrix.as_slice()),
+      FftType::Inverse,
+    )?;
+    Ok(DMatrix::from_vec(nrows, ncols, unpad_rows(&values, nrows)))
+  }
+
+  fn transform_matrix<T>(
+    &self,
+    matrix: &DMatrix<Complex<T>>,
+    fft_type: FftType,
+  ) -> Result<DMatrix<Complex<T>>, crate::Error>
+  where
+    T: FftScalar,
+    Complex<T>: BufferContents,
+  {
+    let (nrows, ncols) = matrix.shape();
+    let builder = Config::builder().dim(&[nrows as u32, ncols as u32]);
+    let builder = match fft_type {
+      FftType::Forward => builder,
+      FftType::Inverse => builder.normalize(),
+    };
+    let values = self.transform_complex(builder, matrix.as_slice(), fft_type)?;
+    Ok(DMatrix::from_vec(nrows, ncols, values))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_column_padding() {
+    let matrix = DMatrix::from_row_slice(3, 2, &[1, 2, 3, 4, 5, 6]);
+    assert_eq!(pad_rows(matrix.as_slice(), 3), [1, 3, 5, 0, 2, 4, 6, 0]);
+  }
+}
@@ -0,0 +1,211 @@
+//! Transforms of [ndarray](https://docs.rs/ndarray) arrays, which handle uploading, layout and
+//! reading back:
+//!
+//! ```ignore
+//! let image = Array2::<Complex32>::zeros((512, 1024));
+//! let spectrum = context.fft2(&image.view())?;
+//! let restored = context.ifft2(&spectrum.view())?;
+//!
+//! let heights = Array2::<f32>::zeros((512, 1024));
+//! let half_spectrum = context.rfft2(&heights.view())?; // 512 x 513
+//! ```
+//!
+//! Arrays are indexed like numpy's, with the last axis the fastest, and may have any memory
+//! layout. Inverse transforms are normalized, so they undo the forward ones.
+
+use ndarray::{Array, Array2, Array3, ArrayView2, ArrayView3, ArrayViewMut, Dimension};
+use num_complex::Complex;
+use vulkano::buffer::BufferContents;
+
+use crate::{
+  complex::{complex_to_interleaved, interleaved_to_complex},
+  config::{Config, ConfigError},
+  context::{Context, FftType},
+  r2c_layout::unpad_rows,
+  scalar::FftScalar,
+};
+
+impl Context {
+  /// The 2D transform of `input`.
+  pub fn fft2<T: FftScalar>(
+    &self,
+    input: &ArrayView2<Complex<T>>,
+  ) -> Result<Array2<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let (rows, cols) = input.dim();
+    self.transform_array(
+      input.iter(),
+      [cols, rows],
+      input.raw_dim(),
+      FftType::Forward,
+    )
+  }
+
+  /// The normalized inverse 2D transform of `input`.
+  pub fn ifft2<T: FftScalar>(
+    &self,
+    input: &ArrayView2<Complex<T>>,
+  ) -> Result<Array2<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let (rows, cols) = input.dim();
+    self.transform_array(
+      input.iter(),
+      [cols, rows],
+      input.raw_dim(),
+      FftType::Inverse,
+    )
+  }
+
+  /// The 3D transform of `input`.
+  pub fn fft3<T: FftScalar>(
+    &self,
+    input: &ArrayView3<Complex<T>>,
+  ) -> Result<Array3<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let (depth, rows, cols) = input.dim();
+    self.transform_array(
+      input.iter(),
+      [cols, rows, depth],
+      input.raw_dim(),
+      FftType::Forward,
+    )
+  }
+
+  /// The normalized inverse 3D transform of `input`.
+  pub fn ifft3<T: FftScalar>(
+    &self,
+    input: &ArrayView3<Complex<T>>,
+  ) -> Result<Array3<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let (depth, rows, cols) = input.dim();
+    self.transform_array(
+      input.iter(),
+      [cols, rows, depth],
+      input.raw_dim(),
+      FftType::Inverse,
+    )
+  }
+
+  /// Transforms `array` in place along all of its axes, of which it must have one to four.
+  pub fn fft_inplace<T: FftScalar, D: Dimension>(
+    &self,
+    mut array: ArrayViewMut<Complex<T>, D>,
+    fft_type: FftType,
+  ) -> Result<(), crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let result = match *array.shape() {
+      [cols] => self.transform_vec(array.iter(), [cols], fft_type),
+      [rows, cols] => self.transform_vec(array.iter(), [cols, rows], fft_type),
+      [depth, rows, cols] => self.transform_vec(array.iter(), [cols, rows, depth], fft_type),
+      [planes, depth, rows, cols] => {
+        self.transform_vec(array.iter(), [cols, rows, depth, planes], fft_type)
+      }
+      ref shape => return Err(ConfigError::AxisCount(shape.len()).into()),
+    }?;
+    array
+      .iter_mut()
+      .zip(result)
+      .for_each(|(value, result)| *value = result);
+    Ok(())
+  }
+
+  /// The 2D transform of the real `input`, with the `cols / 2 + 1` non-redundant columns of the
+  /// spectrum.
+  pub fn rfft2<T: FftScalar>(
+    &self,
+    input: &ArrayView2<T>,
+  ) -> Result<Array2<Complex<T>>, crate::Error> {
+    let (rows, cols) = input.dim();
+    let padded = 2 * (cols / 2 + 1);
+    let config_builder = Config::builder().dim(&[cols as u32, rows as u32]);
+    let spectrum =
+      self.transform_real(config_builder, pad_rows(input, padded), FftType::Forward)?;
+    let spectrum = interleaved_to_complex(&spectrum);
+    Ok(Array2::from_shape_vec((rows, cols / 2 + 1), spectrum).unwrap())
+  }
+
+  /// The normalized inverse of [`Context::rfft2`], for real arrays of `cols` columns. `input` must
+  /// have `cols / 2 + 1` columns.
+  pub fn irfft2<T: FftScalar>(
+    &self,
+    input: &ArrayView2<Complex<T>>,
+    cols: usize,
+  ) -> Result<Array2<T>, crate::Error> {
+    let (rows, spectrum_cols) = input.dim();
+    if spectrum_cols != cols / 2 + 1 {
+      return Err(ConfigError::LengthMismatch("spectrum row", spectrum_cols).into());
+    }
+    let config_builder = Config::builder().dim(&[cols as u32, rows as u32]);
+    let data = complex_to_interleaved(&input.iter().copied().collect::<Vec<_>>());
+    let values = self.transform_real(config_builder, data, FftType::Inverse)?;
+    let values = unpad_rows(&values, cols);
+    Ok(Array2::from_shape_vec((rows, cols), values).unwrap())
+  }
+
+  fn transform_array<'a, T: FftScalar, D: Dimension, const N: usize>(
+    &self,
+    values: impl Iterator<Item = &'a Complex<T>>,
+    dims: [usize; N],
+    shape: D,
+    fft_type: FftType,
+  ) -> Result<Array<Complex<T>, D>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let result = self.transform_vec(values, dims, fft_type)?;
+    Ok(Array::from_shape_vec(shape, result).unwrap())
+  }
+
+  /// Transforms `values`, in logical order, with the sizes of the axes fastest first.
+  fn transform_vec<'a, T: FftScalar, const N: usize>(
+    &self,
+    values: impl Iterator<Item = &'a Complex<T>>,
+    dims: [usize; N],
+    fft_type: FftType,
+  ) -> Result<Vec<Complex<T>>, crate::Error>
+  where
+    Complex<T>: BufferContents,
+  {
+    let builder = Config::builder().dim(&dims.map(|dim| dim as u32));
+    let builder = match fft_type {
+      FftType::Forward => builder,
+      FftType::Inverse => builder.normalize(),
+    };
+    self.transform_complex(builder, &values.copied().collect::<Vec<_>>(), fft_type)
+  }
+}
+
+/// The rows of `input`, each padded to `padded` values to make room for its spectrum, the layout
+/// of in-place real transforms.
+fn pad_rows<T: Copy + Default>(input: &ArrayView2<T>, padded: usize) -> Vec<T> {
+  let mut data = Vec::with_capacity(input.nrows() * padded);
+  for row in input.rows() {
+    data.extend(row.iter().copied());
+    data.resize(data.len() + padded - row.len(), T::default());
+  }
+  data
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_row_padding() {
+    let array = Array2::from_shape_vec((2, 3), (1..=6).collect()).unwrap();
+    // Rows of a transposed view are padded in logical order
+    let padded = pad_rows(&array.t(), 4);
+    assert_eq!(padded, [1, 4, 0, 0, 2, 5, 0, 0, 3, 6, 0, 0]);
+    assert_eq!(unpad_rows(&padded, 2), [1, 4, 2, 5, 3, 6]);
+  }
+}
@@ -0,0 +1,151 @@
+//! Performance overrides read from the environment and applied on top of every config, so tuning
+//! experiments and driver workarounds don't need a rebuild.
+//!
+//! | Variable                      | Effect                                   |
+//! |-------------------------------|------------------------------------------|
+//! | `VKFFT_RS_FORCE_LUT`          | Enables (`1`) or disables (`0`) LUTs     |
+//! | `VKFFT_RS_DISABLE_REORDER`    | Sets `disableReorderFourStep`            |
+//! | `VKFFT_RS_COALESCED_MEMORY`   | Sets `coalescedMemory`, in bytes         |
+//! | `VKFFT_RS_AIM_THREADS`        | Sets `aimThreads`                        |
+//! | `VKFFT_RS_NUM_SHARED_BANKS`   | Sets `numSharedBanks`                    |
+//! | `VKFFT_RS_REGISTER_BOOST`     | Sets `registerBoost`                     |
+//!
+//! Booleans accept `1`/`0`, `true`/`false`, `yes`/`no` and `on`/`off`. Numbers must be positive,
+//! `VKFFT_RS_COALESCED_MEMORY` a power of two and `VKFFT_RS_REGISTER_BOOST` 1, 2 or 4.
+//!
+//! The environment is read once, the first time a plan is described. Planning fails with
+//! [`ConfigError::InvalidOverride`] while a value is invalid.
+
+use std::sync::OnceLock;
+
+use crate::config::ConfigError;
+
+pub const FORCE_LUT: &str = "VKFFT_RS_FORCE_LUT";
+pub const DISABLE_REORDER: &str = "VKFFT_RS_DISABLE_REORDER";
+pub const COALESCED_MEMORY: &str = "VKFFT_RS_COALESCED_MEMORY";
+pub const AIM_THREADS: &str = "VKFFT_RS_AIM_THREADS";
+pub const NUM_SHARED_BANKS: &str = "VKFFT_RS_NUM_SHARED_BANKS";
+pub const REGISTER_BOOST: &str = "VKFFT_RS_REGISTER_BOOST";
+
+/// The overrides every plan uses, or the first invalid variable and its value.
+static CAPTURED: OnceLock<Result<EnvOverrides, (&'static str, String)>> = OnceLock::new();
+
+/// The overrides in effect. They change the generated kernels, so they are part of a
+/// [`PlanKey`](crate::config::PlanKey).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct EnvOverrides {
+  pub use_lut: Option<bool>,
+  pub disable_reorder_four_step: Option<bool>,
+  pub coalesced_memory: Option<u64>,
+  pub aim_threads: Option<u64>,
+  pub num_shared_banks: Option<u64>,
+  pub register_boost: Option<u64>,
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+  match value.trim().to_ascii_lowercase().as_str() {
+    "1" | "true" | "yes" | "on" => Some(true),
+    "0" | "false" | "no" | "off" => Some(false),
+    _ => None,
+  }
+}
+
+impl EnvOverrides {
+  /// Reads the overrides from the environment now, failing on the first invalid value.
+  pub fn from_env() -> Result<Self, ConfigError> {
+    Self::from_lookup(|name| std::env::var(name).ok())
+      .map_err(|(name, value)| ConfigError::InvalidOverride(name, value))
+  }
+
+  /// The overrides read from the environment the first time this is called, which plans use.
+  pub(crate) fn captured() -> Result<&'static Self, ConfigError> {
+    CAPTURED
+      .get_or_init(|| Self::from_lookup(|name| std::env::var(name).ok()))
+      .as_ref()
+      .map_err(|(name, value)| ConfigError::InvalidOverride(name, value.clone()))
+  }
+
+  fn from_lookup<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self, (&'static str, String)> {
+    let flag = |name: &'static str| match lookup(name) {
+      Some(value) => parse_bool(&value).map(Some).ok_or((name, value)),
+      None => Ok(None),
+    };
+    let number = |name: &'static str, valid: fn(u64) -> bool| match lookup(name) {
+      Some(value) => match value.trim().parse() {
+        Ok(number) if valid(number) => Ok(Some(number)),
+        _ => Err((name, value)),
+      },
+      None => Ok(None),
+    };
+
+    Ok(Self {
+      use_lut: flag(FORCE_LUT)?,
+      disable_reorder_four_step: flag(DISABLE_REORDER)?,
+      coalesced_memory: number(COALESCED_MEMORY, u64::is_power_of_two)?,
+      aim_threads: number(AIM_THREADS, |n| n > 0)?,
+      num_shared_banks: number(NUM_SHARED_BANKS, |n| n > 0)?,
+      register_boost: number(REGISTER_BOOST, |n| matches!(n, 1 | 2 | 4))?,
+    })
+  }
+
+  pub fn is_empty(&self) -> bool {
+    *self == Self::default()
+  }
+
+  pub(crate) fn write_sys(&self, config: &mut vkfft_sys::VkFFTConfiguration) {
+    if let Some(use_lut) = self.use_lut {
+      config.useLUT = use_lut.into();
+    }
+
+    if let Some(disable_reorder_four_step) = self.disable_reorder_four_step {
+      config.disableReorderFourStep = disable_reorder_four_step.into();
+    }
+
+    if let Some(coalesced_memory) = self.coalesced_memory {
+      config.coalescedMemory = coalesced_memory;
+    }
+
+    if let Some(aim_threads) = self.aim_threads {
+      config.aimThreads = aim_threads;
+    }
+
+    if let Some(num_shared_banks) = self.num_shared_banks {
+      config.numSharedBanks = num_shared_banks;
+    }
+
+    if let Some(register_boost) = self.register_boost {
+      config.registerBoost = register_boost;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_from_lookup() {
+    let overrides = EnvOverrides::from_lookup(|name| match name {
+      FORCE_LUT => Some("On".to_string()),
+      COALESCED_MEMORY => Some(" 64 ".to_string()),
+      _ => None,
+    })
+    .unwrap();
+
+    assert_eq!(overrides.use_lut, Some(true));
+    assert_eq!(overrides.coalesced_memory, Some(64));
+    assert_eq!(overrides.aim_threads, None);
+    assert!(EnvOverrides::from_lookup(|_| None).unwrap().is_empty());
+
+    let invalid = |variable: &'static str, value: &str| {
+      EnvOverrides::from_lookup(|name| (name == variable).then(|| value.to_string()))
+    };
+    assert_eq!(
+      invalid(DISABLE_REORDER, "maybe"),
+      Err((DISABLE_REORDER, "maybe".to_string()))
+    );
+    assert!(invalid(COALESCED_MEMORY, "48").is_err());
+    assert!(invalid(AIM_THREADS, "0").is_err());
+    assert!(invalid(REGISTER_BOOST, "3").is_err());
+  }
+}
@@ -0,0 +1,188 @@
+//! GPU detection of spectral peaks, so frequency estimation only needs to read back a compact
+//! list of peaks instead of whole spectra.
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::{
+  buffer::{Buffer, BufferContents, Subbuffer},
+  device::Device,
+};
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  shader::{fill_buffer, group_counts, with_defines, Kernel},
+  stage::Stage,
+};
+
+const PEAK_DETECTION: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) readonly buffer Spectrum { float spectrum[]; };
+layout(std430, binding = 1) buffer Counts { uint counts[]; };
+layout(std430, binding = 2) writeonly buffer Peaks { uvec2 peaks[]; };
+layout(push_constant) uniform Params {
+  uint bins;
+  uint batch_count;
+  uint max_peaks;
+  float threshold;
+};
+
+float magnitude(uint index) {
+#ifdef COMPLEX_INPUT
+  return length(vec2(spectrum[2 * index], spectrum[2 * index + 1]));
+#else
+  return spectrum[index];
+#endif
+}
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= bins * batch_count) {
+    return;
+  }
+
+  uint batch = i / bins;
+  uint bin = i % bins;
+  float value = magnitude(i);
+  if (value <= threshold) {
+    return;
+  }
+  if (bin > 0 && value <= magnitude(i - 1)) {
+    return;
+  }
+  if (bin + 1 < bins && value < magnitude(i + 1)) {
+    return;
+  }
+
+  uint slot = atomicAdd(counts[batch], 1);
+  if (slot < max_peaks) {
+    peaks[batch * max_peaks + slot] = uvec2(bin, floatBitsToUint(value));
+  }
+}
+"#;
+
+/// How spectrum samples are stored in the input buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrumFormat {
+  /// Interleaved complex `f32` values; the magnitude is computed on the fly
+  Complex,
+  /// One real `f32` value per bin, e.g. magnitudes or powers
+  Real,
+}
+
+#[derive(BufferContents, Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct Peak {
+  pub bin: u32,
+  pub magnitude: f32,
+}
+
+/// Finds local maxima above a threshold in each of `batch_count` spectra of `bins` values.
+///
+/// Peaks of each batch are written in no particular order into a fixed number of slots; peaks
+/// beyond `max_peaks` are counted but dropped.
+pub struct PeakDetector {
+  kernel: Kernel,
+  device: Arc<Device>,
+  counts: Subbuffer<[u32]>,
+  peaks: Subbuffer<[Peak]>,
+  max_peaks: u32,
+}
+
+impl PeakDetector {
+  pub fn new(
+    context: &Context,
+    spectrum: Arc<Buffer>,
+    format: SpectrumFormat,
+    bins: u32,
+    batch_count: u32,
+    max_peaks: u32,
+    threshold: f32,
+  ) -> Result<Self, crate::Error> {
+    for (value, name) in [
+      (bins, "bin count"),
+      (batch_count, "batch count"),
+      (max_peaks, "peak count"),
+    ] {
+      if value == 0 {
+        return Err(ConfigError::ZeroSize(name).into());
+      }
+    }
+    // The shader indexes values and peak slots with 32-bit integers
+    let components = match format {
+      SpectrumFormat::Complex => 2,
+      SpectrumFormat::Real => 1,
+    };
+    let values = bins as u64 * batch_count as u64 * components;
+    if values > u32::MAX as u64 || batch_count as u64 * max_peaks as u64 > u32::MAX as u64 {
+      return Err(ConfigError::SizeOverflow("spectrum or peak count").into());
+    }
+    if spectrum.size() / 4 < values {
+      return Err(ConfigError::BufferTooSmall("spectrum").into());
+    }
+
+    let counts = context.new_buffer_from_iter((0..batch_count).map(|_| 0u32))?;
+    let peaks = context.new_buffer_from_iter(
+      (0..batch_count as usize * max_peaks as usize).map(|_| Peak::default()),
+    )?;
+
+    let source = match format {
+      SpectrumFormat::Complex => with_defines(PEAK_DETECTION, &["COMPLEX_INPUT"]),
+      SpectrumFormat::Real => PEAK_DETECTION.to_string(),
+    };
+    let kernel = Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      &source,
+      [spectrum, counts.buffer().clone(), peaks.buffer().clone()],
+      vec![bins, batch_count, max_peaks, threshold.to_bits()],
+      group_counts(bins as u64 * batch_count as u64),
+    )?;
+
+    Ok(Self {
+      kernel,
+      device: context.device.clone(),
+      counts,
+      peaks,
+      max_peaks,
+    })
+  }
+
+  pub fn counts(&self) -> &Subbuffer<[u32]> {
+    &self.counts
+  }
+
+  pub fn peak_buffer(&self) -> &Subbuffer<[Peak]> {
+    &self.peaks
+  }
+
+  /// Reads back the peaks found by the last submission, one list per batch, sorted by bin.
+  pub fn peaks(&self) -> Result<Vec<Vec<Peak>>, crate::Error> {
+    let counts = self.counts.read()?;
+    let peaks = self.peaks.read()?;
+
+    Ok(
+      counts
+        .iter()
+        .enumerate()
+        .map(|(batch, &count)| {
+          let start = batch * self.max_peaks as usize;
+          let count = count.min(self.max_peaks) as usize;
+          let mut batch_peaks = peaks[start..start + count].to_vec();
+          batch_peaks.sort_by_key(|p| p.bin);
+          batch_peaks
+        })
+        .collect(),
+    )
+  }
+}
+
+impl Stage for PeakDetector {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    fill_buffer(&self.device, command_buffer, self.counts.buffer(), 0);
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
@@ -0,0 +1,90 @@
+//! A process-wide cache of generated kernels, shareable between contexts.
+
+use std::{
+  collections::HashMap,
+  pin::Pin,
+  sync::{Arc, Mutex},
+};
+
+use crate::{
+  app::App,
+  bundle::BundleTarget,
+  config::{Config, PlanKey},
+  error,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+  device_uuid: Option<[u8; 16]>,
+  target: BundleTarget,
+  plan: PlanKey,
+}
+
+/// Serialized kernels keyed by device and plan. Clones share the same storage, so one cache can
+/// be handed to every `Context` in a process and each plan is generated once per device, unless
+/// it is first requested from several threads at once.
+#[derive(Clone, Default)]
+pub struct PlanCache {
+  entries: Arc<Mutex<HashMap<CacheKey, Arc<[u8]>>>>,
+}
+
+impl PlanCache {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Creates an `App` for `config`, loading its kernels from the cache if the plan was already
+  /// generated for the config's device, and generating and caching them otherwise.
+  pub fn app(&self, mut config: Config) -> error::Result<Pin<Box<App>>> {
+    // Configs built from raw handles can't identify their device, so they aren't cached
+    let physical_device = match config.vulkano_physical_device.clone() {
+      Some(physical_device) => physical_device,
+      None => return App::new(config),
+    };
+    let key = CacheKey {
+      device_uuid: physical_device.properties().device_uuid,
+      target: BundleTarget::of(&physical_device),
+      plan: config.plan_key(),
+    };
+
+    if let Some(blob) = self.get(&key) {
+      // The blob was saved by this cache from an app of the same plan and device
+      return unsafe { App::from_bytes(config, &blob) };
+    }
+
+    // Generated without the lock, so other plans aren't held up. Concurrent requests for the same
+    // plan may both generate it, and the first to finish is kept.
+    config.save_application = true;
+    let app = App::new(config)?;
+    if let Some(blob) = app.save_to_bytes() {
+      self
+        .entries
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(key)
+        .or_insert_with(|| blob.into());
+    }
+    Ok(app)
+  }
+
+  fn get(&self, key: &CacheKey) -> Option<Arc<[u8]>> {
+    let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    entries.get(key).cloned()
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn clear(&self) {
+    self
+      .entries
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .clear();
+  }
+}
@@ -0,0 +1,107 @@
+//! A structured report of how VkFFT decomposed a plan, read from a created application with
+//! [`App::plan_info`](crate::app::App::plan_info).
+//!
+//! Each axis is transformed by one or more kernels ("uploads"): one when the whole sequence fits
+//! in shared memory, several when it is split with the four-step algorithm. Each kernel performs
+//! its sequence as a series of radix stages, possibly through Bluestein's or Rader's algorithm
+//! for lengths with large prime factors.
+
+use std::convert::TryFrom;
+
+use vkfft_sys::{PfContainer, VkFFTAxis};
+
+/// One kernel of a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelInfo {
+  /// Whether the kernel belongs to the inverse transform
+  pub inverse: bool,
+  /// The axis the kernel transforms
+  pub axis: usize,
+  /// The index of the kernel among the uploads of its axis
+  pub upload: usize,
+  /// Length of the sequence the kernel transforms, or `None` if VkFFT didn't record it
+  pub length: Option<u64>,
+  /// Radices of the stages the sequence is decomposed into, in order
+  pub radices: Vec<u32>,
+  /// Workgroup size of the kernel, or `None` for dimensions VkFFT didn't record
+  pub workgroup_size: [Option<u64>; 3],
+  pub registers_per_thread: u32,
+  /// Bytes of shared memory the kernel uses, or `None` if VkFFT didn't record it
+  pub shared_memory: Option<u64>,
+  pub bluestein: bool,
+  pub rader: bool,
+}
+
+impl KernelInfo {
+  pub(crate) fn from_axis(inverse: bool, axis: usize, upload: usize, fft_axis: &VkFFTAxis) -> Self {
+    let constants = &fft_axis.specializationConstants;
+    let num_stages = (constants.numStages.max(0) as usize).min(constants.stageRadix.len());
+    Self {
+      inverse,
+      axis,
+      upload,
+      length: integer(&constants.fftDim),
+      radices: constants.stageRadix[..num_stages]
+        .iter()
+        .map(|r| *r as u32)
+        .collect(),
+      workgroup_size: [
+        integer(&constants.localSize[0]),
+        integer(&constants.localSize[1]),
+        integer(&constants.localSize[2]),
+      ],
+      registers_per_thread: constants.registers_per_thread.max(0) as u32,
+      shared_memory: integer(&constants.usedSharedMemory),
+      bluestein: constants.useBluesteinFFT != 0,
+      rader: constants.useRader != 0,
+    }
+  }
+}
+
+/// The decomposition of a whole plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanInfo {
+  /// Number of kernels per axis of the forward transform
+  pub axis_uploads: Vec<u64>,
+  /// Axes transformed with Bluestein's algorithm
+  pub bluestein_axes: Vec<usize>,
+  /// Every kernel of the forward transform, then of the inverse
+  pub kernels: Vec<KernelInfo>,
+  /// Bytes of the buffer the plan transforms, 0 if not known
+  pub buffer_size: u64,
+  /// Bytes of the temp buffer the plan uses, if any
+  pub temp_buffer_size: Option<u64>,
+}
+
+/// The value of an integer container, which VkFFT tags with a type ending in 1.
+fn integer(container: &PfContainer) -> Option<u64> {
+  if container.type_ % 10 != 1 {
+    return None;
+  }
+  u64::try_from(unsafe { container.data.i }).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_from_axis() {
+    let mut fft_axis = unsafe { std::mem::zeroed::<VkFFTAxis>() };
+    let constants = &mut fft_axis.specializationConstants;
+    constants.fftDim.type_ = 31;
+    constants.fftDim.data.i = 1000;
+    constants.localSize[0].type_ = 31;
+    constants.localSize[0].data.i = 64;
+    constants.numStages = 3;
+    constants.stageRadix[..3].copy_from_slice(&[10, 10, 10]);
+    constants.useRader = 1;
+
+    let info = KernelInfo::from_axis(true, 1, 0, &fft_axis);
+    assert_eq!(info.length, Some(1000));
+    assert_eq!(info.radices, vec![10, 10, 10]);
+    assert_eq!(info.workgroup_size, [Some(64), None, None]);
+    assert_eq!(info.shared_memory, None);
+    assert!(info.rader && !info.bluestein && info.inverse);
+  }
+}
@@ -0,0 +1,145 @@
+//! Reuse of initialized applications across identical configs.
+//!
+//! Creating an [`App`] generates and compiles its kernels, which dominates the cost of small
+//! transforms. An [`FftPlanner`] keeps every application it creates and hands out the existing
+//! one whenever a config describes the same plan on the same device and buffers, like the
+//! planners of FFTW or rustfft.
+//!
+//! ```ignore
+//! let mut planner = FftPlanner::new();
+//! let app = planner.plan(config)?;
+//! app.borrow_mut().forward(&mut params)?;
+//! ```
+//!
+//! Plans made with launch-time buffers (see
+//! [`ConfigBuilder::buffer_size`](crate::config::ConfigBuilder::buffer_size)) are independent of
+//! the data they transform, so a single application serves every buffer of that shape.
+
+use std::{cell::RefCell, collections::HashMap, pin::Pin, rc::Rc};
+
+use ash::vk;
+
+use crate::{
+  app::App,
+  config::{Config, PlanKey},
+  error,
+};
+
+/// An application handed out by an [`FftPlanner`], shared with every other user of the plan.
+pub type PlannedApp = Rc<RefCell<Pin<Box<App>>>>;
+
+/// Everything an application is bound to: the plan, the device and the buffers in its config.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PlannerKey {
+  device: vk::Device,
+  plan: PlanKey,
+  /// Handles and bound sizes of the config's buffers, or the sizes planned for launch-time ones
+  buffers: [(Option<vk::Buffer>, Option<u64>); 5],
+}
+
+impl PlannerKey {
+  fn of(config: &Config) -> Self {
+    let bindings = [
+      &config.buffer,
+      &config.temp_buffer,
+      &config.input_buffer,
+      &config.output_buffer,
+      &config.kernel,
+    ];
+    let mut buffers = [(None, None); 5];
+    for (i, binding) in bindings.iter().enumerate() {
+      buffers[i] = match binding {
+        Some(b) => (Some(b.handle()), b.bound_size()),
+        None => (None, config.launch_buffer_sizes[i]),
+      };
+    }
+
+    Self {
+      device: config.device,
+      plan: config.plan_key(),
+      buffers,
+    }
+  }
+}
+
+/// Creates applications on demand and reuses them for configs describing the same plan.
+///
+/// Applications are shared through [`PlannedApp`] handles and live as long as the planner or any
+/// handle does. Since appends rebind launch-time buffers, users sharing a plan must not have
+/// command buffers with different buffers pending at once.
+#[derive(Default)]
+pub struct FftPlanner {
+  apps: HashMap<PlannerKey, PlannedApp>,
+}
+
+impl FftPlanner {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The application for `config`, created if no config with the same plan, device and buffers
+  /// was planned before.
+  pub fn plan(&mut self, config: Config) -> error::Result<PlannedApp> {
+    let key = PlannerKey::of(&config);
+    if let Some(app) = self.apps.get(&key) {
+      return Ok(app.clone());
+    }
+
+    let app = Rc::new(RefCell::new(App::new(config)?));
+    self.apps.insert(key, app.clone());
+    Ok(app)
+  }
+
+  /// Number of applications kept.
+  pub fn len(&self) -> usize {
+    self.apps.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.apps.is_empty()
+  }
+
+  /// Drops the planner's references to its applications. Handles still in use keep theirs.
+  pub fn clear(&mut self) {
+    self.apps.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use ash::vk::Handle;
+
+  use super::*;
+  use crate::config::{ConfigBuilder, DeviceHandles};
+
+  fn config(device: u64, dim: u32) -> Config {
+    let handles = DeviceHandles {
+      physical_device: vk::PhysicalDevice::from_raw(1),
+      device: vk::Device::from_raw(device),
+      queue: vk::Queue::from_raw(3),
+      command_pool: vk::CommandPool::from_raw(4),
+      fence: vk::Fence::from_raw(5),
+    };
+    unsafe { ConfigBuilder::new().device_handles(handles) }
+      .dim(&[dim])
+      .buffer_size(dim as u64 * 8)
+      .build()
+      .unwrap()
+  }
+
+  #[test]
+  fn check_planner_key() {
+    assert_eq!(
+      PlannerKey::of(&config(2, 64)),
+      PlannerKey::of(&config(2, 64))
+    );
+    assert_ne!(
+      PlannerKey::of(&config(2, 64)),
+      PlannerKey::of(&config(2, 128))
+    );
+    assert_ne!(
+      PlannerKey::of(&config(2, 64)),
+      PlannerKey::of(&config(6, 64))
+    );
+  }
+}
@@ -0,0 +1,109 @@
+//! Control over how VkFFT transforms sequences with prime factors larger than 13.
+//!
+//! VkFFT handles a large prime `p` with Rader's algorithm, which turns it into a transform of
+//! length `p - 1`, either computed as an FFT or by direct multiplication, or with Bluestein's
+//! algorithm, which turns the sequence into a convolution of a padded, easier length. Rader's
+//! algorithm is usually more accurate, Bluestein's applies to any length. Every option left
+//! unset keeps VkFFT's choice:
+//!
+//! ```ignore
+//! let config = Config::builder()
+//!   .dim(&[4099])
+//!   .fix_max_rader_prime_fft(0)
+//!   .bluestein_padding_pattern(&[(4099, 8400)])
+//!   .build()?;
+//! ```
+
+use crate::config::ConfigError;
+
+/// Options selecting the algorithm for large primes. They change the generated kernels, so they
+/// are part of a [`PlanKey`](crate::config::PlanKey).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PrimeOptions {
+  /// Largest radix the padded sequence of Bluestein's algorithm may have,
+  /// `fixMaxRadixBluestein`.
+  pub max_radix_bluestein: Option<u64>,
+  /// Smallest prime transformed with Rader's algorithm by direct multiplication,
+  /// `fixMinRaderPrimeMult`.
+  pub min_rader_prime_mult: Option<u64>,
+  /// Largest prime transformed with Rader's algorithm by direct multiplication,
+  /// `fixMaxRaderPrimeMult`.
+  pub max_rader_prime_mult: Option<u64>,
+  /// Smallest prime transformed with Rader's algorithm through an FFT, `fixMinRaderPrimeFFT`.
+  pub min_rader_prime_fft: Option<u64>,
+  /// Largest prime transformed with Rader's algorithm through an FFT, `fixMaxRaderPrimeFFT`.
+  /// Larger primes use Bluestein's algorithm.
+  pub max_rader_prime_fft: Option<u64>,
+  /// Pairs of a sequence length and the length Bluestein's algorithm pads it to, replacing
+  /// VkFFT's choice for those lengths, `useCustomBluesteinPaddingPattern`
+  pub bluestein_padding: Vec<(u64, u64)>,
+}
+
+impl PrimeOptions {
+  pub fn is_empty(&self) -> bool {
+    *self == Self::default()
+  }
+
+  /// Checks that every padded length fits the convolution of Bluestein's algorithm, which needs
+  /// at least `2n - 1` points for a sequence of `n`.
+  pub(crate) fn check(&self) -> Result<(), ConfigError> {
+    let too_short = self
+      .bluestein_padding
+      .iter()
+      .any(|(len, padded)| *len == 0 || *padded < len.saturating_mul(2) - 1);
+    if too_short {
+      return Err(ConfigError::InvalidConfig);
+    }
+    Ok(())
+  }
+
+  /// Writes the options, except for the padding pattern, whose arrays have to outlive the
+  /// configuration.
+  pub(crate) fn write_sys(&self, config: &mut vkfft_sys::VkFFTConfiguration) {
+    if let Some(max_radix_bluestein) = self.max_radix_bluestein {
+      config.fixMaxRadixBluestein = max_radix_bluestein;
+    }
+
+    if let Some(min_rader_prime_mult) = self.min_rader_prime_mult {
+      config.fixMinRaderPrimeMult = min_rader_prime_mult;
+    }
+
+    if let Some(max_rader_prime_mult) = self.max_rader_prime_mult {
+      config.fixMaxRaderPrimeMult = max_rader_prime_mult;
+    }
+
+    if let Some(min_rader_prime_fft) = self.min_rader_prime_fft {
+      config.fixMinRaderPrimeFFT = min_rader_prime_fft;
+    }
+
+    if let Some(max_rader_prime_fft) = self.max_rader_prime_fft {
+      config.fixMaxRaderPrimeFFT = max_rader_prime_fft;
+    }
+  }
+
+  /// The sequence lengths and padded lengths of the padding pattern, as VkFFT takes them.
+  pub(crate) fn padding_arrays(&self) -> (Vec<u64>, Vec<u64>) {
+    self.bluestein_padding.iter().copied().unzip()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_padding() {
+    let options = PrimeOptions {
+      bluestein_padding: vec![(17, 33), (4099, 8400)],
+      ..Default::default()
+    };
+    assert!(options.check().is_ok());
+    assert_eq!(options.padding_arrays(), (vec![17, 4099], vec![33, 8400]));
+
+    let too_short = PrimeOptions {
+      bluestein_padding: vec![(17, 32)],
+      ..Default::default()
+    };
+    assert!(too_short.check().is_err());
+  }
+}
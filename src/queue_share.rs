@@ -0,0 +1,150 @@
+//! Coexisting with a renderer on the same device.
+//!
+//! A context created with [`Context::from_queue`](crate::context::Context::from_queue) submits
+//! transforms to a renderer's queue, and [`Context::with_queue`](crate::context::Context::with_queue)
+//! lets the renderer submit raw command buffers under the same lock. When the renderer and the
+//! transforms use queues of different families, buffers with exclusive sharing have to be
+//! transferred between them with an [`OwnershipTransfer`] on each side.
+//!
+//! ```ignore
+//! // On the compute queue, after the transform writes `spectrum`
+//! let release = OwnershipTransfer::release(device.clone(), compute_family, graphics_family)
+//!   .buffer(spectrum.clone());
+//! context.run(&mut Chain::new(device.clone()).push(fft).push(release))?;
+//!
+//! // At the start of the frame's command buffer on the graphics queue
+//! OwnershipTransfer::acquire(device.clone(), compute_family, graphics_family)
+//!   .buffer(spectrum)
+//!   .record_into(frame_command_buffer);
+//! ```
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::device::Device;
+
+use crate::{
+  buffer::{BufferBinding, FftBuffer},
+  error,
+  stage::Stage,
+};
+
+/// Which half of a queue family ownership transfer is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferSide {
+  /// Recorded on the queue giving up the buffers, after its last use of them
+  Release,
+  /// Recorded on the queue taking the buffers, before its first use of them
+  Acquire,
+}
+
+/// Barriers transferring buffers between queue families.
+///
+/// A transfer takes effect once both its release, submitted on a queue of the source family,
+/// and its matching acquire, submitted on a queue of the destination family, have executed. The
+/// acquire must not start before the release has completed, e.g. by waiting on a semaphore it
+/// signals. The barriers cover every pipeline stage, so either side can be a transform or
+/// rendering work.
+pub struct OwnershipTransfer {
+  device: Arc<Device>,
+  buffers: Vec<BufferBinding>,
+  src_queue_family: u32,
+  dst_queue_family: u32,
+  side: TransferSide,
+}
+
+impl OwnershipTransfer {
+  pub fn new(
+    device: Arc<Device>,
+    side: TransferSide,
+    src_queue_family: u32,
+    dst_queue_family: u32,
+  ) -> Self {
+    Self {
+      device,
+      buffers: Vec::new(),
+      src_queue_family,
+      dst_queue_family,
+      side,
+    }
+  }
+
+  pub fn release(device: Arc<Device>, src_queue_family: u32, dst_queue_family: u32) -> Self {
+    Self::new(
+      device,
+      TransferSide::Release,
+      src_queue_family,
+      dst_queue_family,
+    )
+  }
+
+  pub fn acquire(device: Arc<Device>, src_queue_family: u32, dst_queue_family: u32) -> Self {
+    Self::new(
+      device,
+      TransferSide::Acquire,
+      src_queue_family,
+      dst_queue_family,
+    )
+  }
+
+  pub fn buffer<B: FftBuffer>(mut self, buffer: B) -> Self {
+    self.buffers.push(buffer.binding());
+    self
+  }
+
+  pub fn side(&self) -> TransferSide {
+    self.side
+  }
+
+  /// Records the barriers into `command_buffer`, which may belong to a renderer. Nothing is
+  /// recorded when both families are the same.
+  pub fn record_into(&self, command_buffer: vk::CommandBuffer) {
+    if self.src_queue_family == self.dst_queue_family || self.buffers.is_empty() {
+      return;
+    }
+
+    let (src_access_mask, dst_access_mask) = match self.side {
+      TransferSide::Release => (vk::AccessFlags::MEMORY_WRITE, vk::AccessFlags::empty()),
+      TransferSide::Acquire => (
+        vk::AccessFlags::empty(),
+        vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+      ),
+    };
+    let barriers = self
+      .buffers
+      .iter()
+      .map(|b| vk::BufferMemoryBarrier {
+        src_access_mask,
+        dst_access_mask,
+        src_queue_family_index: self.src_queue_family,
+        dst_queue_family_index: self.dst_queue_family,
+        buffer: b.handle(),
+        offset: b.offset(),
+        size: b.size(),
+        ..Default::default()
+      })
+      .collect::<Vec<_>>();
+
+    unsafe {
+      (self.device.fns().v1_0.cmd_pipeline_barrier)(
+        command_buffer,
+        vk::PipelineStageFlags::ALL_COMMANDS,
+        vk::PipelineStageFlags::ALL_COMMANDS,
+        vk::DependencyFlags::empty(),
+        0,
+        std::ptr::null(),
+        barriers.len() as u32,
+        barriers.as_ptr(),
+        0,
+        std::ptr::null(),
+      );
+    }
+  }
+}
+
+impl Stage for OwnershipTransfer {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.record_into(command_buffer);
+    Ok(())
+  }
+}
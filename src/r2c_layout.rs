@@ -0,0 +1,228 @@
+//! Converting contiguous real arrays to and from the padded layout of in-place R2C transforms.
+//!
+//! In-place real transforms need every row of `cols` values padded to `2 * (cols / 2 + 1)`
+//! values. [`PadRows`] and [`UnpadRows`] repack buffers on the GPU, so data from other libraries
+//! can be transformed without a round trip through the host, and [`pad_rows`] and
+//! [`unpad_rows`] do the same on the host.
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  scalar::FftScalar,
+  shader::{group_counts, with_defines, words_per_value, Kernel},
+  stage::Stage,
+};
+
+const REPACK: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) readonly buffer Input { uint src[]; };
+layout(std430, binding = 1) writeonly buffer Output { uint dst[]; };
+layout(push_constant) uniform Params {
+  uint cols;
+  uint padded_cols;
+  uint rows;
+  uint words;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+#if defined(PAD)
+  uint row_len = padded_cols;
+#else
+  uint row_len = cols;
+#endif
+  if (i >= rows * row_len * words) {
+    return;
+  }
+
+  uint value = i / words;
+  uint word = i % words;
+  uint row = value / row_len;
+  uint col = value % row_len;
+
+#if defined(PAD)
+  dst[i] = col < cols ? src[(row * cols + col) * words + word] : 0;
+#else
+  dst[i] = src[(row * padded_cols + col) * words + word];
+#endif
+}
+"#;
+
+/// Shape of a real array of `rows` rows of `cols` values, where rows are the runs of values
+/// along the first axis of the transform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowLayout {
+  pub cols: u32,
+  pub rows: u32,
+}
+
+impl RowLayout {
+  pub fn new(cols: u32, rows: u32) -> Result<Self, ConfigError> {
+    let layout = Self { cols, rows };
+    layout.check(1)?;
+    Ok(layout)
+  }
+
+  /// The number of values in each padded row, with room for its spectrum.
+  pub fn padded_cols(&self) -> u32 {
+    self.stride() as u32
+  }
+
+  fn stride(&self) -> u64 {
+    2 * (self.cols as u64 / 2 + 1)
+  }
+
+  /// Rejects empty arrays and ones whose indices overflow the shader's 32-bit index math, for
+  /// values of `words` words each.
+  fn check(&self, words: u32) -> Result<(), ConfigError> {
+    if self.cols == 0 || self.rows == 0 {
+      return Err(ConfigError::ZeroSize("row layout"));
+    }
+    if self.padded_len() * words as u64 > u32::MAX as u64 {
+      return Err(ConfigError::SizeOverflow("row layout"));
+    }
+    Ok(())
+  }
+
+  /// The number of values in the contiguous array.
+  pub fn compact_len(&self) -> u64 {
+    self.rows as u64 * self.cols as u64
+  }
+
+  /// The number of values in the padded array.
+  pub fn padded_len(&self) -> u64 {
+    self.rows as u64 * self.stride()
+  }
+
+  fn kernel<T: FftScalar>(
+    &self,
+    context: &Context,
+    input: Arc<Buffer>,
+    output: Arc<Buffer>,
+    pad: bool,
+  ) -> Result<Kernel, crate::Error> {
+    let words = words_per_value(std::mem::size_of::<T>())?;
+    self.check(words)?;
+    let (defines, input_len, output_len): (&[&str], _, _) = if pad {
+      (&["PAD"], self.compact_len(), self.padded_len())
+    } else {
+      (&[], self.padded_len(), self.compact_len())
+    };
+    if input.size() / 4 < input_len * words as u64 {
+      return Err(ConfigError::BufferTooSmall("input").into());
+    }
+    if output.size() / 4 < output_len * words as u64 {
+      return Err(ConfigError::BufferTooSmall("output").into());
+    }
+
+    Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      &with_defines(REPACK, defines),
+      [input, output],
+      vec![self.cols, self.padded_cols(), self.rows, words],
+      group_counts(output_len * words as u64),
+    )
+  }
+}
+
+/// Copies a contiguous real array into the padded layout of in-place R2C transforms, zeroing the
+/// padding.
+pub struct PadRows {
+  kernel: Kernel,
+}
+
+impl PadRows {
+  /// Pads the rows of `compact` into `padded`, which hold values of `T`.
+  /// Real `f16` values are too small to copy and fail.
+  pub fn new<T: FftScalar>(
+    context: &Context,
+    compact: Arc<Buffer>,
+    padded: Arc<Buffer>,
+    layout: RowLayout,
+  ) -> Result<Self, crate::Error> {
+    Ok(Self {
+      kernel: layout.kernel::<T>(context, compact, padded, true)?,
+    })
+  }
+}
+
+impl Stage for PadRows {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+/// Copies the real values out of the padded layout of in-place R2C transforms, e.g. after an
+/// inverse transform, into a contiguous array.
+pub struct UnpadRows {
+  kernel: Kernel,
+}
+
+impl UnpadRows {
+  /// Strips the padding of the rows of `padded` into `compact`, which hold values of `T`.
+  /// Real `f16` values are too small to copy and fail.
+  pub fn new<T: FftScalar>(
+    context: &Context,
+    padded: Arc<Buffer>,
+    compact: Arc<Buffer>,
+    layout: RowLayout,
+  ) -> Result<Self, crate::Error> {
+    Ok(Self {
+      kernel: layout.kernel::<T>(context, padded, compact, false)?,
+    })
+  }
+}
+
+impl Stage for UnpadRows {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+/// The host equivalent of [`PadRows`], for rows of `cols` values in `data`.
+pub fn pad_rows<T: Copy + Default>(data: &[T], cols: usize) -> Vec<T> {
+  let padded_cols = 2 * (cols / 2 + 1);
+  data
+    .chunks_exact(cols)
+    .flat_map(|row| {
+      row
+        .iter()
+        .copied()
+        .chain(std::iter::repeat_n(T::default(), padded_cols - cols))
+    })
+    .collect()
+}
+
+/// The host equivalent of [`UnpadRows`], for rows of `cols` values in `data`.
+pub fn unpad_rows<T: Copy>(data: &[T], cols: usize) -> Vec<T> {
+  data
+    .chunks_exact(2 * (cols / 2 + 1))
+    .flat_map(|row| row[..cols].iter().copied())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_host_repacking() {
+    let layout = RowLayout::new(3, 2).unwrap();
+    assert_eq!((layout.padded_cols(), layout.padded_len()), (4, 8));
+    assert!(RowLayout::new(0, 2).is_err());
+    assert!(RowLayout::new(u32::MAX, 2).is_err());
+
+    let padded = pad_rows(&[1, 2, 3, 4, 5, 6], 3);
+    assert_eq!(padded, [1, 2, 3, 0, 4, 5, 6, 0]);
+    assert_eq!(unpad_rows(&padded, 3), [1, 2, 3, 4, 5, 6]);
+  }
+}
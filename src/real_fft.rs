@@ -0,0 +1,204 @@
+//! 2D real transforms without the padded layout.
+//!
+//! In-place real transforms pad every row of real values to `2 * (nx / 2 + 1)` values, the size
+//! of its spectrum. A [`RealFft2d`] instead owns an unpadded buffer of real values and a separate
+//! buffer of `nx / 2 + 1` by `ny` complex values, and both are indexed by their logical `(x, y)`
+//! coordinates:
+//!
+//! ```ignore
+//! let mut fft = RealFft2d::<f32>::new(&context, [640, 480])?;
+//! fft.real_view_mut()?[(10, 20)] = 1.0;
+//! fft.forward()?;
+//! let [re, im] = fft.spectrum_view()?[(3, 4)];
+//! fft.inverse()?;
+//! ```
+//!
+//! The inverse transform is normalized and returns to the real buffer.
+
+use std::{
+  convert::TryInto,
+  ops::{Deref, DerefMut, Index, IndexMut},
+};
+
+use vulkano::buffer::{BufferReadGuard, BufferWriteGuard, Subbuffer};
+
+use crate::{
+  config::Config,
+  context::{Context, FftType},
+  scalar::FftScalar,
+  stage::FftStage,
+};
+
+/// A planned 2D real transform with buffers of its own.
+pub struct RealFft2d<'c, T: FftScalar> {
+  context: &'c Context,
+  forward: FftStage,
+  inverse: FftStage,
+  real: Subbuffer<[T]>,
+  spectrum: Subbuffer<[T]>,
+  dims: [usize; 2],
+}
+
+impl<'c, T: FftScalar> RealFft2d<'c, T> {
+  /// Allocates buffers for real data of `nx` by `ny` values and plans their transform.
+  pub fn new(context: &'c Context, [nx, ny]: [u32; 2]) -> Result<Self, crate::Error> {
+    let dims = [nx as usize, ny as usize];
+    let real =
+      context.new_buffer_from_iter(std::iter::repeat_n(T::default(), dims[0] * dims[1]))?;
+    let spectrum = context.new_buffer_from_iter(std::iter::repeat_n(
+      T::default(),
+      2 * (dims[0] / 2 + 1) * dims[1],
+    ))?;
+    let config_builder = Config::builder()
+      .dim(&[nx, ny])
+      .r2c()
+      .input_buffer(real.clone())
+      .input_formatted(true)
+      .inverse_return_to_input()
+      .buffer(spectrum.clone())
+      .normalize()
+      .precision(T::PRECISION);
+    let forward = context.fft_stage(config_builder, FftType::Forward)?;
+    let inverse = forward.reversed();
+    Ok(Self {
+      context,
+      forward,
+      inverse,
+      real,
+      spectrum,
+      dims,
+    })
+  }
+
+  /// Transforms the real buffer into the spectrum buffer, blocking until it has completed.
+  pub fn forward(&mut self) -> Result<(), crate::Error> {
+    self.context.run(&mut self.forward)
+  }
+
+  /// Transforms the spectrum buffer back into the real buffer, blocking until it has completed.
+  pub fn inverse(&mut self) -> Result<(), crate::Error> {
+    self.context.run(&mut self.inverse)
+  }
+
+  /// The buffer of `nx * ny` real values, row by row.
+  pub fn real_buffer(&self) -> &Subbuffer<[T]> {
+    &self.real
+  }
+
+  /// The buffer of `nx / 2 + 1` by `ny` complex values, as interleaved real and imaginary parts.
+  pub fn spectrum_buffer(&self) -> &Subbuffer<[T]> {
+    &self.spectrum
+  }
+
+  /// The real buffer, indexed by `(x, y)`, once the transforms writing it have completed.
+  pub fn real_view(&self) -> Result<RealView<BufferReadGuard<'_, [T]>>, crate::Error> {
+    Ok(RealView::new(self.real.read()?, self.dims[0]))
+  }
+
+  /// Like [`RealFft2d::real_view`], for writing input.
+  pub fn real_view_mut(&self) -> Result<RealView<BufferWriteGuard<'_, [T]>>, crate::Error> {
+    Ok(RealView::new(self.real.write()?, self.dims[0]))
+  }
+
+  /// The spectrum buffer, indexed by `(x, y)` for `x` up to `nx / 2`.
+  pub fn spectrum_view(&self) -> Result<SpectrumView<BufferReadGuard<'_, [T]>>, crate::Error> {
+    Ok(SpectrumView::new(
+      self.spectrum.read()?,
+      self.dims[0] / 2 + 1,
+    ))
+  }
+
+  /// Like [`RealFft2d::spectrum_view`], for filtering the spectrum before the inverse.
+  pub fn spectrum_view_mut(&self) -> Result<SpectrumView<BufferWriteGuard<'_, [T]>>, crate::Error> {
+    Ok(SpectrumView::new(
+      self.spectrum.write()?,
+      self.dims[0] / 2 + 1,
+    ))
+  }
+}
+
+/// Real values indexed by `(x, y)`.
+pub struct RealView<G> {
+  data: G,
+  width: usize,
+}
+
+impl<G> RealView<G> {
+  pub fn new(data: G, width: usize) -> Self {
+    Self { data, width }
+  }
+
+  /// The number of values along x.
+  pub fn width(&self) -> usize {
+    self.width
+  }
+}
+
+impl<T, G: Deref<Target = [T]>> Index<(usize, usize)> for RealView<G> {
+  type Output = T;
+
+  fn index(&self, (x, y): (usize, usize)) -> &T {
+    assert!(x < self.width);
+    &self.data[y * self.width + x]
+  }
+}
+
+impl<T, G: DerefMut<Target = [T]>> IndexMut<(usize, usize)> for RealView<G> {
+  fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+    assert!(x < self.width);
+    &mut self.data[y * self.width + x]
+  }
+}
+
+/// Complex values, as `[re, im]` pairs, indexed by `(x, y)`.
+pub struct SpectrumView<G> {
+  data: G,
+  width: usize,
+}
+
+impl<G> SpectrumView<G> {
+  pub fn new(data: G, width: usize) -> Self {
+    Self { data, width }
+  }
+
+  /// The number of values along x, `nx / 2 + 1` for spectra of real transforms.
+  pub fn width(&self) -> usize {
+    self.width
+  }
+}
+
+impl<T, G: Deref<Target = [T]>> Index<(usize, usize)> for SpectrumView<G> {
+  type Output = [T; 2];
+
+  fn index(&self, (x, y): (usize, usize)) -> &[T; 2] {
+    assert!(x < self.width);
+    let index = 2 * (y * self.width + x);
+    self.data[index..index + 2].try_into().unwrap()
+  }
+}
+
+impl<T, G: DerefMut<Target = [T]>> IndexMut<(usize, usize)> for SpectrumView<G> {
+  fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut [T; 2] {
+    assert!(x < self.width);
+    let index = 2 * (y * self.width + x);
+    (&mut self.data[index..index + 2]).try_into().unwrap()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_view_indexing() {
+    let mut real = RealView::new((0..12).collect::<Vec<_>>(), 4);
+    assert_eq!(real[(1, 2)], 9);
+    real[(3, 0)] = 42;
+    assert_eq!(real.data[3], 42);
+
+    let mut spectrum = SpectrumView::new((0..12).collect::<Vec<_>>(), 3);
+    assert_eq!(spectrum[(2, 1)], [10, 11]);
+    spectrum[(0, 1)] = [-1, -2];
+    assert_eq!(spectrum.data[6..8], [-1, -2]);
+  }
+}
@@ -0,0 +1,215 @@
+//! Recording transforms into command buffers built with vulkano.
+//!
+//! [`FftRecordExt`] appends VkFFT dispatches, or any other [`Stage`], to an
+//! `AutoCommandBufferBuilder`, so transforms can be interleaved with an application's own
+//! dispatches in a single command buffer:
+//!
+//! ```ignore
+//! let mut builder = AutoCommandBufferBuilder::primary(allocator, family, usage)?;
+//! builder.dispatch(window_groups)?;
+//! unsafe { builder.record_fft(family, &mut app, &mut params, FftType::Forward)? };
+//! builder.dispatch(magnitude_groups)?;
+//! ```
+//!
+//! The stage is recorded into a secondary command buffer executed by the builder, between full
+//! memory barriers ordering it with the commands before and after it. vulkano doesn't see which
+//! buffers VkFFT accesses, so it neither keeps them alive nor accounts for them when checking
+//! later host accesses, which is why recording is unsafe.
+
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
+
+use vulkano::{
+  command_buffer::{
+    allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo},
+    AutoCommandBufferBuilder, CommandBuffer, CommandBufferBeginInfo, CommandBufferInheritanceInfo,
+    CommandBufferLevel, CommandBufferUsage, PrimaryAutoCommandBuffer, RecordingCommandBuffer,
+    SecondaryCommandBufferAbstract, SecondaryCommandBufferResourcesUsage,
+  },
+  device::{Device, DeviceOwned},
+  ValidationError, VulkanObject,
+};
+
+use crate::{
+  app::{App, LaunchParams},
+  context::FftType,
+  error,
+  gpu_future::Fenced,
+  stage::Stage,
+};
+
+/// A one-time secondary command buffer holding a recorded stage.
+pub struct FftCommands {
+  inner: CommandBuffer,
+  recorded: AtomicBool,
+  resources_usage: SecondaryCommandBufferResourcesUsage,
+}
+
+impl FftCommands {
+  /// Records `stage` between memory barriers into a secondary command buffer of
+  /// `queue_family_index`.
+  ///
+  /// # Safety
+  ///
+  /// `stage`, its applications and the buffers they are bound to must stay alive until every
+  /// command buffer executing the commands has finished executing.
+  pub unsafe fn new<S>(
+    device: Arc<Device>,
+    queue_family_index: u32,
+    stage: &mut S,
+  ) -> Result<Arc<Self>, Box<dyn std::error::Error>>
+  where
+    S: Stage + ?Sized,
+  {
+    let allocator = Arc::new(StandardCommandBufferAllocator::new(
+      device.clone(),
+      StandardCommandBufferAllocatorCreateInfo::default(),
+    ));
+    let recording = RecordingCommandBuffer::new(
+      allocator,
+      queue_family_index,
+      CommandBufferLevel::Secondary,
+      CommandBufferBeginInfo {
+        usage: CommandBufferUsage::OneTimeSubmit,
+        inheritance_info: Some(CommandBufferInheritanceInfo::default()),
+        ..Default::default()
+      },
+    )?;
+    Fenced { device, stage }.record(recording.handle())?;
+
+    Ok(Arc::new(Self {
+      inner: recording.end()?,
+      recorded: AtomicBool::new(false),
+      resources_usage: SecondaryCommandBufferResourcesUsage::default(),
+    }))
+  }
+}
+
+unsafe impl VulkanObject for FftCommands {
+  type Handle = ash::vk::CommandBuffer;
+
+  fn handle(&self) -> Self::Handle {
+    self.inner.handle()
+  }
+}
+
+unsafe impl DeviceOwned for FftCommands {
+  fn device(&self) -> &Arc<Device> {
+    self.inner.device()
+  }
+}
+
+unsafe impl SecondaryCommandBufferAbstract for FftCommands {
+  fn as_raw(&self) -> &CommandBuffer {
+    &self.inner
+  }
+
+  fn usage(&self) -> CommandBufferUsage {
+    self.inner.usage()
+  }
+
+  fn inheritance_info(&self) -> &CommandBufferInheritanceInfo {
+    self.inner.inheritance_info().unwrap()
+  }
+
+  fn lock_record(&self) -> Result<(), Box<ValidationError>> {
+    if self.recorded.swap(true, Ordering::SeqCst) {
+      return Err(Box::new(ValidationError {
+        problem: "the FFT commands were already recorded into a command buffer".into(),
+        ..Default::default()
+      }));
+    }
+    Ok(())
+  }
+
+  unsafe fn unlock(&self) {}
+
+  fn resources_usage(&self) -> &SecondaryCommandBufferResourcesUsage {
+    &self.resources_usage
+  }
+}
+
+/// Appends transforms to a primary command buffer being built with vulkano.
+///
+/// `queue_family_index` must be the family the builder was created for.
+pub trait FftRecordExt {
+  /// Appends a transform of `app` in the direction of `fft_type`, overwriting the command
+  /// buffer of `params`.
+  ///
+  /// # Safety
+  ///
+  /// `app` and the buffers bound to it or to `params` must stay alive until the command buffer
+  /// has finished executing.
+  unsafe fn record_fft(
+    &mut self,
+    queue_family_index: u32,
+    app: &mut App,
+    params: &mut LaunchParams,
+    fft_type: FftType,
+  ) -> Result<&mut Self, Box<dyn std::error::Error>>;
+
+  /// Appends any stage, such as a [`Chain`](crate::stage::Chain) of transforms and kernels.
+  ///
+  /// # Safety
+  ///
+  /// `stage`, its applications and the buffers they are bound to must stay alive until the
+  /// command buffer has finished executing.
+  unsafe fn record_stage<S>(
+    &mut self,
+    queue_family_index: u32,
+    stage: &mut S,
+  ) -> Result<&mut Self, Box<dyn std::error::Error>>
+  where
+    S: Stage + ?Sized;
+}
+
+/// A borrowed application launched in a fixed direction.
+struct Launch<'s> {
+  app: &'s mut App,
+  params: &'s mut LaunchParams,
+  fft_type: FftType,
+}
+
+impl Stage for Launch<'_> {
+  fn record(&mut self, command_buffer: ash::vk::CommandBuffer) -> error::Result<()> {
+    self.params.command_buffer = command_buffer;
+    match self.fft_type {
+      FftType::Forward => self.app.forward(self.params),
+      FftType::Inverse => self.app.inverse(self.params),
+    }
+  }
+}
+
+impl FftRecordExt for AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> {
+  unsafe fn record_fft(
+    &mut self,
+    queue_family_index: u32,
+    app: &mut App,
+    params: &mut LaunchParams,
+    fft_type: FftType,
+  ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+    self.record_stage(
+      queue_family_index,
+      &mut Launch {
+        app,
+        params,
+        fft_type,
+      },
+    )
+  }
+
+  unsafe fn record_stage<S>(
+    &mut self,
+    queue_family_index: u32,
+    stage: &mut S,
+  ) -> Result<&mut Self, Box<dyn std::error::Error>>
+  where
+    S: Stage + ?Sized,
+  {
+    let commands = FftCommands::new(self.device().clone(), queue_family_index, stage)?;
+    self.execute_commands(commands)?;
+    Ok(self)
+  }
+}
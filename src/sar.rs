@@ -0,0 +1,266 @@
+//! Building blocks for a basic synthetic-aperture-radar range-Doppler focusing chain.
+//!
+//! Raw data is expected as interleaved complex `f32` samples, with the `range_samples` fast-time
+//! samples of each azimuth line stored contiguously. Range processing works on that layout; a
+//! corner turn then transposes the data so azimuth processing can run on contiguous rows as well.
+
+use std::sync::Arc;
+
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::{Config, ConfigError},
+  context::{Context, FftType},
+  shader::{group_counts, Kernel},
+  stage::{Chain, FftStage},
+};
+
+const MATCHED_FILTER: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) buffer Data { vec2 data[]; };
+layout(std430, binding = 1) readonly buffer Filter { vec2 filter_[]; };
+layout(push_constant) uniform Params { uint filter_len; uint total; };
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= total) {
+    return;
+  }
+  vec2 a = data[i];
+  vec2 b = filter_[i % filter_len];
+  data[i] = vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+"#;
+
+const CORNER_TURN: &str = r#"
+#version 450
+layout(local_size_x = 16, local_size_y = 16) in;
+layout(std430, binding = 0) readonly buffer Input { vec2 src[]; };
+layout(std430, binding = 1) writeonly buffer Output { vec2 dst[]; };
+layout(push_constant) uniform Params { uint rows; uint cols; };
+
+shared vec2 tile[16][17];
+
+void main() {
+  uvec2 origin = gl_WorkGroupID.xy * 16;
+  uvec2 local = gl_LocalInvocationID.xy;
+
+  uint x = origin.x + local.x;
+  uint y = origin.y + local.y;
+  if (x < cols && y < rows) {
+    tile[local.y][local.x] = src[y * cols + x];
+  }
+
+  barrier();
+
+  x = origin.y + local.x;
+  y = origin.x + local.y;
+  if (x < rows && y < cols) {
+    dst[y * rows + x] = tile[local.x][local.y];
+  }
+}
+"#;
+
+/// Dimensions of a raw SAR data block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SarGeometry {
+  /// Number of complex fast-time samples per azimuth line
+  pub range_samples: u32,
+  /// Number of azimuth lines (pulses)
+  pub azimuth_lines: u32,
+}
+
+impl SarGeometry {
+  pub fn new(range_samples: u32, azimuth_lines: u32) -> Self {
+    Self {
+      range_samples,
+      azimuth_lines,
+    }
+  }
+
+  pub fn samples(&self) -> u64 {
+    self.range_samples as u64 * self.azimuth_lines as u64
+  }
+}
+
+/// Batched in-place FFT along range, one transform per azimuth line.
+pub fn range_fft(
+  context: &Context,
+  data: Arc<Buffer>,
+  geometry: SarGeometry,
+  fft_type: FftType,
+) -> Result<FftStage, crate::Error> {
+  let config_builder = Config::builder()
+    .buffer(data)
+    .dim(&[geometry.range_samples])
+    .batch_count(geometry.azimuth_lines)
+    .normalize();
+  context.fft_stage(config_builder, fft_type)
+}
+
+/// Batched in-place FFT along azimuth, one transform per range bin. Expects corner-turned data.
+pub fn azimuth_fft(
+  context: &Context,
+  data: Arc<Buffer>,
+  geometry: SarGeometry,
+  fft_type: FftType,
+) -> Result<FftStage, crate::Error> {
+  let config_builder = Config::builder()
+    .buffer(data)
+    .dim(&[geometry.azimuth_lines])
+    .batch_count(geometry.range_samples)
+    .normalize();
+  context.fft_stage(config_builder, fft_type)
+}
+
+/// Multiplies `total` complex samples of `data` in place by `filter`, which holds `filter_len`
+/// complex samples and is repeated across the data. A filter of one row length is applied to
+/// every row; a filter as long as the data applies a distinct response per row.
+pub fn matched_filter(
+  context: &Context,
+  data: Arc<Buffer>,
+  filter: Arc<Buffer>,
+  filter_len: u32,
+  total: u64,
+) -> Result<Kernel, crate::Error> {
+  if filter_len == 0 {
+    return Err(ConfigError::ZeroSize("filter length").into());
+  }
+  if total > u32::MAX as u64 {
+    return Err(ConfigError::SizeOverflow("sample count").into());
+  }
+  if data.size() / 8 < total {
+    return Err(ConfigError::BufferTooSmall("data").into());
+  }
+  if filter.size() / 8 < filter_len as u64 {
+    return Err(ConfigError::BufferTooSmall("filter").into());
+  }
+
+  Kernel::new(
+    context.descriptor_set_allocator.clone(),
+    MATCHED_FILTER,
+    [data, filter],
+    vec![filter_len, total as u32],
+    group_counts(total),
+  )
+}
+
+/// Transposes a `rows` x `cols` complex matrix from `input` into `output`.
+pub fn corner_turn(
+  context: &Context,
+  input: Arc<Buffer>,
+  output: Arc<Buffer>,
+  rows: u32,
+  cols: u32,
+) -> Result<Kernel, crate::Error> {
+  let total = rows as u64 * cols as u64;
+  if total > u32::MAX as u64 {
+    return Err(ConfigError::SizeOverflow("matrix size").into());
+  }
+  if input.size() / 8 < total {
+    return Err(ConfigError::BufferTooSmall("input").into());
+  }
+  if output.size() / 8 < total {
+    return Err(ConfigError::BufferTooSmall("output").into());
+  }
+
+  Kernel::new(
+    context.descriptor_set_allocator.clone(),
+    CORNER_TURN,
+    [input, output],
+    vec![rows, cols],
+    [cols.div_ceil(16), rows.div_ceil(16), 1],
+  )
+}
+
+/// Range compression: forward range FFT, multiplication by the range matched filter spectrum
+/// (`range_samples` complex values) and inverse range FFT, all in place.
+pub fn range_compression(
+  context: &Context,
+  data: Arc<Buffer>,
+  range_filter: Arc<Buffer>,
+  geometry: SarGeometry,
+) -> Result<Chain, crate::Error> {
+  let forward = range_fft(context, data.clone(), geometry, FftType::Forward)?;
+  let inverse = forward.reversed();
+  let filter = matched_filter(
+    context,
+    data,
+    range_filter,
+    geometry.range_samples,
+    geometry.samples(),
+  )?;
+
+  Ok(
+    Chain::new(context.device.clone())
+      .push(forward)
+      .push(filter)
+      .push(inverse),
+  )
+}
+
+/// Azimuth compression on corner-turned data: forward azimuth FFT, multiplication by the
+/// azimuth matched filter and inverse azimuth FFT. `azimuth_filter` holds either
+/// `azimuth_lines` complex values shared by all range bins, or a full
+/// `range_samples * azimuth_lines` block with one response per range bin.
+pub fn azimuth_compression(
+  context: &Context,
+  data: Arc<Buffer>,
+  azimuth_filter: Arc<Buffer>,
+  azimuth_filter_len: u32,
+  geometry: SarGeometry,
+) -> Result<Chain, crate::Error> {
+  let forward = azimuth_fft(context, data.clone(), geometry, FftType::Forward)?;
+  let inverse = forward.reversed();
+  let filter = matched_filter(
+    context,
+    data,
+    azimuth_filter,
+    azimuth_filter_len,
+    geometry.samples(),
+  )?;
+
+  Ok(
+    Chain::new(context.device.clone())
+      .push(forward)
+      .push(filter)
+      .push(inverse),
+  )
+}
+
+/// A complete basic focusing chain: range compression of `raw` in place, a corner turn into
+/// `turned`, and azimuth compression of `turned` in place. The focused image ends up in `turned`
+/// with range bins as rows.
+pub fn focus(
+  context: &Context,
+  raw: Arc<Buffer>,
+  turned: Arc<Buffer>,
+  range_filter: Arc<Buffer>,
+  azimuth_filter: Arc<Buffer>,
+  azimuth_filter_len: u32,
+  geometry: SarGeometry,
+) -> Result<Chain, crate::Error> {
+  let range = range_compression(context, raw.clone(), range_filter, geometry)?;
+  let turn = corner_turn(
+    context,
+    raw,
+    turned.clone(),
+    geometry.azimuth_lines,
+    geometry.range_samples,
+  )?;
+  let azimuth = azimuth_compression(
+    context,
+    turned,
+    azimuth_filter,
+    azimuth_filter_len,
+    geometry,
+  )?;
+
+  Ok(
+    Chain::new(context.device.clone())
+      .push(range)
+      .push(turn)
+      .push(azimuth),
+  )
+}
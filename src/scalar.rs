@@ -0,0 +1,205 @@
+//! Element types of transform buffers.
+//!
+//! The one-shot helpers of [`Context`](crate::context::Context) are generic over
+//! [`FftScalar`], and pick the plan's [`Precision`] from it, so switching precision only takes
+//! changing a type parameter.
+
+use std::{
+  fmt::Debug,
+  ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use crate::config::Precision;
+
+/// A floating point type VkFFT can transform. Complex values are stored as interleaved pairs of
+/// it.
+#[cfg(feature = "vulkano")]
+pub trait FftScalar:
+  vulkano::buffer::BufferContents + Copy + Default + PartialEq + Debug + Send + Sync + 'static
+{
+  const PRECISION: Precision;
+
+  fn from_f64(value: f64) -> Self;
+  fn to_f64(self) -> f64;
+}
+
+/// A floating point type VkFFT can transform. Complex values are stored as interleaved pairs of
+/// it.
+#[cfg(not(feature = "vulkano"))]
+pub trait FftScalar: Copy + Default + PartialEq + Debug + Send + Sync + 'static {
+  const PRECISION: Precision;
+
+  fn from_f64(value: f64) -> Self;
+  fn to_f64(self) -> f64;
+}
+
+impl FftScalar for f32 {
+  const PRECISION: Precision = Precision::Single;
+
+  fn from_f64(value: f64) -> Self {
+    value as f32
+  }
+
+  fn to_f64(self) -> f64 {
+    self as f64
+  }
+}
+
+impl FftScalar for f64 {
+  const PRECISION: Precision = Precision::Double;
+
+  fn from_f64(value: f64) -> Self {
+    value
+  }
+
+  fn to_f64(self) -> f64 {
+    self
+  }
+}
+
+#[cfg(feature = "f16")]
+impl FftScalar for half::f16 {
+  const PRECISION: Precision = Precision::Half;
+
+  fn from_f64(value: f64) -> Self {
+    half::f16::from_f64(value)
+  }
+
+  fn to_f64(self) -> f64 {
+    half::f16::to_f64(self)
+  }
+}
+
+/// A double-double number, `hi + lo` with `|lo|` at most half an ulp of `hi`, the element type of
+/// [`Precision::QuadDoubleDouble`] buffers. The arithmetic operators use the usual double-double
+/// algorithms, so references can be computed on the host at the same precision.
+#[cfg_attr(feature = "vulkano", derive(vulkano::buffer::BufferContents))]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct DoubleDouble {
+  pub hi: f64,
+  pub lo: f64,
+}
+
+impl DoubleDouble {
+  pub fn new(hi: f64, lo: f64) -> Self {
+    let (hi, lo) = quick_two_sum(hi, lo);
+    Self { hi, lo }
+  }
+
+  pub fn sqrt(self) -> Self {
+    if self.hi <= 0.0 {
+      return Self::default();
+    }
+    // One Newton step from the double precision root doubles its bits
+    let root = self.hi.sqrt();
+    let (square, error) = two_prod(root, root);
+    let residual = self
+      - Self {
+        hi: square,
+        lo: error,
+      };
+    Self::new(root, residual.hi / (2.0 * root))
+  }
+}
+
+impl FftScalar for DoubleDouble {
+  const PRECISION: Precision = Precision::QuadDoubleDouble;
+
+  fn from_f64(value: f64) -> Self {
+    Self { hi: value, lo: 0.0 }
+  }
+
+  fn to_f64(self) -> f64 {
+    self.hi + self.lo
+  }
+}
+
+/// `a + b` and its rounding error, for `|a| >= |b|`.
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+  let s = a + b;
+  (s, b - (s - a))
+}
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+  let s = a + b;
+  let v = s - a;
+  (s, (a - (s - v)) + (b - v))
+}
+
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+  let p = a * b;
+  (p, a.mul_add(b, -p))
+}
+
+impl Add for DoubleDouble {
+  type Output = Self;
+
+  fn add(self, other: Self) -> Self {
+    let (s, e) = two_sum(self.hi, other.hi);
+    let (t, f) = two_sum(self.lo, other.lo);
+    let (s, e) = quick_two_sum(s, e + t);
+    Self::new(s, e + f)
+  }
+}
+
+impl Neg for DoubleDouble {
+  type Output = Self;
+
+  fn neg(self) -> Self {
+    Self {
+      hi: -self.hi,
+      lo: -self.lo,
+    }
+  }
+}
+
+impl Sub for DoubleDouble {
+  type Output = Self;
+
+  fn sub(self, other: Self) -> Self {
+    self + -other
+  }
+}
+
+impl Mul for DoubleDouble {
+  type Output = Self;
+
+  fn mul(self, other: Self) -> Self {
+    let (p, e) = two_prod(self.hi, other.hi);
+    Self::new(p, e + (self.hi * other.lo + self.lo * other.hi))
+  }
+}
+
+impl Div for DoubleDouble {
+  type Output = Self;
+
+  fn div(self, other: Self) -> Self {
+    let q = self.hi / other.hi;
+    let remainder = self - other * Self::from_f64(q);
+    let r = remainder.hi / other.hi;
+    let remainder = remainder - other * Self::from_f64(r);
+    Self::new(q, r) + Self::from_f64(remainder.hi / other.hi)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_double_double() {
+    let two = DoubleDouble::from_f64(2.0);
+    let root = two.sqrt();
+    assert!(root.lo != 0.0);
+    assert!((root * root - two).to_f64().abs() < 1e-30);
+
+    let third = DoubleDouble::from_f64(1.0) / DoubleDouble::from_f64(3.0);
+    assert!(
+      (third * DoubleDouble::from_f64(3.0) - DoubleDouble::from_f64(1.0))
+        .to_f64()
+        .abs()
+        < 1e-31
+    );
+  }
+}
@@ -0,0 +1,631 @@
+//! A persistent FFT service for other processes on the same machine.
+//!
+//! The `vkfft-service` binary keeps a device and its plans alive and accepts requests on a Unix
+//! socket. Data is exchanged through shared memory: clients write their input to a file, usually
+//! on a tmpfs such as `/dev/shm` that they map into their own address space, and the service
+//! transforms it in place. [`Client`] implements the protocol for Rust programs.
+//!
+//! The protocol is line based. Each request is answered with `OK`, optionally followed by a
+//! value, or `ERR` followed by a message:
+//!
+//! ```text
+//! PLAN <spec>                                -> OK <buffer bytes>
+//! FFT <forward|inverse> <path> <offset> <spec> -> OK
+//! PING                                       -> OK
+//! ```
+//!
+//! A `<spec>` is a dimension list such as `512x512`, followed by any of `batch=N`, `r2c`,
+//! `normalize` and `double`. `PLAN` generates the plan ahead of time and reports the size of
+//! the buffer it transforms; `FFT` transforms that many bytes of `path` starting at `offset`.
+//! Paths may not contain whitespace and must lie in the data directory the service was started
+//! with. They are opened relative to the directory without following links, so a path can't be
+//! swapped for a link out of it while the request runs.
+//!
+//! Plans whose buffer exceeds the device's largest allocation, or the limit set with
+//! [`Server::with_max_buffer_len`], are refused, and once [`Server::with_max_plans`] plans are
+//! warm, the least recently used one is dropped to make room for a new one.
+
+use std::{
+  collections::HashMap,
+  convert::TryFrom,
+  error::Error,
+  ffi::{CString, OsStr},
+  fmt,
+  fs::{File, OpenOptions},
+  io::{self, BufRead, BufReader, Write},
+  os::unix::{
+    ffi::OsStrExt,
+    fs::{FileExt, OpenOptionsExt},
+    io::{AsRawFd, FromRawFd},
+    net::{UnixListener, UnixStream},
+  },
+  path::{Component, Path, PathBuf},
+  str::FromStr,
+  sync::mpsc::{self, Sender},
+};
+
+use derive_more::{Display, Error};
+use vulkano::buffer::Subbuffer;
+
+use crate::{
+  config::{Config, Precision},
+  context::{Context, FftType},
+  stage::FftStage,
+};
+
+#[derive(Display, Debug, Error, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+  #[display("Unknown command {:?}", _0)]
+  UnknownCommand(#[error(not(source))] String),
+  #[display("Invalid argument {:?}", _0)]
+  InvalidArgument(#[error(not(source))] String),
+  #[display("Missing argument")]
+  MissingArgument,
+  #[display("The buffer of {:?} does not fit in 64 bits", _0)]
+  TooLarge(#[error(not(source))] String),
+  #[display("The buffer of {:?} exceeds the limit of {} bytes", _0, _1)]
+  BufferLimit(String, #[error(not(source))] u64),
+  #[display("{:?} is outside the data directory", _0)]
+  OutsideDataDir(#[error(not(source))] PathBuf),
+  #[display("The service failed: {}", _0)]
+  Service(#[error(not(source))] String),
+}
+
+/// The transform a plan computes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlanSpec {
+  /// Transform size along each axis, fastest first
+  pub size: Vec<u32>,
+  pub batch_count: u32,
+  pub r2c: bool,
+  pub normalize: bool,
+  pub double: bool,
+}
+
+impl PlanSpec {
+  pub fn new(size: &[u32]) -> Self {
+    assert!((1..=3).contains(&size.len()));
+    Self {
+      size: size.to_vec(),
+      batch_count: 1,
+      r2c: false,
+      normalize: false,
+      double: false,
+    }
+  }
+
+  /// Size in bytes of the in-place buffer the plan transforms. R2C rows are padded to
+  /// `size[0] / 2 + 1` complex values.
+  pub fn buffer_len(&self) -> Result<u64, ProtocolError> {
+    let scalar = if self.double { 8 } else { 4 };
+    self
+      .size
+      .iter()
+      .enumerate()
+      .map(|(i, s)| {
+        if i == 0 && self.r2c {
+          *s as u64 / 2 + 1
+        } else {
+          *s as u64
+        }
+      })
+      .chain([self.batch_count as u64, 2, scalar])
+      .try_fold(1u64, |len, n| len.checked_mul(n))
+      .ok_or_else(|| ProtocolError::TooLarge(self.to_string()))
+  }
+
+  fn parse<'a, I>(mut tokens: I) -> Result<Self, ProtocolError>
+  where
+    I: Iterator<Item = &'a str>,
+  {
+    let size = tokens.next().ok_or(ProtocolError::MissingArgument)?;
+    let invalid = |token: &str| ProtocolError::InvalidArgument(token.to_string());
+    let dims = size
+      .split('x')
+      .map(|d| d.parse::<u32>().ok().filter(|d| *d > 0))
+      .collect::<Option<Vec<_>>>()
+      .filter(|dims| (1..=3).contains(&dims.len()))
+      .ok_or_else(|| invalid(size))?;
+
+    let mut res = Self::new(&dims);
+    for token in tokens {
+      match token {
+        "r2c" => res.r2c = true,
+        "normalize" => res.normalize = true,
+        "double" => res.double = true,
+        _ => {
+          res.batch_count = token
+            .strip_prefix("batch=")
+            .and_then(|n| n.parse().ok())
+            .filter(|n| *n > 0)
+            .ok_or_else(|| invalid(token))?
+        }
+      }
+    }
+    res.buffer_len()?;
+    Ok(res)
+  }
+}
+
+impl fmt::Display for PlanSpec {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let size = self
+      .size
+      .iter()
+      .map(|s| s.to_string())
+      .collect::<Vec<_>>()
+      .join("x");
+    write!(f, "{} batch={}", size, self.batch_count)?;
+    for (set, flag) in [
+      (self.r2c, "r2c"),
+      (self.normalize, "normalize"),
+      (self.double, "double"),
+    ] {
+      if set {
+        write!(f, " {}", flag)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for PlanSpec {
+  type Err = ProtocolError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::parse(s.split_whitespace())
+  }
+}
+
+/// A request to the service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+  Ping,
+  Plan(PlanSpec),
+  Fft {
+    fft_type: FftType,
+    path: PathBuf,
+    offset: u64,
+    spec: PlanSpec,
+  },
+}
+
+impl fmt::Display for Request {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Request::Ping => write!(f, "PING"),
+      Request::Plan(spec) => write!(f, "PLAN {}", spec),
+      Request::Fft {
+        fft_type,
+        path,
+        offset,
+        spec,
+      } => {
+        let direction = match fft_type {
+          FftType::Forward => "forward",
+          FftType::Inverse => "inverse",
+        };
+        write!(
+          f,
+          "FFT {} {} {} {}",
+          direction,
+          path.display(),
+          offset,
+          spec
+        )
+      }
+    }
+  }
+}
+
+impl FromStr for Request {
+  type Err = ProtocolError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut tokens = s.split_whitespace();
+    let mut next = || tokens.next().ok_or(ProtocolError::MissingArgument);
+    let invalid = |token: &str| ProtocolError::InvalidArgument(token.to_string());
+
+    match next()? {
+      "PING" => Ok(Request::Ping),
+      "PLAN" => Ok(Request::Plan(PlanSpec::parse(tokens)?)),
+      "FFT" => {
+        let fft_type = match next()? {
+          "forward" => FftType::Forward,
+          "inverse" => FftType::Inverse,
+          token => return Err(invalid(token)),
+        };
+        let path = PathBuf::from(next()?);
+        let offset = next()?;
+        let offset = offset.parse().map_err(|_| invalid(offset))?;
+        Ok(Request::Fft {
+          fft_type,
+          path,
+          offset,
+          spec: PlanSpec::parse(tokens)?,
+        })
+      }
+      command => Err(ProtocolError::UnknownCommand(command.to_string())),
+    }
+  }
+}
+
+/// How many plans a server keeps warm unless set with [`Server::with_max_plans`].
+pub const DEFAULT_MAX_PLANS: usize = 64;
+
+/// A warmed plan and the device buffer it transforms.
+struct Plan {
+  buffer: Subbuffer<[u8]>,
+  forward: FftStage,
+  inverse: FftStage,
+  /// The request count when the plan was last used
+  last_used: u64,
+}
+
+/// Serves requests with the plans of one context, keeping the most recently used plans it
+/// generates.
+pub struct Server {
+  context: Context,
+  plans: HashMap<PlanSpec, Plan>,
+  requests: u64,
+  max_plans: usize,
+  max_buffer_len: u64,
+  data_dir: PathBuf,
+  data_dir_file: File,
+}
+
+impl Server {
+  /// A server transforming files in `data_dir` only, such as `/dev/shm`.
+  pub fn new<P: AsRef<Path>>(context: Context, data_dir: P) -> io::Result<Self> {
+    let data_dir = data_dir.as_ref().canonicalize()?;
+    let data_dir_file = OpenOptions::new()
+      .read(true)
+      .custom_flags(libc::O_DIRECTORY)
+      .open(&data_dir)?;
+    let properties = context.physical.properties();
+    let max_buffer_len = properties.max_memory_allocation_size.unwrap_or_else(|| {
+      let heaps = &context.physical.memory_properties().memory_heaps;
+      heaps.iter().map(|heap| heap.size).max().unwrap_or(0)
+    });
+    Ok(Self {
+      context,
+      plans: HashMap::new(),
+      requests: 0,
+      max_plans: DEFAULT_MAX_PLANS,
+      max_buffer_len,
+      data_dir,
+      data_dir_file,
+    })
+  }
+
+  /// Refuses plans whose buffer is larger than `len` bytes. Defaults to the device's largest
+  /// allocation.
+  pub fn with_max_buffer_len(mut self, len: u64) -> Self {
+    self.max_buffer_len = len;
+    self
+  }
+
+  /// Keeps at most `count` plans warm, dropping the least recently used one when a new plan is
+  /// needed. Defaults to [`DEFAULT_MAX_PLANS`].
+  pub fn with_max_plans(mut self, count: usize) -> Self {
+    self.max_plans = count.max(1);
+    self
+  }
+
+  pub fn context(&self) -> &Context {
+    &self.context
+  }
+
+  /// Number of plans kept warm.
+  pub fn plan_count(&self) -> usize {
+    self.plans.len()
+  }
+
+  /// Generates the plan for `spec` unless it is already warm.
+  fn plan(&mut self, spec: &PlanSpec) -> Result<(), Box<dyn Error>> {
+    self.requests += 1;
+    if !self.plans.contains_key(spec) {
+      let len = spec.buffer_len()?;
+      if len > self.max_buffer_len {
+        return Err(ProtocolError::BufferLimit(spec.to_string(), self.max_buffer_len).into());
+      }
+      let buffer = self
+        .context
+        .new_buffer_from_iter(vec![0u8; usize::try_from(len)?])?;
+      let mut config_builder = Config::builder()
+        .buffer(buffer.clone())
+        .batch_count(spec.batch_count)
+        .precision(if spec.double {
+          Precision::Double
+        } else {
+          Precision::Single
+        });
+      config_builder = match spec.size[..] {
+        [x] => config_builder.dim(&[x]),
+        [x, y] => config_builder.dim(&[x, y]),
+        _ => config_builder.dim(&[spec.size[0], spec.size[1], spec.size[2]]),
+      };
+      if spec.r2c {
+        config_builder = config_builder.r2c();
+      }
+      if spec.normalize {
+        config_builder = config_builder.normalize();
+      }
+
+      let forward = self.context.fft_stage(config_builder, FftType::Forward)?;
+      let inverse = forward.reversed();
+      if self.plans.len() >= self.max_plans {
+        let least_recent = self
+          .plans
+          .iter()
+          .min_by_key(|(_, plan)| plan.last_used)
+          .map(|(spec, _)| spec.clone());
+        if let Some(least_recent) = least_recent {
+          self.plans.remove(&least_recent);
+        }
+      }
+      self.plans.insert(
+        spec.clone(),
+        Plan {
+          buffer,
+          forward,
+          inverse,
+          last_used: 0,
+        },
+      );
+    }
+    self.plans.get_mut(spec).unwrap().last_used = self.requests;
+    Ok(())
+  }
+
+  /// Opens `path` in the data directory for reading and writing. Each component is opened
+  /// relative to the one before without following links, so nothing outside the directory can
+  /// be reached.
+  fn open(&self, path: &Path) -> Result<File, Box<dyn Error>> {
+    let names = data_dir_names(&self.data_dir, path)?;
+    let (file_name, parents) = names.split_last().unwrap();
+    let mut parent = None;
+    for name in parents {
+      let dir = parent.as_ref().unwrap_or(&self.data_dir_file);
+      parent = Some(open_at(dir, name, libc::O_RDONLY | libc::O_DIRECTORY)?);
+    }
+    let file = open_at(
+      parent.as_ref().unwrap_or(&self.data_dir_file),
+      file_name,
+      libc::O_RDWR,
+    )?;
+    if !file.metadata()?.is_file() {
+      return Err(ProtocolError::InvalidArgument(path.display().to_string()).into());
+    }
+    Ok(file)
+  }
+
+  fn fft(
+    &mut self,
+    fft_type: FftType,
+    path: &Path,
+    offset: u64,
+    spec: &PlanSpec,
+  ) -> Result<(), Box<dyn Error>> {
+    let file = self.open(path)?;
+    self.plan(spec)?;
+    let plan = self.plans.get_mut(spec).unwrap();
+    file.read_exact_at(&mut plan.buffer.write()?, offset)?;
+
+    let stage = match fft_type {
+      FftType::Forward => &mut plan.forward,
+      FftType::Inverse => &mut plan.inverse,
+    };
+    self.context.run(stage)?;
+
+    file.write_all_at(&plan.buffer.read()?, offset)?;
+    Ok(())
+  }
+
+  /// Handles one request line and returns the response line, without the newline.
+  pub fn handle(&mut self, line: &str) -> String {
+    let res = line
+      .parse::<Request>()
+      .map_err(Box::<dyn Error>::from)
+      .and_then(|request| match request {
+        Request::Ping => Ok(None),
+        Request::Plan(spec) => self.plan(&spec).and_then(|()| Ok(Some(spec.buffer_len()?))),
+        Request::Fft {
+          fft_type,
+          path,
+          offset,
+          spec,
+        } => self.fft(fft_type, &path, offset, &spec).map(|()| None),
+      });
+
+    match res {
+      Ok(Some(value)) => format!("OK {}", value),
+      Ok(None) => "OK".to_string(),
+      Err(e) => format!("ERR {}", e.to_string().replace('\n', " ")),
+    }
+  }
+
+  /// Serves the connections of `listener` concurrently, each read on its own thread, while the
+  /// requests run one at a time on this thread, which owns the device. Returns once accepting
+  /// fails and the open connections have closed.
+  pub fn serve(&mut self, listener: &UnixListener) -> io::Result<()> {
+    let (requests, received) = mpsc::channel::<(String, Sender<String>)>();
+    std::thread::scope(|scope| {
+      let acceptor = scope.spawn(move || -> io::Result<()> {
+        loop {
+          let (stream, _) = listener.accept()?;
+          let requests = requests.clone();
+          // A client disconnecting mid-request only ends its own connection
+          scope.spawn(move || {
+            let _ = serve_connection(stream, &requests);
+          });
+        }
+      });
+
+      for (line, response) in received {
+        // The connection may have closed in the meantime
+        let _ = response.send(self.handle(&line));
+      }
+      acceptor.join().expect("the accepting thread panicked")
+    })
+  }
+}
+
+/// The components of `path` below `data_dir`, which it must be absolute in or relative to.
+fn data_dir_names<'a>(data_dir: &Path, path: &'a Path) -> Result<Vec<&'a OsStr>, ProtocolError> {
+  let outside = || ProtocolError::OutsideDataDir(path.to_path_buf());
+  let relative = if path.is_absolute() {
+    path.strip_prefix(data_dir).map_err(|_| outside())?
+  } else {
+    path
+  };
+  let names = relative
+    .components()
+    .filter(|component| *component != Component::CurDir)
+    .map(|component| match component {
+      Component::Normal(name) => Ok(name),
+      _ => Err(outside()),
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  if names.is_empty() {
+    return Err(outside());
+  }
+  Ok(names)
+}
+
+/// Opens `name` in `dir` with `flags`, failing if it is a link.
+fn open_at(dir: &File, name: &OsStr, flags: libc::c_int) -> io::Result<File> {
+  let name = CString::new(name.as_bytes())?;
+  let flags = flags | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+  let fd = unsafe { libc::openat(dir.as_raw_fd(), name.as_ptr(), flags) };
+  if fd < 0 {
+    return Err(io::Error::last_os_error());
+  }
+  Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Forwards the request lines of `stream` to the thread running them and writes back the
+/// responses.
+fn serve_connection(
+  stream: UnixStream,
+  requests: &Sender<(String, Sender<String>)>,
+) -> io::Result<()> {
+  let mut writer = stream.try_clone()?;
+  let stopped = || io::Error::from(io::ErrorKind::BrokenPipe);
+  for line in BufReader::new(stream).lines() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let (response, received) = mpsc::channel();
+    requests.send((line, response)).map_err(|_| stopped())?;
+    writeln!(writer, "{}", received.recv().map_err(|_| stopped())?)?;
+  }
+  Ok(())
+}
+
+/// A connection to a running service.
+pub struct Client {
+  reader: BufReader<UnixStream>,
+  writer: UnixStream,
+}
+
+impl Client {
+  pub fn connect<P: AsRef<Path>>(socket: P) -> std::io::Result<Self> {
+    let writer = UnixStream::connect(socket)?;
+    Ok(Self {
+      reader: BufReader::new(writer.try_clone()?),
+      writer,
+    })
+  }
+
+  /// Sends `request` and returns the value of an `OK` response, if any.
+  pub fn request(&mut self, request: &Request) -> Result<Option<String>, Box<dyn Error>> {
+    writeln!(self.writer, "{}", request)?;
+    let mut line = String::new();
+    if self.reader.read_line(&mut line)? == 0 {
+      return Err("The service closed the connection".into());
+    }
+
+    let line = line.trim_end();
+    if let Some(message) = line.strip_prefix("ERR") {
+      Err(ProtocolError::Service(message.trim().to_string()).into())
+    } else if let Some(value) = line.strip_prefix("OK") {
+      let value = value.trim();
+      Ok((!value.is_empty()).then(|| value.to_string()))
+    } else {
+      Err(ProtocolError::InvalidArgument(line.to_string()).into())
+    }
+  }
+
+  /// Generates the plan for `spec` if the service doesn't have it yet, returning the size in
+  /// bytes of the data it transforms.
+  pub fn plan(&mut self, spec: &PlanSpec) -> Result<u64, Box<dyn Error>> {
+    let value = self.request(&Request::Plan(spec.clone()))?;
+    Ok(value.ok_or(ProtocolError::MissingArgument)?.parse()?)
+  }
+
+  /// Transforms [`PlanSpec::buffer_len`] bytes of the shared memory file `path` at `offset` in
+  /// place.
+  pub fn fft<P: AsRef<Path>>(
+    &mut self,
+    fft_type: FftType,
+    path: P,
+    offset: u64,
+    spec: &PlanSpec,
+  ) -> Result<(), Box<dyn Error>> {
+    self.request(&Request::Fft {
+      fft_type,
+      path: path.as_ref().to_path_buf(),
+      offset,
+      spec: spec.clone(),
+    })?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_request_round_trip() {
+    let line = "FFT inverse /dev/shm/signal 4096 512x256 batch=2 r2c double";
+    let request = line.parse::<Request>().unwrap();
+    assert_eq!(request.to_string(), line);
+
+    match request {
+      Request::Fft { spec, .. } => assert_eq!(spec.buffer_len(), Ok(257 * 256 * 2 * 2 * 8)),
+      _ => panic!("unexpected request"),
+    }
+    assert_eq!(
+      "PLAN 0x4".parse::<Request>(),
+      Err(ProtocolError::InvalidArgument("0x4".to_string()))
+    );
+    assert!(matches!(
+      "PLAN 4294967295x4294967295x4294967295 double".parse::<Request>(),
+      Err(ProtocolError::TooLarge(_))
+    ));
+  }
+
+  #[test]
+  fn check_data_dir_names() {
+    let data_dir = Path::new("/dev/shm");
+    let names = |path| data_dir_names(data_dir, Path::new(path));
+    assert_eq!(names("/dev/shm/a/b").unwrap(), ["a", "b"]);
+    assert_eq!(names("./a").unwrap(), ["a"]);
+    for path in [
+      "/dev/shm",
+      "/dev/shm/../etc/passwd",
+      "/tmp/a",
+      "a/../../b",
+      "",
+    ] {
+      assert!(
+        matches!(names(path), Err(ProtocolError::OutsideDataDir(_))),
+        "{}",
+        path
+      );
+    }
+  }
+}
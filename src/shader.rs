@@ -0,0 +1,386 @@
+use std::{
+  ffi::{CStr, CString},
+  sync::Arc,
+};
+
+use ash::vk;
+use derive_more::{Display, Error};
+use vulkano::{
+  buffer::{Buffer, Subbuffer},
+  descriptor_set::{allocator::StandardDescriptorSetAllocator, DescriptorSet, WriteDescriptorSet},
+  device::{Device, DeviceOwned},
+  pipeline::{
+    compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+    ComputePipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
+  },
+  shader::{ShaderModule, ShaderModuleCreateInfo},
+  VulkanObject,
+};
+
+/// Workgroup size used by the element-wise kernels shipped with this crate.
+pub const LOCAL_SIZE: u32 = 256;
+
+/// Maximum workgroup count guaranteed by Vulkan along any single dispatch dimension.
+const MAX_GROUP_COUNT: u32 = 65535;
+
+#[derive(Display, Debug, Error)]
+pub enum ShaderError {
+  InvalidSource,
+  #[display("failed to preprocess shader: {_0}")]
+  Preprocess(#[error(not(source))] String),
+  #[display("failed to parse shader: {_0}")]
+  Parse(#[error(not(source))] String),
+  #[display("failed to link shader: {_0}")]
+  Link(#[error(not(source))] String),
+  NoEntryPoint,
+  #[display("values of {_0} bytes can't be copied as 32-bit words")]
+  UnalignedValues(#[error(not(source))] usize),
+}
+
+struct ProcessGuard;
+
+impl ProcessGuard {
+  fn new() -> Self {
+    unsafe {
+      vkfft_sys::glslang::glslang_initialize_process();
+    }
+    Self
+  }
+}
+
+impl Drop for ProcessGuard {
+  fn drop(&mut self) {
+    unsafe {
+      vkfft_sys::glslang::glslang_finalize_process();
+    }
+  }
+}
+
+unsafe fn info_log(log: *const std::os::raw::c_char) -> String {
+  if log.is_null() {
+    return String::new();
+  }
+  CStr::from_ptr(log).to_string_lossy().into_owned()
+}
+
+/// Compiles a GLSL compute shader into SPIR-V words using the glslang build that VkFFT links
+/// against.
+pub fn compile(source: &str) -> Result<Vec<u32>, ShaderError> {
+  use vkfft_sys::glslang::*;
+
+  let code = CString::new(source).map_err(|_| ShaderError::InvalidSource)?;
+  let _process = ProcessGuard::new();
+
+  unsafe {
+    let input = glslang_input_t {
+      language: glslang_source_t_GLSLANG_SOURCE_GLSL,
+      stage: glslang_stage_t_GLSLANG_STAGE_COMPUTE,
+      client: glslang_client_t_GLSLANG_CLIENT_VULKAN,
+      client_version: glslang_target_client_version_t_GLSLANG_TARGET_VULKAN_1_0,
+      target_language: glslang_target_language_t_GLSLANG_TARGET_SPV,
+      target_language_version: glslang_target_language_version_t_GLSLANG_TARGET_SPV_1_0,
+      code: code.as_ptr(),
+      default_version: 450,
+      default_profile: glslang_profile_t_GLSLANG_NO_PROFILE,
+      force_default_version_and_profile: 0,
+      forward_compatible: 0,
+      messages: glslang_messages_t_GLSLANG_MSG_DEFAULT_BIT,
+      resource: glslang_default_resource(),
+      callbacks: std::mem::zeroed(),
+      callbacks_ctx: std::ptr::null_mut(),
+    };
+
+    let shader = glslang_shader_create(&input);
+
+    if glslang_shader_preprocess(shader, &input) == 0 {
+      let log = info_log(glslang_shader_get_info_log(shader));
+      glslang_shader_delete(shader);
+      return Err(ShaderError::Preprocess(log));
+    }
+
+    if glslang_shader_parse(shader, &input) == 0 {
+      let log = info_log(glslang_shader_get_info_log(shader));
+      glslang_shader_delete(shader);
+      return Err(ShaderError::Parse(log));
+    }
+
+    let program = glslang_program_create();
+    glslang_program_add_shader(program, shader);
+
+    let messages = glslang_messages_t_GLSLANG_MSG_SPV_RULES_BIT
+      | glslang_messages_t_GLSLANG_MSG_VULKAN_RULES_BIT;
+    if glslang_program_link(program, messages as i32) == 0 {
+      let log = info_log(glslang_program_get_info_log(program));
+      glslang_program_delete(program);
+      glslang_shader_delete(shader);
+      return Err(ShaderError::Link(log));
+    }
+
+    glslang_program_SPIRV_generate(program, glslang_stage_t_GLSLANG_STAGE_COMPUTE);
+    let mut words = vec![0u32; glslang_program_SPIRV_get_size(program)];
+    glslang_program_SPIRV_get(program, words.as_mut_ptr());
+
+    glslang_program_delete(program);
+    glslang_shader_delete(shader);
+
+    Ok(words)
+  }
+}
+
+/// Inserts `#define` lines for `defines` right after the `#version` directive of `source`.
+pub fn with_defines(source: &str, defines: &[&str]) -> String {
+  let defines: String = defines.iter().map(|d| format!("#define {}\n", d)).collect();
+  match source.find("#version") {
+    Some(start) => {
+      let end = source[start..]
+        .find('\n')
+        .map(|i| start + i + 1)
+        .unwrap_or(source.len());
+      format!("{}{}{}", &source[..end], defines, &source[end..])
+    }
+    None => format!("{}{}", defines, source),
+  }
+}
+
+/// Splits `invocations` one-dimensional invocations into a dispatch that respects the per-axis
+/// workgroup count limit. Kernels recover the linear index as
+/// `gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * LOCAL_SIZE`.
+pub fn group_counts(invocations: u64) -> [u32; 3] {
+  let groups = invocations.div_ceil(LOCAL_SIZE as u64).max(1);
+  if groups <= MAX_GROUP_COUNT as u64 {
+    [groups as u32, 1, 1]
+  } else {
+    let y = groups.div_ceil(MAX_GROUP_COUNT as u64);
+    [MAX_GROUP_COUNT, y as u32, 1]
+  }
+}
+
+/// The number of 32-bit words in each value of `bytes` bytes, for kernels moving values as words.
+/// Storage buffers of 16-bit values need a device feature, so real half precision values fail.
+pub fn words_per_value(bytes: usize) -> Result<u32, ShaderError> {
+  if bytes == 0 || !bytes.is_multiple_of(4) {
+    return Err(ShaderError::UnalignedValues(bytes));
+  }
+  Ok((bytes / 4) as u32)
+}
+
+/// A compute shader bound to a fixed set of storage buffers, ready to be recorded into a command
+/// buffer alongside VkFFT dispatches.
+///
+/// Buffers are bound in order to `binding = 0, 1, ...` of descriptor set 0. Push constants are
+/// passed as 32-bit words in the order they are declared in the shader's push constant block.
+pub struct Kernel {
+  device: Arc<Device>,
+  pipeline: Arc<ComputePipeline>,
+  descriptor_set: Arc<DescriptorSet>,
+  push_constants: Vec<u32>,
+  group_counts: [u32; 3],
+}
+
+impl Kernel {
+  /// Compiles `source` and binds `buffers` with a descriptor set from `allocator`, such as the
+  /// [`Context::descriptor_set_allocator`](crate::context::Context::descriptor_set_allocator)
+  /// shared by the passes of a context.
+  pub fn new<I>(
+    allocator: Arc<StandardDescriptorSetAllocator>,
+    source: &str,
+    buffers: I,
+    push_constants: Vec<u32>,
+    group_counts: [u32; 3],
+  ) -> Result<Self, crate::Error>
+  where
+    I: IntoIterator<Item = Arc<Buffer>>,
+  {
+    Self::from_spirv(
+      allocator,
+      &compile(source)?,
+      buffers,
+      push_constants,
+      group_counts,
+    )
+  }
+
+  /// Like [`Kernel::new`], for a shader already compiled with [`compile`].
+  pub fn from_spirv<I>(
+    allocator: Arc<StandardDescriptorSetAllocator>,
+    words: &[u32],
+    buffers: I,
+    push_constants: Vec<u32>,
+    group_counts: [u32; 3],
+  ) -> Result<Self, crate::Error>
+  where
+    I: IntoIterator<Item = Arc<Buffer>>,
+  {
+    let device = allocator.device().clone();
+    let module = unsafe { ShaderModule::new(device.clone(), ShaderModuleCreateInfo::new(words))? };
+    let entry_point = module
+      .entry_point("main")
+      .ok_or(ShaderError::NoEntryPoint)?;
+    let stage = PipelineShaderStageCreateInfo::new(entry_point);
+    let layout = PipelineLayout::new(
+      device.clone(),
+      PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+        .into_pipeline_layout_create_info(device.clone())
+        .map_err(|e| e.error)?,
+    )?;
+    let pipeline = ComputePipeline::new(
+      device.clone(),
+      None,
+      ComputePipelineCreateInfo::stage_layout(stage, layout),
+    )?;
+
+    let descriptor_set = DescriptorSet::new(
+      allocator,
+      pipeline.layout().set_layouts()[0].clone(),
+      buffers.into_iter().enumerate().map(|(binding, buffer)| {
+        WriteDescriptorSet::buffer(binding as u32, Subbuffer::new(buffer))
+      }),
+      [],
+    )?;
+
+    Ok(Self {
+      device,
+      pipeline,
+      descriptor_set,
+      push_constants,
+      group_counts,
+    })
+  }
+
+  pub fn group_counts(&self) -> [u32; 3] {
+    self.group_counts
+  }
+
+  pub fn set_push_constants(&mut self, push_constants: Vec<u32>) {
+    self.push_constants = push_constants;
+  }
+
+  pub fn record(&self, command_buffer: vk::CommandBuffer) {
+    let fns = self.device.fns();
+    let layout = self.pipeline.layout().handle();
+    let descriptor_set = self.descriptor_set.as_raw().handle();
+
+    unsafe {
+      (fns.v1_0.cmd_bind_pipeline)(
+        command_buffer,
+        vk::PipelineBindPoint::COMPUTE,
+        self.pipeline.handle(),
+      );
+      (fns.v1_0.cmd_bind_descriptor_sets)(
+        command_buffer,
+        vk::PipelineBindPoint::COMPUTE,
+        layout,
+        0,
+        1,
+        &descriptor_set,
+        0,
+        std::ptr::null(),
+      );
+      if !self.push_constants.is_empty() {
+        (fns.v1_0.cmd_push_constants)(
+          command_buffer,
+          layout,
+          vk::ShaderStageFlags::COMPUTE,
+          0,
+          (self.push_constants.len() * std::mem::size_of::<u32>()) as u32,
+          self.push_constants.as_ptr() as *const std::ffi::c_void,
+        );
+      }
+      (fns.v1_0.cmd_dispatch)(
+        command_buffer,
+        self.group_counts[0],
+        self.group_counts[1],
+        self.group_counts[2],
+      );
+    }
+  }
+}
+
+/// Records a memory barrier making compute shader writes visible to subsequent compute shader
+/// reads and writes.
+pub fn compute_barrier(device: &Device, command_buffer: vk::CommandBuffer) {
+  let barrier = vk::MemoryBarrier {
+    src_access_mask: vk::AccessFlags::SHADER_WRITE,
+    dst_access_mask: vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+    ..Default::default()
+  };
+
+  unsafe {
+    (device.fns().v1_0.cmd_pipeline_barrier)(
+      command_buffer,
+      vk::PipelineStageFlags::COMPUTE_SHADER,
+      vk::PipelineStageFlags::COMPUTE_SHADER,
+      vk::DependencyFlags::empty(),
+      1,
+      &barrier,
+      0,
+      std::ptr::null(),
+      0,
+      std::ptr::null(),
+    );
+  }
+}
+
+/// Records a fill of `buffer` with the 32-bit word `data`, followed by a barrier making the
+/// transfer visible to compute shaders.
+pub fn fill_buffer(device: &Device, command_buffer: vk::CommandBuffer, buffer: &Buffer, data: u32) {
+  let barrier = vk::MemoryBarrier {
+    src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+    dst_access_mask: vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+    ..Default::default()
+  };
+  let fns = device.fns();
+
+  unsafe {
+    (fns.v1_0.cmd_fill_buffer)(command_buffer, buffer.handle(), 0, vk::WHOLE_SIZE, data);
+    (fns.v1_0.cmd_pipeline_barrier)(
+      command_buffer,
+      vk::PipelineStageFlags::TRANSFER,
+      vk::PipelineStageFlags::COMPUTE_SHADER,
+      vk::DependencyFlags::empty(),
+      1,
+      &barrier,
+      0,
+      std::ptr::null(),
+      0,
+      std::ptr::null(),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_group_counts() {
+    assert_eq!(group_counts(0), [1, 1, 1]);
+    assert_eq!(group_counts(256), [1, 1, 1]);
+    assert_eq!(group_counts(257), [2, 1, 1]);
+    assert_eq!(group_counts(65535 * 256), [65535, 1, 1]);
+    assert_eq!(group_counts(65536 * 256), [65535, 2, 1]);
+  }
+
+  #[test]
+  fn check_words_per_value() {
+    assert_eq!(words_per_value(8).unwrap(), 2);
+    assert_eq!(words_per_value(4).unwrap(), 1);
+    assert!(matches!(
+      words_per_value(2),
+      Err(ShaderError::UnalignedValues(2))
+    ));
+  }
+
+  #[test]
+  fn check_with_defines() {
+    let source = "\n#version 450\nvoid main() {}\n";
+    assert_eq!(
+      with_defines(source, &["A", "B 2"]),
+      "\n#version 450\n#define A\n#define B 2\nvoid main() {}\n"
+    );
+    assert_eq!(
+      with_defines("void main() {}", &["A"]),
+      "#define A\nvoid main() {}"
+    );
+  }
+}
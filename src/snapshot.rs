@@ -0,0 +1,276 @@
+//! Readback of intermediate buffers for debugging multi-stage pipelines.
+//!
+//! A [`SnapshotStage`] copies a buffer into host memory at its position in a [`Chain`], leaving
+//! the rest of the pipeline untouched. After the chain has been submitted, [`Snapshots`] returns
+//! the captured data or dumps it as labelled CSV or `.npy` files.
+//!
+//! ```ignore
+//! let mut snapshots = Snapshots::new(&context);
+//! let mut chain = Chain::new(context.device.clone())
+//!   .push(range_fft)
+//!   .push(snapshots.capture("range", data.clone(), SnapshotKind::Complex)?)
+//!   .push(azimuth_fft);
+//! context.run(&mut chain)?;
+//! snapshots.dump_npy("snapshots")?;
+//! ```
+//!
+//! [`Chain`]: crate::stage::Chain
+
+use std::{
+  error::Error,
+  fs,
+  io::{self, Write},
+  path::Path,
+  sync::Arc,
+};
+
+use ash::vk;
+use vulkano::{
+  buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+  device::Device,
+  memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter},
+  VulkanObject,
+};
+
+use crate::{context::Context, error, stage::Stage};
+
+/// How the `f32` values of a captured buffer are interpreted when dumping it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotKind {
+  Real,
+  /// Interleaved complex values
+  Complex,
+}
+
+struct Snapshot {
+  label: String,
+  kind: SnapshotKind,
+  readback: Subbuffer<[f32]>,
+}
+
+/// The snapshots captured by the stages created from it.
+pub struct Snapshots {
+  device: Arc<Device>,
+  allocator: Arc<dyn MemoryAllocator>,
+  snapshots: Vec<Snapshot>,
+}
+
+impl Snapshots {
+  pub fn new(context: &Context) -> Self {
+    Self {
+      device: context.device.clone(),
+      allocator: context.allocator.clone(),
+      snapshots: Vec::new(),
+    }
+  }
+
+  /// Creates a stage copying the whole of `buffer` into a host-visible snapshot named `label`.
+  /// `buffer` must have been created with `TRANSFER_SRC` usage.
+  pub fn capture(
+    &mut self,
+    label: &str,
+    buffer: Arc<Buffer>,
+    kind: SnapshotKind,
+  ) -> Result<SnapshotStage, Box<dyn Error>> {
+    let readback = Buffer::new_slice::<f32>(
+      self.allocator.clone(),
+      BufferCreateInfo {
+        usage: BufferUsage::TRANSFER_DST,
+        ..Default::default()
+      },
+      AllocationCreateInfo {
+        memory_type_filter: MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+        ..Default::default()
+      },
+      (buffer.size() / 4).max(1),
+    )?;
+
+    self.snapshots.push(Snapshot {
+      label: label.to_string(),
+      kind,
+      readback: readback.clone(),
+    });
+
+    Ok(SnapshotStage {
+      device: self.device.clone(),
+      source: buffer,
+      readback,
+    })
+  }
+
+  pub fn labels(&self) -> Vec<&str> {
+    self.snapshots.iter().map(|s| s.label.as_str()).collect()
+  }
+
+  /// The values captured under `label`. Only meaningful once the command buffer containing the
+  /// capture has completed.
+  pub fn read(&self, label: &str) -> Result<Option<Vec<f32>>, Box<dyn Error>> {
+    match self.snapshots.iter().rev().find(|s| s.label == label) {
+      Some(snapshot) => Ok(Some(snapshot.readback.read()?.to_vec())),
+      None => Ok(None),
+    }
+  }
+
+  /// Writes every snapshot to `<dir>/<label>.csv`, one value per line, with complex values
+  /// written as `re,im`.
+  pub fn dump_csv<P: AsRef<Path>>(&self, dir: P) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&dir)?;
+
+    for snapshot in &self.snapshots {
+      let data = snapshot.readback.read()?;
+      let mut out = io::BufWriter::new(fs::File::create(
+        dir
+          .as_ref()
+          .join(format!("{}.csv", file_name(&snapshot.label))),
+      )?);
+      match snapshot.kind {
+        SnapshotKind::Real => {
+          for value in data.iter() {
+            writeln!(out, "{}", value)?;
+          }
+        }
+        SnapshotKind::Complex => {
+          for value in data.chunks_exact(2) {
+            writeln!(out, "{},{}", value[0], value[1])?;
+          }
+        }
+      }
+      out.flush()?;
+    }
+
+    Ok(())
+  }
+
+  /// Writes every snapshot to `<dir>/<label>.npy` as a one-dimensional `float32` or
+  /// `complex64` array.
+  pub fn dump_npy<P: AsRef<Path>>(&self, dir: P) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&dir)?;
+
+    for snapshot in &self.snapshots {
+      let data = snapshot.readback.read()?;
+      let (descr, floats) = match snapshot.kind {
+        SnapshotKind::Real => ("<f4", 1),
+        SnapshotKind::Complex => ("<c8", 2),
+      };
+      let len = data.len() / floats;
+
+      let mut out = io::BufWriter::new(fs::File::create(
+        dir
+          .as_ref()
+          .join(format!("{}.npy", file_name(&snapshot.label))),
+      )?);
+      out.write_all(&npy_header(descr, len))?;
+      for value in &data[..len * floats] {
+        out.write_all(&value.to_le_bytes())?;
+      }
+      out.flush()?;
+    }
+
+    Ok(())
+  }
+}
+
+fn file_name(label: &str) -> String {
+  label
+    .chars()
+    .map(|c| {
+      if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+        c
+      } else {
+        '_'
+      }
+    })
+    .collect()
+}
+
+/// Header of a version 1.0 `.npy` file holding a one-dimensional array.
+fn npy_header(descr: &str, len: usize) -> Vec<u8> {
+  let mut dict = format!(
+    "{{'descr': '{}', 'fortran_order': False, 'shape': ({},), }}",
+    descr, len
+  );
+  // The magic string, version and header length take 10 bytes, and the header ends with a
+  // newline, padded so the data starts on a 64-byte boundary.
+  let padding = 63 - (10 + dict.len()) % 64;
+  dict.push_str(&" ".repeat(padding));
+  dict.push('\n');
+
+  let mut header = b"\x93NUMPY\x01\x00".to_vec();
+  header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+  header.extend_from_slice(dict.as_bytes());
+  header
+}
+
+/// Copies a buffer into a snapshot. Created by [`Snapshots::capture`].
+pub struct SnapshotStage {
+  device: Arc<Device>,
+  source: Arc<Buffer>,
+  readback: Subbuffer<[f32]>,
+}
+
+impl Stage for SnapshotStage {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    let fns = self.device.fns();
+    let before = vk::MemoryBarrier {
+      src_access_mask: vk::AccessFlags::SHADER_WRITE,
+      dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+      ..Default::default()
+    };
+    let region = vk::BufferCopy {
+      src_offset: 0,
+      dst_offset: self.readback.offset(),
+      size: self.source.size().min(self.readback.size()),
+    };
+
+    unsafe {
+      (fns.v1_0.cmd_pipeline_barrier)(
+        command_buffer,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::DependencyFlags::empty(),
+        1,
+        &before,
+        0,
+        std::ptr::null(),
+        0,
+        std::ptr::null(),
+      );
+      (fns.v1_0.cmd_copy_buffer)(
+        command_buffer,
+        self.source.handle(),
+        self.readback.buffer().handle(),
+        1,
+        &region,
+      );
+      // Later stages may overwrite the source, which must wait for the copy to finish reading it
+      (fns.v1_0.cmd_pipeline_barrier)(
+        command_buffer,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::DependencyFlags::empty(),
+        0,
+        std::ptr::null(),
+        0,
+        std::ptr::null(),
+        0,
+        std::ptr::null(),
+      );
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_npy_header() {
+    let header = npy_header("<c8", 1024);
+    assert_eq!(header.len() % 64, 0);
+    assert_eq!(&header[..8], b"\x93NUMPY\x01\x00");
+    assert_eq!(*header.last().unwrap(), b'\n');
+    assert!(String::from_utf8_lossy(&header).contains("'shape': (1024,)"));
+  }
+}
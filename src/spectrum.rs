@@ -0,0 +1,115 @@
+//! GPU conversion of complex spectra to magnitudes, phases or powers, recordable right after the
+//! forward transform for spectrograms and analysis.
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  decibel::{decibel_kernel, DecibelInput, DecibelOptions},
+  error,
+  shader::{group_counts, with_defines, Kernel},
+  stage::Stage,
+};
+
+const SPECTRUM: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) readonly buffer Input { vec2 src[]; };
+layout(std430, binding = 1) writeonly buffer Output { float dst[]; };
+layout(push_constant) uniform Params {
+  uint total;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= total) {
+    return;
+  }
+
+  vec2 value = src[i];
+#if defined(PHASE)
+  // atan is undefined at the origin
+  dst[i] = value == vec2(0.0) ? 0.0 : atan(value.y, value.x);
+#elif defined(POWER)
+  dst[i] = dot(value, value);
+#else
+  dst[i] = length(value);
+#endif
+}
+"#;
+
+/// What a [`SpectrumConversion`] computes from each complex value `x`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpectrumValue {
+  /// `|x|`
+  Magnitude,
+  /// `arg(x)`, in radians between `-π` and `π`
+  Phase,
+  /// `|x|²`
+  Power,
+  /// `20 log10(|x| / reference)`, as a [`DecibelConversion`](crate::decibel::DecibelConversion)
+  /// of complex input computes it
+  Decibels(DecibelOptions),
+}
+
+/// Converts `total` interleaved complex `f32` values into `f32` values of a [`SpectrumValue`].
+pub struct SpectrumConversion {
+  kernel: Kernel,
+}
+
+impl SpectrumConversion {
+  pub fn new(
+    context: &Context,
+    input: Arc<Buffer>,
+    output: Arc<Buffer>,
+    value: SpectrumValue,
+    total: u32,
+  ) -> Result<Self, crate::Error> {
+    let defines: &[&str] = match value {
+      SpectrumValue::Magnitude => &[],
+      SpectrumValue::Phase => &["PHASE"],
+      SpectrumValue::Power => &["POWER"],
+      SpectrumValue::Decibels(options) => {
+        let kernel = decibel_kernel(
+          context,
+          input,
+          output,
+          DecibelInput::Complex,
+          total,
+          options,
+        )?;
+        return Ok(Self { kernel });
+      }
+    };
+    // The shader indexes the input's floats with 32-bit integers
+    if total as u64 * 2 > u32::MAX as u64 {
+      return Err(ConfigError::SizeOverflow("value count").into());
+    }
+    if input.size() / 8 < total as u64 {
+      return Err(ConfigError::BufferTooSmall("input").into());
+    }
+    if output.size() / 4 < total as u64 {
+      return Err(ConfigError::BufferTooSmall("output").into());
+    }
+    let kernel = Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      &with_defines(SPECTRUM, defines),
+      [input, output],
+      vec![total],
+      group_counts(total as u64),
+    )?;
+
+    Ok(Self { kernel })
+  }
+}
+
+impl Stage for SpectrumConversion {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
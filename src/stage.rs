@@ -0,0 +1,434 @@
+use std::{cell::RefCell, pin::Pin, rc::Rc, sync::Arc};
+
+use ash::vk;
+use vulkano::{
+  buffer::Buffer,
+  descriptor_set::allocator::StandardDescriptorSetAllocator,
+  device::{Device, DeviceOwned},
+  sync::event::{Event, EventCreateInfo},
+  VulkanObject,
+};
+
+use crate::{
+  app::{App, LaunchError, LaunchParams},
+  buffer::{BufferBinding, FftBuffer},
+  config::{BufferSlot, BuildError, ConfigBuilder, Normalization, Precision},
+  context::FftType,
+  error::{self, VkfftError},
+  planner::PlannedApp,
+  shader::{compile, compute_barrier, group_counts, with_defines, Kernel},
+};
+
+const SCALE: &str = r#"
+#version 450
+#if defined(DOUBLE)
+#define REAL double
+#else
+#define REAL float
+#endif
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) buffer Data { REAL data[]; };
+layout(push_constant) uniform Params {
+  uint offset;
+  uint count;
+  uvec2 scale;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= count) {
+    return;
+  }
+
+#if defined(DOUBLE)
+  data[offset + i] *= packDouble2x32(scale);
+#else
+  data[offset + i] *= uintBitsToFloat(scale.x);
+#endif
+}
+"#;
+
+/// A unit of GPU work that can be appended to a command buffer, such as a VkFFT transform or
+/// one of the crate's auxiliary compute kernels.
+pub trait Stage {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()>;
+}
+
+impl Stage for Kernel {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    Kernel::record(self, command_buffer);
+    Ok(())
+  }
+}
+
+/// A VkFFT transform in a fixed direction. The plan can be shared with a stage running in the
+/// opposite direction through [`FftStage::reversed`], so a forward/inverse pair only compiles
+/// its kernels once.
+pub struct FftStage {
+  app: Rc<RefCell<Pin<Box<App>>>>,
+  params: LaunchParams,
+  fft_type: FftType,
+  scale: Option<Rc<RefCell<OrthoScale>>>,
+}
+
+impl FftStage {
+  pub fn new(app: Pin<Box<App>>, params: LaunchParams, fft_type: FftType) -> Self {
+    Self {
+      app: Rc::new(RefCell::new(app)),
+      params,
+      fft_type,
+      scale: None,
+    }
+  }
+
+  /// A stage running an application shared with other users, such as one handed out by an
+  /// [`FftPlanner`](crate::planner::FftPlanner).
+  pub fn shared(app: PlannedApp, params: LaunchParams, fft_type: FftType) -> Self {
+    Self {
+      app,
+      params,
+      fft_type,
+      scale: None,
+    }
+  }
+
+  /// Records `scale` after every transform, for plans it applies to.
+  pub(crate) fn with_scale(mut self, scale: Option<OrthoScale>) -> Self {
+    self.scale = scale.map(|scale| Rc::new(RefCell::new(scale)));
+    self
+  }
+
+  pub fn fft_type(&self) -> FftType {
+    self.fft_type
+  }
+
+  /// Transforms `buffer` from the next recording on, for plans made with
+  /// [`ConfigBuilder::buffer_size`](crate::config::ConfigBuilder::buffer_size) instead of a
+  /// buffer.
+  pub fn set_buffer<B: FftBuffer>(&mut self, buffer: B) {
+    self.params.buffer = Some(buffer.binding());
+  }
+
+  /// Like [`FftStage::set_buffer`], for the input buffer of out-of-place plans.
+  pub fn set_input_buffer<B: FftBuffer>(&mut self, input_buffer: B) {
+    self.params.input_buffer = Some(input_buffer.binding());
+  }
+
+  /// Like [`FftStage::set_buffer`], for the output buffer of out-of-place plans.
+  pub fn set_output_buffer<B: FftBuffer>(&mut self, output_buffer: B) {
+    self.params.output_buffer = Some(output_buffer.binding());
+  }
+
+  pub fn reversed(&self) -> Self {
+    Self {
+      app: self.app.clone(),
+      params: self.params.clone(),
+      fft_type: match self.fft_type {
+        FftType::Forward => FftType::Inverse,
+        FftType::Inverse => FftType::Forward,
+      },
+      scale: self.scale.clone(),
+    }
+  }
+}
+
+impl Stage for FftStage {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.params.command_buffer = command_buffer;
+    let mut app = self.app.borrow_mut();
+    match self.fft_type {
+      FftType::Forward => app.forward(&mut self.params)?,
+      FftType::Inverse => app.inverse(&mut self.params)?,
+    }
+    match &self.scale {
+      Some(scale) => scale.borrow_mut().record(
+        command_buffer,
+        &self.params,
+        self.fft_type == FftType::Inverse,
+      ),
+      None => Ok(()),
+    }
+  }
+}
+
+/// The scaling pass recorded after the transforms of [`Normalization::Ortho`] plans, which VkFFT
+/// can't scale itself.
+pub(crate) struct OrthoScale {
+  device: Arc<Device>,
+  allocator: Arc<StandardDescriptorSetAllocator>,
+  words: Vec<u32>,
+  factor: f64,
+  value_size: u64,
+  /// Where forward and inverse transforms write their results
+  results: [(BufferSlot, Option<BufferBinding>); 2],
+  /// The kernel scaling the handle, offset and size of the last result buffer
+  kernel: Option<((vk::Buffer, u64, u64), Kernel)>,
+}
+
+impl OrthoScale {
+  /// The pass the plan of `config_builder` needs, if any.
+  pub(crate) fn for_plan(
+    allocator: &Arc<StandardDescriptorSetAllocator>,
+    config_builder: &ConfigBuilder,
+  ) -> Result<Option<Self>, crate::Error> {
+    let plan_key = config_builder.plan_key();
+    if plan_key.normalization != Normalization::Ortho {
+      return Ok(None);
+    }
+    let (defines, value_size): (&[&str], _) = match plan_key.precision {
+      Precision::Single => (&[], 4),
+      Precision::Double => (&["DOUBLE"], 8),
+      precision => return Err(BuildError::OrthoPrecision(precision).into()),
+    };
+    let results = [
+      config_builder.result_buffer(false),
+      config_builder.result_buffer(true),
+    ];
+    // Buffers bound at launch are checked when recording
+    for binding in results.iter().filter_map(|(_, binding)| binding.as_ref()) {
+      scaled_range(binding, value_size)?;
+    }
+    let words =
+      compile(&with_defines(SCALE, defines)).map_err(|_| VkfftError::FailedShaderParse)?;
+
+    Ok(Some(Self {
+      device: allocator.device().clone(),
+      allocator: allocator.clone(),
+      words,
+      factor: plan_key.scale_factor(false),
+      value_size,
+      results,
+      kernel: None,
+    }))
+  }
+
+  fn record(
+    &mut self,
+    command_buffer: vk::CommandBuffer,
+    params: &LaunchParams,
+    inverse: bool,
+  ) -> error::Result<()> {
+    let (slot, config_buffer) = &self.results[inverse as usize];
+    let launch_buffer = match slot {
+      BufferSlot::Buffer => params.buffer.as_ref(),
+      BufferSlot::Input => params.input_buffer.as_ref(),
+      BufferSlot::Output => params.output_buffer.as_ref(),
+    };
+    let binding = launch_buffer
+      .or(config_buffer.as_ref())
+      .ok_or(VkfftError::EmptyBuffer)?;
+
+    let key = (binding.handle(), binding.offset(), binding.size());
+    if self.kernel.as_ref().map(|(bound, _)| *bound) != Some(key) {
+      let (buffer, offset, count) = scaled_range(binding, self.value_size)?;
+      let scale = match self.value_size {
+        8 => self.factor.to_bits(),
+        _ => (self.factor as f32).to_bits() as u64,
+      };
+      let push_constants = vec![offset, count, scale as u32, (scale >> 32) as u32];
+      let kernel = Kernel::from_spirv(
+        self.allocator.clone(),
+        &self.words,
+        [buffer],
+        push_constants,
+        group_counts(count as u64),
+      )
+      .map_err(|_| VkfftError::FailedToCreateShaderModule)?;
+      self.kernel = Some((key, kernel));
+    }
+
+    compute_barrier(&self.device, command_buffer);
+    if let Some((_, kernel)) = &self.kernel {
+      kernel.record(command_buffer);
+    }
+    Ok(())
+  }
+}
+
+/// The vulkano buffer behind `binding`, with the offset and number of its values of
+/// `value_size` bytes, which the scaling pass indexes with 32-bit integers.
+fn scaled_range(
+  binding: &BufferBinding,
+  value_size: u64,
+) -> Result<(Arc<Buffer>, u32, u32), LaunchError> {
+  let buffer = binding
+    .keep_alive()
+    .and_then(|owner| owner.downcast::<Buffer>().ok())
+    .ok_or(LaunchError::UnscalableBuffer)?;
+  let offset = binding.offset() / value_size;
+  let count = binding.size() / value_size;
+  match offset.checked_add(count) {
+    Some(end) if end <= u32::MAX as u64 => Ok((buffer, offset as u32, count as u32)),
+    _ => Err(LaunchError::UnscalableRange),
+  }
+}
+
+/// How a [`Chain`] orders dependent stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Synchronization {
+  /// A pipeline barrier before each stage with dependencies, which also waits for every other
+  /// earlier stage
+  Barriers,
+  /// An event per stage that others depend on, so a stage only waits for its own dependencies
+  /// and independent stages can overlap. Falls back to barriers on devices without events.
+  Events,
+}
+
+/// An ordered list of stages recorded back to back. By default each stage depends on the one
+/// before it; [`Chain::push_after`] and [`Chain::push_independent`] declare other dependencies.
+/// A chain is itself a stage, so chains can be nested.
+pub struct Chain {
+  device: Arc<Device>,
+  stages: Vec<Box<dyn Stage>>,
+  dependencies: Vec<Vec<usize>>,
+  synchronization: Synchronization,
+  events: Vec<Option<Event>>,
+}
+
+impl Chain {
+  pub fn new(device: Arc<Device>) -> Self {
+    Self {
+      device,
+      stages: Vec::new(),
+      dependencies: Vec::new(),
+      synchronization: Synchronization::Barriers,
+      events: Vec::new(),
+    }
+  }
+
+  pub fn synchronization(mut self, synchronization: Synchronization) -> Self {
+    self.synchronization = synchronization;
+    self
+  }
+
+  /// Appends a stage depending on the previously pushed one.
+  pub fn push<S>(self, stage: S) -> Self
+  where
+    S: Stage + 'static,
+  {
+    let dependencies = self
+      .stages
+      .len()
+      .checked_sub(1)
+      .into_iter()
+      .collect::<Vec<_>>();
+    self.push_after(stage, &dependencies)
+  }
+
+  /// Appends a stage that only depends on the stages at the given indices, in push order.
+  pub fn push_after<S>(mut self, stage: S, dependencies: &[usize]) -> Self
+  where
+    S: Stage + 'static,
+  {
+    assert!(dependencies.iter().all(|d| *d < self.stages.len()));
+    self.stages.push(Box::new(stage));
+    self.dependencies.push(dependencies.to_vec());
+    self
+  }
+
+  /// Appends a stage that doesn't depend on any earlier stage.
+  pub fn push_independent<S>(self, stage: S) -> Self
+  where
+    S: Stage + 'static,
+  {
+    self.push_after(stage, &[])
+  }
+
+  pub fn len(&self) -> usize {
+    self.stages.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.stages.is_empty()
+  }
+
+  fn use_events(&self) -> bool {
+    self.synchronization == Synchronization::Events
+      && (!self.device.enabled_extensions().khr_portability_subset
+        || self.device.enabled_features().events)
+  }
+
+  /// Creates the events of stages that others depend on, if not done by an earlier recording.
+  fn create_events(&mut self) -> error::Result<()> {
+    self.events.resize_with(self.stages.len(), || None);
+    for (i, event) in self.events.iter_mut().enumerate() {
+      let needed = self.dependencies.iter().any(|d| d.contains(&i));
+      if needed && event.is_none() {
+        *event = Some(
+          Event::new(self.device.clone(), EventCreateInfo::default())
+            .map_err(|_| error::VkfftError::FailedToSynchronize)?,
+        );
+      }
+    }
+    Ok(())
+  }
+
+  fn record_with_events(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.create_events()?;
+
+    let fns = self.device.fns();
+    let stage_mask = vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::TRANSFER;
+    let barrier = vk::MemoryBarrier {
+      src_access_mask: vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::TRANSFER_WRITE,
+      dst_access_mask: vk::AccessFlags::SHADER_READ
+        | vk::AccessFlags::SHADER_WRITE
+        | vk::AccessFlags::TRANSFER_READ
+        | vk::AccessFlags::TRANSFER_WRITE,
+      ..Default::default()
+    };
+
+    // Events keep their state across submissions, so they are unsignaled again before use
+    for event in self.events.iter().flatten() {
+      unsafe { (fns.v1_0.cmd_reset_event)(command_buffer, event.handle(), stage_mask) };
+    }
+
+    let events = &self.events;
+    for (i, stage) in self.stages.iter_mut().enumerate() {
+      let waits = self.dependencies[i]
+        .iter()
+        .filter_map(|d| events[*d].as_ref().map(|e| e.handle()))
+        .collect::<Vec<_>>();
+      if !waits.is_empty() {
+        unsafe {
+          (fns.v1_0.cmd_wait_events)(
+            command_buffer,
+            waits.len() as u32,
+            waits.as_ptr(),
+            stage_mask,
+            stage_mask,
+            1,
+            &barrier,
+            0,
+            std::ptr::null(),
+            0,
+            std::ptr::null(),
+          );
+        }
+      }
+
+      stage.record(command_buffer)?;
+
+      if let Some(event) = &events[i] {
+        unsafe { (fns.v1_0.cmd_set_event)(command_buffer, event.handle(), stage_mask) };
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Stage for Chain {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    if self.use_events() {
+      return self.record_with_events(command_buffer);
+    }
+
+    for (i, stage) in self.stages.iter_mut().enumerate() {
+      if !self.dependencies[i].is_empty() {
+        compute_barrier(&self.device, command_buffer);
+      }
+      stage.record(command_buffer)?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,398 @@
+//! Transforming a stream of batches while the next ones upload.
+//!
+//! A [`StreamingFft`] keeps a ring of slots, two by default, each with a host staging buffer, a
+//! device buffer and a read-back buffer. Every batch goes through upload, transform and
+//! download in its slot, so while one slot's batch is transformed on the context's queue, the
+//! next batch is copied into another slot on the transfer queue, if the context has one (see
+//! [`ContextBuilder::transfer_queue`](crate::context::ContextBuilder::transfer_queue)). A
+//! semaphore orders each transform after its upload, and a fence tells when its slot is free
+//! again.
+//!
+//! Batches arriving one at a time, such as audio blocks, are pushed and come out once their
+//! slot is needed again:
+//!
+//! ```ignore
+//! let context = Context::builder(&instance).transfer_queue().build()?;
+//! let mut stream = StreamingFft::new(&context, Config::builder().dim(&[4096]), FftType::Forward)?;
+//! while let Some(block) = source.next_block() {
+//!   if let Some(spectrum) = stream.push(&block)? {
+//!     sink.push(spectrum);
+//!   }
+//! }
+//! for spectrum in stream.flush()? {
+//!   sink.push(spectrum);
+//! }
+//! ```
+
+use std::sync::Arc;
+
+use ash::vk;
+use vulkano::{
+  buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+  command_buffer::{
+    allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo},
+    CommandBuffer, CommandBufferBeginInfo, CommandBufferLevel, CommandBufferUsage,
+    RecordingCommandBuffer,
+  },
+  device::Queue,
+  memory::allocator::{AllocationCreateInfo, MemoryTypeFilter},
+  sync::{
+    fence::{Fence, FenceCreateInfo},
+    semaphore::{Semaphore, SemaphoreCreateInfo},
+    Sharing,
+  },
+  VulkanObject,
+};
+
+use crate::{
+  config::{ConfigBuilder, ConfigError},
+  context::{check_submit, wait_fence, Context, FftType},
+  dry_run::DryRun,
+  scalar::FftScalar,
+  stage::{FftStage, Stage},
+};
+
+/// A submitted batch, with everything its execution uses.
+struct InFlight {
+  fence: Fence,
+  _command_buffers: [CommandBuffer; 2],
+}
+
+struct Slot<T: FftScalar> {
+  staging: Subbuffer<[T]>,
+  buffer: Subbuffer<[T]>,
+  readback: Subbuffer<[T]>,
+  stage: FftStage,
+  uploaded: Semaphore,
+  in_flight: Option<InFlight>,
+}
+
+/// A transform applied to a stream of equally shaped batches, overlapping the upload of each
+/// batch with the transforms of the previous ones.
+pub struct StreamingFft<'c, T: FftScalar> {
+  context: &'c Context,
+  upload_queue: Arc<Queue>,
+  command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+  slots: Vec<Slot<T>>,
+  /// Number of batches pushed so far
+  pushed: usize,
+}
+
+impl<'c, T: FftScalar> StreamingFft<'c, T> {
+  /// Plans the transform `config_builder` describes, in the precision of `T`, with two slots.
+  /// Its buffers are replaced.
+  pub fn new(
+    context: &'c Context,
+    config_builder: ConfigBuilder,
+    fft_type: FftType,
+  ) -> Result<Self, crate::Error> {
+    Self::with_depth(context, config_builder, fft_type, 2)
+  }
+
+  /// Like [`StreamingFft::new`], with `depth` slots, at least one. More slots absorb more jitter in the
+  /// arrival of batches, at the cost of latency and memory.
+  pub fn with_depth(
+    context: &'c Context,
+    config_builder: ConfigBuilder,
+    fft_type: FftType,
+    depth: usize,
+  ) -> Result<Self, crate::Error> {
+    if depth == 0 {
+      return Err(ConfigError::ZeroSize("slot count").into());
+    }
+    let config_builder = config_builder.precision(T::PRECISION);
+    let len =
+      DryRun::new(&config_builder.plan_key())?.buffer_size / std::mem::size_of::<T>() as u64;
+    let upload_queue = context
+      .transfer_queue
+      .clone()
+      .unwrap_or_else(|| context.queue.clone());
+
+    // Both queues access the device buffers, which saves ownership transfers
+    let families = [
+      context.queue.queue_family_index(),
+      upload_queue.queue_family_index(),
+    ];
+    let sharing = if families[0] == families[1] {
+      Sharing::Exclusive
+    } else {
+      Sharing::Concurrent(families.to_vec().into())
+    };
+    let new_buffer = |sharing: Sharing<_>, memory_type_filter| {
+      Buffer::new_slice::<T>(
+        context.allocator.clone(),
+        BufferCreateInfo {
+          sharing,
+          usage: BufferUsage::STORAGE_BUFFER
+            | BufferUsage::TRANSFER_SRC
+            | BufferUsage::TRANSFER_DST,
+          ..Default::default()
+        },
+        AllocationCreateInfo {
+          memory_type_filter,
+          ..Default::default()
+        },
+        len,
+      )
+    };
+
+    let slot = || -> Result<Slot<T>, crate::Error> {
+      let buffer = new_buffer(sharing.clone(), MemoryTypeFilter::PREFER_DEVICE)?;
+      let stage = context.fft_stage(config_builder.clone().buffer(buffer.clone()), fft_type)?;
+      Ok(Slot {
+        staging: new_buffer(
+          Sharing::Exclusive,
+          MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+        )?,
+        buffer,
+        readback: new_buffer(
+          Sharing::Exclusive,
+          MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+        )?,
+        stage,
+        uploaded: Semaphore::new(context.device.clone(), SemaphoreCreateInfo::default())?,
+        in_flight: None,
+      })
+    };
+    let slots = (0..depth).map(|_| slot()).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Self {
+      context,
+      upload_queue,
+      command_buffer_allocator: Arc::new(StandardCommandBufferAllocator::new(
+        context.device.clone(),
+        StandardCommandBufferAllocatorCreateInfo::default(),
+      )),
+      slots,
+      pushed: 0,
+    })
+  }
+
+  /// Number of `T`s in each batch.
+  pub fn batch_len(&self) -> usize {
+    self.slots[0].staging.len() as usize
+  }
+
+  /// Submits `batch`, which must hold [`StreamingFft::batch_len`] values, and returns the result
+  /// of the batch pushed as many slots ago, once its slot is needed again.
+  ///
+  /// Waiting for a slot honors the context's submit timeout. On
+  /// [`SubmitError::Timeout`](crate::context::SubmitError::Timeout) `batch` isn't submitted and
+  /// can be pushed again.
+  pub fn push(&mut self, batch: &[T]) -> Result<Option<Vec<T>>, crate::Error> {
+    let mut result = None;
+    self.push_with(batch, &mut |r: &[T]| result = Some(r.to_vec()))?;
+    Ok(result)
+  }
+
+  /// Waits for every batch still in flight and returns their results in order.
+  pub fn flush(&mut self) -> Result<Vec<Vec<T>>, crate::Error> {
+    let mut results = Vec::new();
+    self.flush_with(&mut |r: &[T]| results.push(r.to_vec()))?;
+    Ok(results)
+  }
+
+  /// Transforms every batch of `batches` and passes the results to `on_result` in order, without
+  /// copying them out of the read-back buffers.
+  pub fn process<I, B, F>(&mut self, batches: I, mut on_result: F) -> Result<(), crate::Error>
+  where
+    I: IntoIterator<Item = B>,
+    B: AsRef<[T]>,
+    F: FnMut(&[T]),
+  {
+    for batch in batches {
+      self.push_with(batch.as_ref(), &mut on_result)?;
+    }
+    self.flush_with(&mut on_result)
+  }
+
+  fn push_with<F: FnMut(&[T])>(
+    &mut self,
+    batch: &[T],
+    on_result: &mut F,
+  ) -> Result<(), crate::Error> {
+    if batch.len() != self.batch_len() {
+      return Err(ConfigError::LengthMismatch("batch", batch.len()).into());
+    }
+    let slot = self.pushed % self.slots.len();
+    // The slot is free once the batch submitted a full ring ago has completed
+    self.finish(slot, on_result)?;
+    self.slots[slot].staging.write()?.copy_from_slice(batch);
+    self.submit(slot)?;
+    self.pushed += 1;
+    Ok(())
+  }
+
+  fn flush_with<F: FnMut(&[T])>(&mut self, on_result: &mut F) -> Result<(), crate::Error> {
+    for i in 0..self.slots.len() {
+      self.finish((self.pushed + i) % self.slots.len(), on_result)?;
+    }
+    Ok(())
+  }
+
+  /// Waits for the batch in `slot`, if any, and passes its result to `on_result`.
+  fn finish<F: FnMut(&[T])>(&mut self, slot: usize, on_result: &mut F) -> Result<(), crate::Error> {
+    let timeout = self.context.submit_timeout;
+    let slot = &mut self.slots[slot];
+    if let Some(in_flight) = &slot.in_flight {
+      // On a timeout the batch stays in flight, and is waited for again on the next call
+      wait_fence(&in_flight.fence, timeout)?;
+      slot.in_flight = None;
+      on_result(&slot.readback.read()?);
+    }
+    Ok(())
+  }
+
+  /// Uploads the staging buffer of `slot` on the upload queue, then transforms and reads it
+  /// back on the context's queue.
+  fn submit(&mut self, slot: usize) -> Result<(), crate::Error> {
+    let device = self.context.device.clone();
+    let fns = device.fns();
+    let queue = self.context.queue.clone();
+    let upload_queue = self.upload_queue.clone();
+    let allocator = self.command_buffer_allocator.clone();
+    let Slot {
+      staging,
+      buffer,
+      readback,
+      stage,
+      uploaded,
+      in_flight,
+    } = &mut self.slots[slot];
+    let copy = |command_buffer: vk::CommandBuffer, src: &Subbuffer<[T]>, dst: &Subbuffer<[T]>| {
+      let region = vk::BufferCopy {
+        src_offset: src.offset(),
+        dst_offset: dst.offset(),
+        size: src.size(),
+      };
+      unsafe {
+        (fns.v1_0.cmd_copy_buffer)(
+          command_buffer,
+          src.buffer().handle(),
+          dst.buffer().handle(),
+          1,
+          &region,
+        )
+      };
+    };
+
+    let upload = record(
+      &allocator,
+      upload_queue.queue_family_index(),
+      |command_buffer| {
+        copy(command_buffer, staging, buffer);
+        Ok(())
+      },
+    )?;
+    let transform = record(&allocator, queue.queue_family_index(), |command_buffer| {
+      stage.record(command_buffer)?;
+      let barrier = vk::MemoryBarrier {
+        src_access_mask: vk::AccessFlags::SHADER_WRITE,
+        dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+        ..Default::default()
+      };
+      unsafe {
+        (fns.v1_0.cmd_pipeline_barrier)(
+          command_buffer,
+          vk::PipelineStageFlags::COMPUTE_SHADER,
+          vk::PipelineStageFlags::TRANSFER,
+          vk::DependencyFlags::empty(),
+          1,
+          &barrier,
+          0,
+          std::ptr::null(),
+          0,
+          std::ptr::null(),
+        )
+      };
+      copy(command_buffer, buffer, readback);
+      let barrier = vk::MemoryBarrier {
+        src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+        dst_access_mask: vk::AccessFlags::HOST_READ,
+        ..Default::default()
+      };
+      unsafe {
+        (fns.v1_0.cmd_pipeline_barrier)(
+          command_buffer,
+          vk::PipelineStageFlags::TRANSFER,
+          vk::PipelineStageFlags::HOST,
+          vk::DependencyFlags::empty(),
+          1,
+          &barrier,
+          0,
+          std::ptr::null(),
+          0,
+          std::ptr::null(),
+        )
+      };
+      Ok(())
+    })?;
+
+    let semaphore = uploaded.handle();
+    let upload_handle = upload.handle();
+    let upload_info = vk::SubmitInfo {
+      command_buffer_count: 1,
+      p_command_buffers: &upload_handle,
+      signal_semaphore_count: 1,
+      p_signal_semaphores: &semaphore,
+      ..Default::default()
+    };
+    check_submit(upload_queue.with(|_| unsafe {
+      (fns.v1_0.queue_submit)(upload_queue.handle(), 1, &upload_info, vk::Fence::null())
+    }))?;
+
+    let fence = Fence::new(device.clone(), FenceCreateInfo::default())?;
+    let transform_handle = transform.handle();
+    let wait_stage = vk::PipelineStageFlags::COMPUTE_SHADER;
+    let transform_info = vk::SubmitInfo {
+      wait_semaphore_count: 1,
+      p_wait_semaphores: &semaphore,
+      p_wait_dst_stage_mask: &wait_stage,
+      command_buffer_count: 1,
+      p_command_buffers: &transform_handle,
+      ..Default::default()
+    };
+    check_submit(queue.with(|_| unsafe {
+      (fns.v1_0.queue_submit)(queue.handle(), 1, &transform_info, fence.handle())
+    }))?;
+
+    *in_flight = Some(InFlight {
+      fence,
+      _command_buffers: [upload, transform],
+    });
+    Ok(())
+  }
+}
+
+/// Records a one-time primary command buffer for `queue_family_index` with `commands`.
+fn record<F>(
+  allocator: &Arc<StandardCommandBufferAllocator>,
+  queue_family_index: u32,
+  commands: F,
+) -> Result<CommandBuffer, crate::Error>
+where
+  F: FnOnce(vk::CommandBuffer) -> Result<(), crate::Error>,
+{
+  let recording = RecordingCommandBuffer::new(
+    allocator.clone(),
+    queue_family_index,
+    CommandBufferLevel::Primary,
+    CommandBufferBeginInfo {
+      usage: CommandBufferUsage::OneTimeSubmit,
+      ..Default::default()
+    },
+  )?;
+  commands(recording.handle())?;
+  Ok(unsafe { recording.end()? })
+}
+
+impl<T: FftScalar> Drop for StreamingFft<'_, T> {
+  fn drop(&mut self) {
+    // Buffers and command buffers must outlive the work using them
+    for slot in &self.slots {
+      if let Some(in_flight) = &slot.in_flight {
+        let _ = in_flight.fence.wait(None);
+      }
+    }
+  }
+}
@@ -0,0 +1,107 @@
+//! Performance knobs of VkFFT's kernel generator, for matching vendor-tuned performance on a
+//! particular GPU. Every option left unset keeps VkFFT's choice for the device.
+//!
+//! ```ignore
+//! let config = Config::builder()
+//!   .dim(&[4096, 4096])
+//!   .tuning(TuningOptions {
+//!     coalesced_memory: Some(64),
+//!     aim_threads: Some(256),
+//!     ..Default::default()
+//!   })
+//!   .build()?;
+//! ```
+//!
+//! [Environment overrides](crate::overrides) are applied after these options, so they win when
+//! both set the same parameter.
+
+use crate::config::MAX_FFT_DIMENSIONS;
+
+/// Options passed to VkFFT's kernel generator. They change the generated kernels, so they are
+/// part of a [`PlanKey`](crate::config::PlanKey).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TuningOptions {
+  /// Bytes of memory coalesced per access, `coalescedMemory`. VkFFT uses 32 on NVIDIA and 64 on
+  /// other vendors.
+  pub coalesced_memory: Option<u64>,
+  /// Number of threads per workgroup VkFFT aims for, `aimThreads`.
+  pub aim_threads: Option<u64>,
+  /// Number of shared memory banks, `numSharedBanks`.
+  pub num_shared_banks: Option<u64>,
+  /// Factor by which to increase register usage for bigger sequences in a single upload,
+  /// `registerBoost`.
+  pub register_boost: Option<u64>,
+  /// Like `register_boost`, for the four-step algorithm, `registerBoost4Step`.
+  pub register_boost_4_step: Option<u64>,
+  /// Number of batches grouped into a workgroup for each axis, `groupedBatch`.
+  pub grouped_batch: Option<[u64; MAX_FFT_DIMENSIONS]>,
+  /// Factor by which to reduce coalescing on strided axes to fit longer sequences in one
+  /// upload, `performBandwidthBoost`. `-1` disables it.
+  pub bandwidth_boost: Option<i32>,
+}
+
+impl TuningOptions {
+  pub fn is_empty(&self) -> bool {
+    *self == Self::default()
+  }
+
+  pub(crate) fn write_sys(&self, config: &mut vkfft_sys::VkFFTConfiguration) {
+    if let Some(coalesced_memory) = self.coalesced_memory {
+      config.coalescedMemory = coalesced_memory;
+    }
+
+    if let Some(aim_threads) = self.aim_threads {
+      config.aimThreads = aim_threads;
+    }
+
+    if let Some(num_shared_banks) = self.num_shared_banks {
+      config.numSharedBanks = num_shared_banks;
+    }
+
+    if let Some(register_boost) = self.register_boost {
+      config.registerBoost = register_boost;
+    }
+
+    if let Some(register_boost_4_step) = self.register_boost_4_step {
+      config.registerBoost4Step = register_boost_4_step;
+    }
+
+    if let Some(grouped_batch) = self.grouped_batch {
+      config.groupedBatch = grouped_batch;
+    }
+
+    if let Some(bandwidth_boost) = self.bandwidth_boost {
+      config.performBandwidthBoost = bandwidth_boost;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{config::ConfigBuilder, overrides::EnvOverrides};
+
+  #[test]
+  fn check_write_sys() {
+    let mut grouped_batch = [1; MAX_FFT_DIMENSIONS];
+    grouped_batch[..2].copy_from_slice(&[4, 2]);
+    let tuning = TuningOptions {
+      aim_threads: Some(128),
+      grouped_batch: Some(grouped_batch),
+      bandwidth_boost: Some(-1),
+      ..Default::default()
+    };
+    let mut key = ConfigBuilder::new().dim(&[256, 256]).tuning(tuning).plan_key();
+    key.overrides = EnvOverrides {
+      aim_threads: Some(64),
+      ..Default::default()
+    };
+
+    let mut sys = unsafe { std::mem::zeroed::<vkfft_sys::VkFFTConfiguration>() };
+    key.write_sys(&mut sys).unwrap();
+    assert_eq!(sys.aimThreads, 64);
+    assert_eq!(sys.groupedBatch, grouped_batch);
+    assert_eq!(sys.performBandwidthBoost, -1);
+    assert_eq!(sys.coalescedMemory, 0);
+  }
+}
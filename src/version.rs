@@ -1,4 +1,7 @@
-use std::fmt::{Display, Formatter};
+use std::fmt::Formatter;
+
+use derive_more::{Display, Error};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Version {
   major: u32,
@@ -7,6 +10,15 @@ pub struct Version {
 }
 
 impl Version {
+  /// Decodes `major * 10000 + minor * 100 + patch`, as returned by `VkFFTGetVersion`.
+  pub fn from_raw(raw: u32) -> Self {
+    Self {
+      major: raw / 10000,
+      minor: raw % 10000 / 100,
+      patch: raw % 100,
+    }
+  }
+
   #[inline]
   pub fn major(&self) -> u32 {
     self.major
@@ -23,18 +35,56 @@ impl Version {
   }
 }
 
-impl Display for Version {
+impl std::fmt::Display for Version {
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
     write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
   }
 }
 
+/// The version of the compiled VkFFT library.
 pub fn version() -> Version {
-  let ver = unsafe { vkfft_sys::VkFFTGetVersion() };
+  Version::from_raw(unsafe { vkfft_sys::VkFFTGetVersion() } as u32)
+}
+
+/// The version of the VkFFT sources the bindings were generated from, if recorded.
+pub fn bindings_version() -> Option<Version> {
+  vkfft_sys::BINDINGS_VERSION
+    .and_then(|raw| raw.parse().ok())
+    .map(Version::from_raw)
+}
+
+/// The compiled VkFFT library doesn't match the bindings, so the layout of its configuration may
+/// differ from the one the crate writes.
+#[derive(Display, Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[display("VkFFT {compiled} was compiled, but the bindings are for {bindings}")]
+pub struct VersionMismatch {
+  pub compiled: Version,
+  pub bindings: Version,
+}
+
+/// Checks that the compiled VkFFT library is the version the bindings were generated from. Passes
+/// when the version of the bindings wasn't recorded.
+pub fn check_version() -> Result<(), VersionMismatch> {
+  match bindings_version() {
+    Some(bindings) if bindings != version() => Err(VersionMismatch {
+      compiled: version(),
+      bindings,
+    }),
+    _ => Ok(()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-  Version {
-    major: (ver / 10000) as u32,
-    minor: (ver % 10000 / 100) as u32,
-    patch: (ver % 100) as u32,
+  #[test]
+  fn check_from_raw() {
+    let version = Version::from_raw(10305);
+    assert_eq!(
+      (version.major(), version.minor(), version.patch()),
+      (1, 3, 5)
+    );
+    assert_eq!(version.to_string(), "1.3.5");
   }
 }
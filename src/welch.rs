@@ -0,0 +1,317 @@
+//! Power spectral density estimation with Welch's method, on the GPU.
+//!
+//! A [`WelchPsd`] splits a real `f32` signal into overlapping segments, windows them, transforms
+//! them as one batched R2C transform, and averages their periodograms into a one-sided density,
+//! like scipy's `signal.welch` with `scaling='density'` and no detrending:
+//!
+//! ```ignore
+//! let options = WelchOptions::new(1024, 48_000.0)?;
+//! let mut welch = WelchPsd::new(&context, signal, psd.clone(), samples, options)?;
+//! context.run(&mut welch)?;
+//! let frequencies = options.frequencies();
+//! ```
+//!
+//! [`welch_psd`] does the same for a signal on the host, uploading it and reading back the
+//! estimate.
+
+use std::{convert::TryFrom, error::Error, sync::Arc};
+
+use ash::vk;
+use derive_more::{Display, Error};
+use vulkano::buffer::Buffer;
+
+use crate::{
+  config::{Config, ConfigError},
+  context::{Context, FftType},
+  error,
+  frequency::rfftfreq,
+  shader::{group_counts, with_defines, Kernel},
+  stage::{Chain, Stage},
+  window::{Window, WindowLayout, Windowing},
+};
+
+const WELCH: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+#if defined(SEGMENT)
+layout(std430, binding = 0) readonly buffer Input { float src[]; };
+layout(std430, binding = 1) writeonly buffer Output { float dst[]; };
+#else
+layout(std430, binding = 0) readonly buffer Input { vec2 src[]; };
+layout(std430, binding = 1) writeonly buffer Output { float dst[]; };
+#endif
+layout(push_constant) uniform Params {
+  uint segment_len;
+  uint hop;
+  uint segments;
+  float scale;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  uint bins = segment_len / 2 + 1;
+#if defined(SEGMENT)
+  // Segments are padded like the rows of in-place R2C transforms
+  if (i >= segments * 2 * bins) {
+    return;
+  }
+
+  uint segment = i / (2 * bins);
+  uint n = i % (2 * bins);
+  dst[i] = n < segment_len ? src[segment * hop + n] : 0.0;
+#else
+  if (i >= bins) {
+    return;
+  }
+
+  float sum = 0.0;
+  for (uint segment = 0; segment < segments; segment++) {
+    vec2 value = src[segment * bins + i];
+    sum += dot(value, value);
+  }
+  // Bins other than DC and Nyquist also hold the power of their negative frequency
+  bool mirrored = i > 0 && (i < bins - 1 || segment_len % 2 == 1);
+  dst[i] = sum * scale * (mirrored ? 2.0 : 1.0);
+#endif
+}
+"#;
+
+#[derive(Display, Debug, Error, Clone, Copy, PartialEq)]
+pub enum WelchError {
+  #[display("The segment length is zero")]
+  ZeroSegmentLength,
+  #[display("The sample rate {} isn't positive", _0)]
+  InvalidSampleRate(#[error(not(source))] f64),
+  #[display("Segments of {} samples can't overlap by {}", _0, _1)]
+  InvalidOverlap(u32, #[error(not(source))] u32),
+  #[display("The signal of {} samples is shorter than a segment", _0)]
+  ShortSignal(#[error(not(source))] u32),
+  #[display("The segments hold more than 2^32 values")]
+  TooManySegments,
+}
+
+/// Segmentation of a Welch estimate. The defaults match scipy's: a periodic Hann window and
+/// segments overlapping by half their length.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WelchOptions {
+  pub segment_len: u32,
+  /// Number of samples shared by consecutive segments
+  pub overlap: u32,
+  pub window: Window,
+  pub sample_rate: f64,
+}
+
+impl WelchOptions {
+  pub fn new(segment_len: u32, sample_rate: f64) -> Result<Self, WelchError> {
+    if segment_len == 0 {
+      return Err(WelchError::ZeroSegmentLength);
+    }
+    if sample_rate.is_nan() || sample_rate <= 0.0 {
+      return Err(WelchError::InvalidSampleRate(sample_rate));
+    }
+    Ok(Self {
+      segment_len,
+      overlap: segment_len / 2,
+      window: Window::Hann,
+      sample_rate,
+    })
+  }
+
+  /// Sets the overlap, which must be shorter than a segment.
+  pub fn overlap(mut self, overlap: u32) -> Result<Self, WelchError> {
+    if overlap >= self.segment_len {
+      return Err(WelchError::InvalidOverlap(self.segment_len, overlap));
+    }
+    self.overlap = overlap;
+    Ok(self)
+  }
+
+  pub fn window(mut self, window: Window) -> Self {
+    self.window = window;
+    self
+  }
+
+  /// Number of samples between the starts of consecutive segments, 0 if fields were set to
+  /// overlap segments entirely.
+  pub fn hop(&self) -> u32 {
+    self.segment_len.saturating_sub(self.overlap)
+  }
+
+  /// Number of whole segments in a signal of `samples` samples. Trailing samples that don't fill
+  /// a segment are ignored.
+  pub fn segments(&self, samples: u32) -> u32 {
+    match samples.checked_sub(self.segment_len) {
+      Some(rest) => rest.checked_div(self.hop()).map_or(0, |hops| hops + 1),
+      None => 0,
+    }
+  }
+
+  /// Number of values in the estimate, `segment_len / 2 + 1`.
+  pub fn bins(&self) -> u32 {
+    self.segment_len / 2 + 1
+  }
+
+  /// The frequency of each value of the estimate, in the unit of the sample rate.
+  pub fn frequencies(&self) -> Vec<f64> {
+    rfftfreq(self.segment_len, 1.0 / self.sample_rate)
+  }
+
+  /// The factor turning the summed periodograms of `segments` segments into their mean density.
+  fn scale(&self, segments: u32) -> f64 {
+    let window_power = self
+      .window
+      .periodic(self.segment_len as usize)
+      .iter()
+      .map(|w| (*w as f64).powi(2))
+      .sum::<f64>();
+    1.0 / (self.sample_rate * window_power * segments as f64)
+  }
+}
+
+/// A Welch estimate of the power spectral density of a signal, recorded as one chain of GPU
+/// passes.
+pub struct WelchPsd {
+  options: WelchOptions,
+  segments: u32,
+  chain: Chain,
+}
+
+impl WelchPsd {
+  /// Estimates the density of the first `samples` `f32` values of `signal` into
+  /// [`WelchOptions::bins`] `f32` values of `psd`. Allocates a buffer for the segments.
+  pub fn new(
+    context: &Context,
+    signal: Arc<Buffer>,
+    psd: Arc<Buffer>,
+    samples: u32,
+    options: WelchOptions,
+  ) -> Result<Self, Box<dyn Error>> {
+    // The fields are public, so they are checked again
+    let options = WelchOptions::new(options.segment_len, options.sample_rate)?
+      .overlap(options.overlap)?
+      .window(options.window);
+    let segments = options.segments(samples);
+    if segments == 0 {
+      return Err(WelchError::ShortSignal(samples).into());
+    }
+    if signal.size() < samples as u64 * 4 {
+      return Err(ConfigError::BufferTooSmall("signal").into());
+    }
+    if psd.size() < options.bins() as u64 * 4 {
+      return Err(ConfigError::BufferTooSmall("density buffer").into());
+    }
+
+    let padded_len = segments as u64 * 2 * options.bins() as u64;
+    if padded_len > u32::MAX as u64 {
+      return Err(WelchError::TooManySegments.into());
+    }
+    let segmented = context
+      .new_buffer_from_iter(std::iter::repeat_n(0.0f32, padded_len as usize))?
+      .buffer()
+      .clone();
+
+    let push_constants = |scale: f32| {
+      vec![
+        options.segment_len,
+        options.hop(),
+        segments,
+        scale.to_bits(),
+      ]
+    };
+    let segment = Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      &with_defines(WELCH, &["SEGMENT"]),
+      [signal, segmented.clone()],
+      push_constants(0.0),
+      group_counts(padded_len),
+    )?;
+    let layout = WindowLayout::real(options.segment_len)
+      .blocks(segments)
+      .padded_r2c()?;
+    let windowing = Windowing::shaped(context, segmented.clone(), options.window, layout)?;
+    let config_builder = Config::builder()
+      .buffer(segmented.clone())
+      .dim(&[options.segment_len])
+      .r2c()
+      .batch_count(segments);
+    let forward = context.fft_stage(config_builder, FftType::Forward)?;
+    let average = Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      WELCH,
+      [segmented, psd],
+      push_constants(options.scale(segments) as f32),
+      group_counts(options.bins() as u64),
+    )?;
+
+    Ok(Self {
+      options,
+      segments,
+      chain: Chain::new(context.device.clone())
+        .push(segment)
+        .push(windowing)
+        .push(forward)
+        .push(average),
+    })
+  }
+
+  pub fn options(&self) -> WelchOptions {
+    self.options
+  }
+
+  /// Number of segments averaged.
+  pub fn segments(&self) -> u32 {
+    self.segments
+  }
+}
+
+/// Estimates the density of `signal` in one call, uploading it and reading the estimate back.
+pub fn welch_psd(
+  context: &Context,
+  signal: &[f32],
+  options: WelchOptions,
+) -> Result<Vec<f32>, Box<dyn Error>> {
+  let samples = u32::try_from(signal.len())?;
+  let signal = context.new_buffer_from_iter(signal.iter().copied())?;
+  let psd = context.new_buffer_from_iter(std::iter::repeat_n(0.0f32, options.bins() as usize))?;
+  let mut welch = WelchPsd::new(
+    context,
+    signal.buffer().clone(),
+    psd.buffer().clone(),
+    samples,
+    options,
+  )?;
+  context.run(&mut welch)?;
+  let psd = psd.read()?.to_vec();
+  Ok(psd)
+}
+
+impl Stage for WelchPsd {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.chain.record(command_buffer)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_segmentation() {
+    let options = WelchOptions::new(8, 2.0).unwrap();
+    assert_eq!((options.hop(), options.bins()), (4, 5));
+    assert_eq!(options.segments(7), 0);
+    assert_eq!(options.segments(23), 4);
+    assert_eq!(options.overlap(0).unwrap().segments(23), 2);
+    assert_eq!(options.overlap(8), Err(WelchError::InvalidOverlap(8, 8)));
+    assert_eq!(
+      WelchOptions::new(0, 2.0),
+      Err(WelchError::ZeroSegmentLength)
+    );
+    assert!(matches!(
+      WelchOptions::new(8, f64::NAN),
+      Err(WelchError::InvalidSampleRate(_))
+    ));
+    // The periodic Hann window of 8 samples has a power of 3
+    assert!((options.scale(4) - 1.0 / (2.0 * 3.0 * 4.0)).abs() < 1e-7);
+  }
+}
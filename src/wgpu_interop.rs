@@ -0,0 +1,274 @@
+//! Running transforms on the Vulkan device of a [wgpu](https://docs.rs/wgpu) application.
+//!
+//! [`Context::from_wgpu`] wraps the instance, device and queue of a `wgpu::Device` on the Vulkan
+//! backend. wgpu doesn't expose the Vulkan handles of its buffers, so buffers shared with the
+//! application are created by the context and imported into wgpu with
+//! [`Context::new_wgpu_buffer`]:
+//!
+//! ```ignore
+//! let context = unsafe { Context::from_wgpu(&device)? };
+//! let buffer = context.new_wgpu_buffer(&device, 2 * 4 * 4096, wgpu::BufferUsages::VERTEX)?;
+//! let builder = Config::builder().dim(&[4096]).buffer(buffer.clone());
+//! context.run(&mut context.fft_stage(builder, FftType::Forward)?)?;
+//! render_pass.set_vertex_buffer(0, buffer.wgpu().slice(..));
+//! ```
+
+use std::{any::Any, ffi::CStr, sync::Arc};
+
+use ash::vk;
+use vulkano::{
+  device::{Device, DeviceCreateInfo, DeviceExtensions, DeviceFeatures, QueueCreateInfo},
+  instance::{Instance, InstanceCreateInfo, InstanceExtensions},
+  memory::{DeviceMemory, MemoryAllocateInfo, MemoryPropertyFlags},
+  Version, VulkanLibrary, VulkanObject,
+};
+use wgpu::hal::api::Vulkan;
+
+use crate::{buffer::FftBuffer, config::ConfigError, context::Context};
+
+/// Raw handles of a wgpu device on the Vulkan backend.
+struct WgpuHandles {
+  instance: vk::Instance,
+  api_version: u32,
+  instance_extensions: Vec<&'static CStr>,
+  physical_device: vk::PhysicalDevice,
+  device: vk::Device,
+  device_extensions: Vec<&'static CStr>,
+  queue_family_index: u32,
+  queue_index: u32,
+}
+
+impl Context {
+  /// Creates a context on the device and queue of `device`, which must use wgpu's Vulkan
+  /// backend. Transforms of precisions whose wgpu features (`SHADER_F64`, `SHADER_F16`) are
+  /// enabled on `device` can run on it.
+  ///
+  /// # Safety
+  ///
+  /// The context must be dropped before `device`. wgpu locks its queue on its own, so the
+  /// context must not submit while wgpu submits from another thread.
+  pub unsafe fn from_wgpu(device: &wgpu::Device) -> Result<Self, crate::Error> {
+    let handles = device
+      .as_hal::<Vulkan, _, _>(|device| {
+        device.map(|device| {
+          let instance = device.shared_instance();
+          WgpuHandles {
+            instance: instance.raw_instance().handle(),
+            api_version: instance.instance_api_version(),
+            instance_extensions: instance.extensions().to_vec(),
+            physical_device: device.raw_physical_device(),
+            device: device.raw_device().handle(),
+            device_extensions: device.enabled_device_extensions().to_vec(),
+            queue_family_index: device.queue_family_index(),
+            queue_index: device.queue_index(),
+          }
+        })
+      })
+      .ok_or(crate::Error::NotVulkan)?;
+
+    let names = |extensions: &[&CStr]| {
+      extensions
+        .iter()
+        .filter_map(|name| name.to_str().ok())
+        .map(str::to_owned)
+        .collect::<Vec<_>>()
+    };
+    let instance = Instance::from_handle(
+      VulkanLibrary::new()?,
+      handles.instance,
+      InstanceCreateInfo {
+        max_api_version: Some(Version::from(handles.api_version)),
+        enabled_extensions: names(&handles.instance_extensions)
+          .iter()
+          .map(String::as_str)
+          .collect::<InstanceExtensions>(),
+        ..Default::default()
+      },
+    );
+    let physical = instance
+      .enumerate_physical_devices()?
+      .find(|physical| physical.handle() == handles.physical_device)
+      .ok_or(crate::Error::NotVulkan)?;
+
+    let features = device.features();
+    let (vulkano_device, mut queues) = Device::from_handle(
+      physical,
+      handles.device,
+      DeviceCreateInfo {
+        queue_create_infos: vec![QueueCreateInfo {
+          queue_family_index: handles.queue_family_index,
+          queues: vec![1.0; handles.queue_index as usize + 1],
+          ..Default::default()
+        }],
+        enabled_extensions: names(&handles.device_extensions)
+          .iter()
+          .map(String::as_str)
+          .collect::<DeviceExtensions>(),
+        enabled_features: DeviceFeatures {
+          shader_float64: features.contains(wgpu::Features::SHADER_F64),
+          shader_int64: features.contains(wgpu::Features::SHADER_INT64),
+          shader_float16: features.contains(wgpu::Features::SHADER_F16),
+          storage_buffer16_bit_access: features.contains(wgpu::Features::SHADER_F16),
+          ..Default::default()
+        },
+        ..Default::default()
+      },
+    );
+    let queue = queues.nth(handles.queue_index as usize).unwrap();
+    // wgpu owns the instance and device, which vulkano would destroy with the last reference
+    std::mem::forget(instance.clone());
+    std::mem::forget(vulkano_device);
+    Self::from_queue(&instance, queue)
+  }
+
+  /// Creates a device-local buffer of `size` bytes and imports it into `device`, the wgpu device
+  /// the context was created from, with `usage` in addition to storage and copies.
+  pub fn new_wgpu_buffer(
+    &self,
+    device: &wgpu::Device,
+    size: u64,
+    usage: wgpu::BufferUsages,
+  ) -> Result<WgpuBuffer, crate::Error> {
+    if size == 0 {
+      return Err(ConfigError::ZeroSize("wgpu buffer").into());
+    }
+    let fns = self.device.fns();
+    let create_info = vk::BufferCreateInfo {
+      size,
+      // Covers every usage wgpu may give the buffer
+      usage: vk::BufferUsageFlags::STORAGE_BUFFER
+        | vk::BufferUsageFlags::TRANSFER_SRC
+        | vk::BufferUsageFlags::TRANSFER_DST
+        | vk::BufferUsageFlags::UNIFORM_BUFFER
+        | vk::BufferUsageFlags::VERTEX_BUFFER
+        | vk::BufferUsageFlags::INDEX_BUFFER
+        | vk::BufferUsageFlags::INDIRECT_BUFFER,
+      sharing_mode: vk::SharingMode::EXCLUSIVE,
+      ..Default::default()
+    };
+    let mut handle = vk::Buffer::null();
+    unsafe {
+      (fns.v1_0.create_buffer)(
+        self.device.handle(),
+        &create_info,
+        std::ptr::null(),
+        &mut handle,
+      )
+    }
+    .result()
+    .map_err(vulkano::VulkanError::from)?;
+
+    let mut requirements = vk::MemoryRequirements::default();
+    unsafe {
+      (fns.v1_0.get_buffer_memory_requirements)(self.device.handle(), handle, &mut requirements)
+    };
+    let memory_types = &self.physical.memory_properties().memory_types;
+    let allowed = |index: &usize| requirements.memory_type_bits & (1 << index) != 0;
+    let memory_type_index = (0..memory_types.len())
+      .filter(allowed)
+      .find(|index| {
+        memory_types[*index]
+          .property_flags
+          .contains(MemoryPropertyFlags::DEVICE_LOCAL)
+      })
+      .or_else(|| (0..memory_types.len()).find(allowed))
+      .unwrap_or_default() as u32;
+    let memory = DeviceMemory::allocate(
+      self.device.clone(),
+      MemoryAllocateInfo {
+        allocation_size: requirements.size,
+        memory_type_index,
+        ..Default::default()
+      },
+    );
+    let destroy =
+      || unsafe { (fns.v1_0.destroy_buffer)(self.device.handle(), handle, std::ptr::null()) };
+    let memory = match memory {
+      Ok(memory) => memory,
+      Err(e) => {
+        destroy();
+        return Err(e.into());
+      }
+    };
+    let bound =
+      unsafe { (fns.v1_0.bind_buffer_memory)(self.device.handle(), handle, memory.handle(), 0) };
+    if let Err(e) = bound.result() {
+      destroy();
+      return Err(vulkano::VulkanError::from(e).into());
+    }
+
+    // From here on wgpu owns the handle and destroys it with the buffer
+    let buffer = unsafe {
+      device.create_buffer_from_hal::<Vulkan>(
+        wgpu::hal::vulkan::Device::buffer_from_raw(handle),
+        &wgpu::BufferDescriptor {
+          label: Some("vkfft"),
+          size,
+          usage: usage
+            | wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_SRC
+            | wgpu::BufferUsages::COPY_DST,
+          mapped_at_creation: false,
+        },
+      )
+    };
+    Ok(WgpuBuffer {
+      handle,
+      size,
+      shared: Arc::new(Shared {
+        device: device.clone(),
+        buffer,
+        _memory: memory,
+      }),
+    })
+  }
+}
+
+/// A buffer allocated by a [`Context`] and imported into wgpu, usable both in transforms, as an
+/// [`FftBuffer`], and in wgpu passes through [`WgpuBuffer::wgpu`].
+///
+/// Dropping the last clone, or the last config or app bound to the buffer, blocks until the wgpu
+/// device has finished the work using it, since its memory can't be freed before.
+#[derive(Clone)]
+pub struct WgpuBuffer {
+  handle: vk::Buffer,
+  size: u64,
+  shared: Arc<Shared>,
+}
+
+/// The wgpu buffer and the memory bound to it, which is freed once wgpu has destroyed the
+/// buffer.
+struct Shared {
+  device: wgpu::Device,
+  buffer: wgpu::Buffer,
+  _memory: DeviceMemory,
+}
+
+impl Drop for Shared {
+  fn drop(&mut self) {
+    // wgpu destroys the buffer once the submissions using it have completed, which polling
+    // waits for
+    self.buffer.destroy();
+    let _ = self.device.poll(wgpu::PollType::Wait);
+  }
+}
+
+impl WgpuBuffer {
+  pub fn wgpu(&self) -> &wgpu::Buffer {
+    &self.shared.buffer
+  }
+}
+
+unsafe impl FftBuffer for WgpuBuffer {
+  fn handle(&self) -> vk::Buffer {
+    self.handle
+  }
+
+  fn size(&self) -> u64 {
+    self.size
+  }
+
+  fn keep_alive(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+    Some(self.shared.clone())
+  }
+}
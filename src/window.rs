@@ -0,0 +1,313 @@
+//! Window functions applied on the GPU to blocks of input before a forward transform.
+//!
+//! A [`Window`] generates its coefficients on the host, and [`Windowing`] multiplies every block
+//! of a buffer by them in place. It is a stage, so [`Windowing::before`] records the window and
+//! the transform into the same command buffer:
+//!
+//! ```ignore
+//! let layout = WindowLayout::real(1024).blocks(64).padded_r2c()?;
+//! let windowing = Windowing::shaped(&context, data, Window::Hann, layout)?;
+//! context.run(&mut windowing.before(forward))?;
+//! ```
+
+use std::{convert::TryFrom, error::Error, f64::consts::PI, sync::Arc};
+
+use ash::vk;
+use derive_more::{Display, Error};
+use vulkano::{buffer::Buffer, device::Device};
+
+use crate::{
+  config::ConfigError,
+  context::Context,
+  error,
+  shader::{group_counts, Kernel},
+  stage::{Chain, FftStage, Stage},
+};
+
+const APPLY_WINDOW: &str = r#"
+#version 450
+layout(local_size_x = 256) in;
+layout(std430, binding = 0) buffer Data { float data[]; };
+layout(std430, binding = 1) readonly buffer Window { float window[]; };
+layout(push_constant) uniform Params {
+  uint len;
+  uint stride;
+  uint blocks;
+  uint components;
+};
+
+void main() {
+  uint i = gl_GlobalInvocationID.x + gl_GlobalInvocationID.y * gl_NumWorkGroups.x * gl_WorkGroupSize.x;
+  if (i >= len * blocks * components) {
+    return;
+  }
+
+  uint component = i % components;
+  uint n = (i / components) % len;
+  uint block = i / (components * len);
+  data[(block * stride + n) * components + component] *= window[n];
+}
+"#;
+
+/// A window function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+  Hann,
+  Hamming,
+  Blackman,
+  /// The Kaiser window with shape parameter `beta`, rectangular at 0 and narrower as it grows
+  Kaiser {
+    beta: f64,
+  },
+}
+
+impl Window {
+  /// The `len` coefficients of the periodic window, which is the usual one for spectral analysis,
+  /// like scipy's `get_window`.
+  pub fn periodic(&self, len: usize) -> Vec<f32> {
+    self.coefficients(len, len as f64)
+  }
+
+  /// The `len` coefficients of the symmetric window, as filter design uses and numpy's `hanning`
+  /// and friends return.
+  pub fn symmetric(&self, len: usize) -> Vec<f32> {
+    self.coefficients(len, len.saturating_sub(1) as f64)
+  }
+
+  fn coefficients(&self, len: usize, period: f64) -> Vec<f32> {
+    if len == 1 {
+      return vec![1.0];
+    }
+    (0..len)
+      .map(|n| {
+        let phase = 2.0 * PI * n as f64 / period;
+        let value = match *self {
+          Window::Hann => 0.5 - 0.5 * phase.cos(),
+          Window::Hamming => 0.54 - 0.46 * phase.cos(),
+          Window::Blackman => 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos(),
+          Window::Kaiser { beta } => {
+            let x = 2.0 * n as f64 / period - 1.0;
+            bessel_i0(beta * (1.0 - x * x).max(0.0).sqrt()) / bessel_i0(beta)
+          }
+        };
+        value as f32
+      })
+      .collect()
+  }
+}
+
+/// The modified Bessel function of the first kind of order 0, from its power series.
+fn bessel_i0(x: f64) -> f64 {
+  let quarter_x2 = x * x / 4.0;
+  let mut term = 1.0;
+  let mut sum = 1.0;
+  for k in 1..500 {
+    term *= quarter_x2 / (k * k) as f64;
+    sum += term;
+    if term < sum * 1e-17 {
+      break;
+    }
+  }
+  sum
+}
+
+#[derive(Display, Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum WindowError {
+  #[display("The blocks are empty")]
+  ZeroLength,
+  #[display("The layout holds no blocks")]
+  ZeroBlocks,
+  #[display("Blocks of {} values can't start {} values apart", _0, _1)]
+  InvalidStride(u32, #[error(not(source))] u32),
+  #[display("Complex blocks can't be padded like the rows of R2C transforms")]
+  PaddedComplex,
+  #[display("The blocks hold more than 2^32 values")]
+  TooManyValues,
+}
+
+/// Layout of the blocks a window applies to: `blocks` runs of `len` real or interleaved complex
+/// `f32` values, each starting `stride` values after the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowLayout {
+  pub len: u32,
+  pub stride: u32,
+  pub blocks: u32,
+  pub complex: bool,
+}
+
+impl WindowLayout {
+  /// One block of `len` real values.
+  pub fn real(len: u32) -> Self {
+    Self {
+      len,
+      stride: len,
+      blocks: 1,
+      complex: false,
+    }
+  }
+
+  /// One block of `len` complex values.
+  pub fn complex(len: u32) -> Self {
+    Self {
+      complex: true,
+      ..Self::real(len)
+    }
+  }
+
+  pub fn blocks(mut self, blocks: u32) -> Self {
+    self.blocks = blocks;
+    self
+  }
+
+  /// Sets the distance between the starts of blocks, which must be at least their length.
+  pub fn stride(mut self, stride: u32) -> Self {
+    self.stride = stride;
+    self
+  }
+
+  /// Real blocks padded like the rows of in-place R2C transforms, `2 * (len / 2 + 1)` values
+  /// apart.
+  pub fn padded_r2c(self) -> Result<Self, WindowError> {
+    if self.complex {
+      return Err(WindowError::PaddedComplex);
+    }
+    Ok(self.stride(2 * (self.len / 2 + 1)))
+  }
+
+  /// Checks the fields, which are public, and returns the number of values windowed.
+  fn values(&self) -> Result<u32, WindowError> {
+    if self.len == 0 {
+      return Err(WindowError::ZeroLength);
+    }
+    if self.blocks == 0 {
+      return Err(WindowError::ZeroBlocks);
+    }
+    if self.stride < self.len {
+      return Err(WindowError::InvalidStride(self.len, self.stride));
+    }
+    let values = self.len as u64 * self.blocks as u64 * self.components() as u64;
+    u32::try_from(values).map_err(|_| WindowError::TooManyValues)
+  }
+
+  fn components(&self) -> u32 {
+    if self.complex {
+      2
+    } else {
+      1
+    }
+  }
+
+  /// Number of `f32` values the windowed buffer must hold.
+  pub fn buffer_len(&self) -> u64 {
+    ((self.blocks.saturating_sub(1) as u64) * self.stride as u64 + self.len as u64)
+      * self.components() as u64
+  }
+}
+
+/// Multiplies blocks of a buffer in place by a window.
+pub struct Windowing {
+  kernel: Kernel,
+  device: Arc<Device>,
+}
+
+impl Windowing {
+  /// Multiplies the blocks of `data` by `window`, which holds `layout.len` `f32` coefficients.
+  pub fn new(
+    context: &Context,
+    data: Arc<Buffer>,
+    window: Arc<Buffer>,
+    layout: WindowLayout,
+  ) -> Result<Self, Box<dyn Error>> {
+    let values = layout.values()?;
+    if data.size() / 4 < layout.buffer_len() {
+      return Err(ConfigError::BufferTooSmall("windowed buffer").into());
+    }
+    if window.size() / 4 < layout.len as u64 {
+      return Err(ConfigError::BufferTooSmall("window").into());
+    }
+
+    let kernel = Kernel::new(
+      context.descriptor_set_allocator.clone(),
+      APPLY_WINDOW,
+      [data, window],
+      vec![
+        layout.len,
+        layout.stride,
+        layout.blocks,
+        layout.components(),
+      ],
+      group_counts(values as u64),
+    )?;
+
+    Ok(Self {
+      kernel,
+      device: context.device.clone(),
+    })
+  }
+
+  /// Multiplies the blocks of `data` by the periodic coefficients of `window`.
+  pub fn shaped(
+    context: &Context,
+    data: Arc<Buffer>,
+    window: Window,
+    layout: WindowLayout,
+  ) -> Result<Self, Box<dyn Error>> {
+    let coefficients = context.new_buffer_from_iter(window.periodic(layout.len as usize))?;
+    Self::new(context, data, coefficients.buffer().clone(), layout)
+  }
+
+  /// A chain running this window and then `forward`, which should transform the windowed buffer.
+  pub fn before(self, forward: FftStage) -> Chain {
+    Chain::new(self.device.clone()).push(self).push(forward)
+  }
+}
+
+impl Stage for Windowing {
+  fn record(&mut self, command_buffer: vk::CommandBuffer) -> error::Result<()> {
+    self.kernel.record(command_buffer);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_close(a: &[f32], b: &[f32]) {
+    assert_eq!(a.len(), b.len());
+    assert!(
+      a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-6),
+      "{:?} != {:?}",
+      a,
+      b
+    );
+  }
+
+  #[test]
+  fn check_coefficients() {
+    assert_close(&Window::Hann.periodic(4), &[0.0, 0.5, 1.0, 0.5]);
+    assert_close(&Window::Hann.symmetric(3), &[0.0, 1.0, 0.0]);
+    assert_close(&Window::Hamming.symmetric(3), &[0.08, 1.0, 0.08]);
+    assert_close(&Window::Blackman.periodic(2), &[0.0, 1.0]);
+    assert_close(&Window::Kaiser { beta: 0.0 }.periodic(3), &[1.0; 3]);
+    // numpy.kaiser(3, 5.0)
+    assert_close(
+      &Window::Kaiser { beta: 5.0 }.symmetric(3),
+      &[0.036_710_89, 1.0, 0.036_710_89],
+    );
+
+    let layout = WindowLayout::real(6).blocks(3).padded_r2c().unwrap();
+    assert_eq!((layout.stride, layout.buffer_len()), (8, 22));
+    assert_eq!(layout.values(), Ok(18));
+    assert_eq!(
+      WindowLayout::complex(6).padded_r2c(),
+      Err(WindowError::PaddedComplex)
+    );
+    assert_eq!(
+      layout.stride(4).values(),
+      Err(WindowError::InvalidStride(6, 4))
+    );
+    assert_eq!(layout.blocks(0).values(), Err(WindowError::ZeroBlocks));
+    assert_eq!(WindowLayout::real(0).values(), Err(WindowError::ZeroLength));
+  }
+}
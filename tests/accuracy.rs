@@ -0,0 +1,197 @@
+//! Compares GPU transforms with double precision references. Skipped on machines without a
+//! Vulkan device.
+
+use vkfft::{
+  accuracy::{verify, verify_convolution, verify_double_double, AccuracyReport},
+  config::{Config, ConfigError, Normalization, Precision, R2RKind},
+  context::{Context, FftType},
+  error::{Error, VkfftError},
+  scalar::DoubleDouble,
+  welch::{welch_psd, WelchOptions},
+};
+
+fn context() -> Option<Context> {
+  match Context::new_standalone() {
+    Ok(context) => Some(context),
+    Err(e) => {
+      eprintln!("Skipping: {}", e);
+      None
+    }
+  }
+}
+
+/// Deterministic values in [-1, 1).
+fn signal(len: usize) -> Vec<f32> {
+  (0..len)
+    .map(|i| ((i * 7919 % 1024) as f32 - 512.0) / 512.0)
+    .collect()
+}
+
+fn check(name: &str, report: AccuracyReport) {
+  println!("{}: {}", name, report);
+  assert!(report.passed(), "{}: {}", name, report);
+}
+
+#[test]
+fn complex_to_complex() {
+  let context = match context() {
+    Some(context) => context,
+    None => return,
+  };
+  for size in [256u32, 1000, 4096, 17] {
+    let data = signal(2 * size as usize);
+    let builder = Config::builder().dim(&[size]);
+    check(
+      &format!("c2c {}", size),
+      verify(&context, builder.clone(), &data, FftType::Forward).unwrap(),
+    );
+    check(
+      &format!("c2c inverse {}", size),
+      verify(&context, builder, &data, FftType::Inverse).unwrap(),
+    );
+  }
+}
+
+#[test]
+fn real_to_complex() {
+  let context = match context() {
+    Some(context) => context,
+    None => return,
+  };
+  let size = [128u32, 30];
+  let data = signal(2 * (size[0] as usize / 2 + 1) * size[1] as usize);
+  check(
+    "r2c 128x30",
+    verify(
+      &context,
+      Config::builder().dim(&size).r2c(),
+      &data,
+      FftType::Forward,
+    )
+    .unwrap(),
+  );
+}
+
+#[test]
+fn orthonormal() {
+  let context = match context() {
+    Some(context) => context,
+    None => return,
+  };
+  let builder = Config::builder()
+    .dim(&[64u32, 8])
+    .normalization(Normalization::Ortho);
+  let data = signal(2 * 64 * 8);
+  for fft_type in [FftType::Forward, FftType::Inverse] {
+    check(
+      &format!("ortho {:?}", fft_type),
+      verify(&context, builder.clone(), &data, fft_type).unwrap(),
+    );
+  }
+
+  // Apps can't record the scaling pass
+  let buffer = context.new_buffer_from_iter(data).unwrap();
+  assert!(matches!(
+    context.app(builder.buffer(buffer)),
+    Err(Error::Vkfft(VkfftError::Config(ConfigError::UnscaledOrtho)))
+  ));
+}
+
+#[test]
+fn discrete_cosine() {
+  let context = match context() {
+    Some(context) => context,
+    None => return,
+  };
+  for kind in [R2RKind::Dct2, R2RKind::Dct3, R2RKind::Dct4] {
+    let data = signal(64);
+    check(
+      &format!("{:?}", kind),
+      verify(
+        &context,
+        Config::builder().dim(&[64]).r2r(kind),
+        &data,
+        FftType::Forward,
+      )
+      .unwrap(),
+    );
+  }
+}
+
+#[test]
+fn convolution() {
+  let context = match context() {
+    Some(context) => context,
+    None => return,
+  };
+  let size = [32u32, 32];
+  let data = signal(2 * 32 * 32);
+  let kernel = signal(2 * 32 * 32).into_iter().rev().collect::<Vec<_>>();
+  check(
+    "convolution 32x32",
+    verify_convolution(
+      &context,
+      Config::builder().dim(&size).normalize(),
+      &data,
+      &kernel,
+    )
+    .unwrap(),
+  );
+}
+
+#[test]
+fn double_double() {
+  let context = match context() {
+    Some(context) => context,
+    None => return,
+  };
+  if !context.supports_double_precision() {
+    eprintln!("Skipping: no 64-bit floats in shaders");
+    return;
+  }
+  // Low words past double precision, so a double precision transform wouldn't pass
+  let data = signal(2 * 256)
+    .into_iter()
+    .enumerate()
+    .map(|(i, v)| DoubleDouble::new(v as f64, v as f64 * 1e-17 * (i % 3) as f64))
+    .collect::<Vec<_>>();
+  let builder = Config::builder()
+    .dim(&[256])
+    .precision(Precision::QuadDoubleDouble);
+  check(
+    "double-double 256",
+    verify_double_double(&context, builder.clone(), &data, FftType::Forward).unwrap(),
+  );
+  check(
+    "double-double inverse 256",
+    verify_double_double(&context, builder.normalize(), &data, FftType::Inverse).unwrap(),
+  );
+}
+
+#[test]
+fn welch() {
+  let context = match context() {
+    Some(context) => context,
+    None => return,
+  };
+  let signal = (0..24)
+    .map(|n| ((0.7 * n as f64).sin() + 0.5 * (2.1 * n as f64).cos()) as f32)
+    .collect::<Vec<_>>();
+  let psd = welch_psd(&context, &signal, WelchOptions::new(8, 2.0).unwrap()).unwrap();
+  // scipy.signal.welch(signal, fs=2.0, nperseg=8, detrend=False)[1]
+  let scipy = [
+    0.569_400_9,
+    1.393_626_6,
+    0.632_053_4,
+    0.284_853_8,
+    0.019_602_39,
+  ];
+  for (value, reference) in psd.iter().zip(scipy) {
+    assert!(
+      (*value as f64 - reference).abs() < 1e-4,
+      "{:?} != {:?}",
+      psd,
+      scipy
+    );
+  }
+}